@@ -64,4 +64,88 @@ pub enum ProtocolError {
 
     #[msg("Position is still active — close it before withdrawing collateral")]
     PositionStillActive,
+
+    #[msg("Leverage exceeds the collateral's maximum")]
+    ExceedsMaxLeverage,
+
+    #[msg("Borrow would exceed the lending vault's borrow cap")]
+    BorrowCapExceeded,
+
+    #[msg("Deposit would exceed the lending vault's supply cap")]
+    SupplyCapExceeded,
+
+    #[msg("Health factor at open is not above the collateral's minimum")]
+    OpenHealthTooLow,
+
+    #[msg("This collateral is paused")]
+    CollateralPaused,
+
+    #[msg("A flash loan is already in flight")]
+    FlashLoanActive,
+
+    #[msg("Flash loan is not repaid in the same transaction")]
+    FlashLoanNotRepaid,
+
+    #[msg("Bin in the liquidity distribution is outside the position's range")]
+    BinOutOfRange,
+
+    #[msg("Bin in the liquidity distribution is on the wrong side of the active bin")]
+    BinWrongSide,
+
+    #[msg("Oracle price moved too far from the last recorded price")]
+    PriceDeviationTooHigh,
+
+    #[msg("Protocol must be paused")]
+    ProtocolNotPaused,
+
+    #[msg("LB pair is not whitelisted")]
+    PairNotWhitelisted,
+
+    #[msg("LB pair whitelist is full")]
+    PairWhitelistFull,
+
+    #[msg("Position is still within its liquidation grace period")]
+    LiquidationGracePeriod,
+
+    #[msg("Queued withdrawals must be processed first")]
+    WithdrawalsQueued,
+
+    #[msg("Mint is not the lending vault's borrow asset")]
+    UnsupportedBorrowMint,
+
+    #[msg("Lending vault accounting invariant violated")]
+    InvariantViolated,
+
+    #[msg("Position spans too many bins")]
+    TooManyBins,
+
+    #[msg("LB pair does not trade this collateral against wSOL")]
+    InvalidSwapPair,
+
+    #[msg("Collateral still backs active positions")]
+    CollateralInUse,
+
+    #[msg("Position is partway through a split close")]
+    PositionClosing,
+
+    #[msg("Borrow would take the position's debt above its cap")]
+    PositionDebtCapExceeded,
+
+    #[msg("Position was opened too recently to be liquidated")]
+    PositionTooNew,
+
+    #[msg("Shares were supplied too recently to withdraw")]
+    SupplyCooldown,
+
+    #[msg("Rent reserve account is required to subsidize or refund DLMM position rent")]
+    RentReserveRequired,
+
+    #[msg("Position already has an open DLMM position or outstanding debt")]
+    PositionAlreadyOpen,
+
+    #[msg("The same position is listed more than once in a liquidation batch")]
+    DuplicatePosition,
+
+    #[msg("Seizure would not lower the position's LTV")]
+    SeizureWorsensLtv,
 }
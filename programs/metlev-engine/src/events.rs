@@ -0,0 +1,209 @@
+use anchor_lang::prelude::*;
+use crate::state::InterestRateParams;
+
+#[event]
+pub struct PositionFeesClaimed {
+    pub position: Pubkey,
+    pub met_position: Pubkey,
+    /// Token X fees, swapped to wSOL
+    pub amount_x: u64,
+    /// wSOL fees
+    pub amount_y: u64,
+    /// wSOL from both sides applied to the position's debt
+    pub debt_repaid: u64,
+    /// wSOL beyond the debt, sent to the owner
+    pub to_owner: u64,
+}
+
+#[event]
+pub struct PositionOpened {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub met_position: Pubkey,
+    /// Position debt after the borrow, in wSOL lamports
+    pub debt_amount: u64,
+    pub strategy_tag: [u8; 16],
+}
+
+#[event]
+pub struct PositionClosed {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    /// Debt repaid at close, interest included
+    pub debt_repaid: u64,
+    /// wSOL recovered from the DLMM position
+    pub proceeds: u64,
+    /// proceeds - debt_repaid, in wSOL lamports
+    pub realized_pnl: i64,
+    pub closed_at: i64,
+    /// Force-closed by the protocol authority rather than the owner
+    pub by_authority: bool,
+    pub strategy_tag: [u8; 16],
+}
+
+/// One `close_position_remove` step of a split close; the position is
+/// finalized, and `PositionClosed` emitted, by `close_position_finalize`.
+#[event]
+pub struct PositionCloseStep {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub from_bin_id: i32,
+    pub to_bin_id: i32,
+    /// wSOL recovered from the bins, fees included
+    pub proceeds: u64,
+    pub debt_repaid: u64,
+    pub debt_remaining: u64,
+}
+
+/// Part of a position's liquidity unwound by `partial_close_position`; the
+/// position stays open.
+#[event]
+pub struct PositionPartiallyClosed {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    /// Share of the bin range's liquidity removed
+    pub bps_removed: u16,
+    /// wSOL recovered from the removed liquidity
+    pub proceeds: u64,
+    pub debt_repaid: u64,
+    pub debt_remaining: u64,
+}
+
+#[event]
+pub struct PositionLiquidated {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub liquidator: Pubkey,
+    /// wSOL recovered from the DLMM position
+    pub proceeds: u64,
+    pub debt_repaid: u64,
+    /// Penalty taken from the proceeds, liquidator bonus included
+    pub penalty: u64,
+    /// Part of the penalty paid to the liquidator; the rest went to insurance
+    pub liquidator_bonus: u64,
+    /// Debt neither proceeds nor collateral could cover; 0 when fully repaid
+    pub bad_debt: u64,
+    /// True when the position was only partly unwound and stays active
+    pub partial: bool,
+    /// Token X handed to the owner unswapped on a full liquidation
+    pub residual_token_x: u64,
+    pub strategy_tag: [u8; 16],
+}
+
+/// Collateral taken for debt a liquidator repaid out of pocket, through
+/// `seize_collateral` or `liquidate_batch`; the DLMM position stays open.
+#[event]
+pub struct CollateralSeized {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub liquidator: Pubkey,
+    pub debt_repaid: u64,
+    /// Collateral taken, penalty included
+    pub collateral_seized: u64,
+    /// Part of the penalty paid to the liquidator; the rest went to insurance
+    pub liquidator_bonus: u64,
+    pub to_insurance: u64,
+}
+
+#[event]
+pub struct StaleOracleLiquidation {
+    pub position: Pubkey,
+    /// Discounted last price the liquidation ran at
+    pub fallback_price: u64,
+    /// When the last price was recorded
+    pub last_price_ts: i64,
+}
+
+#[event]
+pub struct BadDebtAbsorbed {
+    pub position: Pubkey,
+    /// Debt left after LP proceeds and collateral were exhausted
+    pub bad_debt: u64,
+    /// Part paid out of the insurance fund
+    pub from_insurance: u64,
+    /// Part written off LP assets
+    pub socialized: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawal {
+    pub authority: Pubkey,
+    pub destination: Pubkey,
+    /// wSOL moved out of wsol_vault by this call
+    pub amount: u64,
+    /// Lifetime total moved out by emergency_withdraw
+    pub total_withdrawn: u64,
+}
+
+#[event]
+pub struct WsolVaultSynced {
+    pub caller: Pubkey,
+    /// Stray lamports wrapped into wSOL and credited to protocol_fees
+    pub amount: u64,
+    /// protocol_fees after the credit
+    pub protocol_fees: u64,
+}
+
+#[event]
+pub struct LeverageIncreased {
+    pub position: Pubkey,
+    /// wSOL borrowed by this call and added to the DLMM position
+    pub borrowed: u64,
+    /// Total debt after the borrow
+    pub debt_amount: u64,
+    /// debt / collateral after the borrow (basis points, 10_000 = 1x)
+    pub leverage_bps: u64,
+}
+
+#[event]
+pub struct LeverageDecreased {
+    pub position: Pubkey,
+    /// wSOL recovered from the removed liquidity
+    pub proceeds: u64,
+    pub debt_repaid: u64,
+    /// Total debt after the repayment
+    pub debt_amount: u64,
+    /// LTV before and after, in basis points
+    pub ltv_before: u64,
+    pub ltv_after: u64,
+}
+
+#[event]
+pub struct CollateralAdded {
+    pub position: Pubkey,
+    pub amount: u64,
+    pub collateral_amount: u64,
+    /// LTV before and after, in basis points; 0 without debt
+    pub ltv_before: u64,
+    pub ltv_after: u64,
+}
+
+#[event]
+pub struct ExcessCollateralWithdrawn {
+    pub position: Pubkey,
+    pub amount: u64,
+    /// Collateral left in the position
+    pub collateral_amount: u64,
+    /// LTV after the withdrawal, in basis points; 0 without debt
+    pub ltv_after: u64,
+}
+
+#[event]
+pub struct VaultParamsUpdated {
+    pub authority: Pubkey,
+    pub old: InterestRateParams,
+    pub new: InterestRateParams,
+}
+
+#[event]
+pub struct InterestAccrued {
+    /// LP whose supply, withdraw or queued redemption triggered the accrual;
+    /// default for a poke_accrual not aimed at an LP
+    pub lp: Pubkey,
+    /// Borrow index growth since the previous accrual (1e18 = 1.0)
+    pub index_delta: u128,
+    pub borrow_index: u128,
+    pub total_supplied: u64,
+    pub total_borrowed: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{CollateralConfig, LendingVault, Position};
+use crate::errors::ProtocolError;
+use crate::events::CollateralAdded;
+use crate::utils::{read_price, calculate_position_ltv};
+
+/// Top up collateral on an existing active position.
+///
+/// Works for both SOL and SPL collateral: native SOL is sent straight to the
+/// vault PDA, SPL tokens are moved from `user_token_account` into the vault
+/// token account created by `deposit_token_collateral`.
+#[derive(Accounts)]
+pub struct AddCollateral<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, mint.key().as_ref()],
+        bump = collateral_config.bump,
+        constraint = collateral_config.mint == mint.key() @ ProtocolError::InvalidCollateralType,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, user.key().as_ref(), mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Needed to accrue interest on the position's debt before the LTV check.
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    /// Native SOL vault or SPL token vault, depending on the mint.
+    /// CHECK: PDA validated by seeds; the token program validates it for SPL.
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// Source of SPL collateral. Omit for SOL.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> AddCollateral<'info> {
+    /// Not gated on the pause flag or `collateral_config.enabled`: adding
+    /// collateral only ever lowers risk, and borrowers must be able to rescue
+    /// a position while the protocol is paused.
+    pub fn add_collateral(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, ProtocolError::InvalidAmount);
+
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let debt = self.position.debt_amount;
+        let ltv_before = if debt > 0 {
+            let oracle_info = self.price_oracle.to_account_info();
+            let (price, _) = read_price(
+                self.collateral_config.oracle_kind,
+                &oracle_info,
+                self.collateral_config.oracle_max_age,
+                self.collateral_config.max_confidence_bps,
+            )?;
+            Some((price, self.position_ltv(price)?))
+        } else {
+            None
+        };
+
+        self.position.collateral_amount = self.position.collateral_amount
+            .checked_add(amount)
+            .ok_or(ProtocolError::MathOverflow)?;
+
+        let (ltv_before, ltv_after) = match ltv_before {
+            Some((price, before)) => {
+                let after = self.position_ltv(price)?;
+                require!(after <= before, ProtocolError::ExceedsMaxLTV);
+                (before, after)
+            }
+            None => (0, 0),
+        };
+        emit!(CollateralAdded {
+            position: self.position.key(),
+            amount,
+            collateral_amount: self.position.collateral_amount,
+            ltv_before,
+            ltv_after,
+        });
+
+        if self.mint.key() == anchor_spl::token::spl_token::native_mint::id() {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    SystemTransfer {
+                        from: self.user.to_account_info(),
+                        to:   self.vault.to_account_info(),
+                    },
+                ),
+                amount,
+            )
+        } else {
+            let user_token_account = self
+                .user_token_account
+                .as_ref()
+                .ok_or(ProtocolError::InvalidCollateralType)?;
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from:      user_token_account.to_account_info(),
+                        mint:      self.mint.to_account_info(),
+                        to:        self.vault.to_account_info(),
+                        authority: self.user.to_account_info(),
+                    },
+                ),
+                amount,
+                self.mint.decimals,
+            )
+        }
+    }
+
+    fn position_ltv(&self, price: u64) -> Result<u64> {
+        calculate_position_ltv(
+            self.position.collateral_amount,
+            self.position.debt_amount,
+            price,
+            self.collateral_config.decimals,
+        )
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::state::{Config, Keeper};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+#[instruction(keeper: Pubkey)]
+pub struct AddKeeper<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Keeper::INIT_SPACE,
+        seeds = [Keeper::SEED_PREFIX, keeper.as_ref()],
+        bump,
+    )]
+    pub keeper_registration: Account<'info, Keeper>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AddKeeper<'info> {
+    pub fn add_keeper(&mut self, bumps: &AddKeeperBumps, keeper: Pubkey) -> Result<()> {
+        require!(keeper != Pubkey::default(), ProtocolError::InvalidAmount);
+        self.keeper_registration.set_inner(Keeper {
+            keeper,
+            added_at: Clock::get()?.unix_timestamp,
+            bump: bumps.keeper_registration,
+        });
+        Ok(())
+    }
+}
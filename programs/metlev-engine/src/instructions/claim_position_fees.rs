@@ -0,0 +1,309 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{CollateralConfig, Keeper, Position, LendingVault};
+use crate::errors::ProtocolError;
+use crate::events::PositionFeesClaimed;
+use crate::utils::{read_price, calculate_min_wsol_out, swap_bin_arrays};
+use crate::dlmm;
+
+/// Most the token X fee swap may come in under its oracle value (basis points)
+pub const FEE_SWAP_SLIPPAGE_BPS: u16 = 200;
+
+/// Sweep accrued DLMM trading fees from an open position into its debt.
+/// DLMM fees belong to the position's owner on every path: a close or
+/// liquidation counts them as proceeds, and this claim uses them to repay
+/// the position's debt, sending anything beyond it to the owner. Callable by
+/// the owner or a registered keeper, so compounding can run on a schedule.
+#[derive(Accounts)]
+pub struct ClaimPositionFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The payer's keeper registration; required unless the payer owns the
+    /// position.
+    #[account(
+        seeds = [Keeper::SEED_PREFIX, payer.key().as_ref()],
+        bump = keeper.bump,
+    )]
+    pub keeper: Option<Box<Account<'info, Keeper>>>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, position.owner.as_ref(), wsol_mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Box<Account<'info, LendingVault>>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Receives whatever the fees pay beyond the position's debt.
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = position.owner,
+    )]
+    pub owner_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// wSOL collateral config; its oracle prices the wSOL side of the swap.
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+
+    /// Token X's collateral config; its oracle sets the swap's minimum out.
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, token_x_mint.key().as_ref()],
+        bump = token_x_config.bump,
+    )]
+    pub token_x_config: Box<Account<'info, CollateralConfig>>,
+
+    /// CHECK: verified via token_x_config.oracle constraint
+    #[account(
+        constraint = token_x_oracle.key() == token_x_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub token_x_oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Must be the DLMM position recorded at open; the DLMM program
+    /// verifies the rest.
+    #[account(
+        mut,
+        constraint = met_position.key() == position.meteora_position @ ProtocolError::InvalidMeteoraPosition,
+    )]
+    pub met_position: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub lb_pair: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_bitmap_extension: Option<UncheckedAccount<'info>>,
+
+    /// Lending vault's token X ATA — receives the X side of the fees, which
+    /// is then swapped to wSOL.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = token_x_mint,
+        associated_token::authority = lending_vault,
+        associated_token::token_program = token_program,
+    )]
+    pub user_token_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub reserve_x: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub reserve_y: UncheckedAccount<'info>,
+
+    pub token_x_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub token_y_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_upper: UncheckedAccount<'info>,
+
+    /// CHECK: Pool TWAP oracle required by DLMM swap to update price tracking.
+    #[account(mut)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub event_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Address constrained to dlmm::ID.
+    #[account(address = dlmm::ID)]
+    pub dlmm_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClaimPositionFees<'info> {
+    pub fn claim_position_fees(&mut self) -> Result<()> {
+        require!(
+            self.payer.key() == self.position.owner || self.keeper.is_some(),
+            ProtocolError::Unauthorized
+        );
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+
+        let x_before = self.user_token_x.amount;
+        let y_before = self.wsol_vault.amount;
+
+        self.cpi_claim_fee(signer_seeds)?;
+
+        self.user_token_x.reload()?;
+        self.wsol_vault.reload()?;
+        let amount_x = self.user_token_x.amount.saturating_sub(x_before);
+        let amount_y = self.wsol_vault.amount.saturating_sub(y_before);
+
+        // Token X left in the shared ATA would be paid out by the next close
+        // on this pair, so it is sold for wSOL here, no cheaper than the
+        // oracles allow
+        if amount_x > 0 {
+            let min_out = self.fee_swap_min_out(amount_x)?;
+            self.cpi_swap(signer_seeds, amount_x, min_out)?;
+        }
+        self.wsol_vault.reload()?;
+        let claimed = self.wsol_vault.amount.saturating_sub(y_before);
+
+        let repaid = claimed.min(self.position.debt_amount);
+        if repaid > 0 {
+            self.lending_vault.repay(repaid)?;
+            self.position.debt_amount -= repaid;
+        }
+        let to_owner = claimed - repaid;
+        if to_owner > 0 {
+            self.transfer_to_owner(signer_seeds, to_owner)?;
+        }
+
+        emit!(PositionFeesClaimed {
+            position: self.position.key(),
+            met_position: self.met_position.key(),
+            amount_x,
+            amount_y,
+            debt_repaid: repaid,
+            to_owner,
+        });
+        Ok(())
+    }
+
+    /// `amount_x` valued in wSOL at the token X and wSOL oracle prices, less
+    /// `FEE_SWAP_SLIPPAGE_BPS`.
+    fn fee_swap_min_out(&self, amount_x: u64) -> Result<u64> {
+        let (x_price, _) = read_price(
+            self.token_x_config.oracle_kind,
+            &self.token_x_oracle.to_account_info(),
+            self.token_x_config.oracle_max_age,
+            self.token_x_config.max_confidence_bps,
+        )?;
+        let (sol_price, _) = read_price(
+            self.collateral_config.oracle_kind,
+            &self.price_oracle.to_account_info(),
+            self.collateral_config.oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+        calculate_min_wsol_out(
+            amount_x,
+            x_price,
+            self.token_x_mint.decimals,
+            sol_price,
+            FEE_SWAP_SLIPPAGE_BPS,
+        )
+    }
+
+    #[inline(never)]
+    fn cpi_claim_fee(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::ClaimFee {
+                lb_pair:         self.lb_pair.to_account_info(),
+                position:        self.met_position.to_account_info(),
+                bin_array_lower: self.bin_array_lower.to_account_info(),
+                bin_array_upper: self.bin_array_upper.to_account_info(),
+                sender:          self.lending_vault.to_account_info(),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                user_token_x:    self.user_token_x.to_account_info(),
+                user_token_y:    self.wsol_vault.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                token_program:   self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::claim_fee(ctx)
+    }
+
+    #[inline(never)]
+    fn cpi_swap(&self, signer_seeds: &[&[&[u8]]], amount: u64, min_out: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::Swap {
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                user_token_in:   self.user_token_x.to_account_info(),
+                user_token_out:  self.wsol_vault.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                oracle:          self.oracle.to_account_info(),
+                host_fee_in:     None,
+                user:            self.lending_vault.to_account_info(),
+                token_x_program: self.token_program.to_account_info(),
+                token_y_program: self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        )
+        .with_remaining_accounts(swap_bin_arrays(
+            &self.bin_array_lower.to_account_info(),
+            &self.bin_array_upper.to_account_info(),
+        ));
+        dlmm::cpi::swap(ctx, amount, min_out)
+    }
+
+    #[inline(never)]
+    fn transfer_to_owner(&self, signer_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.wsol_vault.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.owner_wsol_ata.to_account_info(),
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+}
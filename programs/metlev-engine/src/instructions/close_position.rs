@@ -4,15 +4,25 @@ use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token_interface::{
     transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
 };
-use crate::state::{Config, Position, LendingVault};
+use crate::state::{Config, Position, LendingVault, CollateralConfig};
 use crate::errors::ProtocolError;
+use crate::events::{BadDebtAbsorbed, PositionCloseStep, PositionClosed, PositionPartiallyClosed};
+use crate::instructions::open_position::dlmm_rent_receiver;
+use crate::instructions::withdraw_collateral::release_sol_collateral;
+use crate::utils::swap_bin_arrays;
 use crate::dlmm;
 
 #[derive(Accounts)]
 pub struct ClosePosition<'info> {
+    /// The position's owner, or the protocol authority force-closing it
+    /// during a wind-down. Pays for any accounts created along the way.
     #[account(mut)]
     pub user: Signer<'info>,
 
+    /// CHECK: Must be position.owner; receives the freed collateral and rent.
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
     #[account(
         seeds = [Config::SEED_PREFIX],
         bump = config.bump,
@@ -24,13 +34,21 @@ pub struct ClosePosition<'info> {
 
     #[account(
         mut,
-        seeds = [Position::SEED_PREFIX, user.key().as_ref(), wsol_mint.key().as_ref()],
+        seeds = [Position::SEED_PREFIX, owner.key().as_ref(), wsol_mint.key().as_ref()],
         bump = position.bump,
-        constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
+        constraint = position.owner == owner.key() @ ProtocolError::InvalidOwner,
+        constraint = user.key() == owner.key() || user.key() == config.authority @ ProtocolError::InvalidOwner,
         constraint = position.is_active() @ ProtocolError::PositionNotActive,
     )]
     pub position: Box<Account<'info, Position>>,
 
+    #[account(
+        mut,
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
     #[account(
         mut,
         seeds = [LendingVault::SEED_PREFIX],
@@ -51,7 +69,7 @@ pub struct ClosePosition<'info> {
         init_if_needed,
         payer = user,
         associated_token::mint = wsol_mint,
-        associated_token::authority = user,
+        associated_token::authority = owner,
         associated_token::token_program = token_program,
     )]
     pub user_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
@@ -59,15 +77,29 @@ pub struct ClosePosition<'info> {
     /// CHECK: PDA validated by seeds.
     #[account(
         mut,
-        seeds = [b"vault", user.key().as_ref(), wsol_mint.key().as_ref()],
+        seeds = [b"vault", owner.key().as_ref(), wsol_mint.key().as_ref()],
         bump,
     )]
     pub collateral_vault: UncheckedAccount<'info>,
 
-    /// CHECK: Verified by the DLMM program.
-    #[account(mut)]
+    /// CHECK: Must be the DLMM position recorded at open; the DLMM program
+    /// verifies the rest.
+    #[account(
+        mut,
+        constraint = met_position.key() == position.meteora_position @ ProtocolError::InvalidMeteoraPosition,
+    )]
     pub met_position: UncheckedAccount<'info>,
 
+    /// Protocol-funded account that paid the DLMM position rent, if it did;
+    /// required then, as the rent goes back to it when the position closes.
+    /// CHECK: PDA validated by seeds; holds lamports only.
+    #[account(
+        mut,
+        seeds = [b"rent_reserve"],
+        bump,
+    )]
+    pub rent_reserve: Option<UncheckedAccount<'info>>,
+
     /// CHECK: Verified by the DLMM program.
     #[account(mut)]
     pub lb_pair: UncheckedAccount<'info>,
@@ -86,6 +118,17 @@ pub struct ClosePosition<'info> {
     )]
     pub user_token_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    /// User's token X ATA — receives the position's token X unswapped when
+    /// closing with `swap_to_wsol = false`.
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = token_x_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_token_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
     /// CHECK: Verified by the DLMM program.
     #[account(mut)]
     pub reserve_x: UncheckedAccount<'info>,
@@ -126,37 +169,148 @@ pub struct ClosePosition<'info> {
 }
 
 impl<'info> ClosePosition<'info> {
+    /// With `swap_to_wsol = false` the position's token X goes to the user
+    /// unsold, debt is repaid from the wSOL side alone, and any shortfall is
+    /// paid from the user's wSOL ATA rather than from collateral, so only
+    /// the owner can close that way.
     pub fn close(
         &mut self,
         bumps: &ClosePositionBumps,
         from_bin_id: i32,
         to_bin_id: i32,
+        swap_to_wsol: bool,
     ) -> Result<()> {
+        require!(
+            swap_to_wsol || !self.by_authority(),
+            ProtocolError::Unauthorized
+        );
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
         let vault_bump = self.lending_vault.bump;
         let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
         let debt = self.position.debt_amount;
 
         let vault_before = self.wsol_vault.amount;
+        let x_before = self.user_token_x.amount;
 
-        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id)?;
+        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id, 10_000)?;
         self.cpi_claim_fee(signer_seeds)?;
 
+        // Only this position's token X: anything else in the shared ATA
+        // isn't the owner's to sell or keep
         self.user_token_x.reload()?;
-        let x_balance = self.user_token_x.amount;
-        if x_balance > 0 {
-            self.cpi_swap(signer_seeds, x_balance)?;
+        let from_position = self.user_token_x.amount.saturating_sub(x_before);
+        if from_position > 0 {
+            if swap_to_wsol {
+                self.cpi_swap(signer_seeds, from_position)?;
+            } else {
+                self.transfer_token_x(signer_seeds, from_position)?;
+            }
         }
 
         self.cpi_close_position(signer_seeds)?;
+        self.position.subsidized_rent = 0;
 
         self.wsol_vault.reload()?;
         let vault_after = self.wsol_vault.amount;
         let proceeds = vault_after.saturating_sub(vault_before);
 
+        self.settle_close(bumps, signer_seeds, proceeds, debt, swap_to_wsol)
+    }
+
+    /// First phase of a close split across transactions: remove all the
+    /// liquidity in `[from_bin_id, to_bin_id]`, claim fees, swap the token X
+    /// back to wSOL and repay debt from what came back; anything above the
+    /// debt goes to the owner. Can be called once per sub-range until the
+    /// DLMM position is empty; `close_position_finalize` then closes it.
+    pub fn close_remove(&mut self, from_bin_id: i32, to_bin_id: i32) -> Result<()> {
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+        let debt = self.position.debt_amount;
+
+        let vault_before = self.wsol_vault.amount;
+        let x_before = self.user_token_x.amount;
+
+        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id, 10_000)?;
+        self.cpi_claim_fee(signer_seeds)?;
+
+        // Swapped now rather than at finalize: token X left in the vault's
+        // shared ATA between transactions would be swept by another close
+        self.user_token_x.reload()?;
+        let from_position = self.user_token_x.amount.saturating_sub(x_before);
+        if from_position > 0 {
+            self.cpi_swap(signer_seeds, from_position)?;
+        }
+
+        self.wsol_vault.reload()?;
+        let proceeds = self.wsol_vault.amount.saturating_sub(vault_before);
+        let repay_amount = proceeds.min(debt);
+        if repay_amount > 0 {
+            self.lending_vault.repay(repay_amount)?;
+            self.position.debt_amount = debt - repay_amount;
+        }
+
+        let surplus = proceeds - repay_amount;
+        if surplus > 0 {
+            self.transfer_surplus(signer_seeds, surplus)?;
+        }
+
+        self.position.record_close_step(proceeds, repay_amount)?;
+        emit!(PositionCloseStep {
+            position: self.position.key(),
+            owner: self.position.owner,
+            from_bin_id,
+            to_bin_id,
+            proceeds,
+            debt_repaid: repay_amount,
+            debt_remaining: self.position.debt_amount,
+        });
+        Ok(())
+    }
+
+    /// Second phase: close the emptied DLMM position (DLMM rejects it while
+    /// any liquidity remains), cover debt the remove steps didn't repay from
+    /// collateral and mark the position Closed.
+    pub fn close_finalize(&mut self, bumps: &ClosePositionBumps) -> Result<()> {
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+        let debt = self.position.debt_amount;
+
+        self.cpi_close_position(signer_seeds)?;
+        self.position.subsidized_rent = 0;
+        self.settle_close(bumps, signer_seeds, 0, debt, true)
+    }
+
+    /// Repay `debt` out of `proceeds`, covering a shortfall from collateral
+    /// (or, without the swap, from the owner's wSOL), pay out any surplus and
+    /// mark the position Closed.
+    fn settle_close(
+        &mut self,
+        bumps: &ClosePositionBumps,
+        signer_seeds: &[&[&[u8]]],
+        proceeds: u64,
+        debt: u64,
+        swap_to_wsol: bool,
+    ) -> Result<()> {
         // If LP lost value (proceeds < debt), cover shortfall from collateral.
         // Transfer SOL from collateral vault wsol_vault, then sync_native
         // so the wSOL token balance reflects the added lamports.
-        if proceeds < debt {
+        if proceeds < debt && !swap_to_wsol {
+            // The user kept the token X, so the wallet makes up the difference
+            let shortfall = debt - proceeds;
+            require!(
+                self.user_wsol_ata.amount >= shortfall,
+                ProtocolError::RepaymentFailed
+            );
+            self.repay_from_wallet(shortfall)?;
+        } else if proceeds < debt {
             let shortfall = debt
                 .checked_sub(proceeds)
                 .ok_or(ProtocolError::MathOverflow)?;
@@ -167,6 +321,18 @@ impl<'info> ClosePosition<'info> {
                     .checked_sub(covered)
                     .ok_or(ProtocolError::MathOverflow)?;
             }
+
+            // Whatever collateral couldn't cover goes to the insurance fund, then LPs
+            let bad_debt = shortfall - covered;
+            if bad_debt > 0 {
+                let (from_insurance, socialized) = self.lending_vault.absorb_bad_debt(bad_debt);
+                emit!(BadDebtAbsorbed {
+                    position: self.position.key(),
+                    bad_debt,
+                    from_insurance,
+                    socialized,
+                });
+            }
         }
 
         self.position.debt_amount = 0;
@@ -178,16 +344,124 @@ impl<'info> ClosePosition<'info> {
             self.transfer_surplus(signer_seeds, surplus)?;
         }
 
-        self.position.mark_closed();
+        let now = Clock::get()?.unix_timestamp;
+        let (proceeds, debt) = self.position.record_close_total(proceeds, debt, now);
+        self.collateral_config.track_position_ended()?;
+        emit!(PositionClosed {
+            position: self.position.key(),
+            owner: self.position.owner,
+            debt_repaid: debt,
+            proceeds,
+            realized_pnl: self.position.realized_pnl,
+            closed_at: now,
+            by_authority: self.by_authority(),
+            strategy_tag: self.position.strategy_tag,
+        });
         Ok(())
     }
 
+    /// Remove `bps_to_remove` of the liquidity in `[from_bin_id, to_bin_id]`
+    /// and repay the same share of the debt, or all of the proceeds if the
+    /// slice is worth less, keeping the DLMM position and the `Position` open.
+    /// Proceeds above the repayment go to the owner; unclaimed fees stay in
+    /// the position.
+    pub fn partial_close(
+        &mut self,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        bps_to_remove: u16,
+    ) -> Result<()> {
+        require!(
+            bps_to_remove > 0 && bps_to_remove < 10_000,
+            ProtocolError::InvalidAmount
+        );
+        require!(!self.position.closing, ProtocolError::PositionClosing);
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let debt = self.position.debt_amount;
+        let repay_cap = (debt as u128)
+            .checked_mul(bps_to_remove as u128)
+            .map(|v| (v / 10_000) as u64)
+            .ok_or(ProtocolError::MathOverflow)?;
+        require!(repay_cap > 0, ProtocolError::InvalidAmount);
+        require!(
+            !self.collateral_config.is_dust_debt(debt - repay_cap),
+            ProtocolError::InvalidAmount
+        );
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+
+        let vault_before = self.wsol_vault.amount;
+        let x_before = self.user_token_x.amount;
+
+        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id, bps_to_remove)?;
+
+        self.user_token_x.reload()?;
+        let from_position = self.user_token_x.amount.saturating_sub(x_before);
+        if from_position > 0 {
+            self.cpi_swap(signer_seeds, from_position)?;
+        }
+
+        self.wsol_vault.reload()?;
+        let proceeds = self.wsol_vault.amount.saturating_sub(vault_before);
+        let repay_amount = repay_cap.min(proceeds);
+        require!(repay_amount > 0, ProtocolError::RepaymentFailed);
+
+        self.lending_vault.repay(repay_amount)?;
+        self.position.debt_amount = debt
+            .checked_sub(repay_amount)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        let surplus = proceeds - repay_amount;
+        if surplus > 0 {
+            self.transfer_surplus(signer_seeds, surplus)?;
+        }
+
+        emit!(PositionPartiallyClosed {
+            position: self.position.key(),
+            owner: self.position.owner,
+            bps_removed: bps_to_remove,
+            proceeds,
+            debt_repaid: repay_amount,
+            debt_remaining: self.position.debt_amount,
+        });
+        Ok(())
+    }
+
+    /// Second half of `close_and_withdraw`: once `close` has marked the
+    /// position Closed, hand back what is left of the collateral and close
+    /// the position account, as `withdraw_collateral` would.
+    pub fn withdraw_collateral(&mut self, bumps: &ClosePositionBumps) -> Result<()> {
+        require!(self.position.is_closed(), ProtocolError::PositionStillActive);
+
+        let collateral = self.position.collateral_amount;
+        self.position.collateral_amount = 0;
+        release_sol_collateral(
+            &self.system_program,
+            &self.collateral_vault,
+            &self.owner,
+            &self.wsol_mint.key(),
+            bumps.collateral_vault,
+            collateral,
+        )?;
+
+        self.position.close(self.owner.to_account_info())
+    }
+
+    /// Whether the protocol authority, not the owner, signed this close
+    fn by_authority(&self) -> bool {
+        self.user.key() != self.position.owner
+    }
+
     #[inline(never)]
     fn cpi_remove_liquidity(
         &self,
         signer_seeds: &[&[&[u8]]],
         from_bin_id: i32,
         to_bin_id: i32,
+        bps_to_remove: u16,
     ) -> Result<()> {
         let ctx = CpiContext::new_with_signer(
             self.dlmm_program.to_account_info(),
@@ -214,7 +488,7 @@ impl<'info> ClosePosition<'info> {
             },
             signer_seeds,
         );
-        dlmm::cpi::remove_liquidity_by_range(ctx, from_bin_id, to_bin_id, 10_000)
+        dlmm::cpi::remove_liquidity_by_range(ctx, from_bin_id, to_bin_id, bps_to_remove)
     }
 
     #[inline(never)]
@@ -268,15 +542,20 @@ impl<'info> ClosePosition<'info> {
             },
             signer_seeds,
         )
-        .with_remaining_accounts(vec![
-            self.bin_array_lower.to_account_info(),
-            self.bin_array_upper.to_account_info(),
-        ]);
+        .with_remaining_accounts(swap_bin_arrays(
+            &self.bin_array_lower.to_account_info(),
+            &self.bin_array_upper.to_account_info(),
+        ));
         dlmm::cpi::swap(ctx, amount, 0)
     }
 
     #[inline(never)]
     fn cpi_close_position(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let rent_receiver = dlmm_rent_receiver(
+            &self.position,
+            &self.rent_reserve,
+            &self.owner.to_account_info(),
+        )?;
         let ctx = CpiContext::new_with_signer(
             self.dlmm_program.to_account_info(),
             dlmm::cpi::accounts::ClosePosition {
@@ -285,7 +564,7 @@ impl<'info> ClosePosition<'info> {
                 bin_array_lower: self.bin_array_lower.to_account_info(),
                 bin_array_upper: self.bin_array_upper.to_account_info(),
                 sender:          self.lending_vault.to_account_info(),
-                rent_receiver:   self.user.to_account_info(),
+                rent_receiver,
                 event_authority: self.event_authority.to_account_info(),
                 program:         self.dlmm_program.to_account_info(),
             },
@@ -298,12 +577,12 @@ impl<'info> ClosePosition<'info> {
     /// to cover the debt shortfall when LP lost value.
     #[inline(never)]
     fn cover_shortfall(&self, bumps: &ClosePositionBumps, amount: u64) -> Result<()> {
-        let user_key = self.user.key();
+        let owner_key = self.owner.key();
         let mint_key = self.wsol_mint.key();
         let vault_bump_arr = [bumps.collateral_vault];
         let collateral_seeds: &[&[&[u8]]] = &[&[
             b"vault",
-            user_key.as_ref(),
+            owner_key.as_ref(),
             mint_key.as_ref(),
             &vault_bump_arr,
         ]];
@@ -335,6 +614,35 @@ impl<'info> ClosePosition<'info> {
         Ok(())
     }
 
+    #[inline(never)]
+    fn transfer_token_x(&self, signer_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.user_token_x.to_account_info(),
+                mint:      self.token_x_mint.to_account_info(),
+                to:        self.owner_token_x.to_account_info(),
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.token_x_mint.decimals)
+    }
+
+    #[inline(never)]
+    fn repay_from_wallet(&self, amount: u64) -> Result<()> {
+        let ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.user_wsol_ata.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.wsol_vault.to_account_info(),
+                authority: self.user.to_account_info(),
+            },
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+
     #[inline(never)]
     fn transfer_surplus(&self, signer_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
         let ctx = CpiContext::new_with_signer(
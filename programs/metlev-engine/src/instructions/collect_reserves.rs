@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Config, LendingVault};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+pub struct CollectReserves<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CollectReserves<'info> {
+    /// Sweep accumulated protocol_fees out of wsol_vault. Interest only turns
+    /// into wSOL once borrowers repay, so this takes whatever part of the
+    /// fees is currently held in the vault and leaves the rest accrued. The
+    /// insurance fund's wSOL is never swept.
+    pub fn collect_reserves(&mut self) -> Result<()> {
+        let available = self.wsol_vault.amount.saturating_sub(self.lending_vault.insurance_fund);
+        let amount = self.lending_vault.protocol_fees.min(available);
+        require!(amount > 0, ProtocolError::InvalidAmount);
+
+        self.lending_vault.protocol_fees = self.lending_vault.protocol_fees
+            .checked_sub(amount)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.wsol_vault.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.destination.to_account_info(),
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+}
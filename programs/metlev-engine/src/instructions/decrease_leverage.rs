@@ -0,0 +1,301 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Position, LendingVault, CollateralConfig};
+use crate::errors::ProtocolError;
+use crate::events::LeverageDecreased;
+use crate::utils::{read_price, calculate_position_ltv, swap_bin_arrays};
+use crate::dlmm;
+
+/// Partially unwind a position: pull liquidity out of a bin range, swap any
+/// X side to wSOL and repay part of the debt with it. The position and its
+/// DLMM position stay open; proceeds beyond `repay_amount` go to the user.
+#[derive(Accounts)]
+pub struct DecreaseLeverage<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, user.key().as_ref(), wsol_mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+        constraint = !position.closing @ ProtocolError::PositionClosing,
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Box<Account<'info, LendingVault>>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's wSOL ATA — receives proceeds above `repay_amount`.
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Must be the DLMM position recorded at open; the DLMM program
+    /// verifies the rest.
+    #[account(
+        mut,
+        constraint = met_position.key() == position.meteora_position @ ProtocolError::InvalidMeteoraPosition,
+    )]
+    pub met_position: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub lb_pair: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_bitmap_extension: Option<UncheckedAccount<'info>>,
+
+    /// Any X-side tokens returned by remove_liquidity land here, then get swapped to wSOL.
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = token_x_mint,
+        associated_token::authority = lending_vault,
+        associated_token::token_program = token_program,
+    )]
+    pub user_token_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub reserve_x: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub reserve_y: UncheckedAccount<'info>,
+
+    pub token_x_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub token_y_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_upper: UncheckedAccount<'info>,
+
+    /// CHECK: Pool TWAP oracle required by DLMM swap to update price tracking.
+    #[account(mut)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub event_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Address constrained to dlmm::ID.
+    #[account(address = dlmm::ID)]
+    pub dlmm_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> DecreaseLeverage<'info> {
+    /// Like repay, allowed while the protocol is paused: it only ever
+    /// lowers a position's risk.
+    pub fn decrease_leverage(
+        &mut self,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        repay_amount: u64,
+    ) -> Result<()> {
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let debt = self.position.debt_amount;
+        require!(
+            repay_amount > 0 && repay_amount <= debt,
+            ProtocolError::InvalidAmount
+        );
+        require!(
+            !self.collateral_config.is_dust_debt(debt - repay_amount),
+            ProtocolError::InvalidAmount
+        );
+
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            self.collateral_config.oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+        let ltv_before = self.position_ltv(price)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+
+        let vault_before = self.wsol_vault.amount;
+        let x_before = self.user_token_x.amount;
+
+        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id)?;
+
+        self.user_token_x.reload()?;
+        let from_position = self.user_token_x.amount.saturating_sub(x_before);
+        if from_position > 0 {
+            self.cpi_swap(signer_seeds, from_position)?;
+        }
+
+        self.wsol_vault.reload()?;
+        let proceeds = self.wsol_vault.amount.saturating_sub(vault_before);
+        require!(proceeds >= repay_amount, ProtocolError::RepaymentFailed);
+
+        self.lending_vault.repay(repay_amount)?;
+        self.position.debt_amount = debt
+            .checked_sub(repay_amount)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        let ltv_after = self.position_ltv(price)?;
+        require!(ltv_after < ltv_before, ProtocolError::ExceedsMaxLTV);
+
+        let surplus = proceeds - repay_amount;
+        if surplus > 0 {
+            self.transfer_surplus(signer_seeds, surplus)?;
+        }
+
+        emit!(LeverageDecreased {
+            position: self.position.key(),
+            proceeds,
+            debt_repaid: repay_amount,
+            debt_amount: self.position.debt_amount,
+            ltv_before,
+            ltv_after,
+        });
+        Ok(())
+    }
+
+    /// LTV = debt / (collateral + debt) — same formula as open_position
+    fn position_ltv(&self, price: u64) -> Result<u64> {
+        calculate_position_ltv(
+            self.position.collateral_amount,
+            self.position.debt_amount,
+            price,
+            self.collateral_config.decimals,
+        )
+    }
+
+    #[inline(never)]
+    fn cpi_remove_liquidity(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+        from_bin_id: i32,
+        to_bin_id: i32,
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::RemoveLiquidityByRange {
+                position:                   self.met_position.to_account_info(),
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                user_token_x:    self.user_token_x.to_account_info(),
+                user_token_y:    self.wsol_vault.to_account_info(),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                bin_array_lower: self.bin_array_lower.to_account_info(),
+                bin_array_upper: self.bin_array_upper.to_account_info(),
+                sender:          self.lending_vault.to_account_info(),
+                token_x_program: self.token_program.to_account_info(),
+                token_y_program: self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::remove_liquidity_by_range(ctx, from_bin_id, to_bin_id, 10_000)
+    }
+
+    #[inline(never)]
+    fn cpi_swap(&self, signer_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::Swap {
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                user_token_in:   self.user_token_x.to_account_info(),
+                user_token_out:  self.wsol_vault.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                oracle:          self.oracle.to_account_info(),
+                host_fee_in:     None,
+                user:            self.lending_vault.to_account_info(),
+                token_x_program: self.token_program.to_account_info(),
+                token_y_program: self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        )
+        .with_remaining_accounts(swap_bin_arrays(
+            &self.bin_array_lower.to_account_info(),
+            &self.bin_array_upper.to_account_info(),
+        ));
+        dlmm::cpi::swap(ctx, amount, 0)
+    }
+
+    #[inline(never)]
+    fn transfer_surplus(&self, signer_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.wsol_vault.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.user_wsol_ata.to_account_info(),
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+}
@@ -22,6 +22,7 @@ pub struct DepositSolCollateral<'info> {
     pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(
+        mut,
         seeds = [CollateralConfig::SEED_PREFIX, mint.key().as_ref()],
         bump = collateral_config.bump,
         constraint = collateral_config.mint == mint.key() @ ProtocolError::InvalidCollateralType,
@@ -56,23 +57,39 @@ impl<'info> DepositSolCollateral<'info> {
         bumps: &DepositSolCollateralBumps,
         amount: u64,
     ) -> Result<()> {
-        require!(!self.config.paused, ProtocolError::ProtocolPaused);
+        require!(amount > 0, ProtocolError::InvalidAmount);
+        require!(!self.config.deposits_paused(), ProtocolError::ProtocolPaused);
+        require!(!self.collateral_config.is_paused(), ProtocolError::CollateralPaused);
 
         require!(
             amount >= self.collateral_config.min_deposit,
             ProtocolError::InsufficientCollateral
         );
 
+        let now = Clock::get()?.unix_timestamp;
         self.position.set_inner(Position {
             owner: self.user.key(),
             collateral_mint: self.collateral_config.mint,
             collateral_amount: amount,
             debt_amount: 0,
+            debt_last_update: now,
+            debt_interest_remainder: 0,
+            borrow_index_snapshot: 0,
             meteora_position: Pubkey::default(),
-            created_at: Clock::get()?.unix_timestamp,
+            created_at: now,
+            opened_at: 0,
+            subsidized_rent: 0,
+            strategy_tag: [0; 16],
+            closed_at: 0,
+            realized_pnl: 0,
+            liquidation_eligible_since: 0,
+            closing: false,
+            close_proceeds: 0,
+            close_repaid: 0,
             status: PositionStatus::Active,
             bump: bumps.position,
         });
+        self.collateral_config.track_position_opened()?;
 
         let cpi_program = self.system_program.to_account_info();
         let cpi_accounts = SystemTransfer {
@@ -14,6 +14,8 @@ pub struct DepositTokenCollateral<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    /// token_program must be the program that owns the mint (SPL Token or
+    /// Token-2022); every token account below is then checked against it.
     #[account(
         mint::token_program = token_program,
         constraint = mint.key() != anchor_spl::token::spl_token::native_mint::id() @ ProtocolError::InvalidCollateralType,
@@ -21,6 +23,7 @@ pub struct DepositTokenCollateral<'info> {
     pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(
+        mut,
         seeds = [CollateralConfig::SEED_PREFIX, mint.key().as_ref()],
         bump = collateral_config.bump,
         constraint = collateral_config.mint == mint.key() @ ProtocolError::InvalidCollateralType,
@@ -28,17 +31,24 @@ pub struct DepositTokenCollateral<'info> {
     )]
     pub collateral_config: Account<'info, CollateralConfig>,
 
+    /// Owned by the mint's own token program, whichever program the caller
+    /// names, so an existing vault is never reused under the other one.
     #[account(
         init_if_needed,
         payer = user,
         token::mint = mint,
         token::authority = vault,
+        token::token_program = token_program,
         seeds = [b"vault", user.key().as_ref(), mint.key().as_ref()],
         bump,
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
@@ -60,23 +70,9 @@ impl<'info> DepositTokenCollateral<'info> {
         bumps: &DepositTokenCollateralBumps,
         amount: u64,
     ) -> Result<()> {
-        require!(!self.config.paused, ProtocolError::ProtocolPaused);
-
-        require!(
-            amount >= self.collateral_config.min_deposit,
-            ProtocolError::InsufficientCollateral
-        );
-
-        self.position.set_inner(Position {
-            owner: self.user.key(),
-            collateral_mint: self.collateral_config.mint,
-            collateral_amount: amount,
-            debt_amount: 0,
-            meteora_position: Pubkey::default(),
-            created_at: Clock::get()?.unix_timestamp,
-            status: PositionStatus::Active,
-            bump: bumps.position,
-        });
+        require!(amount > 0, ProtocolError::InvalidAmount);
+        require!(!self.config.deposits_paused(), ProtocolError::ProtocolPaused);
+        require!(!self.collateral_config.is_paused(), ProtocolError::CollateralPaused);
 
         let transfer_accounts = TransferChecked {
             from: self.user_token_account.to_account_info(),
@@ -90,7 +86,44 @@ impl<'info> DepositTokenCollateral<'info> {
             transfer_accounts,
         );
 
+        // A Token-2022 transfer fee is withheld from what lands in the vault,
+        // so credit the balance change rather than the requested amount.
+        let vault_before = self.vault.amount;
         token_interface::transfer_checked(cpi_ctx, amount, self.mint.decimals)?;
+        self.vault.reload()?;
+        let received = self.vault.amount
+            .checked_sub(vault_before)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        require!(
+            received >= self.collateral_config.min_deposit,
+            ProtocolError::InsufficientCollateral
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        self.position.set_inner(Position {
+            owner: self.user.key(),
+            collateral_mint: self.collateral_config.mint,
+            collateral_amount: received,
+            debt_amount: 0,
+            debt_last_update: now,
+            debt_interest_remainder: 0,
+            borrow_index_snapshot: 0,
+            meteora_position: Pubkey::default(),
+            created_at: now,
+            opened_at: 0,
+            subsidized_rent: 0,
+            strategy_tag: [0; 16],
+            closed_at: 0,
+            realized_pnl: 0,
+            liquidation_eligible_since: 0,
+            closing: false,
+            close_proceeds: 0,
+            close_repaid: 0,
+            status: PositionStatus::Active,
+            bump: bumps.position,
+        });
+        self.collateral_config.track_position_opened()?;
 
         Ok(())
     }
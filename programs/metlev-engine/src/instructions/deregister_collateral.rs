@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+use crate::state::{Config, CollateralConfig};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct DeregisterCollateral<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Closed back to the authority; only once no active position uses it.
+    #[account(
+        mut,
+        close = authority,
+        seeds = [CollateralConfig::SEED_PREFIX, mint.as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+}
+
+impl<'info> DeregisterCollateral<'info> {
+    /// Rent goes back to the authority through the `close` constraint. Closed
+    /// and liquidated positions no longer count, and withdraw_collateral
+    /// doesn't read the config, so their owners can still reclaim collateral.
+    pub fn deregister(&mut self) -> Result<()> {
+        require!(
+            self.collateral_config.active_positions == 0,
+            ProtocolError::CollateralInUse
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Config, LendingVault};
+use crate::events::EmergencyWithdrawal;
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+        constraint = config.is_paused() @ ProtocolError::ProtocolNotPaused,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> EmergencyWithdraw<'info> {
+    /// Break-glass: move wSOL out of wsol_vault while the protocol is paused.
+    /// Vault accounting is left as is so LP claims can be made whole later;
+    /// the amount is tallied in emergency_withdrawn.
+    pub fn emergency_withdraw(&mut self, amount: u64) -> Result<()> {
+        require!(
+            amount > 0 && amount <= self.wsol_vault.amount,
+            ProtocolError::InvalidAmount
+        );
+
+        self.lending_vault.emergency_withdrawn = self.lending_vault.emergency_withdrawn
+            .checked_add(amount)
+            .ok_or(ProtocolError::MathOverflow)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.wsol_vault.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.destination.to_account_info(),
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)?;
+
+        msg!("EMERGENCY WITHDRAWAL: {} lamports of wSOL to {}", amount, self.destination.key());
+        emit!(EmergencyWithdrawal {
+            authority: self.authority.key(),
+            destination: self.destination.key(),
+            amount,
+            total_withdrawn: self.lending_vault.emergency_withdrawn,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use crate::state::{CollateralConfig, Position};
+use crate::errors::ProtocolError;
+use crate::utils::{read_price, calculate_position_ltv};
+
+/// Permissionless: keepers call this to start a position's liquidation grace
+/// period, and anyone can call it again to clear the flag once the position
+/// is healthy.
+#[derive(Accounts)]
+pub struct FlagLiquidation<'info> {
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, position.owner.as_ref(), position.collateral_mint.as_ref()],
+        bump = position.bump,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, position.collateral_mint.as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+}
+
+impl<'info> FlagLiquidation<'info> {
+    /// Uses the stored debt like `is_liquidatable`. Returns whether the
+    /// position is flagged after the call.
+    pub fn flag_liquidation(&mut self) -> Result<bool> {
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            self.collateral_config.liquidation_oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+
+        let debt = self.position.debt_amount;
+        let liquidatable = debt > 0 && {
+            let ltv = calculate_position_ltv(
+                self.position.collateral_amount,
+                debt,
+                price,
+                self.collateral_config.decimals,
+            )?;
+            self.collateral_config.is_liquidatable(ltv)
+        };
+
+        if liquidatable {
+            self.position.flag_liquidation(Clock::get()?.unix_timestamp);
+        } else {
+            self.position.clear_liquidation_flag();
+        }
+
+        Ok(liquidatable)
+    }
+}
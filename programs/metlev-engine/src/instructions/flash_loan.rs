@@ -0,0 +1,145 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use solana_instructions_sysvar::{load_current_index_checked, load_instruction_at_checked};
+use crate::state::{Config, LendingVault};
+use crate::errors::ProtocolError;
+
+/// Lend idle wSOL for the span of one transaction. A `flash_loan_repay` for
+/// this program must appear later in the same transaction; the runtime
+/// rolls everything back if it fails, so the vault is always made whole.
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    pub borrower: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Address constrained to the instructions sysvar.
+    #[account(address = solana_instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashLoan<'info> {
+    pub fn flash_loan(&mut self, amount: u64) -> Result<()> {
+        require!(!self.config.paused, ProtocolError::ProtocolPaused);
+        self.require_repay_later()?;
+        self.lending_vault.begin_flash_loan(amount)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.wsol_vault.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.destination.to_account_info(),
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+
+    /// Scan the rest of the transaction for a top-level `flash_loan_repay`.
+    fn require_repay_later(&self) -> Result<()> {
+        let ixs = self.instructions.to_account_info();
+        let current = load_current_index_checked(&ixs)? as usize;
+
+        let mut index = current + 1;
+        while let Ok(ix) = load_instruction_at_checked(index, &ixs) {
+            if ix.program_id == crate::ID
+                && ix.data.starts_with(crate::instruction::FlashLoanRepay::DISCRIMINATOR)
+            {
+                return Ok(());
+            }
+            index += 1;
+        }
+        err!(ProtocolError::FlashLoanNotRepaid)
+    }
+}
+
+#[derive(Accounts)]
+pub struct FlashLoanRepay<'info> {
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = payer,
+    )]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashLoanRepay<'info> {
+    /// Pull principal plus `flash_loan_fee_bps` back into wsol_vault; the fee
+    /// goes to protocol_fees.
+    pub fn flash_loan_repay(&mut self) -> Result<()> {
+        let (amount, fee) = self.lending_vault.end_flash_loan()?;
+        let total = amount.checked_add(fee).ok_or(ProtocolError::MathOverflow)?;
+
+        let ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.source.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.wsol_vault.to_account_info(),
+                authority: self.payer.to_account_info(),
+            },
+        );
+        transfer_checked(ctx, total, self.wsol_mint.decimals)
+    }
+}
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Config, LendingVault};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+pub struct FundInsurance<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Authority's wSOL account for a manual top-up. Omit when moving reserves.
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = authority,
+    )]
+    pub source: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundInsurance<'info> {
+    /// Grow the insurance fund, either by earmarking protocol_fees already in
+    /// wsol_vault or by transferring fresh wSOL in from the authority.
+    pub fn fund_insurance(&mut self, amount: u64, from_reserves: bool) -> Result<()> {
+        require!(amount > 0, ProtocolError::InvalidAmount);
+
+        if !from_reserves {
+            let source = self.source.as_ref().ok_or(ProtocolError::InvalidAmount)?;
+            let ctx = CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from:      source.to_account_info(),
+                    mint:      self.wsol_mint.to_account_info(),
+                    to:        self.wsol_vault.to_account_info(),
+                    authority: self.authority.to_account_info(),
+                },
+            );
+            transfer_checked(ctx, amount, self.wsol_mint.decimals)?;
+        }
+
+        self.lending_vault.fund_insurance(amount, from_reserves)
+    }
+}
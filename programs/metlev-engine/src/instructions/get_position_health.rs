@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+use crate::state::{CollateralConfig, LendingVault, Position};
+use crate::errors::ProtocolError;
+use crate::utils::{
+    read_price, calculate_collateral_value, calculate_debt_value, calculate_ltv,
+    calculate_position_health_factor,
+};
+
+/// Snapshot returned by `get_position_health`. Values are in oracle units
+/// (6 decimals); ratios are in basis points.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PositionHealth {
+    pub collateral_value: u64,
+    /// Debt including interest accrued since `debt_last_update`.
+    pub debt_value: u64,
+    /// LTV = debt / (collateral + debt), same as open_position and liquidate.
+    pub ltv: u64,
+    /// (collateral + debt) weighted by `liquidation_threshold`, over debt.
+    /// At or below 10_000 (1.0) the position is liquidatable; u64::MAX with no debt.
+    pub health_factor: u64,
+    pub liquidatable: bool,
+}
+
+#[derive(Accounts)]
+pub struct GetPositionHealth<'info> {
+    #[account(
+        seeds = [Position::SEED_PREFIX, position.owner.as_ref(), position.collateral_mint.as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, position.collateral_mint.as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    #[account(
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+}
+
+impl<'info> GetPositionHealth<'info> {
+    /// Read-only: pending interest is applied to copies of the vault and the
+    /// position, so nothing is written back.
+    pub fn get_position_health(&self) -> Result<PositionHealth> {
+        let now = Clock::get()?.unix_timestamp;
+        let mut lending_vault = (*self.lending_vault).clone();
+        let mut position = (*self.position).clone();
+        lending_vault.accrue_position(&mut position, now)?;
+
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            self.collateral_config.oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+
+        let decimals = self.collateral_config.decimals;
+        let collateral_value = calculate_collateral_value(position.collateral_amount, price, decimals)?;
+        let debt_value = calculate_debt_value(position.debt_amount, price)?;
+        let total_value = collateral_value
+            .checked_add(debt_value)
+            .ok_or(ProtocolError::MathOverflow)?;
+
+        let ltv = if debt_value == 0 { 0 } else { calculate_ltv(total_value, debt_value)? };
+        let health_factor = calculate_position_health_factor(
+            total_value,
+            debt_value,
+            self.collateral_config.liquidation_threshold,
+        )?;
+
+        Ok(PositionHealth {
+            collateral_value,
+            debt_value,
+            ltv,
+            health_factor,
+            liquidatable: debt_value > 0 && self.collateral_config.is_liquidatable(ltv),
+        })
+    }
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+use crate::state::{CollateralConfig, PriceObservation};
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct GetPriceHistory<'info> {
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, mint.as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+}
+
+impl<'info> GetPriceHistory<'info> {
+    /// Read-only: the last prices open_position, increase_leverage,
+    /// liquidate and seize_collateral acted on for this collateral, oldest
+    /// first, so a disputed liquidation can be checked against them.
+    pub fn get_price_history(&self) -> Result<Vec<PriceObservation>> {
+        Ok(self.collateral_config.price_history())
+    }
+}
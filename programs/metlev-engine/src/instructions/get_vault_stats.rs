@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use crate::state::LendingVault;
+
+/// Snapshot returned by `get_vault_stats`. Ratios and yields are in basis
+/// points per year.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VaultStats {
+    pub total_supplied: u64,
+    pub total_borrowed: u64,
+    pub utilization_bps: u64,
+    pub borrow_apy_bps: u64,
+    /// Net of the reserve factor
+    pub supply_apy_bps: u64,
+}
+
+#[derive(Accounts)]
+pub struct GetVaultStats<'info> {
+    #[account(
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+}
+
+impl<'info> GetVaultStats<'info> {
+    /// Read-only view of the rate model at current utilization.
+    pub fn get_vault_stats(&self) -> Result<VaultStats> {
+        let vault = &self.lending_vault;
+        Ok(VaultStats {
+            total_supplied: vault.total_supplied,
+            total_borrowed: vault.total_borrowed,
+            utilization_bps: vault.utilization_bps(),
+            borrow_apy_bps: vault.borrow_apy_bps(),
+            supply_apy_bps: vault.supply_apy_bps(),
+        })
+    }
+}
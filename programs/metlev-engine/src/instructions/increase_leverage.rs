@@ -0,0 +1,244 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::state::{Config, Position, LendingVault, CollateralConfig};
+use crate::errors::ProtocolError;
+use crate::events::LeverageIncreased;
+use crate::utils::{
+    read_price, calculate_collateral_value, calculate_debt_value, calculate_ltv,
+    calculate_position_health_factor, check_vault_invariants, validate_bin_weights,
+};
+use crate::dlmm;
+
+/// Borrow more wSOL against an open position and add it to the existing DLMM
+/// position. The new total debt has to pass the same leverage, LTV and
+/// health checks as open_position.
+#[derive(Accounts)]
+pub struct IncreaseLeverage<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, user.key().as_ref(), wsol_mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+        constraint = !position.closing @ ProtocolError::PositionClosing,
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+        // Leverage is only wired for wSOL debt against SOL collateral. A vault
+        // with any other borrow_mint can take supply but not leverage: that
+        // needs a price feed for the debt asset, a DLMM deposit on the side
+        // holding it, and close/liquidate paths that don't assume wSOL
+        constraint = lending_vault.borrow_mint == wsol_mint.key() @ ProtocolError::UnsupportedBorrowMint,
+    )]
+    pub lending_vault: Box<Account<'info, LendingVault>>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+        constraint = collateral_config.is_enabled() @ ProtocolError::InvalidCollateralType,
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    /// CHECK: key validated against collateral_config.oracle
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Must be the DLMM position recorded at open; the DLMM program
+    /// verifies the rest.
+    #[account(
+        mut,
+        constraint = met_position.key() == position.meteora_position @ ProtocolError::InvalidMeteoraPosition,
+    )]
+    pub met_position: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub lb_pair: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_bitmap_extension: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub reserve: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_upper: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub event_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// CHECK: Address constrained to dlmm::ID.
+    #[account(address = dlmm::ID)]
+    pub dlmm_program: UncheckedAccount<'info>,
+}
+
+impl<'info> IncreaseLeverage<'info> {
+    pub fn increase_leverage(
+        &mut self,
+        amount: u64,
+        active_id: i32,
+        max_active_bin_slippage: i32,
+        bin_liquidity_dist: Vec<dlmm::types::BinLiquidityDistributionByWeight>,
+    ) -> Result<()> {
+        require!(!self.config.opens_paused(), ProtocolError::ProtocolPaused);
+        require!(!self.collateral_config.is_paused(), ProtocolError::CollateralPaused);
+        require!(amount > 0, ProtocolError::InvalidAmount);
+        validate_bin_weights(&bin_liquidity_dist)?;
+
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let debt_amount = self.position.debt_amount
+            .checked_add(amount)
+            .ok_or(ProtocolError::MathOverflow)?;
+        require!(
+            self.collateral_config.validate_position_debt(debt_amount),
+            ProtocolError::PositionDebtCapExceeded
+        );
+
+        // Same units as open_position: leverage 10_000 = debt equal to collateral
+        let collateral_amount = self.position.collateral_amount;
+        require!(collateral_amount > 0, ProtocolError::InsufficientCollateral);
+        let leverage = (debt_amount as u128)
+            .checked_mul(10_000)
+            .map(|v| v / collateral_amount as u128)
+            .and_then(|v| u64::try_from(v).ok())
+            .ok_or(ProtocolError::MathOverflow)?;
+        require!(
+            self.collateral_config.validate_leverage(leverage),
+            ProtocolError::ExceedsMaxLeverage
+        );
+
+        // Enforces available liquidity and the vault's borrow_cap
+        self.lending_vault.borrow(amount)?;
+
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            self.collateral_config.open_oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+        self.collateral_config
+            .check_and_record_price(price, Clock::get()?.unix_timestamp)?;
+
+        let collateral_value = calculate_collateral_value(
+            collateral_amount,
+            price,
+            self.collateral_config.decimals,
+        )?;
+        let debt_value = calculate_debt_value(debt_amount, price)?;
+        let total_value = collateral_value
+            .checked_add(debt_value)
+            .ok_or(ProtocolError::MathOverflow)?;
+
+        let ltv = calculate_ltv(total_value, debt_value)?;
+        require!(
+            self.collateral_config.validate_ltv(ltv),
+            ProtocolError::ExceedsMaxLTV
+        );
+
+        let health_factor = calculate_position_health_factor(
+            total_value,
+            debt_value,
+            self.collateral_config.liquidation_threshold,
+        )?;
+        require!(
+            self.collateral_config.validate_open_health(health_factor),
+            ProtocolError::OpenHealthTooLow
+        );
+
+        self.position.debt_amount = debt_amount;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+        self.cpi_add_liquidity(
+            signer_seeds,
+            dlmm::types::LiquidityOneSideParameter {
+                amount,
+                active_id,
+                max_active_bin_slippage,
+                bin_liquidity_dist,
+            },
+        )?;
+        check_vault_invariants(&self.lending_vault, &mut self.wsol_vault)?;
+
+        emit!(LeverageIncreased {
+            position: self.position.key(),
+            borrowed: amount,
+            debt_amount,
+            leverage_bps: leverage,
+        });
+        Ok(())
+    }
+
+    #[inline(never)]
+    fn cpi_add_liquidity(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+        params: dlmm::types::LiquidityOneSideParameter,
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::AddLiquidityOneSide {
+                position:                   self.met_position.to_account_info(),
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                user_token:      self.wsol_vault.to_account_info(),
+                reserve:         self.reserve.to_account_info(),
+                token_mint:      self.token_mint.to_account_info(),
+                bin_array_lower: self.bin_array_lower.to_account_info(),
+                bin_array_upper: self.bin_array_upper.to_account_info(),
+                sender:          self.lending_vault.to_account_info(),
+                token_program:   self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::add_liquidity_one_side(ctx, params)
+    }
+}
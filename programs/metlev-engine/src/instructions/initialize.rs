@@ -22,7 +22,12 @@ impl<'info> Initialize<'info> {
     pub fn initialize(&mut self, bumps: &InitializeBumps) -> Result<()> {
         self.config.set_inner(Config {
             authority: self.authority.key(),
+            pending_authority: None,
             paused: false,
+            open_paused: false,
+            deposit_paused: false,
+            permissioned_liquidation: false,
+            lb_pair_whitelist: Vec::new(),
             bump: bumps.config,
         });
 
@@ -24,7 +24,8 @@ pub struct InitializeLendingVault<'info> {
     )]
     pub lending_vault: Account<'info, LendingVault>,
 
-    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    /// Borrow asset. Any SPL mint can back the vault; open_position and the
+    /// rest of the leverage path still require wSOL.
     pub wsol_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
@@ -43,11 +44,35 @@ pub struct InitializeLendingVault<'info> {
 
 impl<'info> InitializeLendingVault<'info> {
     pub fn initialize_lending_vault(&mut self, bumps: &InitializeLendingVaultBumps) -> Result<()> {
+        require!(
+            LendingVault::DEFAULT_MAX_BORROW_RATE_BPS > LendingVault::DEFAULT_BASE_RATE_BPS,
+            ProtocolError::InvalidAmount
+        );
+
         self.lending_vault.set_inner(LendingVault {
             authority: self.authority.key(),
+            borrow_mint: self.wsol_mint.key(),
             total_supplied: 0,
             total_borrowed: 0,
-            interest_rate_bps: 30,
+            total_shares: 0,
+            base_rate_bps: LendingVault::DEFAULT_BASE_RATE_BPS,
+            slope1_bps: LendingVault::DEFAULT_SLOPE1_BPS,
+            slope2_bps: LendingVault::DEFAULT_SLOPE2_BPS,
+            optimal_utilization_bps: LendingVault::DEFAULT_OPTIMAL_UTILIZATION_BPS,
+            max_borrow_rate_bps: LendingVault::DEFAULT_MAX_BORROW_RATE_BPS,
+            reserve_factor_bps: LendingVault::DEFAULT_RESERVE_FACTOR_BPS,
+            protocol_fees: 0,
+            insurance_fund: 0,
+            borrow_index: LendingVault::INDEX_ONE,
+            borrow_cap: u64::MAX,
+            supply_cap: u64::MAX,
+            min_vault_reserve_bps: LendingVault::DEFAULT_MIN_VAULT_RESERVE_BPS,
+            supply_cooldown_seconds: LendingVault::DEFAULT_SUPPLY_COOLDOWN_SECONDS,
+            flash_loan_fee_bps: LendingVault::DEFAULT_FLASH_LOAN_FEE_BPS,
+            flash_loan_amount: 0,
+            emergency_withdrawn: 0,
+            withdrawal_queue_head: 0,
+            withdrawal_queue_tail: 0,
             last_update: Clock::get()?.unix_timestamp,
             bump: bumps.lending_vault,
             vault_bump: bumps.wsol_vault,
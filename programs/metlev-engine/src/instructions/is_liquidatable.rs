@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+use crate::state::{CollateralConfig, Position};
+use crate::errors::ProtocolError;
+use crate::utils::{read_price, calculate_collateral_value, calculate_debt_value, calculate_ltv};
+
+/// Cheap pre-filter for liquidation keepers: no mutation, no CPI, just the
+/// LTV check `liquidate` runs, returned as a bool.
+#[derive(Accounts)]
+pub struct IsLiquidatable<'info> {
+    #[account(
+        seeds = [Position::SEED_PREFIX, position.owner.as_ref(), position.collateral_mint.as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, position.collateral_mint.as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+}
+
+impl<'info> IsLiquidatable<'info> {
+    /// Uses the stored debt without accruing pending interest, which can only
+    /// raise LTV; use `get_position_health` for the exact figure.
+    pub fn is_liquidatable(&self) -> Result<bool> {
+        let debt = self.position.debt_amount;
+        if !self.position.is_active() || debt == 0 {
+            return Ok(false);
+        }
+
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            self.collateral_config.liquidation_oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+
+        let decimals = self.collateral_config.decimals;
+        let collateral_value = calculate_collateral_value(self.position.collateral_amount, price, decimals)?;
+        let debt_value = calculate_debt_value(debt, price)?;
+        let total_value = collateral_value
+            .checked_add(debt_value)
+            .ok_or(ProtocolError::MathOverflow)?;
+        let ltv = calculate_ltv(total_value, debt_value)?;
+
+        Ok(self.collateral_config.is_liquidatable(ltv))
+    }
+}
@@ -1,12 +1,40 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{self, Transfer as SystemTransfer};
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
-use crate::state::{Config, Position, LendingVault, CollateralConfig};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Config, Keeper, Position, LendingVault, CollateralConfig};
 use crate::errors::ProtocolError;
-use crate::utils::{read_oracle_price, calculate_collateral_value, calculate_ltv, calculate_liquidation_penalty};
+use crate::events::{BadDebtAbsorbed, PositionLiquidated, StaleOracleLiquidation};
+use crate::instructions::open_position::dlmm_rent_receiver;
+use crate::instructions::seize_collateral::check_seizable;
+use crate::utils::{
+    read_aggregated_price, calculate_position_ltv, calculate_liquidation_penalty, calculate_liquidator_bonus,
+    calculate_collateral_value, calculate_debt_value, calculate_position_health_factor, lb_pair_active_bin, estimate_y_for_x, swap_bin_arrays,
+};
 use crate::dlmm;
 
+/// Haircut on the active-bin estimate of token X before a full liquidation
+/// trusts it to cover the wSOL still needed, to absorb swap fees and price
+/// impact. Below that it swaps all of it.
+pub const LIQUIDATION_SWAP_BUFFER_BPS: u64 = 1_000;
+
+/// Returned by `liquidate`; the same figures are emitted as `PositionLiquidated`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LiquidationResult {
+    pub proceeds: u64,
+    pub debt_repaid: u64,
+    /// Penalty taken from the proceeds, liquidator bonus included
+    pub penalty: u64,
+    pub liquidator_bonus: u64,
+    /// Debt neither proceeds nor collateral could cover; 0 when fully repaid
+    pub bad_debt: u64,
+    pub partial: bool,
+    /// Token X handed to the owner unswapped on a full liquidation
+    pub residual_token_x: u64,
+}
+
 #[derive(Accounts)]
 pub struct Liquidate<'info> {
     #[account(mut)]
@@ -18,6 +46,14 @@ pub struct Liquidate<'info> {
     )]
     pub config: Box<Account<'info, Config>>,
 
+    /// The liquidator's keeper registration; only required while
+    /// `config.permissioned_liquidation` is on.
+    #[account(
+        seeds = [Keeper::SEED_PREFIX, liquidator.key().as_ref()],
+        bump = keeper.bump,
+    )]
+    pub keeper: Option<Box<Account<'info, Keeper>>>,
+
     #[account(address = anchor_spl::token::spl_token::native_mint::id())]
     pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
 
@@ -37,6 +73,7 @@ pub struct Liquidate<'info> {
     pub lending_vault: Box<Account<'info, LendingVault>>,
 
     #[account(
+        mut,
         seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
         bump = collateral_config.bump,
     )]
@@ -58,6 +95,16 @@ pub struct Liquidate<'info> {
     )]
     pub wsol_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    /// Liquidator's wSOL ATA — receives the liquidation penalty.
+    #[account(
+        init_if_needed,
+        payer = liquidator,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = liquidator,
+        associated_token::token_program = token_program,
+    )]
+    pub liquidator_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
     /// CHECK: Position owner to receive remaining collateral.
     #[account(
         mut,
@@ -65,6 +112,16 @@ pub struct Liquidate<'info> {
     )]
     pub position_owner: UncheckedAccount<'info>,
 
+    /// Position owner's wSOL ATA — receives proceeds left after debt and penalty.
+    #[account(
+        init_if_needed,
+        payer = liquidator,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = position_owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
     /// User's collateral vault — holds native SOL.
     /// CHECK: PDA validated by seeds.
     #[account(
@@ -75,10 +132,24 @@ pub struct Liquidate<'info> {
     pub collateral_vault: UncheckedAccount<'info>,
 
     // ── DLMM accounts ──
-    /// CHECK: Verified by the DLMM program.
-    #[account(mut)]
+    /// CHECK: Must be the DLMM position recorded at open; the DLMM program
+    /// verifies the rest.
+    #[account(
+        mut,
+        constraint = met_position.key() == position.meteora_position @ ProtocolError::InvalidMeteoraPosition,
+    )]
     pub met_position: UncheckedAccount<'info>,
 
+    /// Protocol-funded account that paid the DLMM position rent, if it did;
+    /// required then, as the rent goes back to it when the position closes.
+    /// CHECK: PDA validated by seeds; holds lamports only.
+    #[account(
+        mut,
+        seeds = [b"rent_reserve"],
+        bump,
+    )]
+    pub rent_reserve: Option<UncheckedAccount<'info>>,
+
     /// CHECK: Verified by the DLMM program.
     #[account(mut)]
     pub lb_pair: UncheckedAccount<'info>,
@@ -97,6 +168,17 @@ pub struct Liquidate<'info> {
     )]
     pub user_token_x: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    /// Position owner's token X ATA — receives token X a full liquidation
+    /// didn't need to swap.
+    #[account(
+        init_if_needed,
+        payer = liquidator,
+        associated_token::mint = token_x_mint,
+        associated_token::authority = position_owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_token_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
     /// CHECK: Verified by the DLMM program.
     #[account(mut)]
     pub reserve_x: UncheckedAccount<'info>,
@@ -137,98 +219,329 @@ pub struct Liquidate<'info> {
 }
 
 impl<'info> Liquidate<'info> {
+    /// Partial liquidation removes `close_factor_bps` of the range. A full
+    /// one removes all of it (10_000 bps) rather than only the share debt
+    /// plus penalty needs, since DLMM can't close a position holding
+    /// liquidity; it limits the token X swapped instead, handing the rest to
+    /// the owner. That swap is sized from the active bin, so a liquidator
+    /// facing more price impact than the buffer absorbs passes `swap_all`
+    /// to sell all of it, as a reverted CPI can't fall back on its own.
     pub fn liquidate(
         &mut self,
         bumps: &LiquidateBumps,
         from_bin_id: i32,
         to_bin_id: i32,
-    ) -> Result<()> {
-        let oracle_info = self.price_oracle.to_account_info();
-        let (price, _) = read_oracle_price(&oracle_info, self.collateral_config.oracle_max_age)?;
+        swap_all: bool,
+        backup_oracles: &[AccountInfo],
+    ) -> Result<LiquidationResult> {
+        require!(
+            self.config.can_liquidate(self.keeper.is_some()),
+            ProtocolError::Unauthorized
+        );
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
 
-        let collateral_value = calculate_collateral_value(
-            self.position.collateral_amount,
-            price,
-            self.collateral_config.decimals,
-        )?;
-        let debt_value = calculate_collateral_value(
-            self.position.debt_amount,
-            price,
-            self.collateral_config.decimals,
+        let oracle_info = self.price_oracle.to_account_info();
+        let read = read_aggregated_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            &self.collateral_config.backup_oracles,
+            backup_oracles,
+            self.collateral_config.liquidation_oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        );
+        let debt = self.position.debt_amount;
+        let price = match read {
+            Ok((price, _)) => {
+                self.collateral_config
+                    .check_and_record_price(price, Clock::get()?.unix_timestamp)?;
+                price
+            }
+            // During an outage fall back to the discounted last price, but
+            // only for positions clearly under water at it. Not recorded:
+            // it is not an observation.
+            Err(err) if err == ProtocolError::OracleStale.into() => {
+                let price = self.collateral_config.stale_fallback_price().ok_or(err)?;
+                require!(
+                    self.position_health(price, debt)?
+                        <= CollateralConfig::STALE_LIQUIDATION_MAX_HEALTH_BPS,
+                    ProtocolError::OracleStale
+                );
+                emit!(StaleOracleLiquidation {
+                    position: self.position.key(),
+                    fallback_price: price,
+                    last_price_ts: self.collateral_config.last_price_ts,
+                });
+                price
+            }
+            Err(err) => return Err(err),
+        };
+
+        let ltv = self.position_ltv(price, debt)?;
+        check_seizable(
+            &self.collateral_config,
+            &self.position,
+            ltv,
+            Clock::get()?.unix_timestamp,
         )?;
-        // LTV = debt / (collateral + debt) — same formula as open_position
-        let total_value = collateral_value
-            .checked_add(debt_value)
+        // The deeper under water, the larger the penalty (flat unless tiered)
+        let penalty_bps = self.collateral_config
+            .effective_penalty_bps(self.position_health(price, debt)?);
+
+        // Partial liquidation: repay up to close_factor of the debt, as long as
+        // that is enough to bring the position back under the threshold and
+        // doesn't leave dust debt behind. Otherwise unwind the whole position.
+        let close_factor_bps = self.collateral_config.close_factor_bps;
+        let repay_cap = debt
+            .checked_mul(close_factor_bps as u64)
+            .and_then(|v| v.checked_div(10_000))
             .ok_or(ProtocolError::MathOverflow)?;
-        let ltv = calculate_ltv(total_value, debt_value)?;
-        require!(
-            self.collateral_config.is_liquidatable(ltv),
-            ProtocolError::PositionHealthy
-        );
+        let remaining_debt = debt
+            .checked_sub(repay_cap)
+            .ok_or(ProtocolError::MathUnderflow)?;
+        let partial = repay_cap < debt
+            && !self.collateral_config.is_dust_debt(remaining_debt)
+            && !self.collateral_config.is_liquidatable(self.position_ltv(price, remaining_debt)?);
 
-        // Remove DLMM liquidity
         let vault_bump = self.lending_vault.bump;
         let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
-        let debt = self.position.debt_amount;
 
         let vault_before = self.wsol_vault.amount;
+        let x_before = self.user_token_x.amount;
 
-        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id)?;
+        // Remove DLMM liquidity — a close_factor slice of the range, or all of it
+        let bps_to_remove = if partial { close_factor_bps } else { 10_000 };
+        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id, bps_to_remove)?;
         self.cpi_claim_fee(signer_seeds)?;
 
         self.user_token_x.reload()?;
-        let x_balance = self.user_token_x.amount;
-        if x_balance > 0 {
-            self.cpi_swap(signer_seeds, x_balance)?;
-        }
+        let from_position = self.user_token_x.amount.saturating_sub(x_before);
+        let residual_token_x = if from_position == 0 {
+            0
+        } else if partial || swap_all {
+            self.cpi_swap(signer_seeds, from_position)?;
+            0
+        } else {
+            // Only what debt plus penalty needs is swapped; the owner keeps
+            // the rest of the position's token X
+            self.wsol_vault.reload()?;
+            let received = self.wsol_vault.amount.saturating_sub(vault_before);
+            let target = debt
+                .checked_mul(10_000 + penalty_bps as u64)
+                .map(|v| v / 10_000)
+                .ok_or(ProtocolError::MathOverflow)?;
+            self.unwind_token_x(signer_seeds, x_before, from_position, target.saturating_sub(received))?
+        };
 
-        self.cpi_close_position(signer_seeds)?;
+        if !partial {
+            self.cpi_close_position(signer_seeds)?;
+            self.position.subsidized_rent = 0;
+        }
 
         // Repay debt from LP proceeds
         self.wsol_vault.reload()?;
         let vault_after = self.wsol_vault.amount;
         let proceeds = vault_after.saturating_sub(vault_before);
 
-        if proceeds >= debt {
-            self.lending_vault.repay(debt)?;
-        } else if proceeds > 0 {
-            // Bad debt: repay whatever we can, vault absorbs the loss.
-            self.lending_vault.repay(proceeds)?;
+        if partial {
+            let repaid = std::cmp::min(repay_cap, proceeds);
+            require!(repaid > 0, ProtocolError::RepaymentFailed);
+
+            self.lending_vault.repay(repaid)?;
+            self.position.debt_amount = debt
+                .checked_sub(repaid)
+                .ok_or(ProtocolError::MathUnderflow)?;
+            if !self.collateral_config.is_liquidatable(self.position_ltv(price, self.position.debt_amount)?) {
+                self.position.clear_liquidation_flag();
+            }
+
+            let (penalty, liquidator_bonus) = self.distribute_surplus(signer_seeds, proceeds, repaid, penalty_bps)?;
+            return Ok(self.report(LiquidationResult {
+                proceeds,
+                debt_repaid: repaid,
+                penalty,
+                liquidator_bonus,
+                bad_debt: 0,
+                partial: true,
+                residual_token_x,
+            }));
         }
 
-        // Distribute collateral: penalty to liquidator, remainder to owner
-        let collateral = self.position.collateral_amount;
-        if collateral > 0 {
-            let penalty = calculate_liquidation_penalty(
-                collateral,
-                self.collateral_config.liquidation_penalty,
-            )?;
-            let remainder = collateral
-                .checked_sub(penalty)
+        let owner_key = self.position.owner;
+        let mint_key = self.wsol_mint.key();
+        let vault_bump_arr = [bumps.collateral_vault];
+        let collateral_seeds: &[&[&[u8]]] = &[&[
+            b"vault",
+            owner_key.as_ref(),
+            mint_key.as_ref(),
+            &vault_bump_arr,
+        ]];
+
+        // If LP lost value, cover the shortfall from collateral. Anything the
+        // collateral can't cover is bad debt: the insurance fund absorbs it
+        // first and LPs take the rest.
+        let mut bad_debt = 0;
+        if proceeds < debt {
+            let shortfall = debt
+                .checked_sub(proceeds)
                 .ok_or(ProtocolError::MathOverflow)?;
-
-            let owner_key = self.position.owner;
-            let mint_key = self.wsol_mint.key();
-            let vault_bump_arr = [bumps.collateral_vault];
-            let collateral_seeds: &[&[&[u8]]] = &[&[
-                b"vault",
-                owner_key.as_ref(),
-                mint_key.as_ref(),
-                &vault_bump_arr,
-            ]];
-
-            if penalty > 0 {
-                self.transfer_collateral(collateral_seeds, self.liquidator.to_account_info(), penalty)?;
+            let covered = std::cmp::min(shortfall, self.position.collateral_amount);
+            if covered > 0 {
+                self.cover_shortfall(collateral_seeds, covered)?;
+                self.position.collateral_amount = self.position.collateral_amount
+                    .checked_sub(covered)
+                    .ok_or(ProtocolError::MathOverflow)?;
             }
-            if remainder > 0 {
-                self.transfer_collateral(collateral_seeds, self.position_owner.to_account_info(), remainder)?;
+
+            bad_debt = shortfall - covered;
+            if bad_debt > 0 {
+                let (from_insurance, socialized) = self.lending_vault.absorb_bad_debt(bad_debt);
+                emit!(BadDebtAbsorbed {
+                    position: self.position.key(),
+                    bad_debt,
+                    from_insurance,
+                    socialized,
+                });
             }
         }
 
+        self.lending_vault.repay(debt)?;
+        let (penalty, liquidator_bonus) = self.distribute_surplus(signer_seeds, proceeds, debt, penalty_bps)?;
+
+        // Return collateral to the owner
+        let collateral = self.position.collateral_amount;
+        if collateral > 0 {
+            self.transfer_collateral(collateral_seeds, self.position_owner.to_account_info(), collateral)?;
+        }
+
         self.position.debt_amount = 0;
         self.position.collateral_amount = 0;
         self.position.mark_liquidated();
-        Ok(())
+        self.collateral_config.track_position_ended()?;
+        Ok(self.report(LiquidationResult {
+            proceeds,
+            debt_repaid: debt,
+            penalty,
+            liquidator_bonus,
+            bad_debt,
+            partial: false,
+            residual_token_x,
+        }))
+    }
+
+    fn report(&self, result: LiquidationResult) -> LiquidationResult {
+        emit!(PositionLiquidated {
+            position: self.position.key(),
+            owner: self.position.owner,
+            liquidator: self.liquidator.key(),
+            proceeds: result.proceeds,
+            debt_repaid: result.debt_repaid,
+            penalty: result.penalty,
+            liquidator_bonus: result.liquidator_bonus,
+            bad_debt: result.bad_debt,
+            partial: result.partial,
+            residual_token_x: result.residual_token_x,
+            strategy_tag: self.position.strategy_tag,
+        });
+        result
+    }
+
+    /// Turn enough of the position's own token X (`from_position`) into
+    /// wSOL to cover `needed`, valuing token X at the pair's active bin, and
+    /// send what's left of it to the owner. Swaps all of it when the
+    /// estimate can't safely cover `needed`. Token X that was already in the
+    /// shared ATA (`x_before`) is never touched. Returns the token X returned.
+    fn unwind_token_x(
+        &mut self,
+        signer_seeds: &[&[&[u8]]],
+        x_before: u64,
+        from_position: u64,
+        needed: u64,
+    ) -> Result<u64> {
+        if needed > 0 {
+            let (active_id, bin_step) = lb_pair_active_bin(&self.lb_pair.to_account_info())?;
+            let covers = estimate_y_for_x(from_position, active_id, bin_step)
+                .map(|y| y as u128 * (10_000 - LIQUIDATION_SWAP_BUFFER_BPS) as u128 / 10_000 >= needed as u128)
+                .unwrap_or(false);
+            if !covers {
+                self.cpi_swap(signer_seeds, from_position)?;
+                return Ok(0);
+            }
+            self.cpi_swap_exact_out(signer_seeds, from_position, needed)?;
+            self.user_token_x.reload()?;
+        }
+
+        let residual = self.user_token_x.amount.saturating_sub(x_before).min(from_position);
+        if residual > 0 {
+            self.transfer_token_x(signer_seeds, residual)?;
+        }
+        Ok(residual)
+    }
+
+    /// LTV = debt / (collateral + debt) — same formula as open_position
+    fn position_ltv(&self, price: u64, debt_amount: u64) -> Result<u64> {
+        calculate_position_ltv(
+            self.position.collateral_amount,
+            debt_amount,
+            price,
+            self.collateral_config.decimals,
+        )
+    }
+
+    /// Health factor at `price` with `debt_amount` outstanding, weighted by
+    /// the liquidation threshold (10_000 = 1.0)
+    fn position_health(&self, price: u64, debt_amount: u64) -> Result<u64> {
+        let collateral_value = calculate_collateral_value(
+            self.position.collateral_amount,
+            price,
+            self.collateral_config.decimals,
+        )?;
+        let debt_value = calculate_debt_value(debt_amount, price)?;
+        let total_value = collateral_value
+            .checked_add(debt_value)
+            .ok_or(ProtocolError::MathOverflow)?;
+        calculate_position_health_factor(
+            total_value,
+            debt_value,
+            self.collateral_config.liquidation_threshold,
+        )
+    }
+
+    /// Take the `penalty_bps` liquidation penalty out of the realized
+    /// proceeds, capped at the surplus above what was repaid: the
+    /// liquidator's bonus is paid out, the rest of the penalty stays in the
+    /// vault as insurance. Whatever is left goes to the owner. Returns
+    /// (penalty, liquidator bonus).
+    fn distribute_surplus(
+        &mut self,
+        signer_seeds: &[&[&[u8]]],
+        proceeds: u64,
+        repaid: u64,
+        penalty_bps: u16,
+    ) -> Result<(u64, u64)> {
+        let penalty = calculate_liquidation_penalty(proceeds, penalty_bps)?
+            .min(proceeds.saturating_sub(repaid));
+        let bonus = calculate_liquidator_bonus(
+            penalty,
+            self.collateral_config.liquidation_penalty_bps,
+            self.collateral_config.liquidator_bonus_bps,
+        )?;
+        if bonus > 0 {
+            self.transfer_wsol(signer_seeds, self.liquidator_wsol_ata.to_account_info(), bonus)?;
+        }
+        let to_insurance = penalty - bonus;
+        if to_insurance > 0 {
+            self.lending_vault.fund_insurance(to_insurance, false)?;
+        }
+
+        let remaining = proceeds.saturating_sub(
+            repaid.checked_add(penalty).ok_or(ProtocolError::MathOverflow)?,
+        );
+        if remaining > 0 {
+            self.transfer_wsol(signer_seeds, self.owner_wsol_ata.to_account_info(), remaining)?;
+        }
+
+        Ok((penalty, bonus))
     }
 
     #[inline(never)]
@@ -237,6 +550,7 @@ impl<'info> Liquidate<'info> {
         signer_seeds: &[&[&[u8]]],
         from_bin_id: i32,
         to_bin_id: i32,
+        bps_to_remove: u16,
     ) -> Result<()> {
         let ctx = CpiContext::new_with_signer(
             self.dlmm_program.to_account_info(),
@@ -263,7 +577,7 @@ impl<'info> Liquidate<'info> {
             },
             signer_seeds,
         );
-        dlmm::cpi::remove_liquidity_by_range(ctx, from_bin_id, to_bin_id, 10_000)
+        dlmm::cpi::remove_liquidity_by_range(ctx, from_bin_id, to_bin_id, bps_to_remove)
     }
 
     #[inline(never)]
@@ -317,15 +631,53 @@ impl<'info> Liquidate<'info> {
             },
             signer_seeds,
         )
-        .with_remaining_accounts(vec![
-            self.bin_array_lower.to_account_info(),
-            self.bin_array_upper.to_account_info(),
-        ]);
+        .with_remaining_accounts(swap_bin_arrays(
+            &self.bin_array_lower.to_account_info(),
+            &self.bin_array_upper.to_account_info(),
+        ));
         dlmm::cpi::swap(ctx, amount, 0)
     }
 
+    #[inline(never)]
+    fn cpi_swap_exact_out(&self, signer_seeds: &[&[&[u8]]], max_in: u64, out: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::SwapExactOut {
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                user_token_in:   self.user_token_x.to_account_info(),
+                user_token_out:  self.wsol_vault.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                oracle:          self.oracle.to_account_info(),
+                host_fee_in:     None,
+                user:            self.lending_vault.to_account_info(),
+                token_x_program: self.token_program.to_account_info(),
+                token_y_program: self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        )
+        .with_remaining_accounts(swap_bin_arrays(
+            &self.bin_array_lower.to_account_info(),
+            &self.bin_array_upper.to_account_info(),
+        ));
+        dlmm::cpi::swap_exact_out(ctx, max_in, out)
+    }
+
     #[inline(never)]
     fn cpi_close_position(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let rent_receiver = dlmm_rent_receiver(
+            &self.position,
+            &self.rent_reserve,
+            &self.liquidator.to_account_info(),
+        )?;
         let ctx = CpiContext::new_with_signer(
             self.dlmm_program.to_account_info(),
             dlmm::cpi::accounts::ClosePosition {
@@ -334,7 +686,7 @@ impl<'info> Liquidate<'info> {
                 bin_array_lower: self.bin_array_lower.to_account_info(),
                 bin_array_upper: self.bin_array_upper.to_account_info(),
                 sender:          self.lending_vault.to_account_info(),
-                rent_receiver:   self.liquidator.to_account_info(),
+                rent_receiver,
                 event_authority: self.event_authority.to_account_info(),
                 program:         self.dlmm_program.to_account_info(),
             },
@@ -343,6 +695,71 @@ impl<'info> Liquidate<'info> {
         dlmm::cpi::close_position(ctx)
     }
 
+    /// Transfer SOL from collateral vault to wsol_vault and sync_native
+    /// to cover the debt shortfall when LP lost value.
+    #[inline(never)]
+    fn cover_shortfall(&self, collateral_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                SystemTransfer {
+                    from: self.collateral_vault.to_account_info(),
+                    to:   self.wsol_vault.to_account_info(),
+                },
+                collateral_seeds,
+            ),
+            amount,
+        )?;
+
+        // Sync wSOL token balance to match new lamports
+        let ix = anchor_spl::token::spl_token::instruction::sync_native(
+            &anchor_spl::token::spl_token::id(),
+            &self.wsol_vault.key(),
+        )
+        .map_err(|_| ProtocolError::MathOverflow)?;
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[self.wsol_vault.to_account_info()],
+        )?;
+
+        Ok(())
+    }
+
+    #[inline(never)]
+    fn transfer_wsol(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+        destination: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.wsol_vault.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        destination,
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+
+    #[inline(never)]
+    fn transfer_token_x(&self, signer_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.user_token_x.to_account_info(),
+                mint:      self.token_x_mint.to_account_info(),
+                to:        self.owner_token_x.to_account_info(),
+                authority: self.lending_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.token_x_mint.decimals)
+    }
+
     #[inline(never)]
     fn transfer_collateral(
         &self,
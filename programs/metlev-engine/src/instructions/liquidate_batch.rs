@@ -0,0 +1,369 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Config, Keeper, Position, LendingVault, CollateralConfig};
+use crate::errors::ProtocolError;
+use crate::events::CollateralSeized;
+use crate::instructions::seize_collateral::{check_seizable, seizure_split};
+use crate::utils::{read_aggregated_price, calculate_position_ltv, seizure_lowers_ltv};
+
+/// Most positions one `liquidate_batch` call will take, to stay inside the
+/// default compute budget.
+pub const MAX_LIQUIDATION_BATCH: usize = 8;
+
+/// Remaining accounts per position: the position, then its collateral vault.
+const ACCOUNTS_PER_POSITION: usize = 2;
+
+/// Returned by `liquidate_batch`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BatchLiquidationResult {
+    /// Positions seized; the rest were skipped as not yet liquidatable
+    pub liquidated: u8,
+    pub debt_repaid: u64,
+    /// Collateral taken, penalty included
+    pub collateral_seized: u64,
+}
+
+/// `seize_collateral` over several wSOL positions at once. The positions and
+/// their collateral vaults are passed as (position, collateral_vault) pairs
+/// in remaining accounts, followed by the collateral's configured
+/// `backup_oracles` if any, read as `liquidate` reads them; all of them
+/// share the wSOL collateral config and oracle below. Like
+/// `seize_collateral` it has no stale-oracle fallback: the liquidator pays
+/// in at the price read, so during an outage positions go through
+/// `liquidate`.
+///
+/// This is the seizure path only, not a batched `liquidate`: DLMM positions
+/// are left open and every repayment comes out of the liquidator's wSOL.
+/// Positions backed by any other collateral mint can't be batched and go
+/// through `liquidate` or `seize_collateral` one at a time. Each position may
+/// appear once per call (`DuplicatePosition`), so the close factor caps what
+/// a single batch repays on it.
+#[derive(Accounts)]
+pub struct LiquidateBatch<'info> {
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// The liquidator's keeper registration; only required while
+    /// `config.permissioned_liquidation` is on.
+    #[account(
+        seeds = [Keeper::SEED_PREFIX, liquidator.key().as_ref()],
+        bump = keeper.bump,
+    )]
+    pub keeper: Option<Box<Account<'info, Keeper>>>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Box<Account<'info, LendingVault>>,
+
+    #[account(
+        mut,
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Liquidator's wSOL — source of every repayment.
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = liquidator,
+    )]
+    pub liquidator_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> LiquidateBatch<'info> {
+    /// Seize each listed position that is liquidatable now, repaying the
+    /// close-factor share of its debt (less if its collateral or the
+    /// liquidator's wSOL can't cover that). Healthy positions, ones still in
+    /// their grace period or too new, and inactive ones are skipped rather
+    /// than failing the batch; accounts that aren't what they claim to be
+    /// still fail it.
+    pub fn liquidate_batch(
+        &mut self,
+        remaining: &[AccountInfo<'info>],
+    ) -> Result<BatchLiquidationResult> {
+        require!(
+            self.config.can_liquidate(self.keeper.is_some()),
+            ProtocolError::Unauthorized
+        );
+        let backup_count = self.collateral_config.backup_oracles
+            .iter()
+            .filter(|key| **key != Pubkey::default())
+            .count();
+        let (entries, backup_oracles) = remaining.split_at(
+            remaining.len().checked_sub(backup_count).ok_or(ProtocolError::InvalidAmount)?,
+        );
+        require!(
+            !entries.is_empty()
+                && entries.len() % ACCOUNTS_PER_POSITION == 0
+                && entries.len() / ACCOUNTS_PER_POSITION <= MAX_LIQUIDATION_BATCH,
+            ProtocolError::InvalidAmount
+        );
+        // A repeated entry would be seized again on the debt the first pass
+        // left, getting around the close factor
+        for (i, pair) in entries.chunks(ACCOUNTS_PER_POSITION).enumerate() {
+            require!(
+                entries[..i * ACCOUNTS_PER_POSITION]
+                    .chunks(ACCOUNTS_PER_POSITION)
+                    .all(|earlier| earlier[0].key() != pair[0].key()),
+                ProtocolError::DuplicatePosition
+            );
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        self.lending_vault.accrue_interest(now)?;
+
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_aggregated_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            &self.collateral_config.backup_oracles,
+            backup_oracles,
+            self.collateral_config.liquidation_oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+        self.collateral_config.check_and_record_price(price, now)?;
+
+        let mut result = BatchLiquidationResult {
+            liquidated: 0,
+            debt_repaid: 0,
+            collateral_seized: 0,
+        };
+        let mut wsol_available = self.liquidator_wsol_ata.amount;
+
+        for pair in entries.chunks(ACCOUNTS_PER_POSITION) {
+            let (position_info, collateral_vault) = (&pair[0], &pair[1]);
+            let mut position = self.load_position(position_info)?;
+            let vault_bump = self.check_collateral_vault(&position, collateral_vault)?;
+            if !position.is_active() {
+                continue;
+            }
+
+            self.lending_vault.accrue_position(&mut position, now)?;
+
+            let debt = position.debt_amount;
+            let ltv = calculate_position_ltv(
+                position.collateral_amount,
+                debt,
+                price,
+                self.collateral_config.decimals,
+            )?;
+            if check_seizable(&self.collateral_config, &position, ltv, now).is_err() {
+                // Still persist the accrued interest
+                self.store_position(position_info, &position)?;
+                continue;
+            }
+
+            let repay_amount = self
+                .max_repay(&position)?
+                .min(wsol_available);
+            if repay_amount == 0 || self.collateral_config.is_dust_debt(debt - repay_amount) {
+                self.store_position(position_info, &position)?;
+                continue;
+            }
+            let (seize_amount, bonus, to_insurance) =
+                seizure_split(&self.collateral_config, repay_amount)?;
+            if !seizure_lowers_ltv(
+                position.collateral_amount,
+                debt,
+                repay_amount,
+                seize_amount,
+                price,
+                self.collateral_config.decimals,
+            )? {
+                self.store_position(position_info, &position)?;
+                continue;
+            }
+
+            self.transfer_repayment(repay_amount)?;
+            wsol_available -= repay_amount;
+            self.lending_vault.repay(repay_amount)?;
+            position.debt_amount = debt
+                .checked_sub(repay_amount)
+                .ok_or(ProtocolError::MathUnderflow)?;
+
+            let mint_key = self.wsol_mint.key();
+            let vault_bump_arr = [vault_bump];
+            let collateral_seeds: &[&[&[u8]]] = &[&[
+                b"vault",
+                position.owner.as_ref(),
+                mint_key.as_ref(),
+                &vault_bump_arr,
+            ]];
+            self.transfer_collateral(
+                collateral_vault,
+                collateral_seeds,
+                self.liquidator.to_account_info(),
+                repay_amount + bonus,
+            )?;
+            if to_insurance > 0 {
+                self.transfer_collateral(
+                    collateral_vault,
+                    collateral_seeds,
+                    self.wsol_vault.to_account_info(),
+                    to_insurance,
+                )?;
+                self.sync_wsol_vault()?;
+                self.lending_vault.fund_insurance(to_insurance, false)?;
+            }
+            position.collateral_amount = position.collateral_amount
+                .checked_sub(seize_amount)
+                .ok_or(ProtocolError::MathUnderflow)?;
+            self.store_position(position_info, &position)?;
+
+            result.liquidated += 1;
+            result.debt_repaid = result.debt_repaid
+                .checked_add(repay_amount)
+                .ok_or(ProtocolError::MathOverflow)?;
+            result.collateral_seized = result.collateral_seized
+                .checked_add(seize_amount)
+                .ok_or(ProtocolError::MathOverflow)?;
+            emit!(CollateralSeized {
+                position: position_info.key(),
+                owner: position.owner,
+                liquidator: self.liquidator.key(),
+                debt_repaid: repay_amount,
+                collateral_seized: seize_amount,
+                liquidator_bonus: bonus,
+                to_insurance,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Deserialize a writable wSOL `Position` owned by this program at its PDA.
+    fn load_position(&self, info: &AccountInfo<'info>) -> Result<Position> {
+        require!(info.is_writable, anchor_lang::error::ErrorCode::ConstraintMut);
+        require_keys_eq!(*info.owner, crate::ID, ProtocolError::InvalidOwner);
+        let position = Position::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        let expected = Pubkey::create_program_address(
+            &[
+                Position::SEED_PREFIX,
+                position.owner.as_ref(),
+                self.wsol_mint.key().as_ref(),
+                &[position.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ProtocolError::InvalidOwner)?;
+        require_keys_eq!(info.key(), expected, ProtocolError::InvalidOwner);
+        Ok(position)
+    }
+
+    fn store_position(&self, info: &AccountInfo<'info>, position: &Position) -> Result<()> {
+        let mut data = info.try_borrow_mut_data()?;
+        position.try_serialize(&mut &mut data[..])
+    }
+
+    /// Check `collateral_vault` is the position owner's SOL vault and return its bump.
+    fn check_collateral_vault(
+        &self,
+        position: &Position,
+        collateral_vault: &AccountInfo<'info>,
+    ) -> Result<u8> {
+        let (expected, bump) = Pubkey::find_program_address(
+            &[b"vault", position.owner.as_ref(), self.wsol_mint.key().as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(collateral_vault.key(), expected, ProtocolError::InvalidOwner);
+        require!(collateral_vault.is_writable, anchor_lang::error::ErrorCode::ConstraintMut);
+        Ok(bump)
+    }
+
+    /// Close-factor share of the debt, reduced so the seizure (penalty
+    /// included) fits in the position's collateral.
+    fn max_repay(&self, position: &Position) -> Result<u64> {
+        let repay_cap = position.debt_amount
+            .checked_mul(self.collateral_config.close_factor_bps as u64)
+            .and_then(|v| v.checked_div(10_000))
+            .ok_or(ProtocolError::MathOverflow)?;
+        let collateral_cap = (position.collateral_amount as u128) * 10_000
+            / (10_000 + self.collateral_config.liquidation_penalty_bps as u128);
+        Ok(repay_cap.min(collateral_cap as u64))
+    }
+
+    #[inline(never)]
+    fn transfer_repayment(&self, amount: u64) -> Result<()> {
+        let ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.liquidator_wsol_ata.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.wsol_vault.to_account_info(),
+                authority: self.liquidator.to_account_info(),
+            },
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+
+    #[inline(never)]
+    fn transfer_collateral(
+        &self,
+        collateral_vault: &AccountInfo<'info>,
+        collateral_seeds: &[&[&[u8]]],
+        destination: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                SystemTransfer {
+                    from: collateral_vault.clone(),
+                    to:   destination,
+                },
+                collateral_seeds,
+            ),
+            amount,
+        )
+    }
+
+    /// Sync wSOL token balance to match lamports sent straight to the vault
+    #[inline(never)]
+    fn sync_wsol_vault(&self) -> Result<()> {
+        let ix = anchor_spl::token::spl_token::instruction::sync_native(
+            &anchor_spl::token::spl_token::id(),
+            &self.wsol_vault.key(),
+        )
+        .map_err(|_| ProtocolError::MathOverflow)?;
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[self.wsol_vault.to_account_info()],
+        )?;
+        Ok(())
+    }
+}
@@ -30,13 +30,21 @@ pub struct InitializeMockOracle<'info> {
 }
 
 impl<'info> InitializeMockOracle<'info> {
-    pub fn initialize_mock_oracle(&mut self, bumps: &InitializeMockOracleBumps, price: u64) -> Result<()> {
+    pub fn initialize_mock_oracle(
+        &mut self,
+        bumps: &InitializeMockOracleBumps,
+        price: u64,
+        decimals: u8,
+    ) -> Result<()> {
+        require!(decimals <= MockOracle::MAX_DECIMALS, ProtocolError::InvalidAmount);
         self.mock_oracle.set_inner(
             MockOracle {
                 authority: self.authority.key(),
-                price: price,
-                decimals: 6,
+                price,
+                decimals,
                 timestamp: Clock::get()?.unix_timestamp,
+                heartbeat_seconds: 0,
+                force_stale: false,
                 bump: bumps.mock_oracle
             }
         );
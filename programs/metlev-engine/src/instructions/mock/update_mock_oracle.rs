@@ -1,26 +1,22 @@
 use anchor_lang::prelude::*;
-use crate::state::{MockOracle, Config};
+use crate::state::MockOracle;
 use crate::errors::ProtocolError;
 
+/// Gated on the oracle's own authority rather than the protocol's, so test
+/// harnesses can run independent feeds owned by different keypairs.
 #[derive(Accounts)]
 pub struct UpdateMockOracle<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    #[account(
-        seeds = [Config::SEED_PREFIX],
-        bump = config.bump,
-        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
-    )]
-    pub config: Account<'info, Config>,
-
     /// CHECK: used only as PDA seed
     pub mint: UncheckedAccount<'info>,
 
     #[account(
         mut,
         seeds = [MockOracle::SEED_PREFIX, mint.key().as_ref()],
-        bump = mock_oracle.bump
+        bump = mock_oracle.bump,
+        has_one = authority @ ProtocolError::Unauthorized,
     )]
     pub mock_oracle: Account<'info, MockOracle>,
 }
@@ -31,4 +27,21 @@ impl<'info> UpdateMockOracle<'info> {
         self.mock_oracle.timestamp = Clock::get()?.unix_timestamp;
         Ok(())
     }
+
+    /// Lets tests drive the stale-oracle paths deterministically.
+    pub fn set_mock_oracle_staleness(
+        &mut self,
+        heartbeat_seconds: u64,
+        force_stale: bool,
+    ) -> Result<()> {
+        self.mock_oracle.heartbeat_seconds = heartbeat_seconds;
+        self.mock_oracle.force_stale = force_stale;
+        Ok(())
+    }
+
+    pub fn transfer_oracle_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        require!(new_authority != Pubkey::default(), ProtocolError::InvalidAmount);
+        self.mock_oracle.authority = new_authority;
+        Ok(())
+    }
 }
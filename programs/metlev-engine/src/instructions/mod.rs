@@ -2,26 +2,78 @@ pub mod mock;
 pub mod initialize;
 pub mod initialize_lending_vault;
 pub mod register_collateral;
+pub mod deregister_collateral;
 pub mod deposit_sol_collateral;
 pub mod deposit_token_collateral;
+pub mod add_collateral;
 pub mod open_position;
 pub mod close_position;
+pub mod rebalance_position;
+pub mod claim_position_fees;
+pub mod repay;
+pub mod decrease_leverage;
+pub mod increase_leverage;
 pub mod withdraw_collateral;
+pub mod withdraw_excess_collateral;
 pub mod liquidate;
+pub mod seize_collateral;
+pub mod liquidate_batch;
+pub mod get_position_health;
+pub mod is_liquidatable;
+pub mod flag_liquidation;
+pub mod get_vault_stats;
+pub mod get_price_history;
+pub mod preview_borrow;
 pub mod update_config;
+pub mod add_keeper;
+pub mod remove_keeper;
+pub mod collect_reserves;
+pub mod fund_insurance;
+pub mod flash_loan;
+pub mod emergency_withdraw;
+pub mod sync_wsol_vault;
+pub mod poke_accrual;
 pub mod supply;
 pub mod withdraw;
+pub mod request_withdrawal;
+pub mod process_withdrawal;
 
 pub use mock::*;
 pub use initialize::*;
 pub use initialize_lending_vault::*;
 pub use register_collateral::*;
+pub use deregister_collateral::*;
 pub use deposit_sol_collateral::*;
 pub use deposit_token_collateral::*;
+pub use add_collateral::*;
 pub use open_position::*;
 pub use close_position::*;
+pub use rebalance_position::*;
+pub use claim_position_fees::*;
+pub use repay::*;
+pub use decrease_leverage::*;
+pub use increase_leverage::*;
 pub use withdraw_collateral::*;
+pub use withdraw_excess_collateral::*;
 pub use liquidate::*;
+pub use seize_collateral::*;
+pub use liquidate_batch::*;
+pub use get_position_health::*;
+pub use is_liquidatable::*;
+pub use flag_liquidation::*;
+pub use get_vault_stats::*;
+pub use get_price_history::*;
+pub use preview_borrow::*;
 pub use update_config::*;
+pub use add_keeper::*;
+pub use remove_keeper::*;
+pub use collect_reserves::*;
+pub use fund_insurance::*;
+pub use flash_loan::*;
+pub use emergency_withdraw::*;
+pub use sync_wsol_vault::*;
+pub use poke_accrual::*;
 pub use supply::*;
 pub use withdraw::*;
+pub use request_withdrawal::*;
+pub use process_withdrawal::*;
@@ -2,9 +2,116 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use crate::state::{Config, Position, LendingVault, CollateralConfig};
 use crate::errors::ProtocolError;
-use crate::utils::{read_oracle_price, calculate_collateral_value, calculate_ltv};
+use crate::events::PositionOpened;
+use crate::utils::{
+    read_aggregated_price, calculate_collateral_value, calculate_debt_value, calculate_ltv,
+    calculate_position_health_factor, validate_bin_distribution, validate_bin_weights, check_vault_invariants,
+    lb_pair_mints, calculate_borrow_amount,
+};
 use crate::dlmm;
 
+/// Returned by `open_position` so clients see the outcome without
+/// re-fetching the `Position`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OpenPositionResult {
+    /// Position debt after the borrow, in wSOL lamports
+    pub debt_amount: u64,
+    /// LTV at open, debt / (collateral + debt) in basis points
+    pub ltv_bps: u64,
+    pub dlmm_position: Pubkey,
+}
+
+/// borrow = collateral * leverage / 10_000, rejected below the collateral's
+/// minimum position borrow.
+/// leverage 10_000 = 1x (borrow == collateral), 20_000 = 2x, etc.
+pub(crate) fn borrow_for_leverage(
+    collateral_config: &CollateralConfig,
+    collateral_amount: u64,
+    leverage: u64,
+) -> Result<u64> {
+    let borrow_amount = calculate_borrow_amount(collateral_amount, leverage)?;
+    require!(
+        collateral_config.validate_position_borrow(borrow_amount),
+        ProtocolError::InvalidAmount
+    );
+    Ok(borrow_amount)
+}
+
+/// LTV a position lands at with `debt_amount` owed, after the max-LTV and open-health checks.
+pub(crate) fn check_open_ltv(
+    collateral_config: &CollateralConfig,
+    collateral_amount: u64,
+    debt_amount: u64,
+    price: u64,
+) -> Result<u64> {
+    let collateral_value = calculate_collateral_value(
+        collateral_amount,
+        price,
+        collateral_config.decimals,
+    )?;
+    let debt_value = calculate_debt_value(debt_amount, price)?;
+
+    // LTV = debt / (collateral + debt)
+    // For 2x leverage: debt = 2 * collateral → LTV = 2/3 = 66.7%
+    // For 3x leverage: debt = 3 * collateral → LTV = 3/4 = 75%
+    let total_value = collateral_value
+        .checked_add(debt_value)
+        .ok_or(ProtocolError::MathOverflow)?;
+    let ltv = calculate_ltv(total_value, debt_value)?;
+    require!(collateral_config.validate_ltv(ltv), ProtocolError::ExceedsMaxLTV);
+
+    // Leave a buffer above the liquidation threshold so oracle jitter
+    // can't make a fresh position liquidatable.
+    let health_factor = calculate_position_health_factor(
+        total_value,
+        debt_value,
+        collateral_config.liquidation_threshold,
+    )?;
+    require!(
+        collateral_config.validate_open_health(health_factor),
+        ProtocolError::OpenHealthTooLow
+    );
+    Ok(ltv)
+}
+
+/// DLMM can deposit less than asked when bin amounts round down; the
+/// leftover stays in wsol_vault, so it is handed back to the lending vault
+/// and taken off the position's debt (never below zero). `deposited` is what
+/// was passed to `add_liquidity_one_side` and `vault_before` the wsol_vault
+/// balance just before it. Returns what DLMM consumed.
+pub(crate) fn settle_unused_wsol(
+    lending_vault: &mut LendingVault,
+    position: &mut Position,
+    wsol_vault: &mut InterfaceAccount<TokenAccount>,
+    vault_before: u64,
+    deposited: u64,
+) -> Result<u64> {
+    wsol_vault.reload()?;
+    let consumed = vault_before.saturating_sub(wsol_vault.amount);
+    let unused = deposited.saturating_sub(consumed).min(position.debt_amount);
+    if unused > 0 {
+        lending_vault.repay(unused)?;
+        position.debt_amount -= unused;
+    }
+    Ok(consumed.min(deposited))
+}
+
+/// Where a closing DLMM position's rent goes: back to the rent reserve when
+/// it paid that rent at open, `default` otherwise.
+pub(crate) fn dlmm_rent_receiver<'info>(
+    position: &Position,
+    rent_reserve: &Option<UncheckedAccount<'info>>,
+    default: &AccountInfo<'info>,
+) -> Result<AccountInfo<'info>> {
+    if position.subsidized_rent == 0 {
+        return Ok(default.clone());
+    }
+    rent_reserve
+        .as_ref()
+        .map(|r| r.to_account_info())
+        .ok_or_else(|| error!(ProtocolError::RentReserveRequired))
+}
+
 #[derive(Accounts)]
 pub struct OpenPosition<'info> {
     #[account(mut)]
@@ -25,6 +132,9 @@ pub struct OpenPosition<'info> {
         bump = position.bump,
         constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
         constraint = position.is_active() @ ProtocolError::PositionNotActive,
+        // A second open would orphan the first DLMM position and its debt
+        constraint = position.meteora_position == Pubkey::default()
+            && position.debt_amount == 0 @ ProtocolError::PositionAlreadyOpen,
     )]
     pub position: Account<'info, Position>,
 
@@ -32,6 +142,11 @@ pub struct OpenPosition<'info> {
         mut,
         seeds = [LendingVault::SEED_PREFIX],
         bump = lending_vault.bump,
+        // Leverage is only wired for wSOL debt against SOL collateral. A vault
+        // with any other borrow_mint can take supply but not leverage: that
+        // needs a price feed for the debt asset, a DLMM deposit on the side
+        // holding it, and close/liquidate paths that don't assume wSOL
+        constraint = lending_vault.borrow_mint == wsol_mint.key() @ ProtocolError::UnsupportedBorrowMint,
     )]
     pub lending_vault: Account<'info, LendingVault>,
 
@@ -45,6 +160,7 @@ pub struct OpenPosition<'info> {
     pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
         bump = collateral_config.bump,
         constraint = collateral_config.is_enabled() @ ProtocolError::InvalidCollateralType,
@@ -60,8 +176,21 @@ pub struct OpenPosition<'info> {
     #[account(mut)]
     pub met_position: Signer<'info>,
 
-    /// CHECK: Verified by the DLMM program.
-    #[account(mut)]
+    /// Protocol-funded account that pays the DLMM position rent in place of
+    /// the user when opening with `subsidize_rent`.
+    /// CHECK: PDA validated by seeds; holds lamports only.
+    #[account(
+        mut,
+        seeds = [b"rent_reserve"],
+        bump,
+    )]
+    pub rent_reserve: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Verified by the DLMM program; must be on the config whitelist.
+    #[account(
+        mut,
+        constraint = config.is_pair_whitelisted(&lb_pair.key()) @ ProtocolError::PairNotWhitelisted,
+    )]
     pub lb_pair: UncheckedAccount<'info>,
 
     /// CHECK: Verified by the DLMM program.
@@ -98,77 +227,93 @@ pub struct OpenPosition<'info> {
 }
 
 impl<'info> OpenPosition<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn open(
         &mut self,
+        bumps: &OpenPositionBumps,
         leverage: u64,
         lower_bin_id: i32,
         width: i32,
         active_id: i32,
         max_active_bin_slippage: i32,
         bin_liquidity_dist: Vec<dlmm::types::BinLiquidityDistributionByWeight>,
-    ) -> Result<()> {
-        require!(!self.config.paused, ProtocolError::ProtocolPaused);
-
-        // borrow = collateral * leverage / 10_000
-        // leverage 10_000 = 1x (borrow == collateral), 20_000 = 2x, etc.
-        let borrow_amount = self
-            .position
-            .collateral_amount
-            .checked_mul(leverage)
-            .and_then(|v| v.checked_div(10_000))
-            .ok_or(ProtocolError::MathOverflow)?;
+        subsidize_rent: bool,
+        strategy_tag: Option<[u8; 16]>,
+        backup_oracles: &[AccountInfo],
+    ) -> Result<OpenPositionResult> {
+        require!(!self.config.opens_paused(), ProtocolError::ProtocolPaused);
+        require!(!self.collateral_config.is_paused(), ProtocolError::CollateralPaused);
+        require!(
+            self.collateral_config.validate_leverage(leverage),
+            ProtocolError::ExceedsMaxLeverage
+        );
+        validate_bin_distribution(
+            &bin_liquidity_dist,
+            lower_bin_id,
+            width,
+            active_id,
+            self.wsol_is_token_x()?,
+        )?;
+        validate_bin_weights(&bin_liquidity_dist)?;
 
-        require!(borrow_amount > 0, ProtocolError::InvalidAmount);
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
 
+        let borrow_amount = borrow_for_leverage(
+            &self.collateral_config,
+            self.position.collateral_amount,
+            leverage,
+        )?;
+        let debt_amount = self.position.debt_amount
+            .checked_add(borrow_amount)
+            .ok_or(ProtocolError::MathOverflow)?;
+        require!(
+            self.collateral_config.validate_position_debt(debt_amount),
+            ProtocolError::PositionDebtCapExceeded
+        );
+
+        // Booked before the DLMM CPIs; a failure there reverts it along with
+        // the rest of the instruction, so the borrow and the deposit must
+        // stay in one instruction
         self.lending_vault.borrow(borrow_amount)?;
 
         let oracle_info = self.price_oracle.to_account_info();
-        let (price, _) = read_oracle_price(&oracle_info, self.collateral_config.oracle_max_age)?;
+        let (price, _) = read_aggregated_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            &self.collateral_config.backup_oracles,
+            backup_oracles,
+            self.collateral_config.open_oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+        self.collateral_config
+            .check_and_record_price(price, Clock::get()?.unix_timestamp)?;
 
-        let collateral_value = calculate_collateral_value(
+        let ltv = check_open_ltv(
+            &self.collateral_config,
             self.position.collateral_amount,
+            debt_amount,
             price,
-            self.collateral_config.decimals,
         )?;
-        let debt_value = calculate_collateral_value(
-            borrow_amount,
-            price,
-            self.collateral_config.decimals,
-        )?;
-
-        // LTV = debt / (collateral + debt)
-        // For 2x leverage: debt = 2 * collateral → LTV = 2/3 = 66.7%
-        // For 3x leverage: debt = 3 * collateral → LTV = 3/4 = 75%
-        let total_value = collateral_value
-            .checked_add(debt_value)
-            .ok_or(ProtocolError::MathOverflow)?;
-        let ltv = calculate_ltv(total_value, debt_value)?;
-        require!(
-            self.collateral_config.validate_ltv(ltv),
-            ProtocolError::ExceedsMaxLTV
-        );
 
-        self.position.debt_amount = borrow_amount;
+        self.position.debt_amount = debt_amount;
 
         let vault_bump = self.lending_vault.bump;
         let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
 
-        let init_pos_ctx = CpiContext::new_with_signer(
-            self.dlmm_program.to_account_info(),
-            dlmm::cpi::accounts::InitializePosition {
-                position:        self.met_position.to_account_info(),
-                lb_pair:         self.lb_pair.to_account_info(),
-                payer:           self.user.to_account_info(),
-                owner:           self.lending_vault.to_account_info(),
-                system_program:  self.system_program.to_account_info(),
-                rent:            self.rent.to_account_info(),
-                event_authority: self.event_authority.to_account_info(),
-                program:         self.dlmm_program.to_account_info(),
-            },
-            signer_seeds,
-        );
-        dlmm::cpi::initialize_position(init_pos_ctx, lower_bin_id, width)?;
-
+        self.position.subsidized_rent = if subsidize_rent {
+            self.init_subsidized_dlmm_position(bumps, signer_seeds, lower_bin_id, width)?
+        } else {
+            self.cpi_initialize_position(
+                self.user.to_account_info(),
+                signer_seeds,
+                lower_bin_id,
+                width,
+            )?;
+            0
+        };
+
+        let vault_before = self.wsol_vault.amount;
         let add_liq_ctx = CpiContext::new_with_signer(
             self.dlmm_program.to_account_info(),
             dlmm::cpi::accounts::AddLiquidityOneSide {
@@ -201,7 +346,97 @@ impl<'info> OpenPosition<'info> {
         )?;
 
         self.position.meteora_position = self.met_position.key();
+        self.position.opened_at = Clock::get()?.unix_timestamp;
+        if let Some(tag) = strategy_tag {
+            self.position.strategy_tag = tag;
+        }
+
+        let consumed = settle_unused_wsol(
+            &mut self.lending_vault,
+            &mut self.position,
+            &mut self.wsol_vault,
+            vault_before,
+            borrow_amount,
+        )?;
+        let ltv = if consumed < borrow_amount {
+            check_open_ltv(
+                &self.collateral_config,
+                self.position.collateral_amount,
+                self.position.debt_amount,
+                price,
+            )?
+        } else {
+            ltv
+        };
+
+        check_vault_invariants(&self.lending_vault, &mut self.wsol_vault)?;
+
+        emit!(PositionOpened {
+            position: self.position.key(),
+            owner: self.position.owner,
+            met_position: self.position.meteora_position,
+            debt_amount: self.position.debt_amount,
+            strategy_tag: self.position.strategy_tag,
+        });
+
+        Ok(OpenPositionResult {
+            debt_amount: self.position.debt_amount,
+            ltv_bps: ltv,
+            dlmm_position: self.position.meteora_position,
+        })
+    }
+
+    /// Have the rent reserve pay for the DLMM position and return what it
+    /// paid, which the position owes back at close.
+    fn init_subsidized_dlmm_position(
+        &self,
+        bumps: &OpenPositionBumps,
+        vault_seeds: &[&[&[u8]]],
+        lower_bin_id: i32,
+        width: i32,
+    ) -> Result<u64> {
+        let rent_reserve = self
+            .rent_reserve
+            .as_ref()
+            .ok_or(ProtocolError::RentReserveRequired)?
+            .to_account_info();
+        let reserve_bump = [bumps.rent_reserve.ok_or(ProtocolError::RentReserveRequired)?];
+        let signer_seeds: &[&[&[u8]]] = &[vault_seeds[0], &[b"rent_reserve", &reserve_bump]];
+
+        let reserve_before = rent_reserve.lamports();
+        self.cpi_initialize_position(rent_reserve.clone(), signer_seeds, lower_bin_id, width)?;
+        Ok(reserve_before.saturating_sub(rent_reserve.lamports()))
+    }
+
+    #[inline(never)]
+    fn cpi_initialize_position(
+        &self,
+        payer: AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+        lower_bin_id: i32,
+        width: i32,
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::InitializePosition {
+                position:        self.met_position.to_account_info(),
+                lb_pair:         self.lb_pair.to_account_info(),
+                payer,
+                owner:           self.lending_vault.to_account_info(),
+                system_program:  self.system_program.to_account_info(),
+                rent:            self.rent.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::initialize_position(ctx, lower_bin_id, width)
+    }
 
-        Ok(())
+    /// Which side of the pair wSOL is on, read from the LB pair account.
+    #[inline(never)]
+    fn wsol_is_token_x(&self) -> Result<bool> {
+        let (token_x_mint, _) = lb_pair_mints(&self.lb_pair.to_account_info())?;
+        Ok(token_x_mint == self.wsol_mint.key())
     }
 }
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+use crate::state::{LendingVault, LpPosition};
+use crate::events::InterestAccrued;
+
+#[derive(Accounts)]
+pub struct PokeAccrual<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    /// LP the accrual is reported against; omitted for a plain vault poke.
+    #[account(
+        seeds = [LpPosition::SEED_PREFIX, lp_position.lp.as_ref()],
+        bump = lp_position.bump,
+    )]
+    pub lp_position: Option<Account<'info, LpPosition>>,
+}
+
+impl<'info> PokeAccrual<'info> {
+    /// Permissionless: compound the borrow index up to now and book the
+    /// interest outstanding borrows earned into total_borrowed,
+    /// total_supplied and protocol_fees, so a dormant vault's share price,
+    /// utilization and APYs are current. Position debts catch up to the
+    /// index the next time they are touched, so there is nothing per
+    /// position to advance. Calling it again in the same second changes
+    /// nothing.
+    pub fn poke_accrual(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let index_delta = self.lending_vault.accrue_interest(now)?;
+
+        emit!(InterestAccrued {
+            lp: self.lp_position.as_ref().map(|p| p.lp).unwrap_or_default(),
+            index_delta,
+            borrow_index: self.lending_vault.borrow_index,
+            total_supplied: self.lending_vault.total_supplied,
+            total_borrowed: self.lending_vault.total_borrowed,
+            timestamp: now,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::spl_token::native_mint;
+use crate::state::{Config, CollateralConfig, LendingVault};
+use crate::errors::ProtocolError;
+use crate::utils::read_price;
+use super::{borrow_for_leverage, check_open_ltv};
+
+/// Returned by `preview_borrow`: what `open_position` would do with the
+/// given collateral and leverage right now.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BorrowPreview {
+    /// wSOL lamports the open would borrow; 0 if leverage or the minimum
+    /// position borrow rejected it
+    pub borrow_amount: u64,
+    /// total_supplied - total_borrowed before the borrow
+    pub available_liquidity: u64,
+    /// LTV at open in basis points; 0 unless every check passed
+    pub ltv_bps: u64,
+    pub ok: bool,
+    /// Error code open_position would fail with (6000 + ProtocolError); 0 when ok
+    pub error_code: u32,
+}
+
+#[derive(Accounts)]
+pub struct PreviewBorrow<'info> {
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, native_mint::id().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+}
+
+impl<'info> PreviewBorrow<'info> {
+    /// Read-only: runs the open_position checks in the same order against
+    /// copies of the accounts, and reports the first one that fails instead
+    /// of erroring.
+    pub fn preview_borrow(&self, collateral_amount: u64, leverage: u64) -> Result<BorrowPreview> {
+        let mut preview = BorrowPreview {
+            borrow_amount: 0,
+            available_liquidity: self.lending_vault.available_liquidity(),
+            ltv_bps: 0,
+            ok: false,
+            error_code: 0,
+        };
+
+        match self.run_open_checks(collateral_amount, leverage, &mut preview) {
+            Ok(()) => preview.ok = true,
+            Err(err) => preview.error_code = error_code(&err),
+        }
+        Ok(preview)
+    }
+
+    fn run_open_checks(
+        &self,
+        collateral_amount: u64,
+        leverage: u64,
+        preview: &mut BorrowPreview,
+    ) -> Result<()> {
+        let mut collateral_config = (*self.collateral_config).clone();
+
+        require!(!self.config.opens_paused(), ProtocolError::ProtocolPaused);
+        require!(collateral_config.is_enabled(), ProtocolError::InvalidCollateralType);
+        require!(!collateral_config.is_paused(), ProtocolError::CollateralPaused);
+        require!(
+            collateral_config.validate_leverage(leverage),
+            ProtocolError::ExceedsMaxLeverage
+        );
+
+        preview.borrow_amount = borrow_for_leverage(&collateral_config, collateral_amount, leverage)?;
+        require!(
+            collateral_config.validate_position_debt(preview.borrow_amount),
+            ProtocolError::PositionDebtCapExceeded
+        );
+        self.lending_vault.check_borrow(preview.borrow_amount)?;
+
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_price(
+            collateral_config.oracle_kind,
+            &oracle_info,
+            collateral_config.open_oracle_max_age,
+            collateral_config.max_confidence_bps,
+        )?;
+        collateral_config.check_and_record_price(price, Clock::get()?.unix_timestamp)?;
+
+        preview.ltv_bps = check_open_ltv(
+            &collateral_config,
+            collateral_amount,
+            preview.borrow_amount,
+            price,
+        )?;
+        Ok(())
+    }
+}
+
+fn error_code(err: &Error) -> u32 {
+    match err {
+        Error::AnchorError(e) => e.error_code_number,
+        Error::ProgramError(e) => u64::from(e.program_error.clone()) as u32,
+    }
+}
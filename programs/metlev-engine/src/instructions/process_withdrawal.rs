@@ -0,0 +1,127 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Config, LendingVault, WithdrawalRequest};
+use crate::errors::ProtocolError;
+use crate::events::InterestAccrued;
+
+/// Permissionless: anyone can push the queue forward as liquidity returns.
+#[derive(Accounts)]
+pub struct ProcessWithdrawal<'info> {
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    /// Only the request at the head of the queue can be served
+    #[account(
+        mut,
+        seeds = [
+            WithdrawalRequest::SEED_PREFIX,
+            lending_vault.withdrawal_queue_head.to_le_bytes().as_ref(),
+        ],
+        bump = withdrawal_request.bump,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    /// CHECK: receives the request's rent once it is fully served
+    #[account(
+        mut,
+        address = withdrawal_request.lp @ ProtocolError::InvalidOwner,
+    )]
+    pub lp: UncheckedAccount<'info>,
+
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = lp,
+    )]
+    pub lp_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ProcessWithdrawal<'info> {
+    /// Redeem as much of the head request as the vault's idle liquidity can
+    /// pay; protocol_fees and the insurance fund in wsol_vault are never
+    /// used. A partly served request keeps its place; a fully served one is
+    /// closed and the queue moves on.
+    pub fn process_withdrawal(&mut self) -> Result<()> {
+        require!(!self.config.paused, ProtocolError::ProtocolPaused);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let index_delta = self.lending_vault.accrue_interest(current_time)?;
+        emit!(InterestAccrued {
+            lp: self.lp.key(),
+            index_delta,
+            borrow_index: self.lending_vault.borrow_index,
+            total_supplied: self.lending_vault.total_supplied,
+            total_borrowed: self.lending_vault.total_borrowed,
+            timestamp: current_time,
+        });
+
+        let shares = self.withdrawal_request.shares;
+        let owed = self.lending_vault.assets_for_shares(shares)?;
+        let amount = owed
+            .min(self.lending_vault.available_liquidity())
+            .min(self.wsol_vault.amount);
+        require!(amount > 0 || owed == 0, ProtocolError::InsufficientLiquidity);
+
+        let burned = if amount == owed {
+            shares
+        } else {
+            self.lending_vault.shares_for_withdrawal(amount)?.min(shares)
+        };
+        self.lending_vault.redeem(amount, burned)?;
+        self.withdrawal_request.shares = shares
+            .checked_sub(burned)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        if amount > 0 {
+            let lending_vault_bump = self.lending_vault.bump;
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                LendingVault::SEED_PREFIX,
+                &[lending_vault_bump],
+            ]];
+            let ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from:      self.wsol_vault.to_account_info(),
+                    mint:      self.wsol_mint.to_account_info(),
+                    to:        self.lp_wsol_ata.to_account_info(),
+                    authority: self.lending_vault.to_account_info(),
+                },
+                signer_seeds,
+            );
+            transfer_checked(ctx, amount, self.wsol_mint.decimals)?;
+        }
+
+        if self.withdrawal_request.shares == 0 {
+            self.lending_vault.dequeue_withdrawal();
+            self.withdrawal_request.close(self.lp.to_account_info())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::state::{Config, Position, LendingVault};
+use crate::errors::ProtocolError;
+use crate::instructions::open_position::{dlmm_rent_receiver, settle_unused_wsol};
+use crate::utils::{
+    validate_bin_distribution, validate_bin_weights, check_vault_invariants, lb_pair_mints,
+    swap_bin_arrays,
+};
+use crate::dlmm;
+
+/// Move a position's liquidity to a new bin range around the current active
+/// bin. A DLMM position's range is fixed at creation, so the old position is
+/// emptied and closed and a fresh one is opened in its place. Debt only
+/// changes when DLMM leaves part of the recovered wSOL undeposited, which is
+/// repaid as in `open_position`.
+#[derive(Accounts)]
+pub struct RebalancePosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, user.key().as_ref(), wsol_mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+        constraint = !position.closing @ ProtocolError::PositionClosing,
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Box<Account<'info, LendingVault>>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Must be the DLMM position recorded at open; the DLMM program
+    /// verifies the rest.
+    #[account(
+        mut,
+        constraint = met_position.key() == position.meteora_position @ ProtocolError::InvalidMeteoraPosition,
+    )]
+    pub met_position: UncheckedAccount<'info>,
+
+    /// Protocol-funded account that paid the DLMM position rent, if it did;
+    /// required then, as the rent goes back to it when the position closes.
+    /// CHECK: PDA validated by seeds; holds lamports only.
+    #[account(
+        mut,
+        seeds = [b"rent_reserve"],
+        bump,
+    )]
+    pub rent_reserve: Option<UncheckedAccount<'info>>,
+
+    /// Replacement DLMM position, created by this instruction.
+    #[account(mut)]
+    pub new_met_position: Signer<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub lb_pair: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_bitmap_extension: Option<UncheckedAccount<'info>>,
+
+    /// Any X-side tokens returned by remove_liquidity land here, then get swapped to wSOL.
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = token_x_mint,
+        associated_token::authority = lending_vault,
+        associated_token::token_program = token_program,
+    )]
+    pub user_token_x: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub reserve_x: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub reserve_y: UncheckedAccount<'info>,
+
+    pub token_x_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub token_y_mint: UncheckedAccount<'info>,
+
+    /// Bin arrays covering the old range.
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub bin_array_upper: UncheckedAccount<'info>,
+
+    /// Bin arrays covering the new range (may be the same as the old ones).
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub new_bin_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    #[account(mut)]
+    pub new_bin_array_upper: UncheckedAccount<'info>,
+
+    /// CHECK: Pool TWAP oracle required by DLMM swap to update price tracking.
+    #[account(mut)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Verified by the DLMM program.
+    pub event_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Address constrained to dlmm::ID.
+    #[account(address = dlmm::ID)]
+    pub dlmm_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> RebalancePosition<'info> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn rebalance(
+        &mut self,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        lower_bin_id: i32,
+        width: i32,
+        active_id: i32,
+        max_active_bin_slippage: i32,
+        bin_liquidity_dist: Vec<dlmm::types::BinLiquidityDistributionByWeight>,
+    ) -> Result<()> {
+        require!(!self.config.opens_paused(), ProtocolError::ProtocolPaused);
+        validate_bin_distribution(
+            &bin_liquidity_dist,
+            lower_bin_id,
+            width,
+            active_id,
+            self.wsol_is_token_x()?,
+        )?;
+        validate_bin_weights(&bin_liquidity_dist)?;
+
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let vault_bump = self.lending_vault.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[LendingVault::SEED_PREFIX, &[vault_bump]]];
+
+        let vault_before = self.wsol_vault.amount;
+        let x_before = self.user_token_x.amount;
+
+        self.cpi_remove_liquidity(signer_seeds, from_bin_id, to_bin_id)?;
+        self.cpi_claim_fee(signer_seeds)?;
+
+        self.user_token_x.reload()?;
+        let from_position = self.user_token_x.amount.saturating_sub(x_before);
+        if from_position > 0 {
+            self.cpi_swap(signer_seeds, from_position)?;
+        }
+
+        // The old position's rent goes back to whoever paid it; the user
+        // pays for the replacement
+        self.cpi_close_position(signer_seeds)?;
+        self.position.subsidized_rent = 0;
+
+        // Everything recovered from the old range, fees included, goes back in.
+        self.wsol_vault.reload()?;
+        let proceeds = self.wsol_vault.amount.saturating_sub(vault_before);
+        require!(proceeds > 0, ProtocolError::InvalidAmount);
+
+        self.cpi_initialize_position(signer_seeds, lower_bin_id, width)?;
+        let deposit_before = self.wsol_vault.amount;
+        self.cpi_add_liquidity(
+            signer_seeds,
+            dlmm::types::LiquidityOneSideParameter {
+                amount: proceeds,
+                active_id,
+                max_active_bin_slippage,
+                bin_liquidity_dist,
+            },
+        )?;
+
+        self.position.meteora_position = self.new_met_position.key();
+
+        settle_unused_wsol(
+            &mut self.lending_vault,
+            &mut self.position,
+            &mut self.wsol_vault,
+            deposit_before,
+            proceeds,
+        )?;
+        check_vault_invariants(&self.lending_vault, &mut self.wsol_vault)
+    }
+
+    /// Which side of the pair wSOL is on, read from the LB pair account.
+    #[inline(never)]
+    fn wsol_is_token_x(&self) -> Result<bool> {
+        let (token_x_mint, _) = lb_pair_mints(&self.lb_pair.to_account_info())?;
+        Ok(token_x_mint == self.wsol_mint.key())
+    }
+
+    #[inline(never)]
+    fn cpi_remove_liquidity(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+        from_bin_id: i32,
+        to_bin_id: i32,
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::RemoveLiquidityByRange {
+                position:                   self.met_position.to_account_info(),
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                user_token_x:    self.user_token_x.to_account_info(),
+                user_token_y:    self.wsol_vault.to_account_info(),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                bin_array_lower: self.bin_array_lower.to_account_info(),
+                bin_array_upper: self.bin_array_upper.to_account_info(),
+                sender:          self.lending_vault.to_account_info(),
+                token_x_program: self.token_program.to_account_info(),
+                token_y_program: self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::remove_liquidity_by_range(ctx, from_bin_id, to_bin_id, 10_000)
+    }
+
+    #[inline(never)]
+    fn cpi_claim_fee(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::ClaimFee {
+                lb_pair:         self.lb_pair.to_account_info(),
+                position:        self.met_position.to_account_info(),
+                bin_array_lower: self.bin_array_lower.to_account_info(),
+                bin_array_upper: self.bin_array_upper.to_account_info(),
+                sender:          self.lending_vault.to_account_info(),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                user_token_x:    self.user_token_x.to_account_info(),
+                user_token_y:    self.wsol_vault.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                token_program:   self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::claim_fee(ctx)
+    }
+
+    #[inline(never)]
+    fn cpi_swap(&self, signer_seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::Swap {
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                reserve_x:       self.reserve_x.to_account_info(),
+                reserve_y:       self.reserve_y.to_account_info(),
+                user_token_in:   self.user_token_x.to_account_info(),
+                user_token_out:  self.wsol_vault.to_account_info(),
+                token_x_mint:    self.token_x_mint.to_account_info(),
+                token_y_mint:    self.token_y_mint.to_account_info(),
+                oracle:          self.oracle.to_account_info(),
+                host_fee_in:     None,
+                user:            self.lending_vault.to_account_info(),
+                token_x_program: self.token_program.to_account_info(),
+                token_y_program: self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        )
+        .with_remaining_accounts(swap_bin_arrays(
+            &self.bin_array_lower.to_account_info(),
+            &self.bin_array_upper.to_account_info(),
+        ));
+        dlmm::cpi::swap(ctx, amount, 0)
+    }
+
+    #[inline(never)]
+    fn cpi_close_position(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let rent_receiver = dlmm_rent_receiver(
+            &self.position,
+            &self.rent_reserve,
+            &self.user.to_account_info(),
+        )?;
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::ClosePosition {
+                position:        self.met_position.to_account_info(),
+                lb_pair:         self.lb_pair.to_account_info(),
+                bin_array_lower: self.bin_array_lower.to_account_info(),
+                bin_array_upper: self.bin_array_upper.to_account_info(),
+                sender:          self.lending_vault.to_account_info(),
+                rent_receiver,
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::close_position(ctx)
+    }
+
+    #[inline(never)]
+    fn cpi_initialize_position(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+        lower_bin_id: i32,
+        width: i32,
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::InitializePosition {
+                position:        self.new_met_position.to_account_info(),
+                lb_pair:         self.lb_pair.to_account_info(),
+                payer:           self.user.to_account_info(),
+                owner:           self.lending_vault.to_account_info(),
+                system_program:  self.system_program.to_account_info(),
+                rent:            self.rent.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::initialize_position(ctx, lower_bin_id, width)
+    }
+
+    /// wSOL is token Y, so it goes into reserve_y, as in close_position.
+    #[inline(never)]
+    fn cpi_add_liquidity(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+        params: dlmm::types::LiquidityOneSideParameter,
+    ) -> Result<()> {
+        let ctx = CpiContext::new_with_signer(
+            self.dlmm_program.to_account_info(),
+            dlmm::cpi::accounts::AddLiquidityOneSide {
+                position:                   self.new_met_position.to_account_info(),
+                lb_pair:                    self.lb_pair.to_account_info(),
+                bin_array_bitmap_extension: self
+                    .bin_array_bitmap_extension
+                    .as_ref()
+                    .map(|a| a.to_account_info()),
+                user_token:      self.wsol_vault.to_account_info(),
+                reserve:         self.reserve_y.to_account_info(),
+                token_mint:      self.token_y_mint.to_account_info(),
+                bin_array_lower: self.new_bin_array_lower.to_account_info(),
+                bin_array_upper: self.new_bin_array_upper.to_account_info(),
+                sender:          self.lending_vault.to_account_info(),
+                token_program:   self.token_program.to_account_info(),
+                event_authority: self.event_authority.to_account_info(),
+                program:         self.dlmm_program.to_account_info(),
+            },
+            signer_seeds,
+        );
+        dlmm::cpi::add_liquidity_one_side(ctx, params)
+    }
+}
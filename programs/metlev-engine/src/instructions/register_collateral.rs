@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{Mint, TokenInterface};
-use crate::state::{Config, CollateralConfig};
+use crate::state::{Config, CollateralConfig, OracleKind, PriceObservation};
 use crate::errors::ProtocolError;
 
 #[derive(Accounts)]
@@ -34,37 +34,72 @@ pub struct RegisterCollateral<'info> {
 }
 
 impl<'info> RegisterCollateral<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn register(
         &mut self,
         bumps: &RegisterCollateralBumps,
         oracle: Pubkey,
+        oracle_kind: OracleKind,
         max_ltv: u16,
         liquidation_threshold: u16,
-        liquidation_penalty: u16,
+        liquidation_penalty_bps: u16,
         min_deposit: u64,
         interest_rate_bps: u16,
         oracle_max_age: u64,
+        max_leverage_bps: u64,
     ) -> Result<()> {
         require!(
             liquidation_threshold > max_ltv,
             ProtocolError::InvalidLiquidationThreshold
         );
         require!(
-            liquidation_penalty <= 2000, // Max 20%
+            liquidation_threshold <= 10_000 && max_ltv < 10_000,
+            ProtocolError::InvalidLiquidationThreshold
+        );
+        require!(
+            liquidation_penalty_bps <= 2000, // Max 20%
             ProtocolError::InvalidAmount
         );
+        require!(max_leverage_bps > 0, ProtocolError::InvalidAmount);
 
         self.collateral_config.set_inner(CollateralConfig {
             mint: self.mint.key(),
             oracle,
+            oracle_kind,
+            backup_oracles: [Pubkey::default(); 2],
             max_ltv,
             liquidation_threshold,
-            liquidation_penalty,
+            liquidation_penalty_bps,
+            // The whole penalty goes to the liquidator until the authority
+            // routes part of it to insurance
+            liquidator_bonus_bps: liquidation_penalty_bps,
+            min_penalty_bps: 0,
+            max_penalty_bps: 0,
+            close_factor_bps: CollateralConfig::DEFAULT_CLOSE_FACTOR_BPS,
+            hard_liquidation_threshold: CollateralConfig::DEFAULT_HARD_LIQUIDATION_THRESHOLD,
+            grace_period_seconds: 0,
+            min_position_age_seconds: 0,
+            max_leverage_bps,
+            min_open_health_bps: CollateralConfig::DEFAULT_MIN_OPEN_HEALTH_BPS,
             min_deposit,
+            min_position_borrow: 0,
+            max_position_debt: u64::MAX,
             interest_rate_bps,
             oracle_max_age,
+            open_oracle_max_age: oracle_max_age,
+            liquidation_oracle_max_age: oracle_max_age,
+            max_confidence_bps: CollateralConfig::DEFAULT_MAX_CONFIDENCE_BPS,
+            max_price_deviation_bps: CollateralConfig::DEFAULT_MAX_PRICE_DEVIATION_BPS,
+            stale_liquidation_discount_bps: 0,
+            last_price: 0,
+            last_price_ts: 0,
+            price_history: [PriceObservation::default(); CollateralConfig::PRICE_HISTORY_LEN],
+            price_history_head: 0,
+            swap_lb_pair: Pubkey::default(),
+            active_positions: 0,
             decimals: self.mint.decimals,
             enabled: true,
+            paused: false,
             bump: bumps.collateral_config,
         });
 
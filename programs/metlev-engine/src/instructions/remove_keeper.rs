@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use crate::state::{Config, Keeper};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+#[instruction(keeper: Pubkey)]
+pub struct RemoveKeeper<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Closed back to the authority, which revokes the keeper.
+    #[account(
+        mut,
+        close = authority,
+        seeds = [Keeper::SEED_PREFIX, keeper.as_ref()],
+        bump = keeper_registration.bump,
+    )]
+    pub keeper_registration: Account<'info, Keeper>,
+}
+
+impl<'info> RemoveKeeper<'info> {
+    /// Rent goes back to the authority through the `close` constraint.
+    pub fn remove_keeper(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
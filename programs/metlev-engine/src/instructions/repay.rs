@@ -0,0 +1,117 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{CollateralConfig, LendingVault, Position};
+use crate::errors::ProtocolError;
+use crate::utils::check_vault_invariants;
+
+#[derive(Accounts)]
+pub struct Repay<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, user.key().as_ref(), wsol_mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = user,
+    )]
+    pub user_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Repay<'info> {
+    /// Pay down part (or all) of the position's debt from the user's wSOL ATA
+    /// without touching the DLMM position. Not gated on the pause flag, like
+    /// `add_collateral`, since it only reduces risk.
+    pub fn repay(&mut self, amount: u64) -> Result<()> {
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        require!(
+            amount > 0 && amount <= self.position.debt_amount,
+            ProtocolError::InvalidAmount
+        );
+        require!(
+            !self.collateral_config.is_dust_debt(self.position.debt_amount - amount),
+            ProtocolError::InvalidAmount
+        );
+
+        self.position.debt_amount = self.position.debt_amount
+            .checked_sub(amount)
+            .ok_or(ProtocolError::MathUnderflow)?;
+        self.lending_vault.repay(amount)?;
+        self.transfer_repayment(amount)
+    }
+
+    /// Pay off the whole debt, interest accrued up to now included, and keep
+    /// the DLMM position open and Active: the position becomes unleveraged
+    /// and keeps earning fees on the owner's own capital. Unlike
+    /// `close_position`, no liquidity is removed. Returns the amount repaid.
+    pub fn repay_full(&mut self) -> Result<u64> {
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let amount = self.position.debt_amount;
+        require!(amount > 0, ProtocolError::InvalidAmount);
+
+        self.position.debt_amount = 0;
+        // The sub-lamport interest owed on the repaid debt is forgiven
+        self.position.debt_interest_remainder = 0;
+        // With no debt the position can't be liquidatable
+        self.position.clear_liquidation_flag();
+        self.lending_vault.repay(amount)?;
+        self.transfer_repayment(amount)?;
+        Ok(amount)
+    }
+
+    fn transfer_repayment(&mut self, amount: u64) -> Result<()> {
+        let ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.user_wsol_ata.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.wsol_vault.to_account_info(),
+                authority: self.user.to_account_info(),
+            },
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)?;
+
+        check_vault_invariants(&self.lending_vault, &mut self.wsol_vault)
+    }
+}
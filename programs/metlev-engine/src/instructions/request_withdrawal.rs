@@ -0,0 +1,79 @@
+use anchor_lang::prelude::*;
+use crate::state::{Config, LpPosition, LendingVault, WithdrawalRequest};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LpPosition::SEED_PREFIX, signer.key().as_ref()],
+        bump = lp_position.bump,
+        constraint = lp_position.lp == signer.key() @ ProtocolError::InvalidOwner,
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = WithdrawalRequest::DISCRIMINATOR.len() + WithdrawalRequest::INIT_SPACE,
+        seeds = [
+            WithdrawalRequest::SEED_PREFIX,
+            lending_vault.withdrawal_queue_tail.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RequestWithdrawal<'info> {
+    /// Queue an exit for when the vault can't pay right away. The shares keep
+    /// earning until they are redeemed, in ticket order, by process_withdrawal.
+    pub fn request_withdrawal(&mut self, bumps: &RequestWithdrawalBumps, shares: u64) -> Result<()> {
+        require!(!self.config.paused, ProtocolError::ProtocolPaused);
+        require!(
+            shares > 0 && shares <= self.lp_position.shares,
+            ProtocolError::InvalidAmount
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            self.lp_position.supply_cooldown_elapsed(
+                self.lending_vault.supply_cooldown_seconds,
+                now,
+            ),
+            ProtocolError::SupplyCooldown
+        );
+        self.lp_position.shares = self.lp_position.shares
+            .checked_sub(shares)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        let ticket = self.lending_vault.enqueue_withdrawal()?;
+        self.withdrawal_request.set_inner(WithdrawalRequest {
+            lp: self.signer.key(),
+            ticket,
+            shares,
+            created_at: now,
+            bump: bumps.withdrawal_request,
+        });
+
+        Ok(())
+    }
+}
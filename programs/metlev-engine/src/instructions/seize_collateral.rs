@@ -0,0 +1,314 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use crate::state::{Config, Keeper, Position, LendingVault, CollateralConfig};
+use crate::errors::ProtocolError;
+use crate::events::CollateralSeized;
+use crate::utils::{
+    read_price, calculate_position_ltv, calculate_seize_amount, calculate_liquidator_bonus,
+    seizure_lowers_ltv,
+};
+
+/// "Purchase" liquidation: the liquidator repays part of an unhealthy
+/// position's debt in wSOL from their own account and takes the same amount
+/// of collateral plus the liquidation penalty straight out of the borrower's
+/// vault; the part of the penalty above the liquidator bonus is wrapped into
+/// the insurance fund. The DLMM position is left alone, so there are no
+/// forced swaps.
+#[derive(Accounts)]
+pub struct SeizeCollateral<'info> {
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// The liquidator's keeper registration; only required while
+    /// `config.permissioned_liquidation` is on.
+    #[account(
+        seeds = [Keeper::SEED_PREFIX, liquidator.key().as_ref()],
+        bump = keeper.bump,
+    )]
+    pub keeper: Option<Box<Account<'info, Keeper>>>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, position.owner.key().as_ref(), wsol_mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Box<Account<'info, LendingVault>>,
+
+    #[account(
+        mut,
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Liquidator's wSOL — source of the repayment.
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = liquidator,
+    )]
+    pub liquidator_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Borrower's collateral vault — holds native SOL.
+    /// CHECK: PDA validated by seeds.
+    #[account(
+        mut,
+        seeds = [b"vault", position.owner.key().as_ref(), wsol_mint.key().as_ref()],
+        bump,
+    )]
+    pub collateral_vault: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Fails unless `position`, at `ltv`, can be seized now. Shared with
+/// `liquidate`, and with `liquidate_batch`, which skips the positions this
+/// rejects.
+pub(crate) fn check_seizable(
+    collateral_config: &CollateralConfig,
+    position: &Position,
+    ltv: u64,
+    now: i64,
+) -> Result<()> {
+    require!(
+        collateral_config.is_liquidatable(ltv),
+        ProtocolError::PositionHealthy
+    );
+    // Below the hard threshold a position must first be flagged and
+    // then survive the grace period, so a momentary spike can't take it
+    require!(
+        collateral_config.is_hard_liquidatable(ltv)
+            || position.grace_period_elapsed(collateral_config.grace_period_seconds, now),
+        ProtocolError::LiquidationGracePeriod
+    );
+    // Nor can a freshly opened one, so opening and self-liquidating
+    // against a nudged oracle doesn't pay out the liquidation bonus
+    require!(
+        collateral_config.is_hard_liquidatable(ltv)
+            || position.min_age_elapsed(collateral_config.min_position_age_seconds, now),
+        ProtocolError::PositionTooNew
+    );
+    Ok(())
+}
+
+/// Collateral seized for `repay_amount` of debt, split as (total seized,
+/// liquidator bonus, insurance share of the penalty).
+pub(crate) fn seizure_split(
+    collateral_config: &CollateralConfig,
+    repay_amount: u64,
+) -> Result<(u64, u64, u64)> {
+    let seize_amount = calculate_seize_amount(
+        repay_amount,
+        collateral_config.liquidation_penalty_bps,
+    )?;
+    let penalty = seize_amount - repay_amount;
+    let bonus = calculate_liquidator_bonus(
+        penalty,
+        collateral_config.liquidation_penalty_bps,
+        collateral_config.liquidator_bonus_bps,
+    )?;
+    Ok((seize_amount, bonus, penalty - bonus))
+}
+
+impl<'info> SeizeCollateral<'info> {
+    pub fn seize_collateral(
+        &mut self,
+        bumps: &SeizeCollateralBumps,
+        repay_amount: u64,
+    ) -> Result<()> {
+        require!(
+            self.config.can_liquidate(self.keeper.is_some()),
+            ProtocolError::Unauthorized
+        );
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        let oracle_info = self.price_oracle.to_account_info();
+        let (price, _) = read_price(
+            self.collateral_config.oracle_kind,
+            &oracle_info,
+            self.collateral_config.liquidation_oracle_max_age,
+            self.collateral_config.max_confidence_bps,
+        )?;
+        self.collateral_config
+            .check_and_record_price(price, Clock::get()?.unix_timestamp)?;
+
+        let debt = self.position.debt_amount;
+        let ltv = calculate_position_ltv(
+            self.position.collateral_amount,
+            debt,
+            price,
+            self.collateral_config.decimals,
+        )?;
+        check_seizable(
+            &self.collateral_config,
+            &self.position,
+            ltv,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Same per-call limit as a partial liquidation
+        let repay_cap = debt
+            .checked_mul(self.collateral_config.close_factor_bps as u64)
+            .and_then(|v| v.checked_div(10_000))
+            .ok_or(ProtocolError::MathOverflow)?;
+        require!(
+            repay_amount > 0 && repay_amount <= repay_cap,
+            ProtocolError::InvalidAmount
+        );
+        require!(
+            !self.collateral_config.is_dust_debt(debt - repay_amount),
+            ProtocolError::InvalidAmount
+        );
+
+        let (seize_amount, bonus, to_insurance) =
+            seizure_split(&self.collateral_config, repay_amount)?;
+        require!(
+            seize_amount <= self.position.collateral_amount,
+            ProtocolError::InsufficientCollateral
+        );
+        // Otherwise the position stays seizable and the penalty is charged
+        // again on the next call
+        require!(
+            seizure_lowers_ltv(
+                self.position.collateral_amount,
+                debt,
+                repay_amount,
+                seize_amount,
+                price,
+                self.collateral_config.decimals,
+            )?,
+            ProtocolError::SeizureWorsensLtv
+        );
+
+        self.transfer_repayment(repay_amount)?;
+        self.lending_vault.repay(repay_amount)?;
+        self.position.debt_amount = debt
+            .checked_sub(repay_amount)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        let owner_key = self.position.owner;
+        let mint_key = self.wsol_mint.key();
+        let vault_bump_arr = [bumps.collateral_vault];
+        let collateral_seeds: &[&[&[u8]]] = &[&[
+            b"vault",
+            owner_key.as_ref(),
+            mint_key.as_ref(),
+            &vault_bump_arr,
+        ]];
+        self.transfer_collateral(
+            collateral_seeds,
+            self.liquidator.to_account_info(),
+            repay_amount + bonus,
+        )?;
+        if to_insurance > 0 {
+            self.transfer_collateral(
+                collateral_seeds,
+                self.wsol_vault.to_account_info(),
+                to_insurance,
+            )?;
+            self.sync_wsol_vault()?;
+            self.lending_vault.fund_insurance(to_insurance, false)?;
+        }
+        self.position.collateral_amount = self.position.collateral_amount
+            .checked_sub(seize_amount)
+            .ok_or(ProtocolError::MathUnderflow)?;
+
+        emit!(CollateralSeized {
+            position: self.position.key(),
+            owner: owner_key,
+            liquidator: self.liquidator.key(),
+            debt_repaid: repay_amount,
+            collateral_seized: seize_amount,
+            liquidator_bonus: bonus,
+            to_insurance,
+        });
+        Ok(())
+    }
+
+    #[inline(never)]
+    fn transfer_repayment(&self, amount: u64) -> Result<()> {
+        let ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from:      self.liquidator_wsol_ata.to_account_info(),
+                mint:      self.wsol_mint.to_account_info(),
+                to:        self.wsol_vault.to_account_info(),
+                authority: self.liquidator.to_account_info(),
+            },
+        );
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+    }
+
+    #[inline(never)]
+    fn transfer_collateral(
+        &self,
+        collateral_seeds: &[&[&[u8]]],
+        destination: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                SystemTransfer {
+                    from: self.collateral_vault.to_account_info(),
+                    to:   destination,
+                },
+                collateral_seeds,
+            ),
+            amount,
+        )
+    }
+
+    /// Sync wSOL token balance to match lamports sent straight to the vault
+    #[inline(never)]
+    fn sync_wsol_vault(&self) -> Result<()> {
+        let ix = anchor_spl::token::spl_token::instruction::sync_native(
+            &anchor_spl::token::spl_token::id(),
+            &self.wsol_vault.key(),
+        )
+        .map_err(|_| ProtocolError::MathOverflow)?;
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[self.wsol_vault.to_account_info()],
+        )?;
+        Ok(())
+    }
+}
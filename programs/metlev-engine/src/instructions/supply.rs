@@ -2,14 +2,22 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{
     transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
 };
-use crate::state::{LendingVault, LpPosition};
+use crate::state::{Config, LendingVault, LpPosition};
 use crate::errors::ProtocolError;
+use crate::events::InterestAccrued;
+use crate::utils::check_vault_invariants;
 
 #[derive(Accounts)]
 pub struct Supply<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
     #[account(
         mut,
         seeds = [LendingVault::SEED_PREFIX],
@@ -17,7 +25,7 @@ pub struct Supply<'info> {
     )]
     pub lending_vault: Account<'info, LendingVault>,
 
-    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
     pub wsol_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
@@ -51,26 +59,34 @@ pub struct Supply<'info> {
 
 impl<'info> Supply<'info> {
     pub fn supply(&mut self, bumps: &SupplyBumps, amount: u64) -> Result<()> {
+        require!(amount > 0, ProtocolError::InvalidAmount);
+        require!(!self.config.paused, ProtocolError::ProtocolPaused);
+
         let current_time = Clock::get()?.unix_timestamp;
+        let index_delta = self.lending_vault.accrue_interest(current_time)?;
+        emit!(InterestAccrued {
+            lp: self.signer.key(),
+            index_delta,
+            borrow_index: self.lending_vault.borrow_index,
+            total_supplied: self.lending_vault.total_supplied,
+            total_borrowed: self.lending_vault.total_borrowed,
+            timestamp: current_time,
+        });
 
         if self.lp_position.lp == Pubkey::default() {
             self.lp_position.lp = self.signer.key();
-            self.lp_position.last_update = current_time;
             self.lp_position.bump = bumps.lp_position;
-        } else {
-            self.lp_position.accrue_interest(
-                self.lending_vault.interest_rate_bps,
-                current_time,
-            );
         }
 
-        self.lp_position.supplied_amount = self.lp_position.supplied_amount
-            .checked_add(amount)
-            .ok_or(ProtocolError::MathOverflow)?;
+        let shares = self.lending_vault.shares_for_deposit(amount)?;
+        require!(shares > 0, ProtocolError::InvalidAmount);
 
-        self.lending_vault.total_supplied = self.lending_vault.total_supplied
-            .checked_add(amount)
+        self.lp_position.shares = self.lp_position.shares
+            .checked_add(shares)
             .ok_or(ProtocolError::MathOverflow)?;
+        self.lp_position.last_update = current_time;
+
+        self.lending_vault.deposit(amount, shares)?;
 
         let accounts = TransferChecked {
             from: self.signer_wsol_ata.to_account_info(),
@@ -79,6 +95,8 @@ impl<'info> Supply<'info> {
             authority: self.signer.to_account_info(),
         };
         let ctx = CpiContext::new(self.token_program.to_account_info(), accounts);
-        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)?;
+
+        check_vault_invariants(&self.lending_vault, &mut self.wsol_vault)
     }
 }
@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    sync_native, Mint, SyncNative, TokenAccount, TokenInterface,
+};
+use crate::state::LendingVault;
+use crate::errors::ProtocolError;
+use crate::events::WsolVaultSynced;
+
+#[derive(Accounts)]
+pub struct SyncWsolVault<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"wsol_vault", lending_vault.key().as_ref()],
+        bump = lending_vault.vault_bump,
+        token::mint = wsol_mint,
+        token::authority = lending_vault,
+    )]
+    pub wsol_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> SyncWsolVault<'info> {
+    /// Permissionless: wrap lamports sent straight to wsol_vault (rent
+    /// refunds, stray transfers) with sync_native. Nobody's books account
+    /// for them, so the newly wrapped wSOL is credited to protocol_fees.
+    pub fn sync_wsol_vault(&mut self) -> Result<()> {
+        let before = self.wsol_vault.amount;
+
+        sync_native(CpiContext::new(
+            self.token_program.to_account_info(),
+            SyncNative {
+                account: self.wsol_vault.to_account_info(),
+            },
+        ))?;
+        self.wsol_vault.reload()?;
+
+        let synced = self.wsol_vault.amount.saturating_sub(before);
+        require!(synced > 0, ProtocolError::InvalidAmount);
+        self.lending_vault.credit_stray_lamports(synced)?;
+
+        emit!(WsolVaultSynced {
+            caller: self.caller.key(),
+            amount: synced,
+            protocol_fees: self.lending_vault.protocol_fees,
+        });
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
-use crate::state::{Config, CollateralConfig};
+use anchor_spl::token::spl_token::native_mint;
+use crate::state::{Config, CollateralConfig, InterestRateParams, LendingVault, MAX_WHITELISTED_PAIRS};
+use crate::utils::lb_pair_mints;
 use crate::errors::ProtocolError;
+use crate::events::VaultParamsUpdated;
 
 #[derive(Accounts)]
 pub struct UpdateConfig<'info> {
@@ -21,6 +24,186 @@ impl<'info> UpdateConfig<'info> {
         self.config.paused = paused;
         Ok(())
     }
+
+    /// Pause only new borrows or only new deposits, leaving closes, repays
+    /// and liquidations available. `None` leaves a flag unchanged.
+    pub fn update_pause_flags(
+        &mut self,
+        open_paused: Option<bool>,
+        deposit_paused: Option<bool>,
+    ) -> Result<()> {
+        if let Some(open_paused) = open_paused {
+            self.config.open_paused = open_paused;
+        }
+        if let Some(deposit_paused) = deposit_paused {
+            self.config.deposit_paused = deposit_paused;
+        }
+        Ok(())
+    }
+
+    /// Limit liquidations to registered keepers, or open them to anyone again
+    pub fn update_permissioned_liquidation(&mut self, enabled: bool) -> Result<()> {
+        self.config.permissioned_liquidation = enabled;
+        Ok(())
+    }
+
+    pub fn propose_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        require!(new_authority != Pubkey::default(), ProtocolError::InvalidAmount);
+        self.config.pending_authority = Some(new_authority);
+        Ok(())
+    }
+
+    /// Adding a pair that is already listed is a no-op.
+    pub fn whitelist_pair(&mut self, lb_pair: Pubkey) -> Result<()> {
+        if self.config.is_pair_whitelisted(&lb_pair) {
+            return Ok(());
+        }
+        require!(
+            self.config.lb_pair_whitelist.len() < MAX_WHITELISTED_PAIRS,
+            ProtocolError::PairWhitelistFull
+        );
+        self.config.lb_pair_whitelist.push(lb_pair);
+        Ok(())
+    }
+
+    /// Existing positions on a removed pair can still be managed and closed;
+    /// only new opens are blocked.
+    pub fn remove_whitelisted_pair(&mut self, lb_pair: Pubkey) -> Result<()> {
+        require!(
+            self.config.is_pair_whitelisted(&lb_pair),
+            ProtocolError::PairNotWhitelisted
+        );
+        self.config.lb_pair_whitelist.retain(|pair| *pair != lb_pair);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub pending_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.pending_authority == Some(pending_authority.key()) @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> AcceptAuthority<'info> {
+    pub fn accept_authority(&mut self) -> Result<()> {
+        self.config.authority = self.pending_authority.key();
+        self.config.pending_authority = None;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateLendingVault<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+}
+
+impl<'info> UpdateLendingVault<'info> {
+    /// Lowering the cap below total_borrowed only blocks new borrows;
+    /// existing debt is untouched.
+    pub fn update_borrow_cap(&mut self, borrow_cap: u64) -> Result<()> {
+        self.lending_vault.borrow_cap = borrow_cap;
+        Ok(())
+    }
+
+    /// Applies to interest accrued from now on.
+    pub fn update_reserve_factor(&mut self, reserve_factor_bps: u16) -> Result<()> {
+        require!(reserve_factor_bps <= 10_000, ProtocolError::InvalidAmount);
+        self.lending_vault.reserve_factor_bps = reserve_factor_bps;
+        Ok(())
+    }
+
+    /// Only gates new deposits; withdrawals are never blocked by the cap.
+    pub fn update_supply_cap(&mut self, supply_cap: u64) -> Result<()> {
+        self.lending_vault.supply_cap = supply_cap;
+        Ok(())
+    }
+
+    /// Raising the reserve above idle liquidity only blocks new borrows;
+    /// existing debt is untouched.
+    pub fn update_min_vault_reserve(&mut self, min_vault_reserve_bps: u16) -> Result<()> {
+        require!(min_vault_reserve_bps <= 10_000, ProtocolError::InvalidAmount);
+        self.lending_vault.min_vault_reserve_bps = min_vault_reserve_bps;
+        Ok(())
+    }
+
+    /// 0 lets LPs withdraw right after supplying.
+    pub fn update_supply_cooldown(&mut self, supply_cooldown_seconds: i64) -> Result<()> {
+        require!(supply_cooldown_seconds >= 0, ProtocolError::InvalidAmount);
+        self.lending_vault.supply_cooldown_seconds = supply_cooldown_seconds;
+        Ok(())
+    }
+
+    /// Retune any subset of the interest-rate model; fields left as None keep
+    /// their current value. Interest up to now accrues at the old curve.
+    pub fn update_vault_params(
+        &mut self,
+        base_rate_bps: Option<u16>,
+        slope1_bps: Option<u16>,
+        slope2_bps: Option<u16>,
+        optimal_utilization_bps: Option<u16>,
+        max_borrow_rate_bps: Option<u16>,
+        reserve_factor_bps: Option<u16>,
+    ) -> Result<()> {
+        let old = self.lending_vault.rate_params();
+        let new = InterestRateParams {
+            base_rate_bps: base_rate_bps.unwrap_or(old.base_rate_bps),
+            slope1_bps: slope1_bps.unwrap_or(old.slope1_bps),
+            slope2_bps: slope2_bps.unwrap_or(old.slope2_bps),
+            optimal_utilization_bps: optimal_utilization_bps.unwrap_or(old.optimal_utilization_bps),
+            max_borrow_rate_bps: max_borrow_rate_bps.unwrap_or(old.max_borrow_rate_bps),
+            reserve_factor_bps: reserve_factor_bps.unwrap_or(old.reserve_factor_bps),
+        };
+        require!(new.is_valid(), ProtocolError::InvalidAmount);
+
+        self.lending_vault.accrue_interest(Clock::get()?.unix_timestamp)?;
+        self.lending_vault.set_rate_params(new);
+
+        emit!(VaultParamsUpdated {
+            authority: self.authority.key(),
+            old,
+            new,
+        });
+        Ok(())
+    }
+
+    /// The cap must leave room above the base rate, otherwise the rate
+    /// would be flat at every utilization.
+    pub fn update_max_borrow_rate(&mut self, max_borrow_rate_bps: u16) -> Result<()> {
+        require!(
+            max_borrow_rate_bps > self.lending_vault.base_rate_bps,
+            ProtocolError::InvalidAmount
+        );
+        self.lending_vault.accrue_interest(Clock::get()?.unix_timestamp)?;
+        self.lending_vault.max_borrow_rate_bps = max_borrow_rate_bps;
+        Ok(())
+    }
+
+    pub fn update_flash_loan_fee(&mut self, flash_loan_fee_bps: u16) -> Result<()> {
+        require!(flash_loan_fee_bps <= 10_000, ProtocolError::InvalidAmount);
+        self.lending_vault.flash_loan_fee_bps = flash_loan_fee_bps;
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
@@ -50,6 +233,11 @@ impl<'info> UpdateCollateralConfig<'info> {
         Ok(())
     }
 
+    pub fn update_paused(&mut self, paused: bool) -> Result<()> {
+        self.collateral_config.paused = paused;
+        Ok(())
+    }
+
     pub fn update_ltv_params(
         &mut self,
         max_ltv: Option<u16>,
@@ -72,9 +260,129 @@ impl<'info> UpdateCollateralConfig<'info> {
         Ok(())
     }
 
+    /// Can't drop below the current liquidator bonus.
     pub fn update_liquidation_penalty(&mut self, penalty: u16) -> Result<()> {
         require!(penalty <= 2000, ProtocolError::InvalidAmount); // Max 20%
-        self.collateral_config.liquidation_penalty = penalty;
+        self.collateral_config.liquidation_penalty_bps = penalty;
+        require!(
+            self.collateral_config.validate_liquidator_bonus(),
+            ProtocolError::InvalidAmount
+        );
+        Ok(())
+    }
+
+    /// Scale liquidate's penalty with health; (0, 0) goes back to the flat
+    /// liquidation_penalty_bps.
+    pub fn update_penalty_tiers(&mut self, min_penalty_bps: u16, max_penalty_bps: u16) -> Result<()> {
+        self.collateral_config.min_penalty_bps = min_penalty_bps;
+        self.collateral_config.max_penalty_bps = max_penalty_bps;
+        require!(
+            self.collateral_config.validate_penalty_tiers(),
+            ProtocolError::InvalidAmount
+        );
+        Ok(())
+    }
+
+    /// Share of the liquidation penalty paid to the liquidator; whatever is
+    /// left of the penalty goes to the insurance fund.
+    pub fn update_liquidator_bonus(&mut self, liquidator_bonus_bps: u16) -> Result<()> {
+        self.collateral_config.liquidator_bonus_bps = liquidator_bonus_bps;
+        require!(
+            self.collateral_config.validate_liquidator_bonus(),
+            ProtocolError::InvalidAmount
+        );
+        Ok(())
+    }
+
+    pub fn update_close_factor(&mut self, close_factor_bps: u16) -> Result<()> {
+        require!(
+            close_factor_bps > 0 && close_factor_bps <= 10_000,
+            ProtocolError::InvalidAmount
+        );
+        self.collateral_config.close_factor_bps = close_factor_bps;
+        Ok(())
+    }
+
+    /// Positions flagged past liquidation_threshold wait `grace_period_seconds`
+    /// before a soft liquidation unless LTV reaches `hard_liquidation_threshold`.
+    pub fn update_liquidation_grace(
+        &mut self,
+        grace_period_seconds: i64,
+        hard_liquidation_threshold: u16,
+    ) -> Result<()> {
+        require!(grace_period_seconds >= 0, ProtocolError::InvalidAmount);
+        require!(hard_liquidation_threshold <= 10_000, ProtocolError::InvalidAmount);
+        self.collateral_config.grace_period_seconds = grace_period_seconds;
+        self.collateral_config.hard_liquidation_threshold = hard_liquidation_threshold;
+        require!(
+            self.collateral_config.validate_thresholds(),
+            ProtocolError::InvalidLiquidationThreshold
+        );
+        Ok(())
+    }
+
+    pub fn update_max_leverage(&mut self, max_leverage_bps: u64) -> Result<()> {
+        require!(max_leverage_bps > 0, ProtocolError::InvalidAmount);
+        self.collateral_config.max_leverage_bps = max_leverage_bps;
+        Ok(())
+    }
+
+    /// Must stay at or above 1.0, otherwise a position could be opened
+    /// already liquidatable.
+    pub fn update_min_open_health(&mut self, min_open_health_bps: u64) -> Result<()> {
+        require!(min_open_health_bps >= 10_000, ProtocolError::InvalidAmount);
+        self.collateral_config.min_open_health_bps = min_open_health_bps;
+        Ok(())
+    }
+
+    pub fn update_max_confidence(&mut self, max_confidence_bps: u16) -> Result<()> {
+        require!(
+            max_confidence_bps > 0 && max_confidence_bps <= 10_000,
+            ProtocolError::InvalidAmount
+        );
+        self.collateral_config.max_confidence_bps = max_confidence_bps;
+        Ok(())
+    }
+
+    /// Separate staleness limits for opening and liquidating; either can be
+    /// left unchanged.
+    pub fn update_oracle_max_age(
+        &mut self,
+        open_oracle_max_age: Option<u64>,
+        liquidation_oracle_max_age: Option<u64>,
+    ) -> Result<()> {
+        if let Some(max_age) = open_oracle_max_age {
+            require!(max_age > 0, ProtocolError::InvalidAmount);
+            self.collateral_config.open_oracle_max_age = max_age;
+        }
+
+        if let Some(max_age) = liquidation_oracle_max_age {
+            require!(max_age > 0, ProtocolError::InvalidAmount);
+            self.collateral_config.liquidation_oracle_max_age = max_age;
+        }
+
+        Ok(())
+    }
+
+    /// 0 turns the price-deviation breaker off.
+    pub fn update_max_price_deviation(&mut self, max_price_deviation_bps: u16) -> Result<()> {
+        require!(max_price_deviation_bps <= 10_000, ProtocolError::InvalidAmount);
+        self.collateral_config.max_price_deviation_bps = max_price_deviation_bps;
+        Ok(())
+    }
+
+    /// Seconds after open before a position below `hard_liquidation_threshold`
+    /// can be liquidated; 0 turns the check off.
+    pub fn update_min_position_age(&mut self, min_position_age_seconds: i64) -> Result<()> {
+        require!(min_position_age_seconds >= 0, ProtocolError::InvalidAmount);
+        self.collateral_config.min_position_age_seconds = min_position_age_seconds;
+        Ok(())
+    }
+
+    /// 0 turns the stale-oracle liquidation fallback off.
+    pub fn update_stale_liquidation_discount(&mut self, discount_bps: u16) -> Result<()> {
+        require!(discount_bps < 10_000, ProtocolError::InvalidAmount);
+        self.collateral_config.stale_liquidation_discount_bps = discount_bps;
         Ok(())
     }
 
@@ -83,8 +391,80 @@ impl<'info> UpdateCollateralConfig<'info> {
         Ok(())
     }
 
+    pub fn update_min_position_borrow(&mut self, min_position_borrow: u64) -> Result<()> {
+        self.collateral_config.min_position_borrow = min_position_borrow;
+        Ok(())
+    }
+
+    /// Existing positions above a lowered cap keep their debt; they just
+    /// can't borrow more.
+    pub fn update_max_position_debt(&mut self, max_position_debt: u64) -> Result<()> {
+        self.collateral_config.max_position_debt = max_position_debt;
+        Ok(())
+    }
+
     pub fn update_oracle(&mut self, oracle: Pubkey) -> Result<()> {
+        require!(oracle != Pubkey::default(), ProtocolError::OraclePriceUnavailable);
+        require!(
+            !self.collateral_config.backup_oracles.contains(&oracle),
+            ProtocolError::OraclePriceUnavailable
+        );
         self.collateral_config.oracle = oracle;
         Ok(())
     }
+
+    /// Pass `Pubkey::default()` to leave a slot unused
+    pub fn update_backup_oracles(&mut self, backup_oracles: [Pubkey; 2]) -> Result<()> {
+        let [first, second] = backup_oracles;
+        require!(
+            first != self.collateral_config.oracle
+                && second != self.collateral_config.oracle
+                && (first != second || first == Pubkey::default()),
+            ProtocolError::OraclePriceUnavailable
+        );
+        self.collateral_config.backup_oracles = backup_oracles;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct UpdateCollateralSwapPair<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [CollateralConfig::SEED_PREFIX, mint.as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    /// CHECK: owner and layout checked in `lb_pair_mints`
+    pub lb_pair: UncheckedAccount<'info>,
+}
+
+impl<'info> UpdateCollateralSwapPair<'info> {
+    /// Register the DLMM pair that converts this collateral into wSOL. The
+    /// pair must trade exactly the collateral mint against wSOL, either way
+    /// round.
+    pub fn update_swap_pair(&mut self) -> Result<()> {
+        let (token_x_mint, token_y_mint) = lb_pair_mints(&self.lb_pair.to_account_info())?;
+        let collateral_mint = self.collateral_config.mint;
+        let wsol = native_mint::id();
+        require!(
+            collateral_mint != wsol
+                && ((token_x_mint == collateral_mint && token_y_mint == wsol)
+                    || (token_x_mint == wsol && token_y_mint == collateral_mint)),
+            ProtocolError::InvalidSwapPair
+        );
+        self.collateral_config.swap_lb_pair = self.lb_pair.key();
+        Ok(())
+    }
 }
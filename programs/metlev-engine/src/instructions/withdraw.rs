@@ -2,17 +2,25 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{
     transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
 };
-use crate::state::{LpPosition, LendingVault};
+use crate::state::{Config, LpPosition, LendingVault};
 use crate::errors::ProtocolError;
+use crate::events::InterestAccrued;
+use crate::utils::check_vault_invariants;
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
+    #[account(
+        seeds = [Config::SEED_PREFIX],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Closed to the signer once all shares are burned
     #[account(
         mut,
-        close = signer,
         seeds = [LpPosition::SEED_PREFIX, signer.key().as_ref()],
         bump = lp_position.bump,
         constraint = lp_position.lp == signer.key() @ ProtocolError::InvalidOwner,
@@ -26,7 +34,7 @@ pub struct Withdraw<'info> {
     )]
     pub lending_vault: Account<'info, LendingVault>,
 
-    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    #[account(address = lending_vault.borrow_mint @ ProtocolError::UnsupportedBorrowMint)]
     pub wsol_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
@@ -50,19 +58,54 @@ pub struct Withdraw<'info> {
 }
 
 impl<'info> Withdraw<'info> {
-    pub fn withdraw(&mut self) -> Result<()> {
-        // TODO: interest should come from borrower repayments, not time-based accrual
-        let amount = self.lp_position.supplied_amount;
+    pub fn withdraw(&mut self, shares: u64) -> Result<()> {
+        require!(!self.config.paused, ProtocolError::ProtocolPaused);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let index_delta = self.lending_vault.accrue_interest(current_time)?;
+        emit!(InterestAccrued {
+            lp: self.signer.key(),
+            index_delta,
+            borrow_index: self.lending_vault.borrow_index,
+            total_supplied: self.lending_vault.total_supplied,
+            total_borrowed: self.lending_vault.total_borrowed,
+            timestamp: current_time,
+        });
 
         require!(
-            self.wsol_vault.amount >= amount,
+            shares > 0 && shares <= self.lp_position.shares,
+            ProtocolError::InvalidAmount
+        );
+        // Shares supplied just ahead of an accrual can't leave straight after it
+        require!(
+            self.lp_position.supply_cooldown_elapsed(
+                self.lending_vault.supply_cooldown_seconds,
+                current_time,
+            ),
+            ProtocolError::SupplyCooldown
+        );
+        // No jumping the queue while earlier exits are still waiting
+        require!(
+            !self.lending_vault.has_queued_withdrawals(),
+            ProtocolError::WithdrawalsQueued
+        );
+
+        let amount = self.lending_vault.assets_for_shares(shares)?;
+
+        // wsol_vault also holds protocol_fees and the insurance fund, which
+        // aren't LP liquidity
+        require!(
+            amount <= self.lending_vault.available_liquidity()
+                && amount <= self.wsol_vault.amount,
             ProtocolError::InsufficientLiquidity
         );
 
-        self.lending_vault.total_supplied = self.lending_vault.total_supplied
-            .checked_sub(self.lp_position.supplied_amount)
+        self.lp_position.shares = self.lp_position.shares
+            .checked_sub(shares)
             .ok_or(ProtocolError::MathUnderflow)?;
 
+        self.lending_vault.redeem(amount, shares)?;
+
         // lending_vault PDA is the authority of wsol_vault
         let lending_vault_bump = self.lending_vault.bump;
         let signer_seeds: &[&[&[u8]]] = &[&[
@@ -81,6 +124,13 @@ impl<'info> Withdraw<'info> {
             accounts,
             signer_seeds,
         );
-        transfer_checked(ctx, amount, self.wsol_mint.decimals)
+        transfer_checked(ctx, amount, self.wsol_mint.decimals)?;
+        check_vault_invariants(&self.lending_vault, &mut self.wsol_vault)?;
+
+        if self.lp_position.shares == 0 {
+            self.lp_position.close(self.signer.to_account_info())?;
+        }
+
+        Ok(())
     }
 }
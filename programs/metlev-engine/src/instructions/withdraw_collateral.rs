@@ -37,37 +37,53 @@ pub struct WithdrawCollateral<'info> {
 impl<'info> WithdrawCollateral<'info> {
     pub fn withdraw(&mut self, bumps: &WithdrawCollateralBumps) -> Result<()> {
         let collateral = self.position.collateral_amount;
+        self.position.collateral_amount = 0;
+        release_sol_collateral(
+            &self.system_program,
+            &self.collateral_vault,
+            &self.user,
+            &self.wsol_mint.key(),
+            bumps.collateral_vault,
+            collateral,
+        )
+    }
+}
 
-        if collateral > 0 {
-            require!(
-                self.collateral_vault.lamports() >= collateral,
-                ProtocolError::WithdrawalFailed
-            );
-
-            self.position.collateral_amount = 0;
-
-            let user_key       = self.user.key();
-            let wsol_key       = self.wsol_mint.key();
-            let vault_bump_arr = [bumps.collateral_vault];
-            let vault_seeds: &[&[&[u8]]] = &[&[
-                b"vault",
-                user_key.as_ref(),
-                wsol_key.as_ref(),
-                &vault_bump_arr,
-            ]];
-            system_program::transfer(
-                CpiContext::new_with_signer(
-                    self.system_program.to_account_info(),
-                    SystemTransfer {
-                        from: self.collateral_vault.to_account_info(),
-                        to:   self.user.to_account_info(),
-                    },
-                    vault_seeds,
-                ),
-                collateral,
-            )?;
-        }
-
-        Ok(())
+/// Send `amount` lamports of SOL collateral from the user's collateral vault
+/// PDA back to the user.
+pub(crate) fn release_sol_collateral<'info>(
+    system_program: &Program<'info, System>,
+    collateral_vault: &UncheckedAccount<'info>,
+    user: &AccountInfo<'info>,
+    wsol_mint: &Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
     }
+    require!(
+        collateral_vault.lamports() >= amount,
+        ProtocolError::WithdrawalFailed
+    );
+
+    let user_key       = user.key();
+    let vault_bump_arr = [vault_bump];
+    let vault_seeds: &[&[&[u8]]] = &[&[
+        b"vault",
+        user_key.as_ref(),
+        wsol_mint.as_ref(),
+        &vault_bump_arr,
+    ]];
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            SystemTransfer {
+                from: collateral_vault.to_account_info(),
+                to:   user.to_account_info(),
+            },
+            vault_seeds,
+        ),
+        amount,
+    )
 }
@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use crate::state::{CollateralConfig, LendingVault, Position};
+use crate::errors::ProtocolError;
+use crate::events::ExcessCollateralWithdrawn;
+use crate::utils::{read_price, calculate_position_ltv};
+use crate::instructions::withdraw_collateral::release_sol_collateral;
+
+/// Pull SOL collateral out of an active position, keeping its LTV within
+/// `max_ltv`.
+#[derive(Accounts)]
+pub struct WithdrawExcessCollateral<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(address = anchor_spl::token::spl_token::native_mint::id())]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [CollateralConfig::SEED_PREFIX, wsol_mint.key().as_ref()],
+        bump = collateral_config.bump,
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    #[account(
+        mut,
+        seeds = [Position::SEED_PREFIX, user.key().as_ref(), wsol_mint.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ProtocolError::InvalidOwner,
+        constraint = position.is_active() @ ProtocolError::PositionNotActive,
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Needed to accrue interest on the position's debt before the LTV check.
+    #[account(
+        mut,
+        seeds = [LendingVault::SEED_PREFIX],
+        bump = lending_vault.bump,
+    )]
+    pub lending_vault: Account<'info, LendingVault>,
+
+    /// CHECK: seeds validated below.
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref(), wsol_mint.key().as_ref()],
+        bump,
+    )]
+    pub collateral_vault: UncheckedAccount<'info>,
+
+    /// CHECK: verified via collateral_config.oracle constraint
+    #[account(
+        constraint = price_oracle.key() == collateral_config.oracle @ ProtocolError::OraclePriceUnavailable,
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> WithdrawExcessCollateral<'info> {
+    /// Raises risk like an open does, so the price is read with the open
+    /// staleness limit. A position without debt can be emptied freely.
+    pub fn withdraw_excess_collateral(
+        &mut self,
+        bumps: &WithdrawExcessCollateralBumps,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ProtocolError::InvalidAmount);
+        require!(
+            amount <= self.position.collateral_amount,
+            ProtocolError::InsufficientCollateral
+        );
+
+        self.lending_vault
+            .accrue_position(&mut self.position, Clock::get()?.unix_timestamp)?;
+
+        self.position.collateral_amount -= amount;
+
+        let ltv_after = if self.position.debt_amount > 0 {
+            let oracle_info = self.price_oracle.to_account_info();
+            let (price, _) = read_price(
+                self.collateral_config.oracle_kind,
+                &oracle_info,
+                self.collateral_config.open_oracle_max_age,
+                self.collateral_config.max_confidence_bps,
+            )?;
+            let ltv = calculate_position_ltv(
+                self.position.collateral_amount,
+                self.position.debt_amount,
+                price,
+                self.collateral_config.decimals,
+            )?;
+            require!(self.collateral_config.validate_ltv(ltv), ProtocolError::ExceedsMaxLTV);
+            ltv
+        } else {
+            0
+        };
+        emit!(ExcessCollateralWithdrawn {
+            position: self.position.key(),
+            amount,
+            collateral_amount: self.position.collateral_amount,
+            ltv_after,
+        });
+
+        release_sol_collateral(
+            &self.system_program,
+            &self.collateral_vault,
+            &self.user,
+            &self.wsol_mint.key(),
+            bumps.collateral_vault,
+            amount,
+        )
+    }
+}
@@ -4,9 +4,11 @@ use anchor_lang::prelude::*;
 mod state;
 mod instructions;
 mod errors;
+mod events;
 mod utils;
 
 use instructions::*;
+use state::{OracleKind, PriceObservation};
 
 declare_id!("6ySvjJb41GBCBbtVvmaCd7cQUuzWFtqZ1SA931rEuSSx");
 declare_program!(dlmm);
@@ -23,28 +25,41 @@ pub mod metlev_engine {
         ctx.accounts.initialize_lending_vault(&ctx.bumps)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn register_collateral(
         ctx: Context<RegisterCollateral>,
         oracle: Pubkey,
+        oracle_kind: OracleKind,
         max_ltv: u16,
         liquidation_threshold: u16,
-        liquidation_penalty: u16,
+        liquidation_penalty_bps: u16,
         min_deposit: u64,
         interest_rate_bps: u16,
         oracle_max_age: u64,
+        max_leverage_bps: u64,
     ) -> Result<()> {
         ctx.accounts.register(
             &ctx.bumps,
             oracle,
+            oracle_kind,
             max_ltv,
             liquidation_threshold,
-            liquidation_penalty,
+            liquidation_penalty_bps,
             min_deposit,
             interest_rate_bps,
             oracle_max_age,
+            max_leverage_bps,
         )
     }
 
+    /// Close a collateral's config once no active position uses it.
+    pub fn deregister_collateral(
+        ctx: Context<DeregisterCollateral>,
+        _mint: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.deregister()
+    }
+
     pub fn deposit_sol_collateral(
         ctx: Context<DepositSolCollateral>,
         amount: u64,
@@ -58,7 +73,14 @@ pub mod metlev_engine {
     ) -> Result<()> {
         ctx.accounts.deposit(&ctx.bumps, amount)
     }
-    
+
+    pub fn add_collateral(
+        ctx: Context<AddCollateral>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.add_collateral(amount)
+    }
+
     pub fn supply(
         ctx: Context<Supply>,
         amount: u64,
@@ -68,11 +90,39 @@ pub mod metlev_engine {
 
     pub fn withdraw(
         ctx: Context<Withdraw>,
+        shares: u64,
+    ) -> Result<()> {
+        ctx.accounts.withdraw(shares)
+    }
+
+    pub fn request_withdrawal(
+        ctx: Context<RequestWithdrawal>,
+        shares: u64,
+    ) -> Result<()> {
+        ctx.accounts.request_withdrawal(&ctx.bumps, shares)
+    }
+
+    pub fn process_withdrawal(ctx: Context<ProcessWithdrawal>) -> Result<()> {
+        ctx.accounts.process_withdrawal()
+    }
+
+    pub fn flash_loan(
+        ctx: Context<FlashLoan>,
+        amount: u64,
     ) -> Result<()> {
-        ctx.accounts.withdraw()
+        ctx.accounts.flash_loan(amount)
+    }
+
+    pub fn flash_loan_repay(ctx: Context<FlashLoanRepay>) -> Result<()> {
+        ctx.accounts.flash_loan_repay()
     }
 
-    /// Modified to include all DLMM and leverage parameters
+    /// Modified to include all DLMM and leverage parameters. With
+    /// `subsidize_rent` the rent reserve pays the DLMM position rent and is
+    /// refunded when the position closes. `strategy_tag` labels the position
+    /// for an integrator and is carried in its events; `None` keeps any tag
+    /// already set.
+    #[allow(clippy::too_many_arguments)]
     pub fn open_position(
         ctx: Context<OpenPosition>,
         leverage: u64,
@@ -81,14 +131,20 @@ pub mod metlev_engine {
         active_id: i32,
         max_active_bin_slippage: i32,
         bin_liquidity_dist: Vec<dlmm::types::BinLiquidityDistributionByWeight>,
-    ) -> Result<()> {
+        subsidize_rent: bool,
+        strategy_tag: Option<[u8; 16]>,
+    ) -> Result<OpenPositionResult> {
         ctx.accounts.open(
+            &ctx.bumps,
             leverage,
             lower_bin_id,
             width,
             active_id,
             max_active_bin_slippage,
             bin_liquidity_dist,
+            subsidize_rent,
+            strategy_tag,
+            ctx.remaining_accounts,
         )
     }
 
@@ -96,20 +152,170 @@ pub mod metlev_engine {
         ctx: Context<ClosePosition>,
         from_bin_id: i32,
         to_bin_id: i32,
+        swap_to_wsol: bool,
+    ) -> Result<()> {
+        ctx.accounts.close(&ctx.bumps, from_bin_id, to_bin_id, swap_to_wsol)
+    }
+
+    /// First phase of a close too wide for one transaction: unwind one bin
+    /// sub-range and repay debt from it. Repeat until the position is empty.
+    pub fn close_position_remove(
+        ctx: Context<ClosePosition>,
+        from_bin_id: i32,
+        to_bin_id: i32,
+    ) -> Result<()> {
+        ctx.accounts.close_remove(from_bin_id, to_bin_id)
+    }
+
+    /// Second phase: close the drained DLMM position and settle the rest.
+    pub fn close_position_finalize(ctx: Context<ClosePosition>) -> Result<()> {
+        ctx.accounts.close_finalize(&ctx.bumps)
+    }
+
+    /// Unwind `bps_to_remove` of the given bin range and repay the same share
+    /// of the debt; the position stays open.
+    pub fn partial_close_position(
+        ctx: Context<ClosePosition>,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        bps_to_remove: u16,
+    ) -> Result<()> {
+        ctx.accounts.partial_close(from_bin_id, to_bin_id, bps_to_remove)
+    }
+
+    /// `close_position` followed by `withdraw_collateral` in one instruction.
+    pub fn close_and_withdraw(
+        ctx: Context<ClosePosition>,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        swap_to_wsol: bool,
+    ) -> Result<()> {
+        ctx.accounts.close(&ctx.bumps, from_bin_id, to_bin_id, swap_to_wsol)?;
+        ctx.accounts.withdraw_collateral(&ctx.bumps)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn rebalance_position(
+        ctx: Context<RebalancePosition>,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        lower_bin_id: i32,
+        width: i32,
+        active_id: i32,
+        max_active_bin_slippage: i32,
+        bin_liquidity_dist: Vec<dlmm::types::BinLiquidityDistributionByWeight>,
+    ) -> Result<()> {
+        ctx.accounts.rebalance(
+            from_bin_id,
+            to_bin_id,
+            lower_bin_id,
+            width,
+            active_id,
+            max_active_bin_slippage,
+            bin_liquidity_dist,
+        )
+    }
+
+    pub fn claim_position_fees(ctx: Context<ClaimPositionFees>) -> Result<()> {
+        ctx.accounts.claim_position_fees()
+    }
+
+    pub fn repay(
+        ctx: Context<Repay>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.repay(amount)
+    }
+
+    pub fn repay_full(ctx: Context<Repay>) -> Result<u64> {
+        ctx.accounts.repay_full()
+    }
+
+    pub fn decrease_leverage(
+        ctx: Context<DecreaseLeverage>,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        repay_amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.decrease_leverage(from_bin_id, to_bin_id, repay_amount)
+    }
+
+    pub fn increase_leverage(
+        ctx: Context<IncreaseLeverage>,
+        amount: u64,
+        active_id: i32,
+        max_active_bin_slippage: i32,
+        bin_liquidity_dist: Vec<dlmm::types::BinLiquidityDistributionByWeight>,
     ) -> Result<()> {
-        ctx.accounts.close(&ctx.bumps, from_bin_id, to_bin_id)
+        ctx.accounts.increase_leverage(amount, active_id, max_active_bin_slippage, bin_liquidity_dist)
     }
 
     pub fn withdraw_collateral(ctx: Context<WithdrawCollateral>) -> Result<()> {
         ctx.accounts.withdraw(&ctx.bumps)
     }
 
+    pub fn withdraw_excess_collateral(
+        ctx: Context<WithdrawExcessCollateral>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.withdraw_excess_collateral(&ctx.bumps, amount)
+    }
+
     pub fn liquidate(
         ctx: Context<Liquidate>,
         from_bin_id: i32,
         to_bin_id: i32,
+        swap_all: bool,
+    ) -> Result<LiquidationResult> {
+        ctx.accounts.liquidate(&ctx.bumps, from_bin_id, to_bin_id, swap_all, ctx.remaining_accounts)
+    }
+
+    pub fn seize_collateral(
+        ctx: Context<SeizeCollateral>,
+        repay_amount: u64,
     ) -> Result<()> {
-        ctx.accounts.liquidate(&ctx.bumps, from_bin_id, to_bin_id)
+        ctx.accounts.seize_collateral(&ctx.bumps, repay_amount)
+    }
+
+    /// `seize_collateral` over up to MAX_LIQUIDATION_BATCH positions passed
+    /// as (position, collateral_vault) pairs in remaining accounts, skipping
+    /// any that can't be liquidated yet. wSOL collateral only; each position
+    /// may be listed once.
+    pub fn liquidate_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LiquidateBatch<'info>>,
+    ) -> Result<BatchLiquidationResult> {
+        ctx.accounts.liquidate_batch(ctx.remaining_accounts)
+    }
+
+    pub fn get_position_health(ctx: Context<GetPositionHealth>) -> Result<PositionHealth> {
+        ctx.accounts.get_position_health()
+    }
+
+    pub fn is_liquidatable(ctx: Context<IsLiquidatable>) -> Result<bool> {
+        ctx.accounts.is_liquidatable()
+    }
+
+    pub fn flag_liquidation(ctx: Context<FlagLiquidation>) -> Result<bool> {
+        ctx.accounts.flag_liquidation()
+    }
+
+    pub fn get_vault_stats(ctx: Context<GetVaultStats>) -> Result<VaultStats> {
+        ctx.accounts.get_vault_stats()
+    }
+
+    pub fn get_price_history(
+        ctx: Context<GetPriceHistory>,
+        _mint: Pubkey,
+    ) -> Result<Vec<PriceObservation>> {
+        ctx.accounts.get_price_history()
+    }
+
+    pub fn preview_borrow(
+        ctx: Context<PreviewBorrow>,
+        collateral_amount: u64,
+        leverage: u64,
+    ) -> Result<BorrowPreview> {
+        ctx.accounts.preview_borrow(collateral_amount, leverage)
     }
 
     pub fn update_pause_state(
@@ -119,6 +325,155 @@ pub mod metlev_engine {
         ctx.accounts.update_pause_state(paused)
     }
 
+    pub fn update_pause_flags(
+        ctx: Context<UpdateConfig>,
+        open_paused: Option<bool>,
+        deposit_paused: Option<bool>,
+    ) -> Result<()> {
+        ctx.accounts.update_pause_flags(open_paused, deposit_paused)
+    }
+
+    pub fn update_permissioned_liquidation(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.update_permissioned_liquidation(enabled)
+    }
+
+    pub fn add_keeper(
+        ctx: Context<AddKeeper>,
+        keeper: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.add_keeper(&ctx.bumps, keeper)
+    }
+
+    pub fn remove_keeper(
+        ctx: Context<RemoveKeeper>,
+        _keeper: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.remove_keeper()
+    }
+
+    pub fn propose_authority(
+        ctx: Context<UpdateConfig>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.propose_authority(new_authority)
+    }
+
+    pub fn whitelist_pair(
+        ctx: Context<UpdateConfig>,
+        lb_pair: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.whitelist_pair(lb_pair)
+    }
+
+    pub fn remove_whitelisted_pair(
+        ctx: Context<UpdateConfig>,
+        lb_pair: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.remove_whitelisted_pair(lb_pair)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        ctx.accounts.accept_authority()
+    }
+
+    pub fn update_borrow_cap(
+        ctx: Context<UpdateLendingVault>,
+        borrow_cap: u64,
+    ) -> Result<()> {
+        ctx.accounts.update_borrow_cap(borrow_cap)
+    }
+
+    pub fn update_supply_cap(
+        ctx: Context<UpdateLendingVault>,
+        supply_cap: u64,
+    ) -> Result<()> {
+        ctx.accounts.update_supply_cap(supply_cap)
+    }
+
+    pub fn update_min_vault_reserve(
+        ctx: Context<UpdateLendingVault>,
+        min_vault_reserve_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_min_vault_reserve(min_vault_reserve_bps)
+    }
+
+    pub fn update_supply_cooldown(
+        ctx: Context<UpdateLendingVault>,
+        supply_cooldown_seconds: i64,
+    ) -> Result<()> {
+        ctx.accounts.update_supply_cooldown(supply_cooldown_seconds)
+    }
+
+    pub fn update_reserve_factor(
+        ctx: Context<UpdateLendingVault>,
+        reserve_factor_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_reserve_factor(reserve_factor_bps)
+    }
+
+    pub fn update_vault_params(
+        ctx: Context<UpdateLendingVault>,
+        base_rate_bps: Option<u16>,
+        slope1_bps: Option<u16>,
+        slope2_bps: Option<u16>,
+        optimal_utilization_bps: Option<u16>,
+        max_borrow_rate_bps: Option<u16>,
+        reserve_factor_bps: Option<u16>,
+    ) -> Result<()> {
+        ctx.accounts.update_vault_params(
+            base_rate_bps,
+            slope1_bps,
+            slope2_bps,
+            optimal_utilization_bps,
+            max_borrow_rate_bps,
+            reserve_factor_bps,
+        )
+    }
+
+    pub fn update_max_borrow_rate(
+        ctx: Context<UpdateLendingVault>,
+        max_borrow_rate_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_max_borrow_rate(max_borrow_rate_bps)
+    }
+
+    pub fn update_flash_loan_fee(
+        ctx: Context<UpdateLendingVault>,
+        flash_loan_fee_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_flash_loan_fee(flash_loan_fee_bps)
+    }
+
+    pub fn collect_reserves(ctx: Context<CollectReserves>) -> Result<()> {
+        ctx.accounts.collect_reserves()
+    }
+
+    pub fn fund_insurance(
+        ctx: Context<FundInsurance>,
+        amount: u64,
+        from_reserves: bool,
+    ) -> Result<()> {
+        ctx.accounts.fund_insurance(amount, from_reserves)
+    }
+
+    pub fn emergency_withdraw(
+        ctx: Context<EmergencyWithdraw>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.emergency_withdraw(amount)
+    }
+
+    pub fn sync_wsol_vault(ctx: Context<SyncWsolVault>) -> Result<()> {
+        ctx.accounts.sync_wsol_vault()
+    }
+
+    pub fn poke_accrual(ctx: Context<PokeAccrual>) -> Result<()> {
+        ctx.accounts.poke_accrual()
+    }
+
     pub fn update_collateral_enabled(
         ctx: Context<UpdateCollateralConfig>,
         _mint: Pubkey,
@@ -127,6 +482,14 @@ pub mod metlev_engine {
         ctx.accounts.update_enabled(enabled)
     }
 
+    pub fn update_collateral_paused(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        paused: bool,
+    ) -> Result<()> {
+        ctx.accounts.update_paused(paused)
+    }
+
     pub fn update_collateral_ltv_params(
         ctx: Context<UpdateCollateralConfig>,
         _mint: Pubkey,
@@ -144,6 +507,104 @@ pub mod metlev_engine {
         ctx.accounts.update_liquidation_penalty(penalty)
     }
 
+    pub fn update_collateral_swap_pair(
+        ctx: Context<UpdateCollateralSwapPair>,
+        _mint: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.update_swap_pair()
+    }
+
+    pub fn update_collateral_liquidator_bonus(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        liquidator_bonus_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_liquidator_bonus(liquidator_bonus_bps)
+    }
+
+    pub fn update_collateral_penalty_tiers(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        min_penalty_bps: u16,
+        max_penalty_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_penalty_tiers(min_penalty_bps, max_penalty_bps)
+    }
+
+    pub fn update_collateral_close_factor(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        close_factor_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_close_factor(close_factor_bps)
+    }
+
+    pub fn update_collateral_liquidation_grace(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        grace_period_seconds: i64,
+        hard_liquidation_threshold: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_liquidation_grace(grace_period_seconds, hard_liquidation_threshold)
+    }
+
+    pub fn update_collateral_max_leverage(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        max_leverage_bps: u64,
+    ) -> Result<()> {
+        ctx.accounts.update_max_leverage(max_leverage_bps)
+    }
+
+    pub fn update_collateral_min_open_health(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        min_open_health_bps: u64,
+    ) -> Result<()> {
+        ctx.accounts.update_min_open_health(min_open_health_bps)
+    }
+
+    pub fn update_collateral_max_confidence(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_max_confidence(max_confidence_bps)
+    }
+
+    pub fn update_collateral_oracle_max_age(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        open_oracle_max_age: Option<u64>,
+        liquidation_oracle_max_age: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.update_oracle_max_age(open_oracle_max_age, liquidation_oracle_max_age)
+    }
+
+    pub fn update_collateral_max_price_deviation(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        max_price_deviation_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_max_price_deviation(max_price_deviation_bps)
+    }
+
+    pub fn update_collateral_min_position_age(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        min_position_age_seconds: i64,
+    ) -> Result<()> {
+        ctx.accounts.update_min_position_age(min_position_age_seconds)
+    }
+
+    pub fn update_collateral_stale_liquidation_discount(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        discount_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.update_stale_liquidation_discount(discount_bps)
+    }
+
     pub fn update_collateral_min_deposit(
         ctx: Context<UpdateCollateralConfig>,
         _mint: Pubkey,
@@ -152,6 +613,22 @@ pub mod metlev_engine {
         ctx.accounts.update_min_deposit(min_deposit)
     }
 
+    pub fn update_collateral_min_position_borrow(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        min_position_borrow: u64,
+    ) -> Result<()> {
+        ctx.accounts.update_min_position_borrow(min_position_borrow)
+    }
+
+    pub fn update_collateral_max_position_debt(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        max_position_debt: u64,
+    ) -> Result<()> {
+        ctx.accounts.update_max_position_debt(max_position_debt)
+    }
+
     pub fn update_collateral_oracle(
         ctx: Context<UpdateCollateralConfig>,
         _mint: Pubkey,
@@ -160,11 +637,20 @@ pub mod metlev_engine {
         ctx.accounts.update_oracle(oracle)
     }
 
+    pub fn update_collateral_backup_oracles(
+        ctx: Context<UpdateCollateralConfig>,
+        _mint: Pubkey,
+        backup_oracles: [Pubkey; 2],
+    ) -> Result<()> {
+        ctx.accounts.update_backup_oracles(backup_oracles)
+    }
+
     pub fn initialize_mock_oracle(
         ctx: Context<InitializeMockOracle>,
         price: u64,
+        decimals: u8,
     ) -> Result<()> {
-        ctx.accounts.initialize_mock_oracle(&ctx.bumps, price)
+        ctx.accounts.initialize_mock_oracle(&ctx.bumps, price, decimals)
     }
 
     pub fn update_mock_oracle(
@@ -173,4 +659,19 @@ pub mod metlev_engine {
     ) -> Result<()> {
         ctx.accounts.update_mock_oracle(price)
     }
+
+    pub fn set_mock_oracle_staleness(
+        ctx: Context<UpdateMockOracle>,
+        heartbeat_seconds: u64,
+        force_stale: bool,
+    ) -> Result<()> {
+        ctx.accounts.set_mock_oracle_staleness(heartbeat_seconds, force_stale)
+    }
+
+    pub fn transfer_oracle_authority(
+        ctx: Context<UpdateMockOracle>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.transfer_oracle_authority(new_authority)
+    }
 }
\ No newline at end of file
@@ -1,10 +1,26 @@
 use anchor_lang::prelude::*;
+use crate::errors::ProtocolError;
+
+/// Capacity of `Config::lb_pair_whitelist`
+pub const MAX_WHITELISTED_PAIRS: usize = 16;
 
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
     pub authority: Pubkey,
+    /// Proposed new authority; takes over only once it calls accept_authority
+    pub pending_authority: Option<Pubkey>,
     pub paused: bool,
+    /// Blocks new borrows (open, increase, rebalance) only; closes, repays
+    /// and liquidations keep working
+    pub open_paused: bool,
+    /// Blocks new collateral deposits only
+    pub deposit_paused: bool,
+    /// Restricts liquidate and seize_collateral to registered keepers
+    pub permissioned_liquidation: bool,
+    /// DLMM pairs open_position may deploy into
+    #[max_len(MAX_WHITELISTED_PAIRS)]
+    pub lb_pair_whitelist: Vec<Pubkey>,
     pub bump: u8,
 }
 
@@ -14,6 +30,41 @@ impl Config {
     pub fn is_paused(&self) -> bool {
         self.paused
     }
+
+    /// The global pause still covers everything it used to.
+    pub fn opens_paused(&self) -> bool {
+        self.paused || self.open_paused
+    }
+
+    pub fn deposits_paused(&self) -> bool {
+        self.paused || self.deposit_paused
+    }
+
+    /// Anyone may liquidate unless `permissioned_liquidation` is on, in which
+    /// case only a signer with a `Keeper` registration may.
+    pub fn can_liquidate(&self, registered_keeper: bool) -> bool {
+        !self.permissioned_liquidation || registered_keeper
+    }
+
+    pub fn is_pair_whitelisted(&self, lb_pair: &Pubkey) -> bool {
+        self.lb_pair_whitelist.contains(lb_pair)
+    }
+}
+
+/// Which price account layout `CollateralConfig::oracle` points at
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum OracleKind {
+    /// `MockOracle` PDA owned by this program (localnet/devnet)
+    Mock,
+    /// Pyth v2 push-oracle price account
+    Pyth,
+}
+
+/// A price the protocol acted on, kept in `CollateralConfig::price_history`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct PriceObservation {
+    pub price: u64,
+    pub timestamp: i64,
 }
 
 #[account]
@@ -25,50 +76,552 @@ pub struct CollateralConfig {
     /// Price oracle account (Pyth/Switchboard)
     pub oracle: Pubkey,
 
+    /// Parser used to read `oracle`
+    pub oracle_kind: OracleKind,
+
+    /// Extra feeds of the same kind aggregated with `oracle` by median
+    /// (default = unused)
+    pub backup_oracles: [Pubkey; 2],
+
     /// Maximum loan-to-value ratio (basis points, 7500 = 75%)
     pub max_ltv: u16,
 
     /// Liquidation threshold (basis points, 8000 = 80%)
     pub liquidation_threshold: u16,
 
-    /// Liquidation penalty paid to liquidator (basis points, 500 = 5%)
-    pub liquidation_penalty: u16,
+    /// Total taken from a liquidated position above the debt repaid (basis points, 500 = 5%)
+    pub liquidation_penalty_bps: u16,
+
+    /// Liquidator's share of that penalty (basis points of value, <= liquidation_penalty_bps);
+    /// the rest goes to the insurance fund
+    pub liquidator_bonus_bps: u16,
+
+    /// Penalty charged by liquidate at health 1.0 when tiering is on (basis points)
+    pub min_penalty_bps: u16,
+
+    /// Penalty charged at or below PENALTY_FLOOR_HEALTH_BPS (basis points, 0 = tiering
+    /// off and liquidation_penalty_bps applies flat)
+    pub max_penalty_bps: u16,
+
+    /// Max share of debt repaid by a single partial liquidation (basis points, 5000 = 50%)
+    pub close_factor_bps: u16,
+
+    /// LTV at which liquidation skips the grace period (basis points, 10_000 = never)
+    pub hard_liquidation_threshold: u16,
+
+    /// Delay between a position being flagged and a soft liquidation (seconds, 0 = off)
+    pub grace_period_seconds: i64,
+
+    /// Time after open_position before the position can be liquidated unless
+    /// LTV reaches `hard_liquidation_threshold` (seconds, 0 = off)
+    pub min_position_age_seconds: i64,
+
+    /// Maximum leverage accepted by open_position (basis points, 10_000 = 1x borrow)
+    pub max_leverage_bps: u64,
+
+    /// Health factor a position must exceed at open (basis points, 10_500 = 1.05)
+    pub min_open_health_bps: u64,
 
     /// Minimum deposit amount (in native token units)
     pub min_deposit: u64,
 
+    /// Smallest borrow open_position accepts, and the smallest debt a partial
+    /// unwind, repay, seizure or partial liquidation may leave behind (wSOL
+    /// lamports, 0 = off)
+    pub min_position_borrow: u64,
+
+    /// Largest debt a single position may carry after open_position or
+    /// increase_leverage, whatever its collateral (wSOL lamports, u64::MAX = uncapped)
+    pub max_position_debt: u64,
+
     /// Interest rate for borrowing (basis points per year, 500 = 5%)
     pub interest_rate_bps: u16,
 
     /// Maximum oracle staleness in seconds
     pub oracle_max_age: u64,
 
+    /// Maximum oracle staleness accepted when opening or growing a position
+    pub open_oracle_max_age: u64,
+
+    /// Maximum oracle staleness accepted when liquidating a position
+    pub liquidation_oracle_max_age: u64,
+
+    /// Maximum oracle confidence interval relative to price (basis points, 100 = 1%)
+    pub max_confidence_bps: u16,
+
+    /// Largest move from `last_price` accepted within the deviation window (basis points, 0 = off)
+    pub max_price_deviation_bps: u16,
+
+    /// Haircut on `last_price` that liquidate falls back to while the oracle
+    /// is stale (basis points, 0 = off)
+    pub stale_liquidation_discount_bps: u16,
+
+    /// Price recorded by the last open or liquidation
+    pub last_price: u64,
+
+    /// When `last_price` was recorded
+    pub last_price_ts: i64,
+
+    /// Ring buffer of the last PRICE_HISTORY_LEN recorded prices, for audits
+    pub price_history: [PriceObservation; 8],
+
+    /// Slot the next recorded price goes into
+    pub price_history_head: u8,
+
+    /// DLMM pair trading this collateral against wSOL, used to turn seized
+    /// collateral into wSOL for repayment (default = none registered)
+    pub swap_lb_pair: Pubkey,
+
+    /// Positions deposited against this collateral that are still active;
+    /// it can only be deregistered at zero
+    pub active_positions: u64,
+
     /// Token decimals (e.g., 9 for SOL, 6 for USDC)
     pub decimals: u8,
 
     /// Whether this collateral is enabled
     pub enabled: bool,
 
+    /// Temporarily blocks new deposits and opens for this collateral only
+    pub paused: bool,
+
     /// Bump seed for PDA
     pub bump: u8,
 }
 
 impl CollateralConfig {
     pub const SEED_PREFIX: &'static [u8] = b"collateral_config";
+    pub const DEFAULT_CLOSE_FACTOR_BPS: u16 = 5000;
+    pub const DEFAULT_HARD_LIQUIDATION_THRESHOLD: u16 = 10_000;
+    pub const DEFAULT_MAX_CONFIDENCE_BPS: u16 = 100;
+    pub const DEFAULT_MIN_OPEN_HEALTH_BPS: u64 = 10_500;
+    pub const DEFAULT_MAX_PRICE_DEVIATION_BPS: u16 = 2000;
+    /// How long a recorded price keeps constraining the next one
+    pub const PRICE_DEVIATION_WINDOW: i64 = 300;
+    /// Health factor at and below which the full max_penalty_bps applies
+    pub const PENALTY_FLOOR_HEALTH_BPS: u64 = 8_000;
+    /// Entries kept in price_history
+    pub const PRICE_HISTORY_LEN: usize = 8;
+    /// Health factor a position must be at or below to be liquidated at the
+    /// stale fallback price
+    pub const STALE_LIQUIDATION_MAX_HEALTH_BPS: u64 = 9_500;
 
     pub fn validate_ltv(&self, ltv: u64) -> bool {
         ltv <= self.max_ltv as u64
     }
 
+    pub fn validate_leverage(&self, leverage: u64) -> bool {
+        leverage <= self.max_leverage_bps
+    }
+
+    pub fn validate_position_borrow(&self, borrow_amount: u64) -> bool {
+        borrow_amount > 0 && borrow_amount >= self.min_position_borrow
+    }
+
+    /// Checked against the position's total debt after a borrow, not the
+    /// borrow alone.
+    pub fn validate_position_debt(&self, debt_amount: u64) -> bool {
+        debt_amount <= self.max_position_debt
+    }
+
+    /// Remaining debt too small to be worth repaying on its own.
+    pub fn is_dust_debt(&self, debt_amount: u64) -> bool {
+        debt_amount > 0 && debt_amount < self.min_position_borrow
+    }
+
+    pub fn validate_open_health(&self, health_factor: u64) -> bool {
+        health_factor > self.min_open_health_bps
+    }
+
     pub fn is_liquidatable(&self, ltv: u64) -> bool {
         ltv >= self.liquidation_threshold as u64
     }
 
+    /// Past this LTV a position is liquidated without waiting out the grace period
+    pub fn is_hard_liquidatable(&self, ltv: u64) -> bool {
+        ltv >= self.hard_liquidation_threshold as u64
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// max_ltv < liquidation_threshold <= hard_liquidation_threshold, all
+    /// within 100%. A threshold above 100% could never be reached and would
+    /// silently disable liquidation.
     pub fn validate_thresholds(&self) -> bool {
         self.liquidation_threshold > self.max_ltv
+            && self.liquidation_threshold <= 10_000
+            && self.hard_liquidation_threshold >= self.liquidation_threshold
+    }
+
+    pub fn track_position_opened(&mut self) -> Result<()> {
+        self.active_positions = self.active_positions
+            .checked_add(1)
+            .ok_or(ProtocolError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Fails rather than wrapping if a position ends that was never counted.
+    pub fn track_position_ended(&mut self) -> Result<()> {
+        self.active_positions = self.active_positions
+            .checked_sub(1)
+            .ok_or(ProtocolError::MathUnderflow)?;
+        Ok(())
+    }
+
+    /// The liquidator can't be paid more than the borrower is charged.
+    pub fn validate_liquidator_bonus(&self) -> bool {
+        self.liquidator_bonus_bps <= self.liquidation_penalty_bps
+    }
+
+    /// Tiers must rise with risk and stay under the 20% penalty ceiling;
+    /// (0, 0) turns tiering off.
+    pub fn validate_penalty_tiers(&self) -> bool {
+        self.min_penalty_bps <= self.max_penalty_bps && self.max_penalty_bps <= 2000
     }
+
+    /// Penalty liquidate charges at `health_factor` (basis points, 10_000 =
+    /// 1.0): min_penalty_bps at 1.0, rising linearly to max_penalty_bps at
+    /// PENALTY_FLOOR_HEALTH_BPS and below. With tiering off it is
+    /// liquidation_penalty_bps whatever the health.
+    pub fn effective_penalty_bps(&self, health_factor: u64) -> u16 {
+        if self.max_penalty_bps == 0 {
+            return self.liquidation_penalty_bps;
+        }
+        let floor = Self::PENALTY_FLOOR_HEALTH_BPS;
+        if health_factor >= 10_000 {
+            return self.min_penalty_bps;
+        }
+        if health_factor <= floor {
+            return self.max_penalty_bps;
+        }
+        let range = (self.max_penalty_bps - self.min_penalty_bps) as u64;
+        let step = range * (10_000 - health_factor) / (10_000 - floor);
+        self.min_penalty_bps + step as u16
+    }
+
+    /// Circuit breaker against single-block oracle manipulation: reject a
+    /// price more than `max_price_deviation_bps` away from the one recorded
+    /// within the last `PRICE_DEVIATION_WINDOW` seconds, otherwise record it.
+    pub fn check_and_record_price(&mut self, price: u64, now: i64) -> Result<()> {
+        let recent = self.last_price > 0
+            && now.saturating_sub(self.last_price_ts) <= Self::PRICE_DEVIATION_WINDOW;
+
+        if recent && self.max_price_deviation_bps > 0 {
+            let deviation_bps = (price.abs_diff(self.last_price) as u128)
+                .checked_mul(10_000)
+                .map(|v| v / self.last_price as u128)
+                .ok_or(ProtocolError::MathOverflow)?;
+            require!(
+                deviation_bps <= self.max_price_deviation_bps as u128,
+                ProtocolError::PriceDeviationTooHigh
+            );
+        }
+
+        self.last_price = price;
+        self.last_price_ts = now;
+        self.record_price_observation(price, now);
+        Ok(())
+    }
+
+    /// Price liquidate may use while the oracle is stale: the last recorded
+    /// price minus stale_liquidation_discount_bps. None when the fallback is
+    /// off or no price has been recorded yet.
+    pub fn stale_fallback_price(&self) -> Option<u64> {
+        if self.stale_liquidation_discount_bps == 0 || self.last_price == 0 {
+            return None;
+        }
+        let discounted = self.last_price as u128
+            * (10_000 - self.stale_liquidation_discount_bps.min(10_000)) as u128
+            / 10_000;
+        u64::try_from(discounted).ok().filter(|&p| p > 0)
+    }
+
+    /// Overwrite the oldest price_history entry
+    fn record_price_observation(&mut self, price: u64, timestamp: i64) {
+        let head = self.price_history_head as usize % Self::PRICE_HISTORY_LEN;
+        self.price_history[head] = PriceObservation { price, timestamp };
+        self.price_history_head = ((head + 1) % Self::PRICE_HISTORY_LEN) as u8;
+    }
+
+    /// Recorded prices, oldest first, skipping slots never written
+    pub fn price_history(&self) -> Vec<PriceObservation> {
+        let head = self.price_history_head as usize % Self::PRICE_HISTORY_LEN;
+        (0..Self::PRICE_HISTORY_LEN)
+            .map(|i| self.price_history[(head + i) % Self::PRICE_HISTORY_LEN])
+            .filter(|obs| obs.timestamp != 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_granular_pause_flags() {
+        let mut cfg = Config {
+            authority: Pubkey::default(),
+            pending_authority: None,
+            paused: false,
+            open_paused: true,
+            deposit_paused: false,
+            permissioned_liquidation: false,
+            lb_pair_whitelist: Vec::new(),
+            bump: 0,
+        };
+        assert!(cfg.opens_paused());
+        assert!(!cfg.deposits_paused());
+
+        cfg.open_paused = false;
+        cfg.deposit_paused = true;
+        assert!(!cfg.opens_paused());
+        assert!(cfg.deposits_paused());
+
+        // The global switch covers both
+        cfg.deposit_paused = false;
+        cfg.paused = true;
+        assert!(cfg.opens_paused());
+        assert!(cfg.deposits_paused());
+    }
+
+    #[test]
+    fn test_permissioned_liquidation() {
+        let mut cfg = Config {
+            authority: Pubkey::default(),
+            pending_authority: None,
+            paused: false,
+            open_paused: false,
+            deposit_paused: false,
+            permissioned_liquidation: false,
+            lb_pair_whitelist: Vec::new(),
+            bump: 0,
+        };
+        assert!(cfg.can_liquidate(false));
+        assert!(cfg.can_liquidate(true));
+
+        cfg.permissioned_liquidation = true;
+        assert!(!cfg.can_liquidate(false));
+        assert!(cfg.can_liquidate(true));
+    }
+
+    fn config() -> CollateralConfig {
+        CollateralConfig {
+            mint: Pubkey::default(),
+            oracle: Pubkey::default(),
+            oracle_kind: OracleKind::Mock,
+            backup_oracles: [Pubkey::default(); 2],
+            max_ltv: 7500,
+            liquidation_threshold: 8000,
+            liquidation_penalty_bps: 500,
+            liquidator_bonus_bps: 500,
+            min_penalty_bps: 0,
+            max_penalty_bps: 0,
+            close_factor_bps: CollateralConfig::DEFAULT_CLOSE_FACTOR_BPS,
+            hard_liquidation_threshold: CollateralConfig::DEFAULT_HARD_LIQUIDATION_THRESHOLD,
+            grace_period_seconds: 0,
+            min_position_age_seconds: 0,
+            max_leverage_bps: 50_000,
+            min_open_health_bps: CollateralConfig::DEFAULT_MIN_OPEN_HEALTH_BPS,
+            min_deposit: 0,
+            min_position_borrow: 0,
+            max_position_debt: u64::MAX,
+            interest_rate_bps: 500,
+            oracle_max_age: 60,
+            open_oracle_max_age: 60,
+            liquidation_oracle_max_age: 60,
+            max_confidence_bps: CollateralConfig::DEFAULT_MAX_CONFIDENCE_BPS,
+            max_price_deviation_bps: CollateralConfig::DEFAULT_MAX_PRICE_DEVIATION_BPS,
+            stale_liquidation_discount_bps: 0,
+            last_price: 0,
+            last_price_ts: 0,
+            price_history: [PriceObservation::default(); CollateralConfig::PRICE_HISTORY_LEN],
+            price_history_head: 0,
+            swap_lb_pair: Pubkey::default(),
+            active_positions: 0,
+            decimals: 9,
+            enabled: true,
+            paused: false,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_price_breaker_rejects_sudden_jump() {
+        let mut cfg = config();
+        // First read has nothing to compare against
+        cfg.check_and_record_price(150_000_000, 1_000).unwrap();
+
+        // A 50% jump (either way) a few seconds later trips the breaker
+        assert_eq!(
+            cfg.check_and_record_price(225_000_000, 1_005).unwrap_err(),
+            ProtocolError::PriceDeviationTooHigh.into()
+        );
+        assert_eq!(
+            cfg.check_and_record_price(75_000_000, 1_005).unwrap_err(),
+            ProtocolError::PriceDeviationTooHigh.into()
+        );
+        assert_eq!(cfg.last_price, 150_000_000);
+
+        // A move within the limit is accepted and becomes the new reference
+        cfg.check_and_record_price(165_000_000, 1_010).unwrap();
+        assert_eq!(cfg.last_price, 165_000_000);
+        assert_eq!(cfg.last_price_ts, 1_010);
+    }
+
+    #[test]
+    fn test_price_breaker_resets_after_window() {
+        let mut cfg = config();
+        cfg.check_and_record_price(150_000_000, 1_000).unwrap();
+
+        let later = 1_000 + CollateralConfig::PRICE_DEVIATION_WINDOW + 1;
+        cfg.check_and_record_price(225_000_000, later).unwrap();
+        assert_eq!(cfg.last_price, 225_000_000);
+
+        // Disabled breaker accepts anything
+        cfg.max_price_deviation_bps = 0;
+        cfg.check_and_record_price(1_000_000, later + 1).unwrap();
+    }
+
+    #[test]
+    fn test_price_history_keeps_last_entries_in_order() {
+        let mut cfg = config();
+        cfg.max_price_deviation_bps = 0;
+        assert!(cfg.price_history().is_empty());
+
+        for i in 1..=3u64 {
+            cfg.check_and_record_price(i * 1_000, i as i64).unwrap();
+        }
+        let prices: Vec<u64> = cfg.price_history().iter().map(|o| o.price).collect();
+        assert_eq!(prices, vec![1_000, 2_000, 3_000]);
+
+        // Wrapping past the buffer drops the oldest entries first
+        for i in 4..=10u64 {
+            cfg.check_and_record_price(i * 1_000, i as i64).unwrap();
+        }
+        let history = cfg.price_history();
+        assert_eq!(history.len(), CollateralConfig::PRICE_HISTORY_LEN);
+        assert_eq!(history[0], PriceObservation { price: 3_000, timestamp: 3 });
+        assert_eq!(history[7], PriceObservation { price: 10_000, timestamp: 10 });
+
+        // A price the breaker rejects is not logged
+        cfg.max_price_deviation_bps = 100;
+        assert!(cfg.check_and_record_price(20_000, 11).is_err());
+        assert_eq!(cfg.price_history()[7].price, 10_000);
+    }
+
+    #[test]
+    fn test_penalty_tiers_scale_with_health() {
+        let mut c = config();
+        // Tiering off: the flat penalty at any health
+        assert_eq!(c.effective_penalty_bps(9_900), 500);
+        assert_eq!(c.effective_penalty_bps(7_000), 500);
+
+        c.min_penalty_bps = 200;
+        c.max_penalty_bps = 1_000;
+        assert!(c.validate_penalty_tiers());
+
+        // Barely under water pays close to the minimum
+        assert_eq!(c.effective_penalty_bps(9_900), 240);
+        // Halfway to the floor pays halfway between the tiers
+        assert_eq!(c.effective_penalty_bps(9_000), 600);
+        // Deeply under water pays the maximum
+        assert_eq!(c.effective_penalty_bps(7_000), 1_000);
+
+        assert_eq!(c.effective_penalty_bps(10_000), 200);
+        assert_eq!(c.effective_penalty_bps(CollateralConfig::PENALTY_FLOOR_HEALTH_BPS), 1_000);
+
+        c.min_penalty_bps = 1_100;
+        assert!(!c.validate_penalty_tiers());
+        c.min_penalty_bps = 0;
+        c.max_penalty_bps = 2_001;
+        assert!(!c.validate_penalty_tiers());
+    }
+
+    #[test]
+    fn test_min_position_borrow_boundary() {
+        let mut c = config();
+        // Off by default, but a zero borrow is never a position
+        assert!(c.validate_position_borrow(1));
+        assert!(!c.validate_position_borrow(0));
+        assert!(!c.is_dust_debt(1));
+
+        c.min_position_borrow = 10_000_000;
+        assert!(!c.validate_position_borrow(9_999_999));
+        assert!(c.validate_position_borrow(10_000_000));
+
+        // Paying debt off entirely is always fine; leaving a sliver is not
+        assert!(!c.is_dust_debt(0));
+        assert!(c.is_dust_debt(9_999_999));
+        assert!(!c.is_dust_debt(10_000_000));
+    }
+
+    #[test]
+    fn test_max_position_debt_boundary() {
+        let mut c = config();
+        // Uncapped by default
+        assert!(c.validate_position_debt(u64::MAX));
+
+        c.max_position_debt = 50 * 1_000_000_000;
+        assert!(c.validate_position_debt(50 * 1_000_000_000));
+        assert!(!c.validate_position_debt(50 * 1_000_000_000 + 1));
+    }
+
+    #[test]
+    fn test_thresholds_bounded_at_100_percent() {
+        let mut c = config();
+        c.max_ltv = 9_999;
+        c.liquidation_threshold = 10_000;
+        c.hard_liquidation_threshold = 10_000;
+        assert!(c.validate_thresholds());
+
+        c.liquidation_threshold = 10_001;
+        c.hard_liquidation_threshold = 10_001;
+        assert!(!c.validate_thresholds());
+
+        // A typo'd 600% LTV can't slip past a matching threshold
+        c.max_ltv = 60_000;
+        c.liquidation_threshold = 60_001;
+        assert!(!c.validate_thresholds());
+    }
+
+    #[test]
+    fn test_active_positions_tracks_lifecycle() {
+        let mut c = config();
+        c.track_position_opened().unwrap();
+        c.track_position_opened().unwrap();
+        assert_eq!(c.active_positions, 2);
+
+        c.track_position_ended().unwrap();
+        c.track_position_ended().unwrap();
+        assert_eq!(c.active_positions, 0);
+
+        // Never wraps below zero
+        assert_eq!(
+            c.track_position_ended().unwrap_err(),
+            ProtocolError::MathUnderflow.into()
+        );
+        assert_eq!(c.active_positions, 0);
+    }
+
+    #[test]
+    fn test_stale_fallback_price_discounts_last_price() {
+        let mut c = config();
+        // Off by default, and meaningless before any price is recorded
+        assert_eq!(c.stale_fallback_price(), None);
+        c.stale_liquidation_discount_bps = 1_000;
+        assert_eq!(c.stale_fallback_price(), None);
+
+        c.check_and_record_price(150_000_000, 1_000).unwrap();
+        assert_eq!(c.stale_fallback_price(), Some(135_000_000));
+
+        c.stale_liquidation_discount_bps = 0;
+        assert_eq!(c.stale_fallback_price(), None);
+    }
+
 }
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+/// Registration of a keeper allowed to liquidate while
+/// `Config::permissioned_liquidation` is on. Its existence is the permission.
+#[account]
+#[derive(InitSpace)]
+pub struct Keeper {
+    /// Keeper wallet that signs liquidations
+    pub keeper: Pubkey,
+
+    /// Timestamp when the authority registered it
+    pub added_at: i64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl Keeper {
+    pub const SEED_PREFIX: &'static [u8] = b"keeper";
+}
@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use super::Position;
 
 /// Mock lending vault for POC
 #[account]
@@ -6,40 +7,963 @@ use anchor_lang::prelude::*;
 pub struct LendingVault {
     /// Vault authority (program PDA)
     pub authority: Pubkey,
+    /// Asset LPs supply and borrowers draw, fixed at init. Only wSOL can be
+    /// borrowed through `open_position` / `increase_leverage` so far
+    pub borrow_mint: Pubkey,
+    /// LP assets: supplied principal plus borrower interest earned
     pub total_supplied: u64,
     pub total_borrowed: u64,
-    /// Simple interest rate (basis points per year, 500 = 5%)
-    pub interest_rate_bps: u16,
+    /// LP shares outstanding; each is a claim on total_supplied / total_shares
+    pub total_shares: u64,
+    /// Borrow rate at 0% utilization (basis points per year)
+    pub base_rate_bps: u16,
+    /// Rate added between 0% and optimal utilization (basis points per year)
+    pub slope1_bps: u16,
+    /// Rate added between optimal and 100% utilization (basis points per year)
+    pub slope2_bps: u16,
+    /// Utilization at which the rate curve kinks (basis points, 8000 = 80%)
+    pub optimal_utilization_bps: u16,
+    /// Ceiling on the borrow rate, whatever the utilization (basis points per year)
+    pub max_borrow_rate_bps: u16,
+    /// Share of borrower interest kept by the protocol (basis points, 1000 = 10%)
+    pub reserve_factor_bps: u16,
+    /// Protocol's cut of interest, held in wsol_vault until collected
+    pub protocol_fees: u64,
+    /// wSOL held in wsol_vault to absorb bad debt before LPs take a loss
+    pub insurance_fund: u64,
+    /// Cumulative borrow index, compounded on every accrual (INDEX_ONE = 1.0)
+    pub borrow_index: u128,
+    /// Ceiling on total_borrowed; u64::MAX means uncapped
+    pub borrow_cap: u64,
+    /// Ceiling on total_supplied for new deposits; u64::MAX means uncapped
+    pub supply_cap: u64,
+    /// Share of total_supplied kept back from borrowers so LPs can always
+    /// withdraw something (basis points, 0 = off)
+    pub min_vault_reserve_bps: u16,
+    /// Time an LP must wait after supplying before withdrawing or queueing a
+    /// withdrawal (seconds, 0 = off)
+    pub supply_cooldown_seconds: i64,
+    /// Fee on flash loans, paid into protocol_fees (basis points, 9 = 0.09%)
+    pub flash_loan_fee_bps: u16,
+    /// Principal of the flash loan in flight; 0 when none is outstanding
+    pub flash_loan_amount: u64,
+    /// Lifetime wSOL moved out of wsol_vault by emergency_withdraw
+    pub emergency_withdrawn: u64,
+    /// Ticket of the next withdrawal request to be served
+    pub withdrawal_queue_head: u64,
+    /// Ticket handed to the next withdrawal request queued
+    pub withdrawal_queue_tail: u64,
     /// Last time interest was accrued
     pub last_update: i64,
     pub bump: u8,
     pub vault_bump: u8,
 }
 
+/// The tunable part of the interest-rate model, as set by `update_vault_params`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterestRateParams {
+    pub base_rate_bps: u16,
+    pub slope1_bps: u16,
+    pub slope2_bps: u16,
+    pub optimal_utilization_bps: u16,
+    pub max_borrow_rate_bps: u16,
+    pub reserve_factor_bps: u16,
+}
+
+impl InterestRateParams {
+    /// Highest rate the uncapped curve may reach at 100% utilization
+    pub const MAX_MODEL_RATE_BPS: u32 = 50_000;
+
+    /// The kink must sit strictly inside (0%, 100%), the full curve must stay
+    /// under MAX_MODEL_RATE_BPS, the cap must leave room above the base rate
+    /// and the reserve factor can't exceed 100%.
+    pub fn is_valid(&self) -> bool {
+        let model_max =
+            self.base_rate_bps as u32 + self.slope1_bps as u32 + self.slope2_bps as u32;
+        self.optimal_utilization_bps > 0
+            && self.optimal_utilization_bps < 10_000
+            && model_max <= Self::MAX_MODEL_RATE_BPS
+            && self.max_borrow_rate_bps > self.base_rate_bps
+            && self.reserve_factor_bps <= 10_000
+    }
+}
+
 impl LendingVault {
     pub const SEED_PREFIX: &'static [u8] = b"lending_vault";
+    pub const DEFAULT_BASE_RATE_BPS: u16 = 0;
+    pub const DEFAULT_SLOPE1_BPS: u16 = 400;
+    pub const DEFAULT_SLOPE2_BPS: u16 = 7500;
+    pub const DEFAULT_OPTIMAL_UTILIZATION_BPS: u16 = 8000;
+    pub const DEFAULT_MAX_BORROW_RATE_BPS: u16 = 10_000;
+    pub const DEFAULT_RESERVE_FACTOR_BPS: u16 = 1000;
+    pub const DEFAULT_FLASH_LOAN_FEE_BPS: u16 = 9;
+    pub const DEFAULT_MIN_VAULT_RESERVE_BPS: u16 = 0;
+    pub const DEFAULT_SUPPLY_COOLDOWN_SECONDS: i64 = 0;
+    pub const INDEX_ONE: u128 = 1_000_000_000_000_000_000;
+
+    pub fn rate_params(&self) -> InterestRateParams {
+        InterestRateParams {
+            base_rate_bps: self.base_rate_bps,
+            slope1_bps: self.slope1_bps,
+            slope2_bps: self.slope2_bps,
+            optimal_utilization_bps: self.optimal_utilization_bps,
+            max_borrow_rate_bps: self.max_borrow_rate_bps,
+            reserve_factor_bps: self.reserve_factor_bps,
+        }
+    }
+
+    pub fn set_rate_params(&mut self, params: InterestRateParams) {
+        self.base_rate_bps = params.base_rate_bps;
+        self.slope1_bps = params.slope1_bps;
+        self.slope2_bps = params.slope2_bps;
+        self.optimal_utilization_bps = params.optimal_utilization_bps;
+        self.max_borrow_rate_bps = params.max_borrow_rate_bps;
+        self.reserve_factor_bps = params.reserve_factor_bps;
+    }
 
     /// Get available liquidity to borrow
     pub fn available_liquidity(&self) -> u64 {
         self.total_supplied.saturating_sub(self.total_borrowed)
     }
 
-    /// Check if vault has enough liquidity for borrow amount
+    /// Liquidity held back from borrowers: min_vault_reserve_bps of total_supplied
+    pub fn min_reserve(&self) -> u64 {
+        let reserve = (self.total_supplied as u128) * (self.min_vault_reserve_bps as u128) / 10_000;
+        reserve as u64
+    }
+
+    /// Available liquidity less the reserve; what borrowers may still draw
+    pub fn borrowable_liquidity(&self) -> u64 {
+        self.available_liquidity().saturating_sub(self.min_reserve())
+    }
+
+    /// Check if vault has enough liquidity for borrow amount without
+    /// dipping into the reserve
     pub fn can_borrow(&self, amount: u64) -> bool {
-        self.available_liquidity() >= amount
+        self.borrowable_liquidity() >= amount
+    }
+
+    /// Share of supplied liquidity currently borrowed (basis points, capped at 100%)
+    pub fn utilization_bps(&self) -> u64 {
+        if self.total_supplied == 0 {
+            return 0;
+        }
+        let utilization = (self.total_borrowed as u128) * 10_000 / (self.total_supplied as u128);
+        utilization.min(10_000) as u64
+    }
+
+    /// Annual borrow rate from the kinked model (basis points), clamped to
+    /// max_borrow_rate_bps.
+    /// Below the kink: base + slope1 * u / optimal
+    /// Above the kink: base + slope1 + slope2 * (u - optimal) / (1 - optimal)
+    pub fn borrow_rate_bps(&self) -> u64 {
+        let utilization = self.utilization_bps();
+        let optimal = self.optimal_utilization_bps as u64;
+        let base = self.base_rate_bps as u64;
+        let slope1 = self.slope1_bps as u64;
+        let slope2 = self.slope2_bps as u64;
+
+        let rate = if utilization <= optimal {
+            if optimal == 0 {
+                base
+            } else {
+                base + slope1 * utilization / optimal
+            }
+        } else {
+            base + slope1 + slope2 * (utilization - optimal) / (10_000 - optimal)
+        };
+        rate.min(self.max_borrow_rate_bps as u64)
+    }
+
+    /// Annual rate earned by suppliers: borrow interest spread over all supplied liquidity
+    pub fn supply_rate_bps(&self) -> u64 {
+        self.borrow_rate_bps() * self.utilization_bps() / 10_000
+    }
+
+    /// Borrow APY (basis points). The index compounds on every accrual, so the
+    /// rate is compounded continuously: e^rate - 1.
+    pub fn borrow_apy_bps(&self) -> u64 {
+        apy_from_apr_bps(self.borrow_rate_bps())
+    }
+
+    /// Supply APY (basis points), net of the reserve factor kept by the protocol
+    pub fn supply_apy_bps(&self) -> u64 {
+        let lp_share = 10_000u64.saturating_sub(self.reserve_factor_bps as u64);
+        apy_from_apr_bps(self.supply_rate_bps() * lp_share / 10_000)
+    }
+
+    /// Compound the borrow index at the current rate over the time since the
+    /// last accrual, and book the interest outstanding borrows earned over
+    /// it, so LP value keeps up whether or not any position is touched.
+    /// Must run before anything that changes utilization.
+    /// Interest rounds up, keeping total_borrowed at or above the sum of
+    /// position debts (which round down); the difference is dust of at most
+    /// a lamport per accrual. Returns how much the index grew.
+    pub fn accrue_interest(&mut self, current_time: i64) -> Result<u128> {
+        let before = self.borrow_index;
+        let elapsed = (current_time - self.last_update).max(0) as u128;
+        if elapsed > 0 {
+            self.borrow_index = compound(self.borrow_index, self.borrow_rate_bps(), elapsed);
+        }
+        self.last_update = current_time;
+
+        let index_delta = self.borrow_index - before;
+        if index_delta > 0 && self.total_borrowed > 0 {
+            let interest = (self.total_borrowed as u128)
+                .saturating_mul(index_delta)
+                .div_ceil(before);
+            let interest = u64::try_from(interest)
+                .map_err(|_| crate::errors::ProtocolError::MathOverflow)?;
+            self.accrue_debt(interest)?;
+        }
+        Ok(index_delta)
+    }
+
+    /// Shares minted for a deposit: amount * total_shares / total_supplied,
+    /// 1:1 for the first depositor. Rounds down in favour of the vault.
+    pub fn shares_for_deposit(&self, amount: u64) -> Result<u64> {
+        if self.total_shares == 0 {
+            return Ok(amount);
+        }
+        require!(self.total_supplied > 0, crate::errors::ProtocolError::MathOverflow);
+        let shares = (amount as u128) * (self.total_shares as u128) / (self.total_supplied as u128);
+        u64::try_from(shares).map_err(|_| crate::errors::ProtocolError::MathOverflow.into())
+    }
+
+    /// Assets redeemed by burning shares: shares * total_supplied / total_shares.
+    /// Rounds down in favour of the vault.
+    pub fn assets_for_shares(&self, shares: u64) -> Result<u64> {
+        require!(
+            shares <= self.total_shares,
+            crate::errors::ProtocolError::InvalidAmount
+        );
+        if shares == 0 {
+            return Ok(0);
+        }
+        let assets = (shares as u128) * (self.total_supplied as u128) / (self.total_shares as u128);
+        Ok(assets as u64)
+    }
+
+    /// Shares burned to pay out `amount` of a larger queued redemption:
+    /// amount * total_shares / total_supplied, rounded up in favour of the vault.
+    pub fn shares_for_withdrawal(&self, amount: u64) -> Result<u64> {
+        require!(self.total_supplied > 0, crate::errors::ProtocolError::MathOverflow);
+        let shares = ((amount as u128) * (self.total_shares as u128))
+            .div_ceil(self.total_supplied as u128);
+        u64::try_from(shares).map_err(|_| crate::errors::ProtocolError::MathOverflow.into())
+    }
+
+    pub fn has_queued_withdrawals(&self) -> bool {
+        self.withdrawal_queue_head < self.withdrawal_queue_tail
+    }
+
+    /// Hand out the next queue ticket
+    pub fn enqueue_withdrawal(&mut self) -> Result<u64> {
+        let ticket = self.withdrawal_queue_tail;
+        self.withdrawal_queue_tail = ticket.checked_add(1)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        Ok(ticket)
+    }
+
+    /// Move on to the next ticket once the head request is fully served
+    pub fn dequeue_withdrawal(&mut self) {
+        self.withdrawal_queue_head += 1;
+    }
+
+    /// Record an LP deposit and the shares minted for it
+    pub fn deposit(&mut self, amount: u64, shares: u64) -> Result<()> {
+        let total_supplied = self.total_supplied.checked_add(amount)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        require!(
+            total_supplied <= self.supply_cap,
+            crate::errors::ProtocolError::SupplyCapExceeded
+        );
+        self.total_supplied = total_supplied;
+        self.total_shares = self.total_shares.checked_add(shares)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Record an LP redemption and the shares burned for it. LPs can only
+    /// take idle liquidity: what is lent out, the protocol's reserves and
+    /// the insurance fund stay behind.
+    pub fn redeem(&mut self, amount: u64, shares: u64) -> Result<()> {
+        require!(
+            amount <= self.available_liquidity(),
+            crate::errors::ProtocolError::InsufficientLiquidity
+        );
+        self.total_supplied = self.total_supplied.checked_sub(amount)
+            .ok_or(crate::errors::ProtocolError::MathUnderflow)?;
+        self.total_shares = self.total_shares.checked_sub(shares)
+            .ok_or(crate::errors::ProtocolError::MathUnderflow)?;
+        Ok(())
     }
 
     pub fn borrow(&mut self, amount: u64) -> Result<()> {
+        self.total_borrowed = self.check_borrow(amount)?;
+        Ok(())
+    }
+
+    /// total_borrowed after lending `amount`, failing the way `borrow` would
+    /// without recording anything.
+    pub fn check_borrow(&self, amount: u64) -> Result<u64> {
         require!(self.can_borrow(amount), crate::errors::ProtocolError::InsufficientLiquidity);
-        self.total_borrowed = self.total_borrowed.checked_add(amount)
+        let total_borrowed = self.total_borrowed.checked_add(amount)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        require!(
+            total_borrowed <= self.borrow_cap,
+            crate::errors::ProtocolError::BorrowCapExceeded
+        );
+        Ok(total_borrowed)
+    }
+
+    /// Record interest accrued on outstanding borrows. reserve_factor_bps of
+    /// it goes to protocol_fees; the rest is owed to LPs, so it grows
+    /// total_supplied and every share appreciates.
+    pub fn accrue_debt(&mut self, interest: u64) -> Result<()> {
+        let reserve = (interest as u128) * (self.reserve_factor_bps as u128) / 10_000;
+        let reserve = reserve as u64;
+        self.total_borrowed = self.total_borrowed.checked_add(interest)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        self.total_supplied = self.total_supplied.checked_add(interest - reserve)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        self.protocol_fees = self.protocol_fees.checked_add(reserve)
             .ok_or(crate::errors::ProtocolError::MathOverflow)?;
         Ok(())
     }
 
+    /// Bring the vault and a position's debt up to date before the debt is
+    /// read. The vault already booked the position's interest in its totals.
+    pub fn accrue_position(&mut self, position: &mut Position, now: i64) -> Result<()> {
+        self.accrue_interest(now)?;
+        position.accrue_debt_interest(self.borrow_index, now);
+        Ok(())
+    }
+
+    /// Move reserves into the insurance fund, or credit wSOL the authority
+    /// has just transferred into wsol_vault.
+    pub fn fund_insurance(&mut self, amount: u64, from_reserves: bool) -> Result<()> {
+        if from_reserves {
+            require!(
+                amount <= self.protocol_fees,
+                crate::errors::ProtocolError::InvalidAmount
+            );
+            self.protocol_fees -= amount;
+        }
+        self.insurance_fund = self.insurance_fund.checked_add(amount)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Credit lamports that landed in wsol_vault outside any instruction and
+    /// were just wrapped by sync_native. They belong to no LP or borrower,
+    /// so they count as protocol revenue.
+    pub fn credit_stray_lamports(&mut self, amount: u64) -> Result<()> {
+        self.protocol_fees = self.protocol_fees.checked_add(amount)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Absorb a loss the position's collateral couldn't cover. The insurance
+    /// fund pays first; whatever it can't cover is written off total_supplied
+    /// so every LP share takes a pro-rata hit. Returns (from_insurance, socialized).
+    pub fn absorb_bad_debt(&mut self, shortfall: u64) -> (u64, u64) {
+        let from_insurance = shortfall.min(self.insurance_fund);
+        self.insurance_fund -= from_insurance;
+        let socialized = (shortfall - from_insurance).min(self.total_supplied);
+        self.total_supplied -= socialized;
+        (from_insurance, socialized)
+    }
+
+    /// Fee owed on a flash loan of `amount`, rounded up in favour of the vault
+    pub fn flash_loan_fee(&self, amount: u64) -> u64 {
+        let fee = ((amount as u128) * (self.flash_loan_fee_bps as u128)).div_ceil(10_000);
+        fee as u64
+    }
+
+    /// Record a flash loan going out. Only one may be in flight, and it can
+    /// only draw on idle liquidity. It is repaid within the transaction, so
+    /// the withdrawal reserve is not held back from it.
+    pub fn begin_flash_loan(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.flash_loan_amount == 0,
+            crate::errors::ProtocolError::FlashLoanActive
+        );
+        require!(amount > 0, crate::errors::ProtocolError::InvalidAmount);
+        require!(
+            self.available_liquidity() >= amount,
+            crate::errors::ProtocolError::InsufficientLiquidity
+        );
+        self.flash_loan_amount = amount;
+        Ok(())
+    }
+
+    /// Settle the flash loan in flight. Returns (principal, fee); the fee is
+    /// credited to protocol_fees.
+    pub fn end_flash_loan(&mut self) -> Result<(u64, u64)> {
+        let amount = self.flash_loan_amount;
+        require!(amount > 0, crate::errors::ProtocolError::FlashLoanNotRepaid);
+        let fee = self.flash_loan_fee(amount);
+        self.protocol_fees = self.protocol_fees.checked_add(fee)
+            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+        self.flash_loan_amount = 0;
+        Ok((amount, fee))
+    }
+
     /// Record debt repayment
     pub fn repay(&mut self, amount: u64) -> Result<()> {
         self.total_borrowed = self.total_borrowed.checked_sub(amount)
-            .ok_or(crate::errors::ProtocolError::MathOverflow)?;
+            .ok_or(crate::errors::ProtocolError::MathUnderflow)?;
         Ok(())
     }
+
+    /// wSOL that wsol_vault should hold according to the books: liquidity not
+    /// lent out, plus protocol fees and the insurance fund, less any flash
+    /// loan in flight and whatever emergency_withdraw moved out. Negative if
+    /// the books promise more than was ever lent.
+    pub fn expected_vault_balance(&self) -> i128 {
+        self.total_supplied as i128 + self.protocol_fees as i128 + self.insurance_fund as i128
+            - self.total_borrowed as i128
+            - self.flash_loan_amount as i128
+            - self.emergency_withdrawn as i128
+    }
+
+    /// Accounting invariants checked under the `invariant-checks` feature.
+    /// The reserve share of accrued interest is counted in total_borrowed but
+    /// owed to the protocol rather than LPs, so borrows are bounded by
+    /// total_supplied + protocol_fees. Anyone can transfer wSOL into the
+    /// vault, so a balance above the books is tolerated; one below is drift.
+    pub fn accounting_holds(&self, vault_balance: u64) -> bool {
+        let lendable = self.total_supplied as u128 + self.protocol_fees as u128;
+        (self.total_borrowed as u128) <= lendable
+            && vault_balance as i128 >= self.expected_vault_balance()
+    }
+}
+
+/// index * (1 + rate_bps * elapsed_seconds / (365 * 24 * 3600 * 10000))
+fn compound(index: u128, rate_bps: u64, elapsed: u128) -> u128 {
+    let growth = index
+        .saturating_mul(rate_bps as u128)
+        .saturating_mul(elapsed)
+        / (365u128 * 24 * 3600 * 10000);
+    index.saturating_add(growth)
+}
+
+/// e^(apr_bps / 10_000) - 1 in basis points, summing the Taylor series in
+/// 1e12 fixed point until the terms vanish.
+fn apy_from_apr_bps(apr_bps: u64) -> u64 {
+    const ONE: u128 = 1_000_000_000_000;
+    let rate = apr_bps as u128 * (ONE / 10_000);
+    let mut term = ONE;
+    let mut sum: u128 = 0;
+    let mut k: u128 = 1;
+    while term > 0 {
+        term = term.saturating_mul(rate) / (ONE * k);
+        sum = sum.saturating_add(term);
+        k += 1;
+    }
+    (sum / (ONE / 10_000)).min(u64::MAX as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ProtocolError;
+
+    fn vault(total_supplied: u64, total_borrowed: u64) -> LendingVault {
+        LendingVault {
+            authority: Pubkey::default(),
+            borrow_mint: Pubkey::default(),
+            total_supplied,
+            total_borrowed,
+            total_shares: total_supplied,
+            base_rate_bps: LendingVault::DEFAULT_BASE_RATE_BPS,
+            slope1_bps: LendingVault::DEFAULT_SLOPE1_BPS,
+            slope2_bps: LendingVault::DEFAULT_SLOPE2_BPS,
+            optimal_utilization_bps: LendingVault::DEFAULT_OPTIMAL_UTILIZATION_BPS,
+            max_borrow_rate_bps: LendingVault::DEFAULT_MAX_BORROW_RATE_BPS,
+            reserve_factor_bps: 0,
+            protocol_fees: 0,
+            insurance_fund: 0,
+            borrow_index: LendingVault::INDEX_ONE,
+            borrow_cap: u64::MAX,
+            supply_cap: u64::MAX,
+            min_vault_reserve_bps: 0,
+            supply_cooldown_seconds: 0,
+            flash_loan_fee_bps: 0,
+            flash_loan_amount: 0,
+            emergency_withdrawn: 0,
+            withdrawal_queue_head: 0,
+            withdrawal_queue_tail: 0,
+            last_update: 0,
+            bump: 0,
+            vault_bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_accounting_invariants_track_vault_balance() {
+        let mut v = vault(0, 0);
+        v.reserve_factor_bps = 1000;
+        let mut balance = 0u64;
+
+        v.deposit(1_000, 1_000).unwrap();
+        balance += 1_000;
+        assert!(v.accounting_holds(balance));
+
+        // Borrowing everything keeps the books balanced at zero idle liquidity
+        v.borrow(1_000).unwrap();
+        balance -= 1_000;
+        assert!(v.accounting_holds(balance));
+
+        // 100 of interest at full utilization: borrowed outgrows supplied by
+        // the reserve share, which the bound allows for
+        v.accrue_debt(100).unwrap();
+        assert!(v.total_borrowed > v.total_supplied);
+        assert!(v.accounting_holds(balance));
+
+        v.repay(1_100).unwrap();
+        balance += 1_100;
+        assert_eq!(v.expected_vault_balance(), 1_100);
+        assert!(v.accounting_holds(balance));
+
+        // A donation is fine, a missing lamport is not
+        assert!(v.accounting_holds(balance + 1));
+        assert!(!v.accounting_holds(balance - 1));
+
+        // Books lending out more than LPs ever supplied
+        let overlent = vault(1_000, 1_001);
+        assert!(!overlent.accounting_holds(u64::MAX));
+    }
+
+    #[test]
+    fn test_stray_lamports_credit_protocol_fees() {
+        let mut v = vault(1_000, 400);
+        let mut balance = 600u64;
+        assert!(v.accounting_holds(balance));
+
+        // 50 lamports wrapped by sync_native go to the protocol, not LPs
+        balance += 50;
+        v.credit_stray_lamports(50).unwrap();
+        assert_eq!(v.protocol_fees, 50);
+        assert_eq!(v.total_supplied, 1_000);
+        assert_eq!(v.expected_vault_balance(), balance as i128);
+        assert!(v.accounting_holds(balance));
+    }
+
+    #[test]
+    fn test_borrow_within_available_liquidity() {
+        let mut v = vault(1_000, 400);
+        assert_eq!(v.available_liquidity(), 600);
+
+        v.borrow(600).unwrap();
+        assert_eq!(v.total_borrowed, 1_000);
+        assert_eq!(v.available_liquidity(), 0);
+    }
+
+    #[test]
+    fn test_borrow_beyond_available_liquidity() {
+        let mut v = vault(1_000, 400);
+        assert_eq!(
+            v.borrow(601).unwrap_err(),
+            ProtocolError::InsufficientLiquidity.into()
+        );
+        // State untouched on failure
+        assert_eq!(v.total_borrowed, 400);
+    }
+
+    #[test]
+    fn test_borrow_respects_min_vault_reserve() {
+        let mut v = vault(1_000, 400);
+        v.min_vault_reserve_bps = 1_000;
+        assert_eq!(v.min_reserve(), 100);
+        assert_eq!(v.borrowable_liquidity(), 500);
+
+        // Breaching the reserve by one lamport is rejected
+        assert_eq!(
+            v.borrow(501).unwrap_err(),
+            ProtocolError::InsufficientLiquidity.into()
+        );
+        assert_eq!(v.total_borrowed, 400);
+
+        // Up to the reserve is fine, leaving it idle for withdrawals
+        v.borrow(500).unwrap();
+        assert_eq!(v.available_liquidity(), 100);
+        assert_eq!(v.borrowable_liquidity(), 0);
+
+        // Flash loans are repaid in the same transaction and may use it
+        v.begin_flash_loan(100).unwrap();
+    }
+
+    #[test]
+    fn test_borrow_cap_boundary() {
+        let mut v = vault(1_000, 400);
+        v.borrow_cap = 700;
+
+        // Exactly at the cap is allowed
+        v.borrow(300).unwrap();
+        assert_eq!(v.total_borrowed, 700);
+
+        // One lamport over is not, even with liquidity left
+        assert_eq!(
+            v.borrow(1).unwrap_err(),
+            ProtocolError::BorrowCapExceeded.into()
+        );
+        assert_eq!(v.total_borrowed, 700);
+    }
+
+    #[test]
+    fn test_supply_cap_boundary() {
+        let mut v = vault(1_000, 0);
+        v.supply_cap = 1_500;
+
+        v.deposit(500, 500).unwrap();
+        assert_eq!(v.total_supplied, 1_500);
+
+        assert_eq!(
+            v.deposit(1, 1).unwrap_err(),
+            ProtocolError::SupplyCapExceeded.into()
+        );
+        assert_eq!(v.total_supplied, 1_500);
+        assert_eq!(v.total_shares, 1_500);
+    }
+
+    #[test]
+    fn test_rate_at_zero_utilization() {
+        let v = vault(1_000, 0);
+        assert_eq!(v.utilization_bps(), 0);
+        assert_eq!(v.borrow_rate_bps(), LendingVault::DEFAULT_BASE_RATE_BPS as u64);
+        assert_eq!(v.supply_rate_bps(), 0);
+
+        // Empty vault counts as 0% utilized
+        assert_eq!(vault(0, 0).utilization_bps(), 0);
+    }
+
+    #[test]
+    fn test_rate_at_kink() {
+        let v = vault(1_000, 800);
+        assert_eq!(v.utilization_bps(), 8_000);
+        // base 0 + slope1 4%
+        assert_eq!(v.borrow_rate_bps(), 400);
+        // 4% * 80%
+        assert_eq!(v.supply_rate_bps(), 320);
+
+        // Halfway to the kink earns half of slope1
+        assert_eq!(vault(1_000, 400).borrow_rate_bps(), 200);
+    }
+
+    #[test]
+    fn test_rate_at_full_utilization() {
+        let v = vault(1_000, 1_000);
+        assert_eq!(v.utilization_bps(), 10_000);
+        // base 0 + slope1 4% + slope2 75%
+        assert_eq!(v.borrow_rate_bps(), 7_900);
+        assert_eq!(v.supply_rate_bps(), 7_900);
+
+        // Halfway up the steep slope
+        assert_eq!(vault(1_000, 900).borrow_rate_bps(), 400 + 3_750);
+
+        // Borrowed above supplied still caps at 100%
+        assert_eq!(vault(1_000, 1_500).utilization_bps(), 10_000);
+    }
+
+    #[test]
+    fn test_apy_from_rate_model() {
+        assert_eq!(apy_from_apr_bps(0), 0);
+        // 100% APR compounded continuously -> e - 1
+        assert_eq!(apy_from_apr_bps(10_000), 17_182);
+
+        // At the kink: borrow 4% -> 4.08%, supply 3.2% -> 3.25%
+        let mut v = vault(1_000, 800);
+        assert_eq!(v.borrow_apy_bps(), 408);
+        assert_eq!(v.supply_apy_bps(), 325);
+
+        // The reserve factor comes out of the supply side only
+        v.reserve_factor_bps = 5_000;
+        assert_eq!(v.borrow_apy_bps(), 408);
+        assert_eq!(v.supply_apy_bps(), 161);
+
+        // Idle vault earns nothing
+        assert_eq!(vault(1_000, 0).supply_apy_bps(), 0);
+    }
+
+    #[test]
+    fn test_rate_params_validation() {
+        let v = vault(1_000, 0);
+        let defaults = v.rate_params();
+        assert!(defaults.is_valid());
+
+        let kink_at_edge = InterestRateParams { optimal_utilization_bps: 10_000, ..defaults };
+        assert!(!kink_at_edge.is_valid());
+        let no_kink = InterestRateParams { optimal_utilization_bps: 0, ..defaults };
+        assert!(!no_kink.is_valid());
+
+        // 0 + 400 + 49_600 is the steepest curve allowed
+        let steepest = InterestRateParams { slope2_bps: 49_600, ..defaults };
+        assert!(steepest.is_valid());
+        assert!(!InterestRateParams { slope2_bps: 49_601, ..defaults }.is_valid());
+
+        let cap_at_base = InterestRateParams { base_rate_bps: 200, max_borrow_rate_bps: 200, ..defaults };
+        assert!(!cap_at_base.is_valid());
+        assert!(!InterestRateParams { reserve_factor_bps: 10_001, ..defaults }.is_valid());
+
+        let mut v = v;
+        v.set_rate_params(steepest);
+        assert_eq!(v.rate_params(), steepest);
+    }
+
+    #[test]
+    fn test_borrow_rate_capped_at_full_utilization() {
+        let mut v = vault(1_000, 1_000);
+        v.max_borrow_rate_bps = 5_000;
+        // The model gives 79% at 100% utilization; the cap holds it at 50%
+        assert_eq!(v.borrow_rate_bps(), 5_000);
+        assert_eq!(v.supply_rate_bps(), 5_000);
+
+        // Below the cap the model is unchanged
+        assert_eq!(vault(1_000, 800).borrow_rate_bps(), 400);
+        v.total_borrowed = 800;
+        assert_eq!(v.borrow_rate_bps(), 400);
+    }
+
+    #[test]
+    fn test_borrow_index_compounds_per_accrual() {
+        const YEAR: i64 = 365 * 24 * 3600;
+        // 80% utilization: borrow 4%
+        let mut v = vault(1_000, 800);
+
+        assert_eq!(v.accrue_interest(YEAR).unwrap(), LendingVault::INDEX_ONE * 4 / 100);
+        assert_eq!(v.borrow_index, LendingVault::INDEX_ONE * 104 / 100);
+        assert_eq!(v.last_update, YEAR);
+
+        // Second year compounds on the first; utilization is put back at the
+        // kink so the rate stays at 4%
+        v.total_supplied = 1_000;
+        v.total_borrowed = 800;
+        v.accrue_interest(2 * YEAR).unwrap();
+        assert_eq!(v.borrow_index, LendingVault::INDEX_ONE * 104 * 104 / 10_000);
+
+        // No elapsed time, no growth
+        let index = v.borrow_index;
+        assert_eq!(v.accrue_interest(2 * YEAR).unwrap(), 0);
+        assert_eq!(v.borrow_index, index);
+    }
+
+    #[test]
+    fn test_accrual_books_interest_on_outstanding_borrows() {
+        const YEAR: i64 = 365 * 24 * 3600;
+        // 800 borrowed at 4% for a year, with nothing touching the position
+        let mut v = vault(1_000, 800);
+        v.reserve_factor_bps = 1_000;
+        v.accrue_interest(YEAR).unwrap();
+        assert_eq!(v.total_borrowed, 832);
+        assert_eq!(v.protocol_fees, 3);
+        assert_eq!(v.total_supplied, 1_029);
+
+        // An LP joining now pays for the interest already earned
+        assert_eq!(v.shares_for_deposit(1_029).unwrap(), 1_000);
+
+        // The vault rounds up, so position debts that round down never
+        // exceed total_borrowed
+        let mut v = vault(1_000, 500);
+        v.accrue_interest(1).unwrap();
+        assert_eq!(v.total_borrowed, 501);
+
+        // Nothing borrowed, nothing booked
+        let mut v = vault(1_000, 0);
+        v.accrue_interest(YEAR).unwrap();
+        assert_eq!(v.total_supplied, 1_000);
+    }
+
+    #[test]
+    fn test_first_depositor_gets_shares_one_to_one() {
+        let mut v = vault(0, 0);
+        let shares = v.shares_for_deposit(5_000).unwrap();
+        assert_eq!(shares, 5_000);
+
+        v.deposit(5_000, shares).unwrap();
+        assert_eq!(v.total_shares, 5_000);
+        assert_eq!(v.assets_for_shares(5_000).unwrap(), 5_000);
+    }
+
+    #[test]
+    fn test_later_depositor_after_interest() {
+        let mut v = vault(0, 0);
+        let first = v.shares_for_deposit(1_000).unwrap();
+        v.deposit(1_000, first).unwrap();
+        v.borrow(500).unwrap();
+
+        // Borrowers accrue 100 of interest: each share is now worth 1.1
+        v.accrue_debt(100).unwrap();
+        assert_eq!(v.total_supplied, 1_100);
+
+        let second = v.shares_for_deposit(1_100).unwrap();
+        assert_eq!(second, 1_000);
+        v.deposit(1_100, second).unwrap();
+
+        // First LP keeps the interest, second LP gets back what it put in
+        assert_eq!(v.assets_for_shares(first).unwrap(), 1_100);
+        assert_eq!(v.assets_for_shares(second).unwrap(), 1_100);
+
+        // Redeeming rounds down and can't exceed outstanding shares
+        assert_eq!(v.assets_for_shares(1).unwrap(), 1);
+        assert!(v.assets_for_shares(v.total_shares + 1).is_err());
+
+        v.redeem(1_100, first).unwrap();
+        assert_eq!(v.total_shares, second);
+        assert_eq!(v.total_supplied, 1_100);
+    }
+
+    #[test]
+    fn test_reserve_factor_splits_interest() {
+        let mut v = vault(1_000, 500);
+        v.reserve_factor_bps = 1_000;
+
+        v.accrue_debt(100).unwrap();
+        assert_eq!(v.total_borrowed, 600);
+        assert_eq!(v.protocol_fees, 10);
+        assert_eq!(v.total_supplied, 1_090);
+        // LPs only see their 90%
+        assert_eq!(v.assets_for_shares(1_000).unwrap(), 1_090);
+
+        // Rounds in favour of LPs
+        v.accrue_debt(9).unwrap();
+        assert_eq!(v.protocol_fees, 10);
+        assert_eq!(v.total_supplied, 1_099);
+    }
+
+    #[test]
+    fn test_redeem_leaves_borrowed_liquidity() {
+        let mut v = vault(1_000, 800);
+        v.protocol_fees = 50;
+        v.insurance_fund = 50;
+        assert_eq!(
+            v.redeem(201, 201).unwrap_err(),
+            ProtocolError::InsufficientLiquidity.into()
+        );
+        assert_eq!(v.total_supplied, 1_000);
+
+        v.redeem(200, 200).unwrap();
+        assert_eq!(v.total_supplied, v.total_borrowed);
+        assert_eq!(
+            v.redeem(1, 1).unwrap_err(),
+            ProtocolError::InsufficientLiquidity.into()
+        );
+    }
+
+    #[test]
+    fn test_repay() {
+        let mut v = vault(1_000, 400);
+        v.repay(400).unwrap();
+        assert_eq!(v.total_borrowed, 0);
+        assert_eq!(
+            v.repay(1).unwrap_err(),
+            ProtocolError::MathUnderflow.into()
+        );
+    }
+
+    #[test]
+    fn test_fund_insurance() {
+        let mut v = vault(1_000, 0);
+        v.protocol_fees = 50;
+
+        v.fund_insurance(30, true).unwrap();
+        assert_eq!(v.protocol_fees, 20);
+        assert_eq!(v.insurance_fund, 30);
+        assert!(v.fund_insurance(21, true).is_err());
+
+        // External top-ups leave reserves alone
+        v.fund_insurance(100, false).unwrap();
+        assert_eq!(v.protocol_fees, 20);
+        assert_eq!(v.insurance_fund, 130);
+    }
+
+    #[test]
+    fn test_insurance_fully_covers_bad_debt() {
+        let mut v = vault(1_000, 0);
+        v.insurance_fund = 200;
+
+        assert_eq!(v.absorb_bad_debt(150), (150, 0));
+        assert_eq!(v.insurance_fund, 50);
+        // LPs are made whole
+        assert_eq!(v.total_supplied, 1_000);
+        assert_eq!(v.assets_for_shares(1_000).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_insurance_partially_covers_bad_debt() {
+        let mut v = vault(1_000, 0);
+        v.insurance_fund = 100;
+
+        assert_eq!(v.absorb_bad_debt(300), (100, 200));
+        assert_eq!(v.insurance_fund, 0);
+        // The remainder is spread across every share
+        assert_eq!(v.total_supplied, 800);
+        assert_eq!(v.assets_for_shares(500).unwrap(), 400);
+    }
+
+    #[test]
+    fn test_withdrawal_queue_is_fifo() {
+        let mut v = vault(1_000, 0);
+        assert!(!v.has_queued_withdrawals());
+
+        assert_eq!(v.enqueue_withdrawal().unwrap(), 0);
+        assert_eq!(v.enqueue_withdrawal().unwrap(), 1);
+        assert!(v.has_queued_withdrawals());
+
+        v.dequeue_withdrawal();
+        assert_eq!(v.withdrawal_queue_head, 1);
+        assert!(v.has_queued_withdrawals());
+        v.dequeue_withdrawal();
+        assert!(!v.has_queued_withdrawals());
+    }
+
+    #[test]
+    fn test_partial_withdrawal_burns_shares_rounded_up() {
+        // 1_000 assets backing 600 shares: 1 share = 1.67 assets
+        let mut v = vault(1_000, 0);
+        v.total_shares = 600;
+
+        // Only 100 of a 300-share (500 asset) request can be paid
+        assert_eq!(v.assets_for_shares(300).unwrap(), 500);
+        let burned = v.shares_for_withdrawal(100).unwrap();
+        assert_eq!(burned, 60);
+
+        // 101 assets cost 60.6 shares, rounded up to 61
+        assert_eq!(v.shares_for_withdrawal(101).unwrap(), 61);
+
+        // Burning never leaves the remaining shares worth more than before
+        v.redeem(101, 61).unwrap();
+        assert!(v.assets_for_shares(300 - 61).unwrap() <= 500 - 101);
+    }
+
+    #[test]
+    fn test_flash_loan_lifecycle() {
+        let mut v = vault(1_000, 400);
+        v.flash_loan_fee_bps = 9;
+
+        // Only idle liquidity can be flash-borrowed
+        assert_eq!(
+            v.begin_flash_loan(601).unwrap_err(),
+            ProtocolError::InsufficientLiquidity.into()
+        );
+        v.begin_flash_loan(600).unwrap();
+        assert_eq!(
+            v.begin_flash_loan(1).unwrap_err(),
+            ProtocolError::FlashLoanActive.into()
+        );
+
+        // 9 bps of 600 is 0.54, rounded up
+        assert_eq!(v.end_flash_loan().unwrap(), (600, 1));
+        assert_eq!(v.protocol_fees, 1);
+        assert_eq!(v.flash_loan_amount, 0);
+        // LP accounting never moves
+        assert_eq!(v.total_supplied, 1_000);
+        assert_eq!(v.total_borrowed, 400);
+
+        assert_eq!(
+            v.end_flash_loan().unwrap_err(),
+            ProtocolError::FlashLoanNotRepaid.into()
+        );
+    }
 }
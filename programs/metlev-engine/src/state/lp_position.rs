@@ -6,13 +6,11 @@ pub struct LpPosition {
     /// LP provider wallet
     pub lp: Pubkey,
 
-    /// Total WSOL supplied (in lamports, 9 decimals)
-    pub supplied_amount: u64,
+    /// Vault shares owned; redeemable for shares * total_supplied / total_shares
+    pub shares: u64,
 
-    /// Accumulated interest earned so far (in lamports, 9 decimals)
-    pub interest_earned: u64,
-
-    /// Last time interest was accrued (unix timestamp)
+    /// Time of the last supply (unix timestamp); the vault's supply cooldown
+    /// runs from here
     pub last_update: i64,
 
     /// Bump seed for PDA
@@ -22,21 +20,27 @@ pub struct LpPosition {
 impl LpPosition {
     pub const SEED_PREFIX: &'static [u8] = b"lp_position";
 
-    /// Accrue simple annual interest based on elapsed time and update state.
-    /// interest = principal * rate_bps * elapsed_seconds / (365 * 24 * 3600 * 10000)
-    pub fn accrue_interest(&mut self, interest_rate_bps: u16, current_time: i64) {
-        let elapsed = (current_time - self.last_update).max(0) as u128;
-        let interest = (self.supplied_amount as u128)
-            .saturating_mul(interest_rate_bps as u128)
-            .saturating_mul(elapsed)
-            / (365u128 * 24 * 3600 * 10000);
-
-        self.interest_earned = self.interest_earned.saturating_add(interest as u64);
-        self.last_update = current_time;
+    /// Whether `cooldown_seconds` have passed since the last supply, so the
+    /// shares may leave. A zero cooldown never holds them back.
+    pub fn supply_cooldown_elapsed(&self, cooldown_seconds: i64, now: i64) -> bool {
+        cooldown_seconds == 0 || now.saturating_sub(self.last_update) >= cooldown_seconds
     }
+}
 
-    /// Total claimable amount (principal + accrued interest)
-    pub fn claimable(&self) -> u64 {
-        self.supplied_amount.saturating_add(self.interest_earned)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supply_cooldown() {
+        let p = LpPosition {
+            lp: Pubkey::default(),
+            shares: 1_000,
+            last_update: 1_000,
+            bump: 0,
+        };
+        assert!(p.supply_cooldown_elapsed(0, 1_000));
+        assert!(!p.supply_cooldown_elapsed(3_600, 4_599));
+        assert!(p.supply_cooldown_elapsed(3_600, 4_600));
     }
 }
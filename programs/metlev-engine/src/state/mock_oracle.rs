@@ -5,11 +5,25 @@ use anchor_lang::prelude::*;
 pub struct MockOracle {
     pub authority: Pubkey,
     pub price: u64,
+    /// Decimals `price` is quoted in; readers rescale it to 6
     pub decimals: u8,
     pub timestamp: i64,
+    /// Treat the feed as stale once this many seconds pass without an update (0 = off)
+    pub heartbeat_seconds: u64,
+    /// Treat the feed as stale regardless of its timestamp
+    pub force_stale: bool,
     pub bump: u8,
 }
 
 impl MockOracle {
     pub const SEED_PREFIX: &'static [u8] = b"mock_oracle";
+    /// Keeps the rescaling factor within a u64
+    pub const MAX_DECIMALS: u8 = 18;
+
+    /// Staleness the feed reports about itself, on top of the caller's max age
+    pub fn is_stale(&self, now: i64) -> bool {
+        self.force_stale
+            || (self.heartbeat_seconds > 0
+                && now.saturating_sub(self.timestamp) > self.heartbeat_seconds as i64)
+    }
 }
\ No newline at end of file
@@ -2,10 +2,14 @@ pub mod config;
 pub mod position;
 pub mod lending_vault;
 pub mod lp_position;
+pub mod withdrawal_request;
 pub mod mock_oracle;
+pub mod keeper;
 
 pub use config::*;
 pub use position::*;
 pub use lending_vault::*;
 pub use lp_position::*;
-pub use mock_oracle::*;
\ No newline at end of file
+pub use withdrawal_request::*;
+pub use mock_oracle::*;
+pub use keeper::*;
\ No newline at end of file
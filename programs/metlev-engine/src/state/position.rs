@@ -1,4 +1,6 @@
 use anchor_lang::prelude::*;
+use crate::errors::ProtocolError;
+use super::LendingVault;
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
 pub enum PositionStatus {
@@ -19,15 +21,58 @@ pub struct Position {
     /// Amount of collateral deposited (in native token units)
     pub collateral_amount: u64,
 
-    /// Amount of debt borrowed (in USDC, 6 decimals)
+    /// Debt borrowed plus accrued interest, in wSOL lamports (9 decimals)
     pub debt_amount: u64,
 
+    /// Last time interest was accrued on the debt (unix timestamp)
+    pub debt_last_update: i64,
+
+    /// Interest not yet realized as a whole lamport, in units of
+    /// 1 / INDEX_ONE lamports, carried into the next accrual
+    pub debt_interest_remainder: u64,
+
+    /// The vault's borrow_index when the debt was last brought up to date;
+    /// the debt grows by borrow_index / borrow_index_snapshot from here
+    pub borrow_index_snapshot: u128,
+
     /// Meteora DLMM position reference (position pubkey or ID)
     pub meteora_position: Pubkey,
 
     /// Timestamp when position was created
     pub created_at: i64,
 
+    /// When open_position borrowed against it; 0 until then
+    pub opened_at: i64,
+
+    /// DLMM position rent the rent reserve paid at open, owed back to it
+    /// when the DLMM position is closed; 0 when the user paid
+    pub subsidized_rent: u64,
+
+    /// Free-form tag set at open by the integrator managing the position
+    /// (e.g. a strategy id); all zeros when untagged. Informational only
+    pub strategy_tag: [u8; 16],
+
+    /// Timestamp when the position was closed; 0 while open
+    pub closed_at: i64,
+
+    /// wSOL lamports recovered from the DLMM position minus the debt repaid,
+    /// recorded at close. Negative when collateral had to cover a shortfall.
+    pub realized_pnl: i64,
+
+    /// When the position was first flagged past liquidation_threshold; 0 while healthy
+    pub liquidation_eligible_since: i64,
+
+    /// Set by the first close_position_remove; the position can then only be
+    /// unwound further with close_position_remove, finalized or liquidated
+    /// (partial closes and leverage changes are rejected)
+    pub closing: bool,
+
+    /// wSOL recovered by close_position_remove steps so far
+    pub close_proceeds: u64,
+
+    /// Debt those steps repaid
+    pub close_repaid: u64,
+
     /// Position status
     pub status: PositionStatus,
 
@@ -50,7 +95,271 @@ impl Position {
         self.status = PositionStatus::Closed;
     }
 
+    /// Mark the position closed and record its result. Collateral never sits
+    /// in the DLMM position, so the LP's proceeds net of debt are the gain or
+    /// loss on the collateral the trader put up.
+    pub fn record_close(&mut self, proceeds: u64, debt: u64, now: i64) {
+        let pnl = proceeds as i128 - debt as i128;
+        self.realized_pnl = pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        self.closed_at = now;
+        self.mark_closed();
+    }
+
+    /// Record one close_position_remove step
+    pub fn record_close_step(&mut self, proceeds: u64, repaid: u64) -> Result<()> {
+        self.close_proceeds = self.close_proceeds.checked_add(proceeds)
+            .ok_or(ProtocolError::MathOverflow)?;
+        self.close_repaid = self.close_repaid.checked_add(repaid)
+            .ok_or(ProtocolError::MathOverflow)?;
+        self.closing = true;
+        Ok(())
+    }
+
+    /// Finish a close, counting any earlier close_position_remove steps
+    /// alongside the final `proceeds` and `debt`. Returns the totals.
+    pub fn record_close_total(&mut self, proceeds: u64, debt: u64, now: i64) -> (u64, u64) {
+        let proceeds = self.close_proceeds.saturating_add(proceeds);
+        let debt = self.close_repaid.saturating_add(debt);
+        self.record_close(proceeds, debt, now);
+        (proceeds, debt)
+    }
+
     pub fn mark_liquidated(&mut self) {
         self.status = PositionStatus::Liquidated;
     }
+
+    /// Start the liquidation grace period, keeping the original timestamp if
+    /// the position was already flagged.
+    pub fn flag_liquidation(&mut self, now: i64) {
+        if self.liquidation_eligible_since == 0 {
+            self.liquidation_eligible_since = now;
+        }
+    }
+
+    pub fn clear_liquidation_flag(&mut self) {
+        self.liquidation_eligible_since = 0;
+    }
+
+    /// Whether a soft liquidation may go ahead: the position must have been
+    /// flagged at least `grace_period_seconds` ago. A zero grace period
+    /// disables the delay.
+    pub fn grace_period_elapsed(&self, grace_period_seconds: i64, now: i64) -> bool {
+        grace_period_seconds == 0
+            || (self.liquidation_eligible_since != 0
+                && now.saturating_sub(self.liquidation_eligible_since) >= grace_period_seconds)
+    }
+
+    /// Whether the position has been open long enough to be liquidated:
+    /// at least `min_age_seconds` since open_position. Zero disables the
+    /// check.
+    pub fn min_age_elapsed(&self, min_age_seconds: i64, now: i64) -> bool {
+        min_age_seconds == 0 || now.saturating_sub(self.opened_at) >= min_age_seconds
+    }
+
+    /// Grow the debt by the vault's borrow index since the last snapshot,
+    /// debt * borrow_index / borrow_index_snapshot, and take a new snapshot.
+    /// Interest is charged at the rates in force over the whole period, not
+    /// the rate at the moment of the touch. The fraction of a lamport left
+    /// over is carried in `debt_interest_remainder`, so frequent accruals on
+    /// a small debt add up instead of each rounding to zero.
+    /// Returns the interest added.
+    pub fn accrue_debt_interest(&mut self, borrow_index: u128, current_time: i64) -> u64 {
+        let snapshot = self.borrow_index_snapshot;
+        self.borrow_index_snapshot = borrow_index;
+        self.debt_last_update = current_time;
+        if self.debt_amount == 0 || snapshot == 0 {
+            self.debt_interest_remainder = 0;
+            return 0;
+        }
+
+        // Growth since the snapshot in INDEX_ONE fixed point
+        let growth = borrow_index.saturating_sub(snapshot)
+            .saturating_mul(LendingVault::INDEX_ONE)
+            / snapshot;
+        let scaled = (self.debt_amount as u128)
+            .saturating_mul(growth)
+            .saturating_add(self.debt_interest_remainder as u128);
+        // Always below INDEX_ONE, which fits in a u64
+        self.debt_interest_remainder = (scaled % LendingVault::INDEX_ONE) as u64;
+        let interest = (scaled / LendingVault::INDEX_ONE).min(u64::MAX as u128) as u64;
+
+        self.debt_amount = self.debt_amount.saturating_add(interest);
+        interest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YEAR: i64 = 365 * 24 * 3600;
+    const INDEX_ONE: u128 = LendingVault::INDEX_ONE;
+
+    fn position(debt_amount: u64, debt_last_update: i64) -> Position {
+        Position {
+            owner: Pubkey::default(),
+            collateral_mint: Pubkey::default(),
+            collateral_amount: 0,
+            debt_amount,
+            debt_last_update,
+            debt_interest_remainder: 0,
+            borrow_index_snapshot: LendingVault::INDEX_ONE,
+            meteora_position: Pubkey::default(),
+            created_at: 0,
+            opened_at: 0,
+            subsidized_rent: 0,
+            strategy_tag: [0; 16],
+            closed_at: 0,
+            realized_pnl: 0,
+            liquidation_eligible_since: 0,
+            closing: false,
+            close_proceeds: 0,
+            close_repaid: 0,
+            status: PositionStatus::Active,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_debt_grows_with_borrow_index() {
+        // 10 SOL while the index grows 5% -> 0.5 SOL interest
+        let mut p = position(10_000_000_000, 0);
+        let interest = p.accrue_debt_interest(INDEX_ONE / 20 * 21, YEAR);
+        assert_eq!(interest, 500_000_000);
+        assert_eq!(p.debt_amount, 10_500_000_000);
+        assert_eq!(p.debt_last_update, YEAR);
+        assert_eq!(p.borrow_index_snapshot, INDEX_ONE / 20 * 21);
+
+        // A further 5% on the grown debt
+        let interest = p.accrue_debt_interest(INDEX_ONE / 400 * 441, YEAR + YEAR / 2);
+        assert_eq!(interest, 525_000_000);
+    }
+
+    #[test]
+    fn test_debt_accrual_unchanged_index() {
+        let mut p = position(1_000, 100);
+        assert_eq!(p.accrue_debt_interest(INDEX_ONE, 200), 0);
+        assert_eq!(p.debt_amount, 1_000);
+
+        // No debt, no interest; the snapshot still moves so a later borrow
+        // only pays from here
+        let mut empty = position(0, 0);
+        assert_eq!(empty.accrue_debt_interest(2 * INDEX_ONE, YEAR), 0);
+        assert_eq!(empty.debt_last_update, YEAR);
+        assert_eq!(empty.borrow_index_snapshot, 2 * INDEX_ONE);
+        empty.debt_amount = 1_000;
+        assert_eq!(empty.accrue_debt_interest(2 * INDEX_ONE, YEAR + 1), 0);
+    }
+
+    #[test]
+    fn test_touch_timing_does_not_change_interest() {
+        // The index carries the rate history, so debt touched once after a
+        // year matches debt touched along the way, whatever the rate is at
+        // the moment of the touch
+        let mut once = position(1_000_000_000, 0);
+        once.accrue_debt_interest(INDEX_ONE / 10 * 11, YEAR);
+
+        let mut stepped = position(1_000_000_000, 0);
+        stepped.accrue_debt_interest(INDEX_ONE / 100 * 101, YEAR / 4);
+        stepped.accrue_debt_interest(INDEX_ONE / 100 * 108, YEAR / 2);
+        stepped.accrue_debt_interest(INDEX_ONE / 10 * 11, YEAR);
+        assert_eq!(once.debt_amount, 1_100_000_000);
+        assert!(once.debt_amount - stepped.debt_amount <= 1);
+    }
+
+    #[test]
+    fn test_tiny_accruals_sum_to_full_interest() {
+        // A 0.001% index step on 1_000 lamports is 0.01 lamports
+        let mut p = position(1_000, 0);
+        let step = INDEX_ONE / 100_000;
+        assert_eq!(p.accrue_debt_interest(INDEX_ONE + step, 1), 0);
+        assert!(p.debt_interest_remainder > 0);
+
+        // Each accrual alone rounds to zero
+        let mut total = 0;
+        for i in 2..=10_000u128 {
+            total += p.accrue_debt_interest(INDEX_ONE + step * i, i as i64);
+        }
+        let mut once = position(1_000, 0);
+        let full = once.accrue_debt_interest(INDEX_ONE + step * 10_000, 10_000);
+        assert_eq!(full, 100);
+        assert!(full - total <= 1);
+        assert_eq!(p.debt_amount, 1_000 + total);
+    }
+
+    #[test]
+    fn test_liquidation_grace_period() {
+        let mut p = position(1_000, 0);
+        // Disabled grace period never holds a liquidation back
+        assert!(p.grace_period_elapsed(0, 100));
+        // Unflagged positions can't be soft-liquidated
+        assert!(!p.grace_period_elapsed(600, 100));
+
+        p.flag_liquidation(100);
+        // Re-flagging keeps the first breach time
+        p.flag_liquidation(400);
+        assert_eq!(p.liquidation_eligible_since, 100);
+        assert!(!p.grace_period_elapsed(600, 699));
+        assert!(p.grace_period_elapsed(600, 700));
+
+        p.clear_liquidation_flag();
+        assert!(!p.grace_period_elapsed(600, 10_000));
+    }
+
+    #[test]
+    fn test_min_position_age() {
+        let mut p = position(1_000, 0);
+        p.opened_at = 1_000;
+        // Off by default
+        assert!(p.min_age_elapsed(0, 1_000));
+
+        assert!(!p.min_age_elapsed(300, 1_299));
+        assert!(p.min_age_elapsed(300, 1_300));
+    }
+
+    #[test]
+    fn test_record_close_pnl() {
+        // LP returned 2.1 SOL on 2 SOL of debt
+        let mut p = position(2_000_000_000, 0);
+        p.record_close(2_100_000_000, 2_000_000_000, 500);
+        assert!(p.is_closed());
+        assert_eq!(p.closed_at, 500);
+        assert_eq!(p.realized_pnl, 100_000_000);
+
+        // LP came back short; the loss is signed
+        let mut p = position(2_000_000_000, 0);
+        p.record_close(1_700_000_000, 2_000_000_000, 600);
+        assert_eq!(p.realized_pnl, -300_000_000);
+
+        // Extremes saturate instead of wrapping
+        let mut p = position(u64::MAX, 0);
+        p.record_close(0, u64::MAX, 700);
+        assert_eq!(p.realized_pnl, i64::MIN);
+    }
+
+    #[test]
+    fn test_split_close_totals() {
+        // Two remove steps: 1.2 SOL repaying 1.2 of 2 SOL debt, then 0.9 SOL
+        // repaying the remaining 0.8
+        let mut p = position(2_000_000_000, 0);
+        p.record_close_step(1_200_000_000, 1_200_000_000).unwrap();
+        p.record_close_step(900_000_000, 800_000_000).unwrap();
+        assert!(p.closing);
+        assert!(!p.is_closed());
+
+        // Finalize has nothing more to recover or repay
+        let (proceeds, debt) = p.record_close_total(0, 0, 900);
+        assert_eq!((proceeds, debt), (2_100_000_000, 2_000_000_000));
+        assert_eq!(p.realized_pnl, 100_000_000);
+        assert!(p.is_closed());
+
+        // A one-shot close with no prior steps is unchanged
+        let mut p = position(2_000_000_000, 0);
+        assert_eq!(p.record_close_total(1_700_000_000, 2_000_000_000, 600), (1_700_000_000, 2_000_000_000));
+        assert_eq!(p.realized_pnl, -300_000_000);
+
+        let mut p = position(0, 0);
+        p.close_proceeds = u64::MAX;
+        assert!(p.record_close_step(1, 0).is_err());
+    }
 }
@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+/// An LP's place in the vault's withdrawal queue. Shares are escrowed here
+/// (moved out of the LP's position) until `process_withdrawal` redeems them.
+#[account]
+#[derive(InitSpace)]
+pub struct WithdrawalRequest {
+    /// LP provider wallet, paid out and refunded the rent
+    pub lp: Pubkey,
+
+    /// Position in the queue; served in increasing order
+    pub ticket: u64,
+
+    /// Shares still waiting to be redeemed
+    pub shares: u64,
+
+    /// Timestamp when the request was queued
+    pub created_at: i64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl WithdrawalRequest {
+    pub const SEED_PREFIX: &'static [u8] = b"withdrawal_request";
+}
@@ -0,0 +1,273 @@
+use anchor_lang::prelude::*;
+use crate::errors::ProtocolError;
+use crate::dlmm;
+use crate::dlmm::types::BinLiquidityDistributionByWeight;
+
+/// Read fields of a DLMM LB pair straight from the account after checking
+/// its owner and discriminator.
+fn read_lb_pair<T>(lb_pair: &AccountInfo, read: impl FnOnce(&dlmm::accounts::LbPair) -> T) -> Result<T> {
+    use dlmm::accounts::LbPair;
+
+    require_keys_eq!(*lb_pair.owner, dlmm::ID, ProtocolError::InvalidMeteoraPosition);
+    let data = lb_pair.try_borrow_data()?;
+    let disc = LbPair::DISCRIMINATOR;
+    let end = disc.len() + std::mem::size_of::<LbPair>();
+    require!(
+        data.len() >= end && data.starts_with(disc),
+        ProtocolError::InvalidMeteoraPosition
+    );
+    let pair: &LbPair = bytemuck::try_from_bytes(&data[disc.len()..end])
+        .map_err(|_| ProtocolError::InvalidMeteoraPosition)?;
+    Ok(read(pair))
+}
+
+/// `(token_x_mint, token_y_mint)` of a DLMM LB pair.
+pub fn lb_pair_mints(lb_pair: &AccountInfo) -> Result<(Pubkey, Pubkey)> {
+    read_lb_pair(lb_pair, |pair| (pair.token_x_mint, pair.token_y_mint))
+}
+
+/// `(active_id, bin_step)` of a DLMM LB pair.
+pub fn lb_pair_active_bin(lb_pair: &AccountInfo) -> Result<(i32, u16)> {
+    read_lb_pair(lb_pair, |pair| (pair.active_id, pair.bin_step))
+}
+
+/// Fixed-point scale of `bin_price`.
+pub const BIN_PRICE_SCALE: u128 = 1_000_000_000_000;
+
+/// Price of one base unit of token X in base units of token Y at `bin_id`,
+/// `(1 + bin_step / 10_000) ^ bin_id`, scaled by `BIN_PRICE_SCALE`. `None`
+/// if it doesn't fit; callers treat that as "can't estimate".
+pub fn bin_price(bin_id: i32, bin_step: u16) -> Option<u128> {
+    let base = BIN_PRICE_SCALE + (bin_step as u128) * BIN_PRICE_SCALE / 10_000;
+    let mut exp = bin_id.unsigned_abs();
+    let mut factor = base;
+    let mut price = BIN_PRICE_SCALE;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            price = price.checked_mul(factor)? / BIN_PRICE_SCALE;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            factor = factor.checked_mul(factor)? / BIN_PRICE_SCALE;
+        }
+    }
+    if bin_id < 0 {
+        (BIN_PRICE_SCALE * BIN_PRICE_SCALE).checked_div(price)
+    } else {
+        Some(price)
+    }
+}
+
+/// Token Y that `x_amount` of token X is worth at the pair's active bin,
+/// before swap fees and price impact.
+pub fn estimate_y_for_x(x_amount: u64, active_id: i32, bin_step: u16) -> Option<u64> {
+    let y = (x_amount as u128).checked_mul(bin_price(active_id, bin_step)?)? / BIN_PRICE_SCALE;
+    u64::try_from(y).ok()
+}
+
+/// Widest position the protocol will open. DLMM bin arrays hold 70 bins, so
+/// a position this wide spans at most the two arrays `open_position` and
+/// `rebalance_position` pass in, and the add-liquidity CPI stays well inside
+/// the compute budget.
+pub const MAX_BINS_PER_POSITION: i32 = 70;
+
+/// Reject positions wider than `MAX_BINS_PER_POSITION`, and distributions
+/// with more entries than that, before they reach DLMM.
+pub fn validate_bin_count(width: i32, distribution_len: usize) -> Result<()> {
+    require!(
+        width <= MAX_BINS_PER_POSITION && distribution_len <= MAX_BINS_PER_POSITION as usize,
+        ProtocolError::TooManyBins
+    );
+    Ok(())
+}
+
+/// Check a one-sided wSOL distribution before it reaches DLMM. Every bin must
+/// sit inside the position, `[lower_bin_id, lower_bin_id + width - 1]`, and
+/// on the side of `active_id` that holds wSOL: above it when wSOL is token X,
+/// at or below it when wSOL is token Y.
+pub fn validate_bin_distribution(
+    bin_liquidity_dist: &[BinLiquidityDistributionByWeight],
+    lower_bin_id: i32,
+    width: i32,
+    active_id: i32,
+    wsol_is_x: bool,
+) -> Result<()> {
+    require!(width > 0, ProtocolError::BinOutOfRange);
+    validate_bin_count(width, bin_liquidity_dist.len())?;
+    let upper_bin_id = lower_bin_id
+        .checked_add(width - 1)
+        .ok_or(ProtocolError::MathOverflow)?;
+
+    for bin in bin_liquidity_dist {
+        require!(
+            bin.bin_id >= lower_bin_id && bin.bin_id <= upper_bin_id,
+            ProtocolError::BinOutOfRange
+        );
+        let on_wsol_side = if wsol_is_x {
+            bin.bin_id > active_id
+        } else {
+            bin.bin_id <= active_id
+        };
+        require!(on_wsol_side, ProtocolError::BinWrongSide);
+    }
+
+    Ok(())
+}
+
+/// Reject an empty distribution or one whose weights are all zero: DLMM
+/// would then deposit nothing (or fail without saying why) while the borrow
+/// is already booked as debt.
+pub fn validate_bin_weights(bin_liquidity_dist: &[BinLiquidityDistributionByWeight]) -> Result<()> {
+    require!(
+        bin_liquidity_dist.iter().any(|bin| bin.weight > 0),
+        ProtocolError::InvalidAmount
+    );
+    Ok(())
+}
+
+/// Bin arrays handed to DLMM swap as remaining accounts. A position inside a
+/// single bin array passes the same account as lower and upper; swap loads
+/// every remaining bin array mutably, so a repeat is dropped rather than
+/// loaded twice.
+pub fn swap_bin_arrays<'info>(
+    lower: &AccountInfo<'info>,
+    upper: &AccountInfo<'info>,
+) -> Vec<AccountInfo<'info>> {
+    if lower.key == upper.key {
+        vec![lower.clone()]
+    } else {
+        vec![lower.clone(), upper.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist(bin_ids: &[i32]) -> Vec<BinLiquidityDistributionByWeight> {
+        bin_ids
+            .iter()
+            .map(|&bin_id| BinLiquidityDistributionByWeight { bin_id, weight: 1000 })
+            .collect()
+    }
+
+    #[test]
+    fn test_accepts_valid_distribution() {
+        // wSOL as Y: bins at and below the active bin
+        assert!(validate_bin_distribution(&dist(&[96, 98, 100]), 96, 5, 100, false).is_ok());
+        // wSOL as X: bins strictly above the active bin
+        assert!(validate_bin_distribution(&dist(&[101, 105]), 101, 5, 100, true).is_ok());
+        assert!(validate_bin_distribution(&[], 101, 5, 100, true).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_bin_outside_position() {
+        // Below lower_bin_id
+        assert_eq!(
+            validate_bin_distribution(&dist(&[95]), 96, 5, 100, false).unwrap_err(),
+            ProtocolError::BinOutOfRange.into()
+        );
+        // Past lower_bin_id + width - 1
+        assert_eq!(
+            validate_bin_distribution(&dist(&[101, 106]), 101, 5, 100, true).unwrap_err(),
+            ProtocolError::BinOutOfRange.into()
+        );
+        assert_eq!(
+            validate_bin_distribution(&dist(&[101]), 101, 0, 100, true).unwrap_err(),
+            ProtocolError::BinOutOfRange.into()
+        );
+    }
+
+    #[test]
+    fn test_rejects_too_many_bins() {
+        let max = MAX_BINS_PER_POSITION;
+        let full: Vec<i32> = (0..max).collect();
+        assert!(validate_bin_distribution(&dist(&full), 0, max, -1, true).is_ok());
+
+        assert_eq!(
+            validate_bin_distribution(&dist(&[1]), 0, max + 1, -1, true).unwrap_err(),
+            ProtocolError::TooManyBins.into()
+        );
+        // More entries than bins, even with a legal width
+        let over: Vec<i32> = (0..=max).map(|i| i % max).collect();
+        assert_eq!(
+            validate_bin_distribution(&dist(&over), 0, max, -1, true).unwrap_err(),
+            ProtocolError::TooManyBins.into()
+        );
+    }
+
+    #[test]
+    fn test_rejects_empty_or_zero_weight_distribution() {
+        assert!(validate_bin_weights(&dist(&[96, 98])).is_ok());
+        assert_eq!(
+            validate_bin_weights(&[]).unwrap_err(),
+            ProtocolError::InvalidAmount.into()
+        );
+        let zero: Vec<_> = [96, 97, 98]
+            .iter()
+            .map(|&bin_id| BinLiquidityDistributionByWeight { bin_id, weight: 0 })
+            .collect();
+        assert_eq!(
+            validate_bin_weights(&zero).unwrap_err(),
+            ProtocolError::InvalidAmount.into()
+        );
+        // One non-zero weight is enough
+        let mut one = zero.clone();
+        one[1].weight = 1;
+        assert!(validate_bin_weights(&one).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_bin_on_wrong_side() {
+        // wSOL as X can't go into the active bin or below it
+        assert_eq!(
+            validate_bin_distribution(&dist(&[100, 101]), 98, 5, 100, true).unwrap_err(),
+            ProtocolError::BinWrongSide.into()
+        );
+        // wSOL as Y can't go above the active bin
+        assert_eq!(
+            validate_bin_distribution(&dist(&[100, 101]), 98, 5, 100, false).unwrap_err(),
+            ProtocolError::BinWrongSide.into()
+        );
+    }
+
+    #[test]
+    fn test_bin_price_follows_bin_step() {
+        assert_eq!(bin_price(0, 25), Some(BIN_PRICE_SCALE));
+        assert_eq!(bin_price(1, 25), Some(1_002_500_000_000));
+
+        // 1.001 ^ 100 = 1.10512...; the inverse bin prices the other way round
+        let up = bin_price(100, 10).unwrap();
+        assert_eq!(up / 1_000_000, 1_105_115);
+        let down = bin_price(-100, 10).unwrap();
+        // Truncated at each step, so it can land a unit low
+        assert!((904_882..=904_883).contains(&(down / 1_000_000)));
+
+        // Far out of range is reported rather than wrapped
+        assert_eq!(bin_price(400_000, 100), None);
+    }
+
+    #[test]
+    fn test_estimate_y_for_x() {
+        assert_eq!(estimate_y_for_x(1_000, 0, 25), Some(1_000));
+        assert_eq!(estimate_y_for_x(1_000_000, 1, 25), Some(1_002_500));
+        assert_eq!(estimate_y_for_x(u64::MAX, 400_000, 100), None);
+    }
+
+    #[test]
+    fn test_swap_bin_arrays_dedups_single_array() {
+        let (lower_key, upper_key, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), dlmm::ID);
+        let (mut l1, mut l2) = (0, 0);
+        let (mut d1, mut d2) = (vec![], vec![]);
+        let lower = AccountInfo::new(&lower_key, false, true, &mut l1, &mut d1, &owner, false, 0);
+        let upper = AccountInfo::new(&upper_key, false, true, &mut l2, &mut d2, &owner, false, 0);
+
+        let both = swap_bin_arrays(&lower, &upper);
+        assert_eq!(both.len(), 2);
+        assert_eq!((both[0].key, both[1].key), (&lower_key, &upper_key));
+
+        let single = swap_bin_arrays(&lower, &lower.clone());
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].key, &lower_key);
+    }
+}
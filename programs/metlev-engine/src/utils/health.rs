@@ -1,6 +1,10 @@
 use anchor_lang::prelude::*;
 use crate::errors::ProtocolError;
 
+/// Position debt is held in the borrow asset's base units: wSOL lamports, as
+/// borrowed and repaid through the lending vault.
+pub const DEBT_DECIMALS: u8 = anchor_spl::token::spl_token::native_mint::DECIMALS;
+
 /// Calculate loan-to-value ratio in basis points
 /// LTV = (debt_value / collateral_value) * 10000
 pub fn calculate_ltv(collateral_value: u64, debt_value: u64) -> Result<u64> {
@@ -34,7 +38,8 @@ pub fn calculate_health_factor(collateral_value: u64, debt_value: u64) -> Result
 }
 
 /// Calculate collateral value based on oracle price
-/// Returns value in USD terms (6 decimals for USDC)
+/// Returns value in USD terms (6 decimals for USDC). `price` must come from
+/// `read_price`, which normalizes every feed to `PRICE_DECIMALS`.
 pub fn calculate_collateral_value(
     collateral_amount: u64,
     price: u64, // Price in USD with 6 decimals
@@ -52,12 +57,92 @@ pub fn calculate_collateral_value(
         collateral_amount
     };
 
-    let value = adjusted_amount
-        .checked_mul(price)
-        .and_then(|v| v.checked_div(1_000_000)) // Price has 6 decimals
+    // Price has 6 decimals; the product is taken in u128 so only a value
+    // that doesn't fit in a u64 fails
+    let value = (adjusted_amount as u128)
+        .checked_mul(price as u128)
+        .map(|v| v / 1_000_000)
         .ok_or(ProtocolError::MathOverflow)?;
 
-    Ok(value)
+    u64::try_from(value).map_err(|_| ProtocolError::MathOverflow.into())
+}
+
+/// Value of a position's debt (wSOL lamports) at the oracle price, in the same
+/// 6-decimal units as `calculate_collateral_value`.
+pub fn calculate_debt_value(debt_amount: u64, price: u64) -> Result<u64> {
+    calculate_collateral_value(debt_amount, price, DEBT_DECIMALS)
+}
+
+/// Health factor of a position whose deployed value (collateral + debt) is
+/// weighted by the liquidation threshold. At or below 10_000 the position is
+/// liquidatable.
+pub fn calculate_position_health_factor(
+    position_value: u64,
+    debt_value: u64,
+    liquidation_threshold: u16,
+) -> Result<u64> {
+    let weighted_value = (position_value as u128)
+        .checked_mul(liquidation_threshold as u128)
+        .map(|v| v / 10_000)
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or(ProtocolError::MathOverflow)?;
+    calculate_health_factor(weighted_value, debt_value)
+}
+
+/// Position LTV in basis points, where the borrowed amount is deployed
+/// alongside the collateral: LTV = debt / (collateral + debt)
+pub fn calculate_position_ltv(
+    collateral_amount: u64,
+    debt_amount: u64,
+    price: u64,
+    collateral_decimals: u8,
+) -> Result<u64> {
+    let collateral_value = calculate_collateral_value(collateral_amount, price, collateral_decimals)?;
+    let debt_value = calculate_debt_value(debt_amount, price)?;
+    let total_value = collateral_value
+        .checked_add(debt_value)
+        .ok_or(ProtocolError::MathOverflow)?;
+    calculate_ltv(total_value, debt_value)
+}
+
+/// Whether seizing `seize_amount` of collateral for `repay_amount` of debt
+/// leaves the position at a lower LTV than before. With LTV measured as
+/// debt / (collateral + debt), taking more collateral than the debt it
+/// repays only helps while collateral exceeds debt times (1 + penalty).
+pub fn seizure_lowers_ltv(
+    collateral_amount: u64,
+    debt_amount: u64,
+    repay_amount: u64,
+    seize_amount: u64,
+    price: u64,
+    collateral_decimals: u8,
+) -> Result<bool> {
+    let ltv_before = calculate_position_ltv(collateral_amount, debt_amount, price, collateral_decimals)?;
+    let collateral_after = collateral_amount
+        .checked_sub(seize_amount)
+        .ok_or(ProtocolError::InsufficientCollateral)?;
+    let debt_after = debt_amount
+        .checked_sub(repay_amount)
+        .ok_or(ProtocolError::MathUnderflow)?;
+    if debt_after == 0 {
+        return Ok(true);
+    }
+    if collateral_after == 0 {
+        return Ok(false);
+    }
+    let ltv_after = calculate_position_ltv(collateral_after, debt_after, price, collateral_decimals)?;
+    Ok(ltv_after < ltv_before)
+}
+
+/// wSOL borrowed for `leverage` (basis points, 10_000 = 1x) on
+/// `collateral_amount`. The product is taken in u128 so a large collateral at
+/// high leverage only fails if the borrow itself doesn't fit in a u64.
+pub fn calculate_borrow_amount(collateral_amount: u64, leverage: u64) -> Result<u64> {
+    let borrow = (collateral_amount as u128)
+        .checked_mul(leverage as u128)
+        .map(|v| v / 10_000)
+        .ok_or(ProtocolError::MathOverflow)?;
+    u64::try_from(borrow).map_err(|_| ProtocolError::MathOverflow.into())
 }
 
 /// Calculate liquidation penalty amount
@@ -73,6 +158,60 @@ pub fn calculate_liquidation_penalty(
     Ok(penalty)
 }
 
+/// Liquidator's share of a realized `penalty`: `bonus_bps / penalty_bps` of
+/// it, rounded down. The remainder goes to the insurance fund.
+pub fn calculate_liquidator_bonus(
+    penalty: u64,
+    penalty_bps: u16,
+    bonus_bps: u16,
+) -> Result<u64> {
+    if penalty_bps == 0 {
+        return Ok(0);
+    }
+    let bonus = (penalty as u128)
+        .checked_mul(bonus_bps as u128)
+        .map(|v| v / penalty_bps as u128)
+        .ok_or(ProtocolError::MathOverflow)?;
+    u64::try_from(bonus.min(penalty as u128)).map_err(|_| ProtocolError::MathOverflow.into())
+}
+
+/// Collateral handed to a liquidator who repays `repay_amount` of debt out of
+/// pocket: the repaid amount plus the liquidation penalty on top. Debt is wSOL
+/// and is valued with the collateral's own oracle, so the price cancels out.
+pub fn calculate_seize_amount(
+    repay_amount: u64,
+    penalty_bps: u16,
+) -> Result<u64> {
+    let seize = (repay_amount as u128)
+        .checked_mul(10_000 + penalty_bps as u128)
+        .map(|v| v / 10_000)
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or(ProtocolError::MathOverflow)?;
+
+    Ok(seize)
+}
+
+/// Least wSOL a swap of `amount` tokens (with `decimals`) should return when
+/// both sides are valued at their oracle prices, less `slippage_bps`. Prices
+/// come from `read_price`, so both are in `PRICE_DECIMALS`.
+pub fn calculate_min_wsol_out(
+    amount: u64,
+    price: u64,
+    decimals: u8,
+    sol_price: u64,
+    slippage_bps: u16,
+) -> Result<u64> {
+    require!(sol_price > 0, ProtocolError::OraclePriceUnavailable);
+    let wsol = (amount as u128)
+        .checked_mul(price as u128)
+        .and_then(|v| v.checked_mul(10u128.pow(DEBT_DECIMALS as u32)))
+        .and_then(|v| v.checked_mul(10_000u128.saturating_sub(slippage_bps as u128)))
+        .and_then(|v| v.checked_div(10u128.checked_pow(decimals as u32)?))
+        .map(|v| v / (sol_price as u128 * 10_000))
+        .ok_or(ProtocolError::MathOverflow)?;
+    u64::try_from(wsol).map_err(|_| ProtocolError::MathOverflow.into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,10 +238,129 @@ mod tests {
         assert_eq!(hf, 12500);
     }
 
+    #[test]
+    fn test_calculate_position_ltv() {
+        // 1 SOL collateral, 2 SOL debt at $100 → 2/3
+        let ltv = calculate_position_ltv(1_000_000_000, 2_000_000_000, 100_000_000, 9).unwrap();
+        assert_eq!(ltv, 6666);
+
+        // Topping up to 2 SOL collateral brings it down to 50%
+        let ltv = calculate_position_ltv(2_000_000_000, 2_000_000_000, 100_000_000, 9).unwrap();
+        assert_eq!(ltv, 5000);
+    }
+
+    #[test]
+    fn test_debt_valued_in_wsol_decimals() {
+        // 2 SOL collateral opened at 2x borrows 4 SOL; both at $150
+        let collateral = 2_000_000_000u64;
+        let debt = collateral * 20_000 / 10_000;
+        assert_eq!(debt, 4_000_000_000);
+        assert_eq!(calculate_collateral_value(collateral, 150_000_000, 9).unwrap(), 300_000_000);
+        assert_eq!(calculate_debt_value(debt, 150_000_000).unwrap(), 600_000_000);
+        // 600 / (300 + 600)
+        assert_eq!(calculate_position_ltv(collateral, debt, 150_000_000, 9).unwrap(), 6666);
+
+        // A 6-decimal collateral is scaled on its own decimals, debt on wSOL's
+        let ltv = calculate_position_ltv(300_000_000, 4_000_000_000, 150_000_000, 6).unwrap();
+        // 300 tokens * $150 = 45_000 vs 600 of debt
+        assert_eq!(ltv, 600 * 10_000 / (45_000 + 600));
+    }
+
+    #[test]
+    fn test_calculate_position_health_factor() {
+        // 2x: collateral 100, debt 200, threshold 80% → 300 * 0.8 / 200 = 1.2
+        let hf = calculate_position_health_factor(300, 200, 8000).unwrap();
+        assert_eq!(hf, 12_000);
+
+        // LTV exactly at the threshold sits at 1.0
+        let hf = calculate_position_health_factor(100_000, 80_000, 8000).unwrap();
+        assert_eq!(hf, 10_000);
+
+        assert_eq!(calculate_position_health_factor(100, 0, 8000).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn test_calculate_borrow_amount() {
+        assert_eq!(calculate_borrow_amount(1_000_000_000, 20_000).unwrap(), 2_000_000_000);
+
+        // collateral * leverage overflows u64, but the borrow still fits
+        let collateral = u64::MAX / 2;
+        assert!(collateral.checked_mul(15_000).is_none());
+        assert_eq!(
+            calculate_borrow_amount(collateral, 15_000).unwrap(),
+            (collateral as u128 * 15_000 / 10_000) as u64
+        );
+        assert_eq!(calculate_borrow_amount(u64::MAX, 10_000).unwrap(), u64::MAX);
+
+        // A whale-sized 1M SOL position at 3x still values at $150
+        let borrow = calculate_borrow_amount(1_000_000 * 1_000_000_000, 30_000).unwrap();
+        assert_eq!(calculate_debt_value(borrow, 150_000_000).unwrap(), 450_000_000 * 1_000_000);
+
+        // Only a borrow past u64::MAX fails
+        assert_eq!(
+            calculate_borrow_amount(u64::MAX, 10_001).unwrap_err(),
+            ProtocolError::MathOverflow.into()
+        );
+        assert_eq!(
+            calculate_borrow_amount(u64::MAX / 2 + 1, 20_000).unwrap_err(),
+            ProtocolError::MathOverflow.into()
+        );
+    }
+
     #[test]
     fn test_calculate_liquidation_penalty() {
         // 5% penalty on 100_000 = 5_000
         let penalty = calculate_liquidation_penalty(100_000, 500).unwrap();
         assert_eq!(penalty, 5_000);
     }
+
+    #[test]
+    fn test_calculate_liquidator_bonus() {
+        // 5% penalty, 3% to the liquidator: 3/5 of 5_000, 2_000 left for insurance
+        assert_eq!(calculate_liquidator_bonus(5_000, 500, 300).unwrap(), 3_000);
+
+        // Bonus equal to the penalty pays it all out
+        assert_eq!(calculate_liquidator_bonus(5_000, 500, 500).unwrap(), 5_000);
+
+        assert_eq!(calculate_liquidator_bonus(5_000, 500, 0).unwrap(), 0);
+        assert_eq!(calculate_liquidator_bonus(0, 0, 0).unwrap(), 0);
+
+        // Rounds down in favour of the insurance fund
+        assert_eq!(calculate_liquidator_bonus(7, 500, 300).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_calculate_seize_amount() {
+        // Repaying 1 SOL at a 5% penalty seizes 1.05 SOL of collateral
+        let seize = calculate_seize_amount(1_000_000_000, 500).unwrap();
+        assert_eq!(seize, 1_050_000_000);
+
+        assert_eq!(calculate_seize_amount(100, 0).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_calculate_min_wsol_out() {
+        // 1_000 tokens (6 decimals) at $1.50 against SOL at $150 → 10 SOL
+        let out = calculate_min_wsol_out(1_000_000_000, 1_500_000, 6, 150_000_000, 0).unwrap();
+        assert_eq!(out, 10_000_000_000);
+        // 2% slippage allowance
+        let out = calculate_min_wsol_out(1_000_000_000, 1_500_000, 6, 150_000_000, 200).unwrap();
+        assert_eq!(out, 9_800_000_000);
+        // 9-decimal token priced like SOL swaps one for one
+        let out = calculate_min_wsol_out(5_000, 150_000_000, 9, 150_000_000, 0).unwrap();
+        assert_eq!(out, 5_000);
+        assert!(calculate_min_wsol_out(1, 1, 6, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_seizure_lowers_ltv() {
+        let price = 150_000_000;
+        // 80.8% LTV, half the debt repaid at a 5% penalty: LTV goes up
+        assert!(!seizure_lowers_ltv(19_200, 80_800, 9_600, 10_080, price, 9).unwrap());
+        // Collateral well above debt * 1.05: LTV goes down
+        assert!(seizure_lowers_ltv(60_000, 40_000, 20_000, 21_000, price, 9).unwrap());
+        // Clearing the debt always helps
+        assert!(seizure_lowers_ltv(19_200, 80_800, 80_800, 19_200, price, 9).unwrap());
+        assert!(seizure_lowers_ltv(1_000, 1_000, 500, 1_001, price, 9).is_err());
+    }
 }
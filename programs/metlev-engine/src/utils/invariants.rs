@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+use crate::state::LendingVault;
+
+/// Check the vault's books against wsol_vault's real balance after a state
+/// change. Only built with the `invariant-checks` feature, for fuzzing and
+/// integration runs; release builds compile it to a no-op.
+#[cfg(feature = "invariant-checks")]
+pub fn check_vault_invariants(
+    lending_vault: &LendingVault,
+    wsol_vault: &mut InterfaceAccount<TokenAccount>,
+) -> Result<()> {
+    wsol_vault.reload()?;
+    require!(
+        lending_vault.accounting_holds(wsol_vault.amount),
+        crate::errors::ProtocolError::InvariantViolated
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "invariant-checks"))]
+#[inline(always)]
+pub fn check_vault_invariants(
+    _lending_vault: &LendingVault,
+    _wsol_vault: &mut InterfaceAccount<TokenAccount>,
+) -> Result<()> {
+    Ok(())
+}
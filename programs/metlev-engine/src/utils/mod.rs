@@ -1,5 +1,9 @@
+pub mod bins;
 pub mod health;
+pub mod invariants;
 pub mod oracle;
 
+pub use bins::*;
 pub use health::*;
+pub use invariants::*;
 pub use oracle::*;
@@ -1,6 +1,28 @@
 use anchor_lang::prelude::*;
 use crate::errors::ProtocolError;
-use crate::state::{MockOracle};
+use crate::state::{MockOracle, OracleKind};
+
+/// Decimals every price is normalized to before it reaches risk math
+pub const PRICE_DECIMALS: i32 = 6;
+
+/// Pyth v2 price account layout (see `pyth-sdk-solana::state::SolanaPriceAccount`)
+pub const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+pub const PYTH_VERSION_2: u32 = 2;
+pub const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+pub const PYTH_STATUS_TRADING: u32 = 1;
+
+/// Fresh feeds needed before an aggregated price is used
+pub const MIN_FRESH_ORACLES: usize = 2;
+
+const PYTH_MAGIC_OFFSET: usize = 0;
+const PYTH_VERSION_OFFSET: usize = 4;
+const PYTH_ATYPE_OFFSET: usize = 8;
+const PYTH_EXPO_OFFSET: usize = 20;
+const PYTH_TIMESTAMP_OFFSET: usize = 96;
+const PYTH_AGG_PRICE_OFFSET: usize = 208;
+const PYTH_AGG_CONF_OFFSET: usize = 216;
+const PYTH_AGG_STATUS_OFFSET: usize = 224;
+const PYTH_MIN_LEN: usize = 240;
 
 /// Check if oracle price data is stale
 pub fn is_oracle_stale(
@@ -12,37 +34,155 @@ pub fn is_oracle_stale(
     age > max_age_seconds as i64
 }
 
-/// Validate oracle price feed
-pub fn validate_oracle_price(
-    price: u64,
-    timestamp: i64,
+/// Mock oracle price reader (for POC testing)
+/// In production, this would integrate with Pyth, Switchboard, etc.
+/// The stored price is rescaled from the oracle's `decimals` to 6 decimals.
+pub fn read_oracle_price(
+    oracle_account: &AccountInfo,
     max_age: u64,
-) -> Result<()> {
-    // Check price is not zero
-    require!(price > 0, ProtocolError::OraclePriceUnavailable);
-
-    // Check timestamp is not stale
+    max_confidence_bps: u16,
+) -> Result<(u64, i64)> {
+    let data = oracle_account.try_borrow_data()?;
+    let mock = MockOracle::try_deserialize(&mut data.as_ref())?;
     require!(
-        !is_oracle_stale(timestamp, max_age),
+        !mock.is_stale(Clock::get()?.unix_timestamp),
         ProtocolError::OracleStale
     );
-
-    Ok(())
+    let price = normalize_price(mock.price, -(mock.decimals as i32))?;
+    let price_data = PriceData::new(price, 0, mock.timestamp);
+    price_data.validate(max_age)?;
+    price_data.validate_confidence(max_confidence_bps)?;
+    Ok((price_data.price, price_data.timestamp))
 }
 
-/// Mock oracle price reader (for POC testing)
-/// In production, this would integrate with Pyth, Switchboard, etc.
-pub fn read_oracle_price(
+/// Pyth v2 price account reader.
+/// Uses the aggregate price, rescaled from the feed exponent to 6 decimals.
+pub fn read_pyth_price(
     oracle_account: &AccountInfo,
     max_age: u64,
+    max_confidence_bps: u16,
 ) -> Result<(u64, i64)> {
     let data = oracle_account.try_borrow_data()?;
-    let mock = MockOracle::try_deserialize(&mut data.as_ref())?;
+    let price_data = parse_pyth_price(&data)?;
+    price_data.validate(max_age)?;
+    price_data.validate_confidence(max_confidence_bps)?;
+    Ok((price_data.price, price_data.timestamp))
+}
+
+/// Read the collateral price with the parser matching its `OracleKind`
+pub fn read_price(
+    oracle_kind: OracleKind,
+    oracle_account: &AccountInfo,
+    max_age: u64,
+    max_confidence_bps: u16,
+) -> Result<(u64, i64)> {
+    match oracle_kind {
+        OracleKind::Mock => read_oracle_price(oracle_account, max_age, max_confidence_bps),
+        OracleKind::Pyth => read_pyth_price(oracle_account, max_age, max_confidence_bps),
+    }
+}
+
+/// Read the collateral price from `primary` and the configured
+/// `backup_oracles`, whose accounts the caller passes in `remaining`.
+/// Without backups this is `read_price` on `primary`. With backups, a stale
+/// or invalid feed is skipped, fewer than `MIN_FRESH_ORACLES` fresh feeds
+/// fails with `OracleStale`, and the median of the rest is returned along
+/// with the oldest of their timestamps.
+pub fn read_aggregated_price(
+    oracle_kind: OracleKind,
+    primary: &AccountInfo,
+    backup_oracles: &[Pubkey],
+    remaining: &[AccountInfo],
+    max_age: u64,
+    max_confidence_bps: u16,
+) -> Result<(u64, i64)> {
+    let backups: Vec<&Pubkey> = backup_oracles
+        .iter()
+        .filter(|key| **key != Pubkey::default())
+        .collect();
+    if backups.is_empty() {
+        return read_price(oracle_kind, primary, max_age, max_confidence_bps);
+    }
+
+    let mut fresh: Vec<(u64, i64)> = read_price(oracle_kind, primary, max_age, max_confidence_bps)
+        .into_iter()
+        .collect();
+    for key in backups {
+        let account = remaining
+            .iter()
+            .find(|account| account.key == key)
+            .ok_or(ProtocolError::OraclePriceUnavailable)?;
+        if let Ok(feed) = read_price(oracle_kind, account, max_age, max_confidence_bps) {
+            fresh.push(feed);
+        }
+    }
+    require!(fresh.len() >= MIN_FRESH_ORACLES, ProtocolError::OracleStale);
+
+    let timestamp = fresh.iter().map(|(_, ts)| *ts).min().unwrap_or_default();
+    Ok((median_price(&mut fresh)?, timestamp))
+}
+
+/// Middle price of the feeds, or the mean of the two middle ones for an
+/// even count
+pub fn median_price(feeds: &mut [(u64, i64)]) -> Result<u64> {
+    require!(!feeds.is_empty(), ProtocolError::OraclePriceUnavailable);
+    feeds.sort_unstable_by_key(|(price, _)| *price);
+    let mid = feeds.len() / 2;
+    if feeds.len() % 2 == 1 {
+        return Ok(feeds[mid].0);
+    }
+    let sum = feeds[mid - 1].0 as u128 + feeds[mid].0 as u128;
+    Ok((sum / 2) as u64)
+}
+
+fn parse_pyth_price(data: &[u8]) -> Result<PriceData> {
+    require!(data.len() >= PYTH_MIN_LEN, ProtocolError::OraclePriceUnavailable);
     require!(
-        !is_oracle_stale(mock.timestamp, max_age),
-        ProtocolError::OracleStale
+        read_u32(data, PYTH_MAGIC_OFFSET) == PYTH_MAGIC
+            && read_u32(data, PYTH_VERSION_OFFSET) == PYTH_VERSION_2
+            && read_u32(data, PYTH_ATYPE_OFFSET) == PYTH_ACCOUNT_TYPE_PRICE,
+        ProtocolError::OraclePriceUnavailable
     );
-    Ok((mock.price, mock.timestamp))
+
+    let expo = read_u32(data, PYTH_EXPO_OFFSET) as i32;
+    let timestamp = read_u64(data, PYTH_TIMESTAMP_OFFSET) as i64;
+    let raw_price = read_u64(data, PYTH_AGG_PRICE_OFFSET) as i64;
+    let raw_conf = read_u64(data, PYTH_AGG_CONF_OFFSET);
+    let status = read_u32(data, PYTH_AGG_STATUS_OFFSET);
+
+    // Negative or zero aggregate prices are never usable as collateral value
+    require!(raw_price > 0, ProtocolError::OraclePriceUnavailable);
+
+    let price = normalize_price(raw_price as u64, expo)?;
+    let confidence = normalize_price(raw_conf, expo)?;
+
+    let mut price_data = PriceData::new(price, confidence, timestamp);
+    price_data.is_valid = status == PYTH_STATUS_TRADING;
+    Ok(price_data)
+}
+
+/// Rescale `value * 10^expo` to `PRICE_DECIMALS` fixed point
+pub fn normalize_price(value: u64, expo: i32) -> Result<u64> {
+    let shift = expo
+        .checked_add(PRICE_DECIMALS)
+        .ok_or(ProtocolError::MathOverflow)?;
+    let factor = 10u64
+        .checked_pow(shift.unsigned_abs())
+        .ok_or(ProtocolError::MathOverflow)?;
+
+    if shift >= 0 {
+        value.checked_mul(factor).ok_or(ProtocolError::MathOverflow.into())
+    } else {
+        Ok(value / factor)
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
 }
 
 /// Price feed result
@@ -74,14 +214,39 @@ impl PriceData {
         require!(self.price > 0, ProtocolError::OraclePriceUnavailable);
         Ok(())
     }
+
+    /// Reject prices whose confidence interval exceeds `max_confidence_bps` of the price.
+    /// Cross-multiplied so the bound is exact: `confidence / price <= max_bps / 10_000`.
+    pub fn validate_confidence(&self, max_confidence_bps: u16) -> Result<()> {
+        require!(self.price > 0, ProtocolError::OraclePriceUnavailable);
+        let spread = (self.confidence as u128) * 10_000;
+        let limit = (max_confidence_bps as u128) * (self.price as u128);
+        require!(spread <= limit, ProtocolError::OraclePriceUnavailable);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_sysvar::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+    const NOW: i64 = 1_700_000_000;
+
+    /// Serves a fixed `Clock` so `Clock::get()` works off-chain.
+    struct ClockStub;
+
+    impl SyscallStubs for ClockStub {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            let clock = Clock { unix_timestamp: NOW, ..Clock::default() };
+            unsafe { *(var_addr as *mut Clock) = clock };
+            0
+        }
+    }
 
     #[test]
     fn test_is_oracle_stale() {
+        set_syscall_stubs(Box::new(ClockStub));
         let current = Clock::get().unwrap().unix_timestamp;
 
         // Not stale (10 seconds old, max 60)
@@ -93,6 +258,7 @@ mod tests {
 
     #[test]
     fn test_price_data_validation() {
+        set_syscall_stubs(Box::new(ClockStub));
         let current = Clock::get().unwrap().unix_timestamp;
         let price_data = PriceData::new(100_000_000, 10_000, current);
 
@@ -103,4 +269,247 @@ mod tests {
         let old_price = PriceData::new(100_000_000, 10_000, current - 120);
         assert!(old_price.validate(60).is_err());
     }
+
+    fn pyth_account(price: i64, conf: u64, expo: i32, status: u32, timestamp: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 3312];
+        data[PYTH_MAGIC_OFFSET..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[PYTH_VERSION_OFFSET..8].copy_from_slice(&PYTH_VERSION_2.to_le_bytes());
+        data[PYTH_ATYPE_OFFSET..12].copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+        data[PYTH_EXPO_OFFSET..24].copy_from_slice(&expo.to_le_bytes());
+        data[PYTH_TIMESTAMP_OFFSET..104].copy_from_slice(&timestamp.to_le_bytes());
+        data[PYTH_AGG_PRICE_OFFSET..216].copy_from_slice(&price.to_le_bytes());
+        data[PYTH_AGG_CONF_OFFSET..224].copy_from_slice(&conf.to_le_bytes());
+        data[PYTH_AGG_STATUS_OFFSET..228].copy_from_slice(&status.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_normalize_price() {
+        // $150.12345678 at expo -8 -> 6 decimals
+        assert_eq!(normalize_price(15_012_345_678, -8).unwrap(), 150_123_456);
+        // Already 6 decimals
+        assert_eq!(normalize_price(150_000_000, -6).unwrap(), 150_000_000);
+        // Coarser exponent scales up
+        assert_eq!(normalize_price(150, 0).unwrap(), 150_000_000);
+
+        // Overflow on scale up, and on an absurd exponent
+        assert!(normalize_price(u64::MAX, -5).is_err());
+        assert!(normalize_price(1, 100).is_err());
+        assert!(normalize_price(1, i32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_read_pyth_price() {
+        set_syscall_stubs(Box::new(ClockStub));
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = pyth_account(15_000_000_000, 5_000_000, -8, PYTH_STATUS_TRADING, NOW - 5);
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        let (price, timestamp) = read_pyth_price(&info, 60, 100).unwrap();
+        assert_eq!(price, 150_000_000);
+        assert_eq!(timestamp, NOW - 5);
+
+        let (price, _) = read_price(OracleKind::Pyth, &info, 60, 100).unwrap();
+        assert_eq!(price, 150_000_000);
+
+        // Same staleness rule as the mock reader
+        assert!(read_pyth_price(&info, 1, 100).is_err());
+
+        // conf $0.05 on $150 is ~3.3 bps
+        assert!(read_pyth_price(&info, 60, 3).is_err());
+        assert!(read_pyth_price(&info, 60, 4).is_ok());
+    }
+
+    fn mock_oracle(price: u64, decimals: u8, timestamp: i64) -> MockOracle {
+        MockOracle {
+            authority: Pubkey::default(),
+            price,
+            decimals,
+            timestamp,
+            heartbeat_seconds: 0,
+            force_stale: false,
+            bump: 0,
+        }
+    }
+
+    fn mock_oracle_account(price: u64, decimals: u8, timestamp: i64) -> Vec<u8> {
+        serialize_mock(&mock_oracle(price, decimals, timestamp))
+    }
+
+    fn serialize_mock(oracle: &MockOracle) -> Vec<u8> {
+        let mut data = Vec::new();
+        oracle.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_mock_oracle_heartbeat_and_forced_staleness() {
+        set_syscall_stubs(Box::new(ClockStub));
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+
+        // 30s old: fresh under a 60s max age, stale under a 10s heartbeat
+        let mut oracle = mock_oracle(150_000_000, 6, NOW - 30);
+        oracle.heartbeat_seconds = 10;
+        let mut lamports = 0;
+        let mut data = serialize_mock(&oracle);
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(read_oracle_price(&info, 60, 100).is_err());
+
+        oracle.heartbeat_seconds = 30;
+        assert!(!oracle.is_stale(NOW));
+        oracle.force_stale = true;
+        assert!(oracle.is_stale(NOW));
+
+        let mut lamports = 0;
+        let mut data = serialize_mock(&mock_oracle(150_000_000, 6, NOW));
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(read_oracle_price(&info, 60, 100).is_ok());
+    }
+
+    #[test]
+    fn test_read_oracle_price_normalizes_decimals() {
+        set_syscall_stubs(Box::new(ClockStub));
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+
+        // $150 reported at 6, 8 and 9 decimals all read back as 150_000_000
+        for (raw, decimals) in [(150_000_000, 6), (15_000_000_000, 8), (150_000_000_000, 9)] {
+            let mut lamports = 0;
+            let mut data = mock_oracle_account(raw, decimals, NOW);
+            let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+            let (price, _) = read_oracle_price(&info, 60, 100).unwrap();
+            assert_eq!(price, 150_000_000, "decimals {}", decimals);
+        }
+    }
+
+    #[test]
+    fn test_eight_decimal_feed_values_collateral_like_six() {
+        use crate::utils::calculate_collateral_value;
+        set_syscall_stubs(Box::new(ClockStub));
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+
+        let value = |raw: u64, decimals: u8, collateral_decimals: u8, amount: u64| {
+            let mut lamports = 0;
+            let mut data = mock_oracle_account(raw, decimals, NOW);
+            let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+            let (price, _) = read_oracle_price(&info, 60, 100).unwrap();
+            calculate_collateral_value(amount, price, collateral_decimals).unwrap()
+        };
+
+        // 2 SOL (9 decimals, scaled down) and 2 tokens at 4 decimals (scaled up)
+        // at $150, from a 6- and an 8-decimal feed alike
+        for (collateral_decimals, amount) in [(9, 2_000_000_000), (4, 20_000)] {
+            assert_eq!(value(150_000_000, 6, collateral_decimals, amount), 300_000_000);
+            assert_eq!(value(15_000_000_000, 8, collateral_decimals, amount), 300_000_000);
+        }
+    }
+
+    #[test]
+    fn test_read_pyth_price_nine_decimal_feed() {
+        set_syscall_stubs(Box::new(ClockStub));
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = pyth_account(150_000_000_000, 50_000_000, -9, PYTH_STATUS_TRADING, NOW);
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        let (price, _) = read_pyth_price(&info, 60, 100).unwrap();
+        assert_eq!(price, 150_000_000);
+
+        // 1 SOL at that price is worth $150 in 6-decimal USD
+        let value = crate::utils::calculate_collateral_value(1_000_000_000, price, 9).unwrap();
+        assert_eq!(value, 150_000_000);
+    }
+
+    #[test]
+    fn test_validate_confidence_boundary() {
+        // $100 price, max 1% -> conf up to exactly $1
+        let at_limit = PriceData::new(100_000_000, 1_000_000, NOW);
+        assert!(at_limit.validate_confidence(100).is_ok());
+
+        let over_limit = PriceData::new(100_000_000, 1_000_001, NOW);
+        assert_eq!(
+            over_limit.validate_confidence(100).unwrap_err(),
+            ProtocolError::OraclePriceUnavailable.into()
+        );
+
+        // Zero confidence always passes; max values don't overflow
+        assert!(PriceData::new(1, 0, NOW).validate_confidence(1).is_ok());
+        assert!(PriceData::new(u64::MAX, u64::MAX, NOW).validate_confidence(10_000).is_ok());
+    }
+
+    #[test]
+    fn test_parse_pyth_price_rejects_invalid() {
+        set_syscall_stubs(Box::new(ClockStub));
+
+        // Negative and zero prices
+        assert!(parse_pyth_price(&pyth_account(-1, 0, -8, PYTH_STATUS_TRADING, NOW)).is_err());
+        assert!(parse_pyth_price(&pyth_account(0, 0, -8, PYTH_STATUS_TRADING, NOW)).is_err());
+
+        // Feed not trading
+        let halted = parse_pyth_price(&pyth_account(100, 0, -2, 0, NOW)).unwrap();
+        assert!(halted.validate(60).is_err());
+
+        // Wrong account type / truncated data
+        let mut product = pyth_account(100, 0, -2, PYTH_STATUS_TRADING, NOW);
+        product[PYTH_ATYPE_OFFSET] = 2;
+        assert!(parse_pyth_price(&product).is_err());
+        assert!(parse_pyth_price(&[0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn test_median_price() {
+        assert_eq!(median_price(&mut [(150, 0)]).unwrap(), 150);
+        assert_eq!(median_price(&mut [(160, 0), (140, 0), (150, 0)]).unwrap(), 150);
+        assert_eq!(median_price(&mut [(160, 0), (140, 0)]).unwrap(), 150);
+        assert_eq!(median_price(&mut [(u64::MAX, 0), (u64::MAX, 0)]).unwrap(), u64::MAX);
+        assert!(median_price(&mut []).is_err());
+    }
+
+    #[test]
+    fn test_read_aggregated_price_skips_stale_feed() {
+        set_syscall_stubs(Box::new(ClockStub));
+        let owner = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2) = (0, 0, 0);
+        // The primary is stale and would fail a single-oracle read
+        let mut d0 = mock_oracle_account(999_000_000, 6, NOW - 120);
+        let mut d1 = mock_oracle_account(150_000_000, 6, NOW - 10);
+        let mut d2 = mock_oracle_account(152_000_000, 6, NOW - 5);
+        let primary = AccountInfo::new(&keys[0], false, false, &mut l0, &mut d0, &owner, false, 0);
+        let b1 = AccountInfo::new(&keys[1], false, false, &mut l1, &mut d1, &owner, false, 0);
+        let b2 = AccountInfo::new(&keys[2], false, false, &mut l2, &mut d2, &owner, false, 0);
+        let backups = [keys[1], keys[2]];
+        let remaining = [b1.clone(), b2.clone()];
+
+        assert!(read_price(OracleKind::Mock, &primary, 60, 100).is_err());
+
+        // Median of the two fresh feeds; the stale outlier is ignored
+        let (price, timestamp) =
+            read_aggregated_price(OracleKind::Mock, &primary, &backups, &remaining, 60, 100).unwrap();
+        assert_eq!(price, 151_000_000);
+        assert_eq!(timestamp, NOW - 10);
+
+        // Only one fresh feed left
+        assert_eq!(
+            read_aggregated_price(OracleKind::Mock, &primary, &backups, &remaining, 8, 100).unwrap_err(),
+            ProtocolError::OracleStale.into()
+        );
+
+        // A configured backup must be passed
+        assert_eq!(
+            read_aggregated_price(OracleKind::Mock, &primary, &backups, &remaining[..1], 60, 100).unwrap_err(),
+            ProtocolError::OraclePriceUnavailable.into()
+        );
+
+        // No backups configured: plain read of the primary
+        let (price, _) = read_aggregated_price(
+            OracleKind::Mock, &b1, &[Pubkey::default(); 2], &[], 60, 100,
+        ).unwrap();
+        assert_eq!(price, 150_000_000);
+    }
 }
@@ -19,6 +19,7 @@ import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   NATIVE_MINT,
   getOrCreateAssociatedTokenAccount,
+  getAssociatedTokenAddressSync,
 } from "@solana/spl-token";
 import DLMM from "@meteora-ag/dlmm";
 
@@ -198,7 +199,6 @@ async function main() {
     .updateMockOracle(oracleBefore.price)
     .accountsStrict({
       authority,
-      config: configPda,
       mint: NATIVE_MINT,
       mockOracle: priceOraclePda,
     })
@@ -207,22 +207,26 @@ async function main() {
   // Step 4: Call liquidate
   console.log("\n[4] Calling liquidate...");
   const tx = await program.methods
-    .liquidate(FROM_BIN_ID, TO_BIN_ID)
+    .liquidate(FROM_BIN_ID, TO_BIN_ID, false)
     .accountsStrict({
       liquidator: authority,
       config: configPda,
+      keeper: null,
       wsolMint: NATIVE_MINT,
       position: positionPda,
       lendingVault: lendingVaultPda,
       collateralConfig: collateralConfigPda,
       priceOracle: priceOraclePda,
       wsolVault: wsolVaultPda,
+      liquidatorWsolAta: getAssociatedTokenAddressSync(NATIVE_MINT, authority),
       positionOwner: POSITION_OWNER,
+      ownerWsolAta: getAssociatedTokenAddressSync(NATIVE_MINT, POSITION_OWNER),
       collateralVault,
       metPosition: DLMM_POSITION,
       lbPair: LB_PAIR,
       binArrayBitmapExtension: null,
       userTokenX: userTokenXAccount.address,
+      ownerTokenX: getAssociatedTokenAddressSync(dlmmPool.lbPair.tokenXMint, POSITION_OWNER),
       reserveX: dlmmPool.lbPair.reserveX,
       reserveY: dlmmPool.lbPair.reserveY,
       tokenXMint: dlmmPool.lbPair.tokenXMint,
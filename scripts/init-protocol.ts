@@ -33,6 +33,7 @@ const SOL_COLLATERAL = {
   minDeposit: new BN(Math.floor(0.1 * LAMPORTS_PER_SOL)),
   interestRateBps: 500,        // 5%
   oracleMaxAge: new BN(3600),  // 1 hour
+  maxLeverageBps: new BN(50_000), // 5×
 };
 
 async function main() {
@@ -95,7 +96,6 @@ async function main() {
       .updateMockOracle(SOL_ORACLE_PRICE)
       .accountsStrict({
         authority,
-        config: configPda,
         mint: NATIVE_MINT,
         mockOracle: mockOraclePda,
       })
@@ -103,7 +103,7 @@ async function main() {
     console.log("       Oracle price refreshed to $" + SOL_ORACLE_PRICE.toNumber() / 1_000_000);
   } catch {
     await program.methods
-      .initializeMockOracle(SOL_ORACLE_PRICE)
+      .initializeMockOracle(SOL_ORACLE_PRICE, 6)
       .accountsStrict({
         authority,
         config: configPda,
@@ -123,12 +123,14 @@ async function main() {
     await program.methods
       .registerCollateral(
         mockOraclePda,
+        { mock: {} },
         SOL_COLLATERAL.maxLtv,
         SOL_COLLATERAL.liquidationThreshold,
         SOL_COLLATERAL.liquidationPenalty,
         SOL_COLLATERAL.minDeposit,
         SOL_COLLATERAL.interestRateBps,
         SOL_COLLATERAL.oracleMaxAge,
+        SOL_COLLATERAL.maxLeverageBps,
       )
       .accountsStrict({
         authority,
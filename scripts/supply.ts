@@ -48,6 +48,10 @@ async function main() {
   const program = anchor.workspace.metlevEngine as Program<MetlevEngine>;
   const signer = provider.wallet.publicKey;
 
+  const [configPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
   const [lendingVaultPda] = PublicKey.findProgramAddressSync(
     [Buffer.from("lending_vault")],
     program.programId
@@ -84,6 +88,7 @@ async function main() {
     .supply(amountLamports)
     .accountsStrict({
       signer,
+      config: configPda,
       lendingVault: lendingVaultPda,
       wsolMint: NATIVE_MINT,
       wsolVault: wsolVaultPda,
@@ -98,7 +103,7 @@ async function main() {
   const vault = await program.account.lendingVault.fetch(lendingVaultPda);
 
   console.log("Supplied", amountSol, "wSOL to lending vault.");
-  console.log("LP total supplied:", lpPosition.suppliedAmount.toNumber() / LAMPORTS_PER_SOL, "wSOL");
+  console.log("LP shares:", lpPosition.shares.toString());
   console.log("Vault total supplied:", vault.totalSupplied.toNumber() / LAMPORTS_PER_SOL, "wSOL");
 }
 
@@ -42,10 +42,6 @@ async function main() {
   const program = anchor.workspace.metlevEngine as Program<MetlevEngine>;
   const authority = provider.wallet.publicKey;
 
-  const [configPda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("config")],
-    program.programId
-  );
   const [mockOraclePda] = PublicKey.findProgramAddressSync(
     [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
     program.programId
@@ -59,7 +55,6 @@ async function main() {
     .updateMockOracle(priceRaw)
     .accountsStrict({
       authority,
-      config: configPda,
       mint: NATIVE_MINT,
       mockOracle: mockOraclePda,
     })
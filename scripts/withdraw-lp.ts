@@ -1,15 +1,15 @@
 /**
  * withdraw-lp.ts
  *
- * Withdraw all supplied wSOL from the lending vault (closes LP position).
- * Returns supplied amount + accrued interest.
+ * Withdraw wSOL from the lending vault by burning LP shares. Burning all
+ * shares closes the LP position.
  *
  * Usage:
- *   npx ts-node scripts/withdraw-lp.ts
+ *   npx ts-node scripts/withdraw-lp.ts [amount_sol]   # defaults to everything
  */
 
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
 import { MetlevEngine } from "../target/types/metlev_engine";
 import {
   PublicKey,
@@ -29,6 +29,10 @@ async function main() {
   const program = anchor.workspace.metlevEngine as Program<MetlevEngine>;
   const signer = provider.wallet.publicKey;
 
+  const [configPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
   const [lendingVaultPda] = PublicKey.findProgramAddressSync(
     [Buffer.from("lending_vault")],
     program.programId
@@ -52,8 +56,10 @@ async function main() {
   }
 
   console.log("LP position found:");
-  console.log("  Supplied:", lpPosition.suppliedAmount.toNumber() / LAMPORTS_PER_SOL, "wSOL");
-  console.log("  Interest earned:", lpPosition.interestEarned.toNumber() / LAMPORTS_PER_SOL, "wSOL");
+  const vault = await program.account.lendingVault.fetch(lendingVaultPda);
+  const redeemable = lpPosition.shares.mul(vault.totalSupplied).div(vault.totalShares);
+  console.log("  Shares:", lpPosition.shares.toString());
+  console.log("  Redeemable:", redeemable.toNumber() / LAMPORTS_PER_SOL, "wSOL");
 
   const signerWsolAta = await getOrCreateAssociatedTokenAccount(
     provider.connection,
@@ -62,12 +68,25 @@ async function main() {
     signer
   );
 
+  const amountArg = process.argv[2];
+  let shares: BN = lpPosition.shares;
+  if (amountArg) {
+    const amountSol = parseFloat(amountArg);
+    if (isNaN(amountSol) || amountSol <= 0) {
+      console.error("Invalid amount:", amountArg);
+      process.exit(1);
+    }
+    const amount = new BN(Math.round(amountSol * LAMPORTS_PER_SOL));
+    shares = BN.min(amount.mul(vault.totalShares).div(vault.totalSupplied), lpPosition.shares);
+  }
+
   const balanceBefore = await provider.connection.getTokenAccountBalance(signerWsolAta.address);
 
   await program.methods
-    .withdraw()
+    .withdraw(shares)
     .accountsStrict({
       signer,
+      config: configPda,
       lpPosition: lpPositionPda,
       lendingVault: lendingVaultPda,
       wsolMint: NATIVE_MINT,
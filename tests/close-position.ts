@@ -14,6 +14,7 @@ import {
 } from "@solana/web3.js";
 import {
   getOrCreateAssociatedTokenAccount,
+  getAssociatedTokenAddressSync,
   createSyncNativeInstruction,
   createMint,
   mintTo,
@@ -57,6 +58,32 @@ function deriveEventAuthority(): PublicKey {
   return pda;
 }
 
+const INDEX_ONE = new BN("1000000000000000000");
+
+// Interest one accrual books on all of the vault's outstanding borrows,
+// rounded up like the program does
+function bookedInterest(
+  before: { totalBorrowed: BN; borrowIndex: BN },
+  after: { borrowIndex: BN }
+): BN {
+  const growth = after.borrowIndex.sub(before.borrowIndex);
+  if (growth.isZero()) return new BN(0);
+  return before.totalBorrowed.mul(growth).add(before.borrowIndex).subn(1).div(before.borrowIndex);
+}
+
+// A position's debt brought up to `borrowIndex`, rounded down like the program does
+function accruedDebt(
+  position: { debtAmount: BN; borrowIndexSnapshot: BN; debtInterestRemainder: BN },
+  borrowIndex: BN
+): BN {
+  const snapshot = position.borrowIndexSnapshot;
+  if (position.debtAmount.isZero() || snapshot.isZero()) return position.debtAmount;
+  const growth = borrowIndex.sub(snapshot).mul(INDEX_ONE).div(snapshot);
+  return position.debtAmount.add(
+    position.debtAmount.mul(growth).add(position.debtInterestRemainder).div(INDEX_ONE)
+  );
+}
+
 describe("Close Position", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -72,6 +99,14 @@ describe("Close Position", () => {
 
   // ─── Helpers ────────────────────────────────────────────────────────────────
 
+  function deriveRentReserve(): PublicKey {
+    const [pda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("rent_reserve")],
+      program.programId
+    );
+    return pda;
+  }
+
   // Send SDK-generated transactions (fee payer = first signer, NOT provider wallet)
   async function sendSdkTx(tx: Transaction, signers: Keypair[]): Promise<string> {
     tx.feePayer = signers[0].publicKey;
@@ -102,10 +137,15 @@ describe("Close Position", () => {
   }
 
   // Opens a fresh leveraged DLMM position for `user` and returns all state needed to close it.
+  // By default the range straddles two bin arrays; `singleArray` keeps a 3-bin range inside one,
+  // so lower and upper are the same account. `subsidizeRent` has the rent reserve pay the DLMM rent.
   async function openPosition(
     user: Keypair,
     positionPda: PublicKey,
     wsolVault: PublicKey,
+    singleArray = false,
+    subsidizeRent = false,
+    strategyTag: number[] | null = null,
   ): Promise<{ metPositionKp: Keypair; minBinId: number; maxBinId: number }> {
     await dlmmPool.refetchStates();
     const activeBin = await dlmmPool.getActiveBin();
@@ -128,7 +168,24 @@ describe("Close Position", () => {
     let lowerIdx = binArrayIndex(minBinId);
     let upperIdx = binArrayIndex(maxBinId);
 
-    if (lowerIdx.eq(upperIdx)) {
+    if (singleArray) {
+      const width = 3;
+      if (isWsolX) {
+        minBinId = activeBinId + 1;
+        if (!binArrayIndex(minBinId).eq(binArrayIndex(minBinId + width - 1))) {
+          minBinId = binArrayIndex(minBinId + width - 1).toNumber() * BIN_ARRAY_SIZE;
+        }
+        maxBinId = minBinId + width - 1;
+      } else {
+        maxBinId = activeBinId;
+        if (!binArrayIndex(maxBinId).eq(binArrayIndex(maxBinId - width + 1))) {
+          maxBinId = binArrayIndex(maxBinId - width + 1).toNumber() * BIN_ARRAY_SIZE + BIN_ARRAY_SIZE - 1;
+        }
+        minBinId = maxBinId - width + 1;
+      }
+      lowerIdx = binArrayIndex(minBinId);
+      upperIdx = binArrayIndex(maxBinId);
+    } else if (lowerIdx.eq(upperIdx)) {
       if (isWsolX) {
         let boundary = (activeArrayIdx + 1) * BIN_ARRAY_SIZE;
         if (boundary - half <= activeBinId) boundary += BIN_ARRAY_SIZE;
@@ -166,7 +223,7 @@ describe("Close Position", () => {
     // Refresh oracle timestamp before opening
     await program.methods
       .updateMockOracle(new BN(150_000_000))
-      .accountsStrict({ authority, config: configPda, mint: NATIVE_MINT, mockOracle: priceOracle })
+      .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOracle })
       .rpc();
 
     const metPositionKp = Keypair.generate();
@@ -178,7 +235,9 @@ describe("Close Position", () => {
         maxBinId - minBinId + 1,
         activeBinId,
         10, // maxActiveBinSlippage
-        binLiquidityDist
+        binLiquidityDist,
+        subsidizeRent,
+        strategyTag
       )
       .accountsStrict({
         user: user.publicKey,
@@ -190,6 +249,7 @@ describe("Close Position", () => {
         collateralConfig: collateralConfigPda,
         priceOracle,
         metPosition: metPositionKp.publicKey,
+        rentReserve: subsidizeRent ? deriveRentReserve() : null,
         lbPair: LB_PAIR,
         binArrayBitmapExtension: null,
         reserve,
@@ -251,17 +311,21 @@ describe("Close Position", () => {
     return {
       accounts: {
         user,
+        owner: user,
         config: configPda,
         wsolMint: NATIVE_MINT,
         position: positionPda,
+        collateralConfig: collateralConfigPda,
         lendingVault: lendingVaultPda,
         wsolVault: wsolVaultPda,
         userWsolAta: userWsolAta.address,
         collateralVault,
         metPosition: metPositionPubkey,
+        rentReserve: null,
         lbPair: LB_PAIR,
         binArrayBitmapExtension: null,
         userTokenX: userTokenXAccount.address,
+        ownerTokenX: getAssociatedTokenAddressSync(dlmmPool.lbPair.tokenXMint, user),
         reserveX: dlmmPool.lbPair.reserveX,
         reserveY: dlmmPool.lbPair.reserveY,
         tokenXMint: dlmmPool.lbPair.tokenXMint,
@@ -307,6 +371,11 @@ describe("Close Position", () => {
         .rpc();
     }
 
+    // Idempotent: the DLMM pair must be whitelisted before opening on it
+    await program.methods.whitelistPair(LB_PAIR)
+      .accountsStrict({ authority, config: configPda })
+      .rpc();
+
     // Idempotent: lending vault
     try {
       await program.account.lendingVault.fetch(lendingVaultPda);
@@ -328,7 +397,7 @@ describe("Close Position", () => {
     try {
       await program.account.mockOracle.fetch(priceOracle);
     } catch {
-      await program.methods.initializeMockOracle(new BN(150_000_000))
+      await program.methods.initializeMockOracle(new BN(150_000_000), 6)
         .accountsStrict({
           authority, config: configPda, mint: NATIVE_MINT, mockOracle: priceOracle,
           systemProgram: SystemProgram.programId,
@@ -349,12 +418,14 @@ describe("Close Position", () => {
     } catch {
       await program.methods.registerCollateral(
         priceOracle,
+        { mock: {} },
         7500,         // max_ltv (75%)
         8000,         // liquidation_threshold (80%)
         500,          // liquidation_penalty (5%)
         new BN(Math.floor(0.1 * LAMPORTS_PER_SOL)), // min_deposit
         500,          // interest_rate_bps (5%)
         new BN(3600), // oracle_max_age (1 hour)
+        new BN(50_000), // max_leverage_bps (5×)
       )
         .accountsStrict({
           authority, config: configPda, mint: NATIVE_MINT,
@@ -380,7 +451,7 @@ describe("Close Position", () => {
     } catch {
       await program.methods.supply(new BN(8 * LAMPORTS_PER_SOL))
         .accountsStrict({
-          signer: lp.publicKey, lendingVault: lendingVaultPda,
+          signer: lp.publicKey, config: configPda, lendingVault: lendingVaultPda,
           wsolMint: NATIVE_MINT, wsolVault: wsolVaultPda, signerWsolAta: lpWsolAta,
           lpPosition: lpPositionPda, tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
@@ -414,6 +485,9 @@ describe("Close Position", () => {
     let metPositionKp: Keypair;
     let openedMinBinId: number;
     let openedMaxBinId: number;
+    let totalBorrowedBeforeOpen: BN;
+    let activePositionsBeforeDeposit: number;
+    let activePositionsWhileOpen: number;
     const depositAmount = new BN(2 * LAMPORTS_PER_SOL);
 
     before("Fund, deposit collateral, open leveraged position", async function () {
@@ -429,6 +503,10 @@ describe("Close Position", () => {
       const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
       await provider.connection.confirmTransaction(sig);
 
+      activePositionsBeforeDeposit = (
+        await program.account.collateralConfig.fetch(collateralConfigPda)
+      ).activePositions.toNumber();
+
       await program.methods.depositSolCollateral(depositAmount)
         .accountsStrict({
           user: user.publicKey, config: configPda, mint: NATIVE_MINT,
@@ -441,6 +519,8 @@ describe("Close Position", () => {
 
       console.log("  Deposited", depositAmount.toNumber() / LAMPORTS_PER_SOL, "SOL as collateral");
 
+      totalBorrowedBeforeOpen = (await program.account.lendingVault.fetch(lendingVaultPda)).totalBorrowed;
+
       const result = await openPosition(user, positionPda, wsolVaultPda);
       metPositionKp = result.metPositionKp;
       openedMinBinId = result.minBinId;
@@ -458,6 +538,10 @@ describe("Close Position", () => {
       const metPositionInfo = await provider.connection.getAccountInfo(metPositionKp.publicKey);
       expect(metPositionInfo).to.not.be.null;
 
+      activePositionsWhileOpen = (
+        await program.account.collateralConfig.fetch(collateralConfigPda)
+      ).activePositions.toNumber();
+
       const vault = await program.account.lendingVault.fetch(lendingVaultPda);
       expect(vault.totalBorrowed.toNumber()).to.be.greaterThan(0, "Vault must have outstanding borrows");
 
@@ -481,7 +565,7 @@ describe("Close Position", () => {
       );
 
       const tx = await program.methods
-        .closePosition(openedMinBinId, openedMaxBinId)
+        .closePosition(openedMinBinId, openedMaxBinId, true)
         .accountsStrict(accounts)
         .signers([user])
         .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
@@ -504,13 +588,27 @@ describe("Close Position", () => {
         0,
         "debt_amount must be zeroed after close"
       );
+      expect(positionAfter.closedAt.toNumber()).to.be.at.least(
+        positionAfter.createdAt.toNumber(),
+        "closed_at must be recorded at close"
+      );
 
       // Verify lending vault accounting
       const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      // The close books interest on every outstanding borrow, then repays
+      // this position's debt with its own share of it
       expect(vaultAfter.totalBorrowed.toString()).to.equal(
-        vaultBefore.totalBorrowed.sub(debtBefore).toString(),
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(accruedDebt(positionBefore, vaultAfter.borrowIndex))
+          .toString(),
         "totalBorrowed must decrease by exactly the debt amount"
       );
+      // Other borrows kept accruing interest while this one was open
+      expect(vaultAfter.totalBorrowed.gte(totalBorrowedBeforeOpen)).to.equal(
+        true,
+        "totalBorrowed must return to its pre-open value plus interest"
+      );
 
       // Verify DLMM position account is gone
       const metPositionInfo = await provider.connection.getAccountInfo(metPositionKp.publicKey);
@@ -522,6 +620,19 @@ describe("Close Position", () => {
       console.log("  DLMM position     : closed on-chain");
     });
 
+    it("Counts the position as active from deposit until close", async () => {
+      expect(activePositionsWhileOpen).to.equal(
+        activePositionsBeforeDeposit + 1,
+        "Deposit must add one active position"
+      );
+
+      const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+      expect(config.activePositions.toNumber()).to.equal(
+        activePositionsBeforeDeposit,
+        "Close must take it back off"
+      );
+    });
+
     it("Withdraws SOL collateral and closes the position account", async () => {
       const userBalanceBefore = await provider.connection.getBalance(user.publicKey);
       const vaultBalanceBefore = await provider.connection.getBalance(collateralVaultPda);
@@ -564,169 +675,1141 @@ describe("Close Position", () => {
     });
   });
 
-  // ─── In-range / losing position ─────────────────────────────────────────────
-
-  describe("closePosition — in-range position (losing position with internal swap)", () => {
-    const mmUser = Keypair.generate();   // market maker — seeds fresh pool
-    const posUser = Keypair.generate();  // position owner
-    const swapUser = Keypair.generate(); // external trader moves price
-
-    let freshPool: DLMM;
-    let freshLbPair: PublicKey;
-    let customMint: PublicKey;
+  describe("closeAndWithdraw", () => {
+    const user = Keypair.generate();
     let positionPda: PublicKey;
     let collateralVaultPda: PublicKey;
     let metPositionKp: Keypair;
     let openedMinBinId: number;
     let openedMaxBinId: number;
-    let debtBefore: BN;
     const depositAmount = new BN(2 * LAMPORTS_PER_SOL);
 
-    // Scoped helpers — use freshLbPair instead of module-level LB_PAIR
-    function deriveFreshBinArrayPda(index: BN): PublicKey {
-      const indexBuf = Buffer.alloc(8);
-      indexBuf.writeBigInt64LE(BigInt(index.toString()));
-      const [pda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("bin_array"), freshLbPair.toBuffer(), indexBuf],
-        DLMM_PROGRAM_ID
+    before("Fund, deposit collateral, open leveraged position", async function () {
+      [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
       );
-      return pda;
-    }
 
-    async function ensureFreshBinArrayExists(index: BN): Promise<void> {
-      const pda = deriveFreshBinArrayPda(index);
-      if (await provider.connection.getAccountInfo(pda)) return;
-      const ixs = await freshPool.initializeBinArrays([index], authority);
-      if (ixs.length > 0) await provider.sendAndConfirm(new Transaction().add(...ixs));
-    }
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
 
-    before("Create fresh pool, seed liquidity, open position, push price", async function () {
-      this.timeout(120_000);
+      await program.methods.depositSolCollateral(depositAmount)
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
 
-      const sigs = await Promise.all([
-        provider.connection.requestAirdrop(mmUser.publicKey, 20 * LAMPORTS_PER_SOL),
-        provider.connection.requestAirdrop(posUser.publicKey, 10 * LAMPORTS_PER_SOL),
-        provider.connection.requestAirdrop(swapUser.publicKey, 15 * LAMPORTS_PER_SOL),
-      ]);
-      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+      const result = await openPosition(user, positionPda, wsolVaultPda);
+      metPositionKp = result.metPositionKp;
+      openedMinBinId = result.minBinId;
+      openedMaxBinId = result.maxBinId;
+    });
 
-      customMint = await createMint(
-        provider.connection,
-        mmUser,            // payer
-        mmUser.publicKey,  // mint authority
-        null,              // freeze authority
-        9
-      );
+    it("Closes the position and returns collateral in one instruction", async () => {
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const positionBefore = await program.account.position.fetch(positionPda);
+      const userBalanceBefore = await provider.connection.getBalance(user.publicKey);
 
-      // Mint to market maker
-      const mmTokenAta = await getOrCreateAssociatedTokenAccount(
-        provider.connection, mmUser, customMint, mmUser.publicKey
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        openedMinBinId,
+        openedMaxBinId
       );
-      await mintTo(
-        provider.connection, mmUser, customMint,
-        mmTokenAta.address, mmUser,
-        BigInt(1_000_000) * BigInt(10 ** 9)
+
+      await program.methods
+        .closeAndWithdraw(openedMinBinId, openedMaxBinId, true)
+        .accountsStrict(accounts)
+        .signers([user])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(accruedDebt(positionBefore, vaultAfter.borrowIndex))
+          .toString(),
+        "totalBorrowed must decrease by exactly the debt amount"
       );
 
-      // Mint to swapUser (they sell customMint to push price through position)
-      const swapTokenAta = await getOrCreateAssociatedTokenAccount(
-        provider.connection, mmUser, customMint, swapUser.publicKey
+      expect(await provider.connection.getAccountInfo(metPositionKp.publicKey)).to.be.null;
+      expect(await provider.connection.getAccountInfo(positionPda)).to.be.null;
+      expect(await provider.connection.getBalance(collateralVaultPda)).to.equal(
+        0,
+        "Collateral vault must be emptied in the same instruction"
       );
-      await mintTo(
-        provider.connection, mmUser, customMint,
-        swapTokenAta.address, mmUser,
-        BigInt(500_000) * BigInt(10 ** 9)
+
+      const userBalanceAfter = await provider.connection.getBalance(user.publicKey);
+      expect(userBalanceAfter).to.be.greaterThan(
+        userBalanceBefore,
+        "User must receive the collateral back"
       );
+    });
+  });
 
-      await wrapSol(mmUser, mmUser.publicKey, 10 * LAMPORTS_PER_SOL);
+  describe("closePosition — accrued interest", () => {
+    const user = Keypair.generate();
+    let positionPda: PublicKey;
+    let collateralVaultPda: PublicKey;
 
-      // new DLMM pool
-      const createPoolTx = await (DLMM as any).createCustomizablePermissionlessLbPair(
-        provider.connection,
-        new BN(10),          // binStep (10 bps = 0.1%)
-        customMint,          // token X
-        NATIVE_MINT,         // token Y (wSOL)
-        new BN(0),           // activeId
-        new BN(50),          // feeBps (0.5%)
-        0,                   // activationType = Slot
-        false,               // hasAlphaVault
-        mmUser.publicKey,    // creator
-        null,                // activationPoint (immediate)
-        false,               // creatorPoolOnOffControl
-        { cluster: "devnet" }
+    before("Fund and deposit collateral", async function () {
+      [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
       );
-      await sendSdkTx(createPoolTx, [mmUser]);
 
-      // Derive pool address and create SDK instance
-      const [derivedPair] = (DLMM as any).deriveCustomizablePermissionlessLbPair(
-        customMint, NATIVE_MINT, DLMM_PROGRAM_ID
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    });
+
+    it("Repays interest for the holding period, leaving it in the vault", async () => {
+      const wsolBeforeOpen = Number(
+        (await provider.connection.getTokenAccountBalance(wsolVaultPda)).value.amount
       );
-      freshLbPair = derivedPair;
-      freshPool = await DLMM.create(provider.connection, freshLbPair, { cluster: "devnet" });
-      await freshPool.refetchStates();
+      const { metPositionKp, minBinId, maxBinId } = await openPosition(user, positionPda, wsolVaultPda);
+      const borrowed = (await program.account.position.fetch(positionPda)).debtAmount;
 
-      const isWsolX = freshPool.lbPair.tokenXMint.equals(NATIVE_MINT);
-      console.log("  Fresh pool:", freshLbPair.toBase58());
-      console.log("  Token X:", freshPool.lbPair.tokenXMint.toBase58(), isWsolX ? "(wSOL)" : "(custom)");
-      console.log("  Token Y:", freshPool.lbPair.tokenYMint.toBase58(), !isWsolX ? "(wSOL)" : "(custom)");
+      // Hold the position for a few slots so interest accrues
+      await new Promise((resolve) => setTimeout(resolve, 3000));
 
-      // ── Seed two-sided liquidity ──
-      const activeBin = await freshPool.getActiveBin();
-      const SEED_RANGE = 30;
-      const seedMinBin = activeBin.binId - SEED_RANGE;
-      const seedMaxBin = activeBin.binId + SEED_RANGE;
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      const tx = await program.methods
+        .closePosition(minBinId, maxBinId, true)
+        .accountsStrict(accounts)
+        .signers([user])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
 
-      const mmPositionKp = Keypair.generate();
-      const addLiqTx = await freshPool.initializePositionAndAddLiquidityByStrategy({
-        positionPubKey: mmPositionKp.publicKey,
-        user: mmUser.publicKey,
-        totalXAmount: new BN(100_000).mul(new BN(10 ** 9)),  // 100K tokens
-        totalYAmount: new BN(5 * LAMPORTS_PER_SOL),          // 5 SOL
-        strategy: {
-          maxBinId: seedMaxBin,
-          minBinId: seedMinBin,
-          strategyType: 0, // Spot
-        },
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
       });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const closed = [...events].find((e) => e.name === "positionClosed")!;
+      const debtRepaid = closed.data.debtRepaid as BN;
+      expect(debtRepaid.gt(borrowed)).to.equal(true, "Repaid debt must include accrued interest");
+
+      const wsolAfterClose = Number(
+        (await provider.connection.getTokenAccountBalance(wsolVaultPda)).value.amount
+      );
+      expect(wsolAfterClose - wsolBeforeOpen).to.equal(
+        debtRepaid.sub(borrowed).toNumber(),
+        "The vault must end up ahead by exactly the interest paid"
+      );
+      expect(wsolAfterClose).to.be.greaterThan(wsolBeforeOpen);
+    });
+  });
 
-      if (Array.isArray(addLiqTx)) {
-        for (const tx of addLiqTx) {
-          await sendSdkTx(tx, [mmUser, mmPositionKp]);
-        }
-      } else {
-        await sendSdkTx(addLiqTx, [mmUser, mmPositionKp]);
-      }
-      console.log("  Two-sided liquidity seeded: bins", seedMinBin, "to", seedMaxBin);
+  describe("closePosition — rent-subsidized DLMM position", () => {
+    const user = Keypair.generate();
+    let positionPda: PublicKey;
+    let collateralVaultPda: PublicKey;
 
-      // ── posUser: deposit SOL collateral ──
+    before("Fund the rent reserve and deposit collateral", async function () {
       [positionPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("position"), posUser.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
         program.programId
       );
       [collateralVaultPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("vault"), posUser.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
         program.programId
       );
 
-      await program.methods.depositSolCollateral(depositAmount)
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: authority,
+            toPubkey: deriveRentReserve(),
+            lamports: LAMPORTS_PER_SOL,
+          })
+        )
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
         .accountsStrict({
-          user: posUser.publicKey, config: configPda, mint: NATIVE_MINT,
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
           collateralConfig: collateralConfigPda, vault: collateralVaultPda,
           position: positionPda, systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
-        .signers([posUser])
+        .signers([user])
         .rpc();
+    });
 
-      // ── posUser: open leveraged position on the fresh pool ──
-      await freshPool.refetchStates();
-      const currentActiveBin = await freshPool.getActiveBin();
-      const activeBinId = currentActiveBin.binId;
+    it("Charges the DLMM rent to the reserve at open and refunds it at close", async () => {
+      const reserveBefore = await provider.connection.getBalance(deriveRentReserve());
+      const { metPositionKp, minBinId, maxBinId } =
+        await openPosition(user, positionPda, wsolVaultPda, false, true);
+
+      const opened = await program.account.position.fetch(positionPda);
+      const dlmmRent = await provider.connection.getBalance(metPositionKp.publicKey);
+      expect(opened.subsidizedRent.toNumber()).to.equal(dlmmRent);
+      expect(reserveBefore - (await provider.connection.getBalance(deriveRentReserve()))).to.equal(
+        dlmmRent,
+        "The reserve must pay exactly the DLMM position rent"
+      );
 
-      // Place bins on the wSOL side (one-sided wSOL deposit)
-      let minBinId: number, maxBinId: number;
-      if (isWsolX) {
-        minBinId = activeBinId + 1;
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      const close = (withReserve: boolean) =>
+        program.methods
+          .closePosition(minBinId, maxBinId, true)
+          .accountsStrict({ ...accounts, rentReserve: withReserve ? deriveRentReserve() : null })
+          .signers([user])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc({ commitment: "confirmed" });
+
+      // The rent is owed back to the reserve, so it has to be passed
+      try {
+        await close(false);
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("RentReserveRequired");
+      }
+
+      await close(true);
+
+      const closed = await program.account.position.fetch(positionPda);
+      expect(closed.status).to.deep.equal({ closed: {} });
+      expect(closed.subsidizedRent.toNumber()).to.equal(0);
+      expect(await provider.connection.getBalance(deriveRentReserve())).to.equal(
+        reserveBefore,
+        "The reserve must get its rent back"
+      );
+    });
+  });
+
+  describe("closePosition — strategy tag", () => {
+    const user = Keypair.generate();
+
+    it("Stores the tag given at open and reports it in PositionClosed", async () => {
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const tag = Array.from(Buffer.alloc(16, 0).fill("vault-07", 0, 8));
+      const { metPositionKp, minBinId, maxBinId } =
+        await openPosition(user, positionPda, wsolVaultPda, false, false, tag);
+      expect((await program.account.position.fetch(positionPda)).strategyTag).to.deep.equal(tag);
+
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      const tx = await program.methods
+        .closePosition(minBinId, maxBinId, true)
+        .accountsStrict(accounts)
+        .signers([user])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const closed = [...events].find((e) => e.name === "positionClosed")!;
+      expect(closed.data.strategyTag).to.deep.equal(tag);
+    });
+  });
+
+  describe("closePosition — forced by the protocol authority", () => {
+    const user = Keypair.generate();
+    let positionPda: PublicKey;
+    let collateralVaultPda: PublicKey;
+
+    before("Fund and deposit collateral", async function () {
+      [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    });
+
+    it("Authority closes a user's position, flagged in PositionClosed, owner keeps the collateral", async () => {
+      const { metPositionKp, minBinId, maxBinId } = await openPosition(user, positionPda, wsolVaultPda);
+      const { accounts: ownerAccounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      const accounts = { ...ownerAccounts, user: authority };
+      const close = (swapToWsol: boolean) =>
+        program.methods
+          .closePosition(minBinId, maxBinId, swapToWsol)
+          .accountsStrict(accounts)
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc({ commitment: "confirmed" });
+
+      // Without the swap a shortfall would be pulled from the owner's wallet,
+      // which only the owner can sign for
+      try {
+        await close(false);
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("Unauthorized");
+      }
+
+      const collateralBefore = (await program.account.position.fetch(positionPda)).collateralAmount;
+      const tx = await close(true);
+
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const closed = [...events].find((e) => e.name === "positionClosed")!;
+      expect(closed.data.byAuthority).to.equal(true);
+      expect(closed.data.owner.toBase58()).to.equal(user.publicKey.toBase58());
+
+      const pos = await program.account.position.fetch(positionPda);
+      expect(pos.status).to.deep.equal({ closed: {} });
+      expect(pos.debtAmount.toNumber()).to.equal(0);
+      // Collateral stays in the owner's vault for the owner to withdraw
+      expect(pos.collateralAmount.toString()).to.equal(collateralBefore.toString());
+    });
+  });
+
+  describe("closePosition — opens and deposits paused", () => {
+    const user = Keypair.generate();
+    let positionPda: PublicKey;
+    let collateralVaultPda: PublicKey;
+    let metPositionKp: Keypair;
+    let minBinId: number;
+    let maxBinId: number;
+
+    before("Fund, deposit collateral, open leveraged position", async function () {
+      [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      ({ metPositionKp, minBinId, maxBinId } = await openPosition(user, positionPda, wsolVaultPda));
+    });
+
+    it("Blocks new opens and deposits but still lets the borrower close", async () => {
+      await program.methods
+        .updatePauseFlags(true, true)
+        .accountsStrict({ authority, config: configPda })
+        .rpc();
+
+      try {
+        const config = await program.account.config.fetch(configPda);
+        expect(config.openPaused).to.equal(true);
+        expect(config.depositPaused).to.equal(true);
+        expect(config.paused).to.equal(false);
+
+        try {
+          await openPosition(user, positionPda, wsolVaultPda);
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.include("ProtocolPaused");
+        }
+
+        const latecomer = Keypair.generate();
+        const sig = await provider.connection.requestAirdrop(latecomer.publicKey, LAMPORTS_PER_SOL);
+        await provider.connection.confirmTransaction(sig);
+        try {
+          await program.methods.depositSolCollateral(new BN(0.5 * LAMPORTS_PER_SOL))
+            .accountsStrict({
+              user: latecomer.publicKey, config: configPda, mint: NATIVE_MINT,
+              collateralConfig: collateralConfigPda,
+              vault: PublicKey.findProgramAddressSync(
+                [Buffer.from("vault"), latecomer.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+                program.programId
+              )[0],
+              position: PublicKey.findProgramAddressSync(
+                [Buffer.from("position"), latecomer.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+                program.programId
+              )[0],
+              systemProgram: SystemProgram.programId,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([latecomer])
+            .rpc();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.include("ProtocolPaused");
+        }
+
+        const { accounts } = await buildCloseAccounts(
+          user.publicKey,
+          positionPda,
+          metPositionKp.publicKey,
+          minBinId,
+          maxBinId
+        );
+        await program.methods
+          .closePosition(minBinId, maxBinId, true)
+          .accountsStrict(accounts)
+          .signers([user])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc();
+
+        const position = await program.account.position.fetch(positionPda);
+        expect(position.status).to.deep.equal({ closed: {} });
+        expect(position.debtAmount.toNumber()).to.equal(0);
+      } finally {
+        await program.methods
+          .updatePauseFlags(false, false)
+          .accountsStrict({ authority, config: configPda })
+          .rpc();
+      }
+    });
+  });
+
+  describe("closePosition — single bin array", () => {
+    const user = Keypair.generate();
+
+    it("Closes a position whose bins all sit in one bin array", async () => {
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const { metPositionKp, minBinId, maxBinId } =
+        await openPosition(user, positionPda, wsolVaultPda, true);
+
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      expect(accounts.binArrayLower.toBase58()).to.equal(accounts.binArrayUpper.toBase58());
+
+      const positionBefore = await program.account.position.fetch(positionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      await program.methods
+        .closePosition(minBinId, maxBinId, true)
+        .accountsStrict(accounts)
+        .signers([user])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const position = await program.account.position.fetch(positionPda);
+      expect(position.status).to.deep.equal({ closed: {} });
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(accruedDebt(positionBefore, vaultAfter.borrowIndex))
+          .toString(),
+        "Debt must be repaid in full"
+      );
+      expect(await provider.connection.getAccountInfo(metPositionKp.publicKey)).to.be.null;
+    });
+  });
+
+  describe("closePosition — without swapping to wSOL", () => {
+    const user = Keypair.generate();
+
+    it("Hands token X to the user and settles debt from wSOL alone, leaving collateral untouched", async () => {
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const { metPositionKp, minBinId, maxBinId } = await openPosition(user, positionPda, wsolVaultPda);
+
+      // wSOL in the wallet to cover a shortfall, since collateral won't be used
+      await wrapSol(user, user.publicKey, LAMPORTS_PER_SOL / 10);
+
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      const before = await program.account.position.fetch(positionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const balance = async (ata: PublicKey) => {
+        const info = await provider.connection.getAccountInfo(ata);
+        if (!info) return 0;
+        return parseInt((await provider.connection.getTokenAccountBalance(ata)).value.amount);
+      };
+      const wsolBefore = await balance(accounts.userWsolAta);
+      const tokenXBefore = await balance(accounts.ownerTokenX);
+
+      await program.methods
+        .closePosition(minBinId, maxBinId, false)
+        .accountsStrict(accounts)
+        .signers([user])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const after = await program.account.position.fetch(positionPda);
+      expect(after.status).to.deep.equal({ closed: {} });
+      expect(after.collateralAmount.toString()).to.equal(
+        before.collateralAmount.toString(),
+        "Collateral must not cover a shortfall when not swapping"
+      );
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(accruedDebt(before, vaultAfter.borrowIndex))
+          .toString(),
+        "Debt must be repaid in full"
+      );
+
+      // The wallet receives the wSOL surplus or pays the shortfall
+      const wsolDelta = (await balance(accounts.userWsolAta)) - wsolBefore;
+      expect(wsolDelta).to.equal(after.realizedPnl.toNumber());
+      expect(await balance(accounts.ownerTokenX)).to.be.at.least(tokenXBefore);
+    });
+  });
+
+  describe("repayFull", () => {
+    const user = Keypair.generate();
+
+    it("Pays off all debt and keeps the DLMM position open, then closes with no debt to repay", async () => {
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const { metPositionKp, minBinId, maxBinId } = await openPosition(user, positionPda, wsolVaultPda);
+      const debt = (await program.account.position.fetch(positionPda)).debtAmount;
+      // The debt plus headroom for interest accrued before the repay lands
+      const userWsolAta = await wrapSol(user, user.publicKey, debt.toNumber() + LAMPORTS_PER_SOL / 100);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      const repayAccounts = {
+        user: user.publicKey,
+        wsolMint: NATIVE_MINT,
+        position: positionPda,
+        collateralConfig: collateralConfigPda,
+        lendingVault: lendingVaultPda,
+        wsolVault: wsolVaultPda,
+        userWsolAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      };
+      const wsolBefore = new BN((await provider.connection.getTokenAccountBalance(userWsolAta)).value.amount);
+      await program.methods.repayFull().accountsStrict(repayAccounts).signers([user]).rpc();
+      const paid = wsolBefore.sub(
+        new BN((await provider.connection.getTokenAccountBalance(userWsolAta)).value.amount)
+      );
+      expect(paid.gte(debt)).to.equal(true, "Pays the stored debt plus any interest since");
+
+      const position = await program.account.position.fetch(positionPda);
+      expect(position.debtAmount.toNumber()).to.equal(0);
+      expect(position.status).to.deep.equal({ active: {} }, "Repaying in full keeps the position open");
+      expect(await provider.connection.getAccountInfo(metPositionKp.publicKey)).to.not.be.null;
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultAfter.totalBorrowed.lte(vaultBefore.totalBorrowed.sub(debt))).to.equal(true);
+
+      // Nothing left to repay
+      try {
+        await program.methods.repayFull().accountsStrict(repayAccounts).signers([user]).rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InvalidAmount");
+      }
+
+      // Closing later unwinds the liquidity with every lamport of proceeds going to the owner
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      await program.methods
+        .closePosition(minBinId, maxBinId, true)
+        .accountsStrict(accounts)
+        .signers([user])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+      const closed = await program.account.position.fetch(positionPda);
+      expect(closed.status).to.deep.equal({ closed: {} });
+      expect(closed.realizedPnl.toNumber()).to.be.greaterThan(0);
+    });
+  });
+
+  describe("closePositionRemove / closePositionFinalize", () => {
+    const user = Keypair.generate();
+
+    it("Unwinds a position in two sub-ranges, then finalizes once it is drained", async () => {
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const { metPositionKp, minBinId, maxBinId } = await openPosition(user, positionPda, wsolVaultPda);
+
+      // Bin arrays stay those of the whole position; only the range argument narrows
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      const debtBefore = (await program.account.position.fetch(positionPda)).debtAmount;
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      // Each step books interest on every outstanding borrow
+      let booked = new BN(0);
+      const accruing = async (send: () => Promise<string>) => {
+        const vault = await program.account.lendingVault.fetch(lendingVaultPda);
+        const tx = await send();
+        booked = booked.add(
+          bookedInterest(vault, await program.account.lendingVault.fetch(lendingVaultPda))
+        );
+        return tx;
+      };
+      const remove = (from: number, to: number) =>
+        program.methods
+          .closePositionRemove(from, to)
+          .accountsStrict(accounts)
+          .signers([user])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc({ commitment: "confirmed" });
+      const finalize = () =>
+        program.methods
+          .closePositionFinalize()
+          .accountsStrict(accounts)
+          .signers([user])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc({ commitment: "confirmed" });
+
+      const mid = minBinId + 1;
+      const stepTx = await accruing(() => remove(minBinId, mid));
+
+      let position = await program.account.position.fetch(positionPda);
+      expect(position.status).to.deep.equal({ active: {} }, "Position stays active between steps");
+      expect(position.closing).to.equal(true);
+      expect(position.closeRepaid.toNumber()).to.be.greaterThan(0);
+      expect(position.debtAmount.lt(debtBefore)).to.equal(true, "Each step repays debt from its proceeds");
+
+      const txInfo = await provider.connection.getTransaction(stepTx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const step = [...events].find((e) => e.name === "positionCloseStep")!;
+      expect(step.data.debtRepaid.toString()).to.equal(position.closeRepaid.toString());
+      expect(step.data.debtRemaining.toString()).to.equal(position.debtAmount.toString());
+
+      // A partial close mid-way would repay debt outside the close totals
+      try {
+        await program.methods
+          .partialClosePosition(mid + 1, maxBinId, 5000)
+          .accountsStrict(accounts)
+          .signers([user])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("PositionClosing");
+      }
+
+      // DLMM refuses to close a position that still holds liquidity
+      try {
+        await finalize();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.not.equal("Should have failed");
+      }
+
+      await accruing(() => remove(mid + 1, maxBinId));
+      await accruing(finalize);
+
+      position = await program.account.position.fetch(positionPda);
+      expect(position.status).to.deep.equal({ closed: {} });
+      expect(position.debtAmount.toNumber()).to.equal(0);
+      // Step totals feed the PnL; any debt left for finalize only lowers it
+      expect(position.realizedPnl.toNumber()).to.be.at.most(
+        position.closeProceeds.toNumber() - position.closeRepaid.toNumber()
+      );
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const repaid = vaultBefore.totalBorrowed.add(booked).sub(vaultAfter.totalBorrowed);
+      expect(repaid.gte(debtBefore)).to.equal(
+        true,
+        "Interest accrued between steps is repaid along with the debt"
+      );
+      expect(await provider.connection.getAccountInfo(metPositionKp.publicKey)).to.be.null;
+    });
+  });
+
+  describe("partialClosePosition", () => {
+    const user = Keypair.generate();
+    let positionPda: PublicKey;
+    let collateralVaultPda: PublicKey;
+    let metPositionKp: Keypair;
+    let minBinId: number;
+    let maxBinId: number;
+
+    before("Fund and deposit collateral", async function () {
+      [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    });
+
+    it("Removes half the range's liquidity and repays half the debt, keeping the position open", async () => {
+      ({ metPositionKp, minBinId, maxBinId } = await openPosition(user, positionPda, wsolVaultPda));
+      const positionLiquidity = async () => {
+        const { positionData } = await dlmmPool.getPosition(metPositionKp.publicKey);
+        return positionData.positionBinData.reduce(
+          (sum, bin) => sum + Number(bin.positionLiquidity),
+          0
+        );
+      };
+
+      const liquidityBefore = await positionLiquidity();
+      const before = await program.account.position.fetch(positionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      const tx = await program.methods
+        .partialClosePosition(minBinId, maxBinId, 5000)
+        .accountsStrict(accounts)
+        .signers([user])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const after = await program.account.position.fetch(positionPda);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const repaid = vaultBefore.totalBorrowed
+        .add(bookedInterest(vaultBefore, vaultAfter))
+        .sub(vaultAfter.totalBorrowed);
+
+      expect(after.status).to.deep.equal({ active: {} });
+      expect(after.meteoraPosition.toBase58()).to.equal(metPositionKp.publicKey.toBase58());
+      expect(after.debtAmount.gtn(0)).to.be.true;
+      // Roughly half the debt is repaid; interest accrued in between keeps it from being exact
+      expect(repaid.gtn(0)).to.be.true;
+      expect(repaid.lte(before.debtAmount.divn(2).addn(10))).to.be.true;
+      expect(repaid.gte(before.debtAmount.muln(49).divn(100))).to.be.true;
+
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const partial = [...events].find((e) => e.name === "positionPartiallyClosed")!;
+      expect(partial.data.bpsRemoved).to.equal(5000);
+      expect(partial.data.debtRepaid.toString()).to.equal(repaid.toString());
+      expect(partial.data.debtRemaining.toString()).to.equal(after.debtAmount.toString());
+
+      const liquidityAfter = await positionLiquidity();
+      expect(liquidityAfter).to.be.greaterThan(0, "Position must keep some liquidity");
+      expect(liquidityAfter).to.be.lessThan(liquidityBefore, "Half the liquidity must be removed");
+      console.log("  Liquidity", liquidityBefore, "->", liquidityAfter);
+    });
+
+    it("Rejects a partial close that would leave less than min_position_borrow outstanding", async () => {
+      const before = await program.account.position.fetch(positionPda);
+      await program.methods
+        .updateCollateralMinPositionBorrow(NATIVE_MINT, before.debtAmount)
+        .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+        .rpc();
+
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      try {
+        await program.methods
+          .partialClosePosition(minBinId, maxBinId, 5000)
+          .accountsStrict(accounts)
+          .signers([user])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InvalidAmount");
+      } finally {
+        await program.methods
+          .updateCollateralMinPositionBorrow(NATIVE_MINT, new BN(0))
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+      }
+    });
+
+    it("Rejects a full removal, which has to go through closePosition", async () => {
+      const { accounts } = await buildCloseAccounts(
+        user.publicKey,
+        positionPda,
+        metPositionKp.publicKey,
+        minBinId,
+        maxBinId
+      );
+      try {
+        await program.methods
+          .partialClosePosition(minBinId, maxBinId, 10_000)
+          .accountsStrict(accounts)
+          .signers([user])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InvalidAmount");
+      }
+    });
+  });
+
+  // ─── In-range / losing position ─────────────────────────────────────────────
+
+  describe("closePosition — in-range position (losing position with internal swap)", () => {
+    const mmUser = Keypair.generate();   // market maker — seeds fresh pool
+    const posUser = Keypair.generate();  // position owner
+    const swapUser = Keypair.generate(); // external trader moves price
+
+    let freshPool: DLMM;
+    let freshLbPair: PublicKey;
+    let customMint: PublicKey;
+    let positionPda: PublicKey;
+    let collateralVaultPda: PublicKey;
+    let metPositionKp: Keypair;
+    let openedMinBinId: number;
+    let openedMaxBinId: number;
+    let debtBefore: BN;
+    const depositAmount = new BN(2 * LAMPORTS_PER_SOL);
+
+    // Scoped helpers — use freshLbPair instead of module-level LB_PAIR
+    function deriveFreshBinArrayPda(index: BN): PublicKey {
+      const indexBuf = Buffer.alloc(8);
+      indexBuf.writeBigInt64LE(BigInt(index.toString()));
+      const [pda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("bin_array"), freshLbPair.toBuffer(), indexBuf],
+        DLMM_PROGRAM_ID
+      );
+      return pda;
+    }
+
+    async function ensureFreshBinArrayExists(index: BN): Promise<void> {
+      const pda = deriveFreshBinArrayPda(index);
+      if (await provider.connection.getAccountInfo(pda)) return;
+      const ixs = await freshPool.initializeBinArrays([index], authority);
+      if (ixs.length > 0) await provider.sendAndConfirm(new Transaction().add(...ixs));
+    }
+
+    before("Create fresh pool, seed liquidity, open position, push price", async function () {
+      this.timeout(120_000);
+
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(mmUser.publicKey, 20 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(posUser.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(swapUser.publicKey, 15 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      customMint = await createMint(
+        provider.connection,
+        mmUser,            // payer
+        mmUser.publicKey,  // mint authority
+        null,              // freeze authority
+        9
+      );
+
+      // Mint to market maker
+      const mmTokenAta = await getOrCreateAssociatedTokenAccount(
+        provider.connection, mmUser, customMint, mmUser.publicKey
+      );
+      await mintTo(
+        provider.connection, mmUser, customMint,
+        mmTokenAta.address, mmUser,
+        BigInt(1_000_000) * BigInt(10 ** 9)
+      );
+
+      // Mint to swapUser (they sell customMint to push price through position)
+      const swapTokenAta = await getOrCreateAssociatedTokenAccount(
+        provider.connection, mmUser, customMint, swapUser.publicKey
+      );
+      await mintTo(
+        provider.connection, mmUser, customMint,
+        swapTokenAta.address, mmUser,
+        BigInt(500_000) * BigInt(10 ** 9)
+      );
+
+      await wrapSol(mmUser, mmUser.publicKey, 10 * LAMPORTS_PER_SOL);
+
+      // new DLMM pool
+      const createPoolTx = await (DLMM as any).createCustomizablePermissionlessLbPair(
+        provider.connection,
+        new BN(10),          // binStep (10 bps = 0.1%)
+        customMint,          // token X
+        NATIVE_MINT,         // token Y (wSOL)
+        new BN(0),           // activeId
+        new BN(50),          // feeBps (0.5%)
+        0,                   // activationType = Slot
+        false,               // hasAlphaVault
+        mmUser.publicKey,    // creator
+        null,                // activationPoint (immediate)
+        false,               // creatorPoolOnOffControl
+        { cluster: "devnet" }
+      );
+      await sendSdkTx(createPoolTx, [mmUser]);
+
+      // Derive pool address and create SDK instance
+      const [derivedPair] = (DLMM as any).deriveCustomizablePermissionlessLbPair(
+        customMint, NATIVE_MINT, DLMM_PROGRAM_ID
+      );
+      freshLbPair = derivedPair;
+      freshPool = await DLMM.create(provider.connection, freshLbPair, { cluster: "devnet" });
+      await freshPool.refetchStates();
+
+      const isWsolX = freshPool.lbPair.tokenXMint.equals(NATIVE_MINT);
+      console.log("  Fresh pool:", freshLbPair.toBase58());
+      console.log("  Token X:", freshPool.lbPair.tokenXMint.toBase58(), isWsolX ? "(wSOL)" : "(custom)");
+      console.log("  Token Y:", freshPool.lbPair.tokenYMint.toBase58(), !isWsolX ? "(wSOL)" : "(custom)");
+
+      // ── Seed two-sided liquidity ──
+      const activeBin = await freshPool.getActiveBin();
+      const SEED_RANGE = 30;
+      const seedMinBin = activeBin.binId - SEED_RANGE;
+      const seedMaxBin = activeBin.binId + SEED_RANGE;
+
+      const mmPositionKp = Keypair.generate();
+      const addLiqTx = await freshPool.initializePositionAndAddLiquidityByStrategy({
+        positionPubKey: mmPositionKp.publicKey,
+        user: mmUser.publicKey,
+        totalXAmount: new BN(100_000).mul(new BN(10 ** 9)),  // 100K tokens
+        totalYAmount: new BN(5 * LAMPORTS_PER_SOL),          // 5 SOL
+        strategy: {
+          maxBinId: seedMaxBin,
+          minBinId: seedMinBin,
+          strategyType: 0, // Spot
+        },
+      });
+
+      if (Array.isArray(addLiqTx)) {
+        for (const tx of addLiqTx) {
+          await sendSdkTx(tx, [mmUser, mmPositionKp]);
+        }
+      } else {
+        await sendSdkTx(addLiqTx, [mmUser, mmPositionKp]);
+      }
+      console.log("  Two-sided liquidity seeded: bins", seedMinBin, "to", seedMaxBin);
+
+      // ── posUser: deposit SOL collateral ──
+      [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), posUser.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), posUser.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(depositAmount)
+        .accountsStrict({
+          user: posUser.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([posUser])
+        .rpc();
+
+      // ── posUser: open leveraged position on the fresh pool ──
+      await freshPool.refetchStates();
+      const currentActiveBin = await freshPool.getActiveBin();
+      const activeBinId = currentActiveBin.binId;
+
+      // Place bins on the wSOL side (one-sided wSOL deposit)
+      let minBinId: number, maxBinId: number;
+      if (isWsolX) {
+        minBinId = activeBinId + 1;
         maxBinId = activeBinId + POSITION_WIDTH;
       } else {
         minBinId = activeBinId - POSITION_WIDTH + 1;
@@ -776,7 +1859,7 @@ describe("Close Position", () => {
       );
       await program.methods
         .updateMockOracle(new BN(150_000_000))
-        .accountsStrict({ authority, config: configPda, mint: NATIVE_MINT, mockOracle: priceOracle })
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOracle })
         .rpc();
 
       metPositionKp = Keypair.generate();
@@ -787,7 +1870,9 @@ describe("Close Position", () => {
           maxBinId - minBinId + 1,
           activeBinId,
           10,
-          binLiquidityDist
+          binLiquidityDist,
+          false, // subsidizeRent
+          null // strategyTag
         )
         .accountsStrict({
           user: posUser.publicKey,
@@ -799,6 +1884,7 @@ describe("Close Position", () => {
           collateralConfig: collateralConfigPda,
           priceOracle,
           metPosition: metPositionKp.publicKey,
+          rentReserve: null,
           lbPair: freshLbPair,
           binArrayBitmapExtension: null,
           reserve,
@@ -893,17 +1979,21 @@ describe("Close Position", () => {
 
       const accounts = {
         user: posUser.publicKey,
+        owner: posUser.publicKey,
         config: configPda,
         wsolMint: NATIVE_MINT,
         position: positionPda,
+        collateralConfig: collateralConfigPda,
         lendingVault: lendingVaultPda,
         wsolVault: wsolVaultPda,
         userWsolAta: posUserWsolAta.address,
         collateralVault: posCollateralVault,
         metPosition: metPositionKp.publicKey,
+        rentReserve: null,
         lbPair: freshLbPair,
         binArrayBitmapExtension: null,
         userTokenX: userTokenXAccount.address,
+        ownerTokenX: getAssociatedTokenAddressSync(freshPool.lbPair.tokenXMint, posUser.publicKey),
         reserveX: freshPool.lbPair.reserveX,
         reserveY: freshPool.lbPair.reserveY,
         tokenXMint: freshPool.lbPair.tokenXMint,
@@ -919,7 +2009,7 @@ describe("Close Position", () => {
       };
 
       await program.methods
-        .closePosition(openedMinBinId, openedMaxBinId)
+        .closePosition(openedMinBinId, openedMaxBinId, true)
         .accountsStrict(accounts)
         .signers([posUser])
         .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
@@ -942,7 +2032,10 @@ describe("Close Position", () => {
 
       const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
       expect(vaultAfter.totalBorrowed.toString()).to.equal(
-        vaultBefore.totalBorrowed.sub(debtBefore).toString(),
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(accruedDebt(positionBefore, vaultAfter.borrowIndex))
+          .toString(),
         "totalBorrowed must decrease by the debt amount"
       );
 
@@ -984,9 +2077,15 @@ describe("Close Position", () => {
         collateralUsed,
         "Collateral vault lamports must decrease by exactly the shortfall amount"
       );
-      expect(delta).to.equal(
+      expect(positionAfter.realizedPnl.toNumber()).to.equal(
+        -collateralUsed,
+        "realized_pnl must be the (negative) shortfall covered from collateral"
+      );
+      // Debt accrues interest between open and close, so the vault is repaid
+      // at least what was borrowed
+      expect(delta).to.be.at.least(
         borrowed,
-        "Vault delta must equal full debt (shortfall topped up from collateral)"
+        "Vault delta must cover the full debt (shortfall topped up from collateral)"
       );
 
       console.log("\n  Position status      : closed");
@@ -1042,6 +2141,383 @@ describe("Close Position", () => {
       constraintMaxBinId = result.maxBinId;
     });
 
+    it("Rebalances into a fresh DLMM position, repaying only wSOL DLMM left undeposited", async () => {
+      const { accounts } = await buildCloseAccounts(
+        constraintUser.publicKey,
+        constraintPositionPda,
+        constraintMetPositionKp.publicKey,
+        constraintMinBinId,
+        constraintMaxBinId
+      );
+      const activeBin = await dlmmPool.getActiveBin();
+      const newMetPositionKp = Keypair.generate();
+
+      const binLiquidityDist = [];
+      for (let i = constraintMinBinId; i <= constraintMaxBinId; i++) {
+        binLiquidityDist.push({ binId: i, weight: 1000 });
+      }
+
+      const before = await program.account.position.fetch(constraintPositionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      await program.methods
+        .rebalancePosition(
+          constraintMinBinId,
+          constraintMaxBinId,
+          constraintMinBinId,
+          constraintMaxBinId - constraintMinBinId + 1,
+          activeBin.binId,
+          10,
+          binLiquidityDist
+        )
+        .accountsStrict({
+          user: accounts.user,
+          config: accounts.config,
+          wsolMint: accounts.wsolMint,
+          position: accounts.position,
+          lendingVault: accounts.lendingVault,
+          wsolVault: accounts.wsolVault,
+          metPosition: accounts.metPosition,
+          rentReserve: null,
+          newMetPosition: newMetPositionKp.publicKey,
+          lbPair: accounts.lbPair,
+          binArrayBitmapExtension: null,
+          userTokenX: accounts.userTokenX,
+          reserveX: accounts.reserveX,
+          reserveY: accounts.reserveY,
+          tokenXMint: accounts.tokenXMint,
+          tokenYMint: accounts.tokenYMint,
+          binArrayLower: accounts.binArrayLower,
+          binArrayUpper: accounts.binArrayUpper,
+          newBinArrayLower: accounts.binArrayLower,
+          newBinArrayUpper: accounts.binArrayUpper,
+          oracle: accounts.oracle,
+          eventAuthority: accounts.eventAuthority,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          dlmmProgram: DLMM_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .signers([constraintUser, newMetPositionKp])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 800_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const after = await program.account.position.fetch(constraintPositionPda);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      // Debt accrues, then drops only by wSOL DLMM didn't take, which goes
+      // back to the vault
+      const unused = accruedDebt(before, vaultAfter.borrowIndex).sub(after.debtAmount);
+      expect(unused.gten(0)).to.be.true;
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(unused)
+          .toString()
+      );
+      expect(after.meteoraPosition.toBase58()).to.equal(newMetPositionKp.publicKey.toBase58());
+      expect(await provider.connection.getAccountInfo(constraintMetPositionKp.publicKey)).to.be.null;
+
+      constraintMetPositionKp = newMetPositionKp;
+      console.log("  Rebalanced into", newMetPositionKp.publicKey.toBase58());
+    });
+
+    it("Claims DLMM fees into the position's debt while it stays open", async () => {
+      const { accounts } = await buildCloseAccounts(
+        constraintUser.publicKey,
+        constraintPositionPda,
+        constraintMetPositionKp.publicKey,
+        constraintMinBinId,
+        constraintMaxBinId
+      );
+      const tokenXMint = accounts.tokenXMint;
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [tokenXOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), tokenXMint.toBuffer()],
+        program.programId
+      );
+      const [tokenXConfig] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral_config"), tokenXMint.toBuffer()],
+        program.programId
+      );
+
+      // Price token X at the pool's own price so the swap clears its
+      // oracle minimum
+      const sol = await program.account.mockOracle.fetch(priceOracle);
+      const solUsd = sol.price.toNumber() / 10 ** sol.decimals;
+      await dlmmPool.refetchStates();
+      const activeBin = await dlmmPool.getActiveBin();
+      const xPrice = new BN(Math.round(Number(activeBin.pricePerToken) * solUsd * 1e12));
+      if (await provider.connection.getAccountInfo(tokenXOracle)) {
+        await program.methods.updateMockOracle(xPrice)
+          .accountsStrict({ authority, mint: tokenXMint, mockOracle: tokenXOracle })
+          .rpc();
+      } else {
+        await program.methods.initializeMockOracle(xPrice, 12)
+          .accountsStrict({
+            authority, config: configPda, mint: tokenXMint, mockOracle: tokenXOracle,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+      if (!(await provider.connection.getAccountInfo(tokenXConfig))) {
+        await program.methods.registerCollateral(
+          tokenXOracle, { mock: {} }, 5000, 6000, 500,
+          new BN(1), 500, new BN(3600), new BN(20_000),
+        )
+          .accountsStrict({
+            authority, config: configPda, mint: tokenXMint,
+            collateralConfig: tokenXConfig,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+
+      const ownerWsolAta = (await getOrCreateAssociatedTokenAccount(
+        provider.connection, constraintUser, NATIVE_MINT, constraintUser.publicKey
+      )).address;
+      const claimAccounts = (payer: PublicKey) => ({
+        payer,
+        keeper: null,
+        wsolMint: NATIVE_MINT,
+        position: constraintPositionPda,
+        lendingVault: lendingVaultPda,
+        wsolVault: wsolVaultPda,
+        ownerWsolAta,
+        collateralConfig: collateralConfigPda,
+        priceOracle,
+        tokenXConfig,
+        tokenXOracle,
+        metPosition: accounts.metPosition,
+        lbPair: accounts.lbPair,
+        binArrayBitmapExtension: null,
+        userTokenX: accounts.userTokenX,
+        reserveX: accounts.reserveX,
+        reserveY: accounts.reserveY,
+        tokenXMint,
+        tokenYMint: accounts.tokenYMint,
+        binArrayLower: accounts.binArrayLower,
+        binArrayUpper: accounts.binArrayUpper,
+        oracle: accounts.oracle,
+        eventAuthority: accounts.eventAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        dlmmProgram: DLMM_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      });
+
+      // Neither the owner nor a keeper
+      try {
+        await program.methods
+          .claimPositionFees()
+          .accountsStrict(claimAccounts(authority))
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("Unauthorized");
+      }
+
+      const positionBefore = await program.account.position.fetch(constraintPositionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const xBefore = await provider.connection.getTokenAccountBalance(accounts.userTokenX);
+
+      const sig = await program.methods
+        .claimPositionFees()
+        .accountsStrict(claimAccounts(constraintUser.publicKey))
+        .signers([constraintUser])
+        .rpc({ commitment: "confirmed" });
+
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const event = [...parser.parseLogs(tx!.meta!.logMessages!)]
+        .find(e => e.name === "positionFeesClaimed")!;
+      expect(event).to.not.be.undefined;
+
+      const positionAfter = await program.account.position.fetch(constraintPositionPda);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      // Fees pay down this position's debt, not LP supply
+      expect(positionAfter.debtAmount.toString()).to.equal(
+        accruedDebt(positionBefore, vaultAfter.borrowIndex).sub(event.data.debtRepaid).toString()
+      );
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(event.data.debtRepaid)
+          .toString()
+      );
+      expect(event.data.toOwner.isZero()).to.be.true;
+      // Token X fees are sold for wSOL, not left in the shared ATA
+      const xAfter = await provider.connection.getTokenAccountBalance(accounts.userTokenX);
+      expect(xAfter.value.amount).to.equal(xBefore.value.amount);
+      expect(positionAfter.status).to.deep.equal({ active: {} });
+      console.log("  Fees repaid", event.data.debtRepaid.toString(), "lamports of debt");
+    });
+
+    it("Decreases leverage by unwinding one bin and repaying part of the debt", async () => {
+      const { accounts } = await buildCloseAccounts(
+        constraintUser.publicKey,
+        constraintPositionPda,
+        constraintMetPositionKp.publicKey,
+        constraintMinBinId,
+        constraintMaxBinId
+      );
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const repayAmount = new BN(1_000_000);
+
+      const before = await program.account.position.fetch(constraintPositionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      const tx = await program.methods
+        .decreaseLeverage(constraintMinBinId, constraintMinBinId, repayAmount)
+        .accountsStrict({
+          user: accounts.user,
+          wsolMint: accounts.wsolMint,
+          position: accounts.position,
+          lendingVault: accounts.lendingVault,
+          collateralConfig: collateralConfigPda,
+          priceOracle,
+          wsolVault: accounts.wsolVault,
+          userWsolAta: accounts.userWsolAta,
+          metPosition: accounts.metPosition,
+          lbPair: accounts.lbPair,
+          binArrayBitmapExtension: null,
+          userTokenX: accounts.userTokenX,
+          reserveX: accounts.reserveX,
+          reserveY: accounts.reserveY,
+          tokenXMint: accounts.tokenXMint,
+          tokenYMint: accounts.tokenYMint,
+          binArrayLower: accounts.binArrayLower,
+          binArrayUpper: accounts.binArrayUpper,
+          oracle: accounts.oracle,
+          eventAuthority: accounts.eventAuthority,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          dlmmProgram: DLMM_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([constraintUser])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const after = await program.account.position.fetch(constraintPositionPda);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      // Interest may accrue between the fetch and the tx, so bound rather than match exactly
+      expect(after.debtAmount.lte(before.debtAmount.sub(repayAmount).addn(10))).to.be.true;
+      expect(after.debtAmount.lt(before.debtAmount)).to.be.true;
+      expect(vaultAfter.totalBorrowed.lt(vaultBefore.totalBorrowed)).to.be.true;
+      expect(after.status).to.deep.equal({ active: {} });
+      expect(after.meteoraPosition.toBase58()).to.equal(constraintMetPositionKp.publicKey.toBase58());
+
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const decreased = [...events].find((e) => e.name === "leverageDecreased")!;
+      expect(decreased.data.debtRepaid.toString()).to.equal(repayAmount.toString());
+      expect(decreased.data.debtAmount.toString()).to.equal(after.debtAmount.toString());
+      expect(decreased.data.ltvAfter.toNumber()).to.be.lessThan(decreased.data.ltvBefore.toNumber());
+      console.log("  Debt", before.debtAmount.toString(), "->", after.debtAmount.toString());
+    });
+
+    it("Increases leverage by borrowing into the existing DLMM position", async () => {
+      const { accounts } = await buildCloseAccounts(
+        constraintUser.publicKey,
+        constraintPositionPda,
+        constraintMetPositionKp.publicKey,
+        constraintMinBinId,
+        constraintMaxBinId
+      );
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const activeBin = await dlmmPool.getActiveBin();
+      const binLiquidityDist = [];
+      for (let i = constraintMinBinId; i <= constraintMaxBinId; i++) {
+        binLiquidityDist.push({ binId: i, weight: 1000 });
+      }
+
+      const increase = (amount: BN) =>
+        program.methods
+          .increaseLeverage(amount, activeBin.binId, 10, binLiquidityDist)
+          .accountsStrict({
+            user: accounts.user,
+            config: accounts.config,
+            wsolMint: accounts.wsolMint,
+            position: accounts.position,
+            lendingVault: accounts.lendingVault,
+            wsolVault: accounts.wsolVault,
+            collateralConfig: collateralConfigPda,
+            priceOracle,
+            metPosition: accounts.metPosition,
+            lbPair: accounts.lbPair,
+            binArrayBitmapExtension: null,
+            reserve: accounts.reserveY,
+            tokenMint: accounts.tokenYMint,
+            binArrayLower: accounts.binArrayLower,
+            binArrayUpper: accounts.binArrayUpper,
+            eventAuthority: accounts.eventAuthority,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            dlmmProgram: DLMM_PROGRAM_ID,
+          })
+          .signers([constraintUser])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc({ commitment: "confirmed" });
+
+      // 10 SOL more on 2 SOL of collateral is well past the 5× cap
+      try {
+        await increase(new BN(10 * LAMPORTS_PER_SOL));
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.match(/ExceedsMaxLeverage/);
+      }
+
+      const before = await program.account.position.fetch(constraintPositionPda);
+      const borrowed = new BN(LAMPORTS_PER_SOL / 2);
+      await increase(borrowed);
+
+      const after = await program.account.position.fetch(constraintPositionPda);
+      expect(after.debtAmount.gte(before.debtAmount.add(borrowed))).to.be.true;
+      expect(after.meteoraPosition.toBase58()).to.equal(constraintMetPositionKp.publicKey.toBase58());
+      console.log("  Debt", before.debtAmount.toString(), "->", after.debtAmount.toString());
+    });
+
+    it("Rejects close with a DLMM position other than the one recorded at open", async () => {
+      const { accounts } = await buildCloseAccounts(
+        constraintUser.publicKey,
+        constraintPositionPda,
+        constraintMetPositionKp.publicKey,
+        constraintMinBinId,
+        constraintMaxBinId
+      );
+
+      try {
+        await program.methods
+          .closePosition(constraintMinBinId, constraintMaxBinId, true)
+          .accountsStrict({ ...accounts, metPosition: Keypair.generate().publicKey })
+          .signers([constraintUser])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.match(/InvalidMeteoraPosition/);
+        console.log("  Correctly rejected close against a foreign DLMM position");
+      }
+    });
+
     it("Rejects close when position is not active (already closed)", async () => {
       const { accounts } = await buildCloseAccounts(
         constraintUser.publicKey,
@@ -1052,7 +2528,7 @@ describe("Close Position", () => {
       );
 
       await program.methods
-        .closePosition(constraintMinBinId, constraintMaxBinId)
+        .closePosition(constraintMinBinId, constraintMaxBinId, true)
         .accountsStrict(accounts)
         .signers([constraintUser])
         .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
@@ -1060,7 +2536,7 @@ describe("Close Position", () => {
 
       try {
         await program.methods
-          .closePosition(constraintMinBinId, constraintMaxBinId)
+          .closePosition(constraintMinBinId, constraintMaxBinId, true)
           .accountsStrict(accounts)
           .signers([constraintUser])
           .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
@@ -1092,7 +2568,7 @@ describe("Close Position", () => {
 
       try {
         await program.methods
-          .closePosition(constraintMinBinId, constraintMaxBinId)
+          .closePosition(constraintMinBinId, constraintMaxBinId, true)
           .accountsStrict({ ...constraintAccounts, user: rogue.publicKey })
           .signers([rogue])
           .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
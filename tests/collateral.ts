@@ -1,13 +1,25 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { MetlevEngine } from "../target/types/metlev_engine";
-import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  LAMPORTS_PER_SOL,
+  Transaction,
+  sendAndConfirmTransaction,
+} from "@solana/web3.js";
 import {
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
   createMint,
   createAccount,
   mintTo,
   getAccount,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
 } from "@solana/spl-token";
 import { assert, expect } from "chai";
 
@@ -38,6 +50,7 @@ describe("Collateral", () => {
     minDeposit: new anchor.BN(0.1 * LAMPORTS_PER_SOL), // 0.1 SOL
     interestRateBps: 500,       // 5% APR
     oracleMaxAge: new anchor.BN(60),
+    maxLeverageBps: new anchor.BN(50_000), // 5×
   };
 
   const USDC_CONFIG = {
@@ -47,6 +60,7 @@ describe("Collateral", () => {
     minDeposit: new anchor.BN(10_000_000), // 10 USDC (6 decimals)
     interestRateBps: 300,       // 3% APR
     oracleMaxAge: new anchor.BN(60),
+    maxLeverageBps: new anchor.BN(90_000), // 9×
   };
 
   before(async () => {
@@ -106,12 +120,14 @@ describe("Collateral", () => {
       await program.methods
         .registerCollateral(
           SOL_ORACLE,
+          { mock: {} },
           SOL_CONFIG.maxLtv,
           SOL_CONFIG.liquidationThreshold,
           SOL_CONFIG.liquidationPenalty,
           SOL_CONFIG.minDeposit,
           SOL_CONFIG.interestRateBps,
-          SOL_CONFIG.oracleMaxAge
+          SOL_CONFIG.oracleMaxAge,
+          SOL_CONFIG.maxLeverageBps
         )
         .accountsStrict({
           authority,
@@ -131,12 +147,14 @@ describe("Collateral", () => {
       await program.methods
         .registerCollateral(
           USDC_ORACLE,
+          { mock: {} },
           USDC_CONFIG.maxLtv,
           USDC_CONFIG.liquidationThreshold,
           USDC_CONFIG.liquidationPenalty,
           USDC_CONFIG.minDeposit,
           USDC_CONFIG.interestRateBps,
-          USDC_CONFIG.oracleMaxAge
+          USDC_CONFIG.oracleMaxAge,
+          USDC_CONFIG.maxLeverageBps
         )
         .accountsStrict({
           authority,
@@ -289,6 +307,55 @@ describe("Collateral", () => {
         console.log("Correctly rejected SOL deposit below minimum");
       }
     });
+
+    it("Rejects a zero SOL deposit without creating a position", async () => {
+      const anotherUser = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(anotherUser.publicKey, LAMPORTS_PER_SOL)
+      );
+      // Even with no minimum configured, zero is not a deposit
+      const { minDeposit } = await program.account.collateralConfig.fetch(solCollateralConfigPda);
+      await program.methods
+        .updateCollateralMinDeposit(SOL_MINT, new anchor.BN(0))
+        .accountsStrict({ authority, config: configPda, collateralConfig: solCollateralConfigPda })
+        .rpc();
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), anotherUser.publicKey.toBuffer(), SOL_MINT.toBuffer()],
+        program.programId
+      );
+      const [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), anotherUser.publicKey.toBuffer(), SOL_MINT.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .depositSolCollateral(new anchor.BN(0))
+          .accountsStrict({
+            user: anotherUser.publicKey,
+            config: configPda,
+            mint: SOL_MINT,
+            collateralConfig: solCollateralConfigPda,
+            vault: vaultPda,
+            position: positionPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([anotherUser])
+          .rpc();
+
+        assert.fail("Should have failed with InvalidAmount");
+      } catch (error) {
+        expect(error.message).to.include("InvalidAmount");
+      } finally {
+        await program.methods
+          .updateCollateralMinDeposit(SOL_MINT, minDeposit)
+          .accountsStrict({ authority, config: configPda, collateralConfig: solCollateralConfigPda })
+          .rpc();
+      }
+      expect(await provider.connection.getAccountInfo(positionPda)).to.be.null;
+    });
   });
 
   describe("SPL Token Deposits", () => {
@@ -468,6 +535,349 @@ describe("Collateral", () => {
         console.log("Correctly rejected USDC deposit below minimum");
       }
     });
+
+    it("Rejects a zero token deposit", async () => {
+      const anotherUser = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(anotherUser.publicKey, LAMPORTS_PER_SOL)
+      );
+      const anotherUserUsdcAccount = await createAccount(
+        provider.connection,
+        provider.wallet.payer,
+        USDC_MINT,
+        anotherUser.publicKey
+      );
+
+      const [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), anotherUser.publicKey.toBuffer(), USDC_MINT.toBuffer()],
+        program.programId
+      );
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), anotherUser.publicKey.toBuffer(), USDC_MINT.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .depositTokenCollateral(new anchor.BN(0))
+          .accountsStrict({
+            user: anotherUser.publicKey,
+            config: configPda,
+            mint: USDC_MINT,
+            collateralConfig: usdcCollateralConfigPda,
+            vault: vaultPda,
+            userTokenAccount: anotherUserUsdcAccount,
+            position: positionPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([anotherUser])
+          .rpc();
+
+        assert.fail("Should have failed with InvalidAmount");
+      } catch (error) {
+        expect(error.message).to.include("InvalidAmount");
+      }
+      expect(await provider.connection.getAccountInfo(positionPda)).to.be.null;
+    });
+
+    it("Credits only what a transfer-fee mint delivers to the vault", async () => {
+      const feeUser = Keypair.generate();
+      const airdrop = await provider.connection.requestAirdrop(
+        feeUser.publicKey,
+        2 * LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdrop);
+
+      // Token-2022 mint withholding 1% of every transfer
+      const feeBps = 100;
+      const mintKp = Keypair.generate();
+      const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+      await sendAndConfirmTransaction(
+        provider.connection,
+        new Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: authority,
+            newAccountPubkey: mintKp.publicKey,
+            space: mintLen,
+            lamports: rent,
+            programId: TOKEN_2022_PROGRAM_ID,
+          }),
+          createInitializeTransferFeeConfigInstruction(
+            mintKp.publicKey,
+            authority,
+            authority,
+            feeBps,
+            BigInt(1_000_000_000),
+            TOKEN_2022_PROGRAM_ID
+          ),
+          createInitializeMintInstruction(
+            mintKp.publicKey,
+            6,
+            authority,
+            null,
+            TOKEN_2022_PROGRAM_ID
+          )
+        ),
+        [provider.wallet.payer, mintKp]
+      );
+      const feeMint = mintKp.publicKey;
+
+      const [feeCollateralConfigPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral_config"), feeMint.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .registerCollateral(
+          USDC_ORACLE,
+          { mock: {} },
+          USDC_CONFIG.maxLtv,
+          USDC_CONFIG.liquidationThreshold,
+          USDC_CONFIG.liquidationPenalty,
+          USDC_CONFIG.minDeposit,
+          USDC_CONFIG.interestRateBps,
+          USDC_CONFIG.oracleMaxAge,
+          USDC_CONFIG.maxLeverageBps
+        )
+        .accountsStrict({
+          authority,
+          config: configPda,
+          mint: feeMint,
+          collateralConfig: feeCollateralConfigPda,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const userFeeAccount = await createAccount(
+        provider.connection,
+        provider.wallet.payer,
+        feeMint,
+        feeUser.publicKey,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      await mintTo(
+        provider.connection,
+        provider.wallet.payer,
+        feeMint,
+        userFeeAccount,
+        authority,
+        100_000_000,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      const [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), feeUser.publicKey.toBuffer(), feeMint.toBuffer()],
+        program.programId
+      );
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), feeUser.publicKey.toBuffer(), feeMint.toBuffer()],
+        program.programId
+      );
+
+      const depositAmount = new anchor.BN(50_000_000);
+      await program.methods
+        .depositTokenCollateral(depositAmount)
+        .accountsStrict({
+          user: feeUser.publicKey,
+          config: configPda,
+          mint: feeMint,
+          collateralConfig: feeCollateralConfigPda,
+          vault: vaultPda,
+          userTokenAccount: userFeeAccount,
+          position: positionPda,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([feeUser])
+        .rpc();
+
+      const expectedReceived = depositAmount.toNumber() * (10_000 - feeBps) / 10_000;
+      const vaultAccount = await getAccount(
+        provider.connection,
+        vaultPda,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(Number(vaultAccount.amount)).to.equal(expectedReceived);
+
+      const position = await program.account.position.fetch(positionPda);
+      expect(position.collateralAmount.toNumber()).to.equal(
+        expectedReceived,
+        "collateral_amount must be what the vault received, net of the transfer fee"
+      );
+      console.log("Fee-on-transfer deposit credited:", expectedReceived / 1_000_000, "tokens");
+    });
+
+    it("Rejects an SPL Token deposit routed through Token-2022", async () => {
+      const mismatchUser = Keypair.generate();
+      const airdrop = await provider.connection.requestAirdrop(
+        mismatchUser.publicKey,
+        LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdrop);
+
+      const userAccount = await createAccount(
+        provider.connection,
+        provider.wallet.payer,
+        USDC_MINT,
+        mismatchUser.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        provider.wallet.payer,
+        USDC_MINT,
+        userAccount,
+        authority,
+        50_000_000
+      );
+
+      const [mismatchVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), mismatchUser.publicKey.toBuffer(), USDC_MINT.toBuffer()],
+        program.programId
+      );
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), mismatchUser.publicKey.toBuffer(), USDC_MINT.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .depositTokenCollateral(new anchor.BN(50_000_000))
+          .accountsStrict({
+            user: mismatchUser.publicKey,
+            config: configPda,
+            mint: USDC_MINT,
+            collateralConfig: usdcCollateralConfigPda,
+            vault: mismatchVaultPda,
+            userTokenAccount: userAccount,
+            position: positionPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([mismatchUser])
+          .rpc();
+        assert.fail("Should have failed with a token program mismatch");
+      } catch (error) {
+        expect(error.message).to.include("ConstraintMintTokenProgram");
+      }
+      expect(await provider.connection.getAccountInfo(mismatchVaultPda)).to.be.null;
+    });
+
+    it("Token-2022 collateral only deposits through Token-2022, into a Token-2022 vault", async () => {
+      const t22User = Keypair.generate();
+      const airdrop = await provider.connection.requestAirdrop(
+        t22User.publicKey,
+        2 * LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdrop);
+
+      const t22Mint = await createMint(
+        provider.connection,
+        provider.wallet.payer,
+        authority,
+        null,
+        6,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      const [t22CollateralConfigPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral_config"), t22Mint.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .registerCollateral(
+          USDC_ORACLE,
+          { mock: {} },
+          USDC_CONFIG.maxLtv,
+          USDC_CONFIG.liquidationThreshold,
+          USDC_CONFIG.liquidationPenalty,
+          USDC_CONFIG.minDeposit,
+          USDC_CONFIG.interestRateBps,
+          USDC_CONFIG.oracleMaxAge,
+          USDC_CONFIG.maxLeverageBps
+        )
+        .accountsStrict({
+          authority,
+          config: configPda,
+          mint: t22Mint,
+          collateralConfig: t22CollateralConfigPda,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const userAccount = await createAccount(
+        provider.connection,
+        provider.wallet.payer,
+        t22Mint,
+        t22User.publicKey,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      await mintTo(
+        provider.connection,
+        provider.wallet.payer,
+        t22Mint,
+        userAccount,
+        authority,
+        100_000_000,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      const [t22VaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), t22User.publicKey.toBuffer(), t22Mint.toBuffer()],
+        program.programId
+      );
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), t22User.publicKey.toBuffer(), t22Mint.toBuffer()],
+        program.programId
+      );
+      const deposit = (tokenProgram: PublicKey) =>
+        program.methods
+          .depositTokenCollateral(new anchor.BN(50_000_000))
+          .accountsStrict({
+            user: t22User.publicKey,
+            config: configPda,
+            mint: t22Mint,
+            collateralConfig: t22CollateralConfigPda,
+            vault: t22VaultPda,
+            userTokenAccount: userAccount,
+            position: positionPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram,
+          })
+          .signers([t22User])
+          .rpc();
+
+      try {
+        await deposit(TOKEN_PROGRAM_ID);
+        assert.fail("Should have failed with a token program mismatch");
+      } catch (error) {
+        expect(error.message).to.include("ConstraintMintTokenProgram");
+      }
+      expect(await provider.connection.getAccountInfo(t22VaultPda)).to.be.null;
+
+      await deposit(TOKEN_2022_PROGRAM_ID);
+      const vaultInfo = await provider.connection.getAccountInfo(t22VaultPda);
+      expect(vaultInfo.owner.toBase58()).to.equal(TOKEN_2022_PROGRAM_ID.toBase58());
+      const vaultAccount = await getAccount(
+        provider.connection,
+        t22VaultPda,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(Number(vaultAccount.amount)).to.equal(50_000_000);
+    });
   });
 
   describe("Protocol Pause", () => {
@@ -529,6 +939,68 @@ describe("Collateral", () => {
         })
         .rpc();
     });
+
+    it("Prevents deposits of a paused collateral while the protocol runs", async () => {
+      await program.methods
+        .updateCollateralPaused(SOL_MINT, true)
+        .accountsStrict({
+          authority,
+          config: configPda,
+          collateralConfig: solCollateralConfigPda,
+        })
+        .rpc();
+
+      const pausedUser = Keypair.generate();
+      await provider.connection.requestAirdrop(
+        pausedUser.publicKey,
+        2 * LAMPORTS_PER_SOL
+      );
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), pausedUser.publicKey.toBuffer(), SOL_MINT.toBuffer()],
+        program.programId
+      );
+
+      const [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), pausedUser.publicKey.toBuffer(), SOL_MINT.toBuffer()],
+        program.programId
+      );
+
+      try {
+        const protocolConfig = await program.account.config.fetch(configPda);
+        expect(protocolConfig.paused).to.equal(false);
+
+        await program.methods
+          .depositSolCollateral(new anchor.BN(LAMPORTS_PER_SOL))
+          .accountsStrict({
+            user: pausedUser.publicKey,
+            config: configPda,
+            mint: SOL_MINT,
+            collateralConfig: solCollateralConfigPda,
+            vault: vaultPda,
+            position: positionPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([pausedUser])
+          .rpc();
+
+        assert.fail("Should have failed with CollateralPaused");
+      } catch (error) {
+        expect(error.message).to.include("CollateralPaused");
+        console.log("Correctly prevented deposit of a paused collateral");
+      } finally {
+        await program.methods
+          .updateCollateralPaused(SOL_MINT, false)
+          .accountsStrict({
+            authority,
+            config: configPda,
+            collateralConfig: solCollateralConfigPda,
+          })
+          .rpc();
+      }
+    });
   });
 
   // ─── Withdraw Collateral ───────────────────────────────────────────────────
@@ -629,4 +1101,81 @@ describe("Collateral", () => {
     });
 
   });
+
+  describe("Deregister Collateral", () => {
+    it("Refuses to deregister a collateral that still backs an active position", async () => {
+      const config = await program.account.collateralConfig.fetch(usdcCollateralConfigPda);
+      expect(config.activePositions.toNumber()).to.be.greaterThan(0);
+
+      try {
+        await program.methods
+          .deregisterCollateral(USDC_MINT)
+          .accountsStrict({
+            authority,
+            config: configPda,
+            collateralConfig: usdcCollateralConfigPda,
+          })
+          .rpc();
+
+        assert.fail("Should have failed with CollateralInUse");
+      } catch (error) {
+        expect(error.message).to.include("CollateralInUse");
+      }
+    });
+
+    it("Closes an unused collateral config and returns its rent to the authority", async () => {
+      const sunsetMint = await createMint(
+        provider.connection,
+        provider.wallet.payer,
+        authority,
+        null,
+        6
+      );
+      const [sunsetConfigPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral_config"), sunsetMint.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .registerCollateral(
+          USDC_ORACLE,
+          { mock: {} },
+          USDC_CONFIG.maxLtv,
+          USDC_CONFIG.liquidationThreshold,
+          USDC_CONFIG.liquidationPenalty,
+          USDC_CONFIG.minDeposit,
+          USDC_CONFIG.interestRateBps,
+          USDC_CONFIG.oracleMaxAge,
+          USDC_CONFIG.maxLeverageBps
+        )
+        .accountsStrict({
+          authority,
+          config: configPda,
+          mint: sunsetMint,
+          collateralConfig: sunsetConfigPda,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const rent = await provider.connection.getBalance(sunsetConfigPda);
+      const authorityBefore = await provider.connection.getBalance(authority);
+
+      await program.methods
+        .deregisterCollateral(sunsetMint)
+        .accountsStrict({
+          authority,
+          config: configPda,
+          collateralConfig: sunsetConfigPda,
+        })
+        .rpc();
+
+      const info = await provider.connection.getAccountInfo(sunsetConfigPda);
+      expect(info).to.be.null;
+
+      // Rent comes back, less the transaction fee
+      const authorityAfter = await provider.connection.getBalance(authority);
+      expect(authorityAfter - authorityBefore).to.be.greaterThan(rent - 10_000);
+    });
+  });
 });
@@ -10,6 +10,7 @@ import {
 import {
   getOrCreateAssociatedTokenAccount,
   createSyncNativeInstruction,
+  createTransferInstruction,
   TOKEN_PROGRAM_ID,
   NATIVE_MINT,
 } from "@solana/spl-token";
@@ -52,6 +53,32 @@ describe("Lending Vault", () => {
     return ata.address;
   }
 
+  // Mirrors LendingVault::shares_for_deposit / assets_for_shares
+  function sharesForDeposit(vault: any, amount: anchor.BN): anchor.BN {
+    if (vault.totalShares.isZero()) return amount;
+    return amount.mul(vault.totalShares).div(vault.totalSupplied);
+  }
+
+  function assetsForShares(vault: any, shares: anchor.BN): anchor.BN {
+    return shares.mul(vault.totalSupplied).div(vault.totalShares);
+  }
+
+  // The vault as an instruction saw it once its accrual booked interest on
+  // outstanding borrows (rounded up), split by the reserve factor
+  function afterAccrual(before: any, after: any): any {
+    const growth = after.borrowIndex.sub(before.borrowIndex);
+    const interest = growth.isZero()
+      ? new anchor.BN(0)
+      : before.totalBorrowed.mul(growth).add(before.borrowIndex).subn(1).div(before.borrowIndex);
+    const reserve = interest.muln(before.reserveFactorBps).divn(10_000);
+    return {
+      ...before,
+      totalSupplied: before.totalSupplied.add(interest).sub(reserve),
+      totalBorrowed: before.totalBorrowed.add(interest),
+      protocolFees: before.protocolFees.add(reserve),
+    };
+  }
+
   before(async () => {
     [configPda] = PublicKey.findProgramAddressSync(
       [Buffer.from("config")],
@@ -123,6 +150,7 @@ describe("Lending Vault", () => {
       const vault = await program.account.lendingVault.fetch(lendingVaultPda);
 
       expect(vault.authority.toBase58()).to.equal(authority.toBase58());
+      expect(vault.borrowMint.toBase58()).to.equal(NATIVE_MINT.toBase58());
 
       expect(vault.totalSupplied.toNumber()).to.be.greaterThanOrEqual(0);
       console.log(
@@ -162,6 +190,7 @@ describe("Lending Vault", () => {
         .supply(supplyAmount)
         .accountsStrict({
           signer: lp.publicKey,
+          config: configPda,
           lendingVault: lendingVaultPda,
           wsolMint: NATIVE_MINT,
           wsolVault: wsolVaultPda,
@@ -173,18 +202,21 @@ describe("Lending Vault", () => {
         .signers([lp])
         .rpc();
 
+      const vaultStateAfter = await program.account.lendingVault.fetch(
+        lendingVaultPda,
+      );
+      const accrued = afterAccrual(vaultStateBefore, vaultStateAfter);
       const lpPosition = await program.account.lpPosition.fetch(lpPositionPda);
       expect(lpPosition.lp.toBase58()).to.equal(lp.publicKey.toBase58());
-      expect(lpPosition.suppliedAmount.toNumber()).to.equal(
-        supplyAmount.toNumber(),
+      expect(lpPosition.shares.toString()).to.equal(
+        sharesForDeposit(accrued, supplyAmount).toString(),
       );
-      expect(lpPosition.interestEarned.toNumber()).to.equal(0);
 
-      const vaultStateAfter = await program.account.lendingVault.fetch(
-        lendingVaultPda,
+      expect(vaultStateAfter.totalSupplied.toString()).to.equal(
+        accrued.totalSupplied.add(supplyAmount).toString(),
       );
-      expect(vaultStateAfter.totalSupplied.toNumber()).to.equal(
-        vaultStateBefore.totalSupplied.toNumber() + supplyAmount.toNumber(),
+      expect(vaultStateAfter.totalShares.toString()).to.equal(
+        vaultStateBefore.totalShares.add(lpPosition.shares).toString(),
       );
 
       const wsolVaultAfter = await provider.connection.getTokenAccountBalance(
@@ -219,11 +251,15 @@ describe("Lending Vault", () => {
       const positionBefore = await program.account.lpPosition.fetch(
         lpPositionPda,
       );
+      const vaultStateBefore = await program.account.lendingVault.fetch(
+        lendingVaultPda,
+      );
 
-      await program.methods
+      const tx = await program.methods
         .supply(topUpAmount)
         .accountsStrict({
           signer: lp.publicKey,
+          config: configPda,
           lendingVault: lendingVaultPda,
           wsolMint: NATIVE_MINT,
           wsolVault: wsolVaultPda,
@@ -233,21 +269,38 @@ describe("Lending Vault", () => {
           systemProgram: SystemProgram.programId,
         })
         .signers([lp])
-        .rpc();
+        .rpc({ commitment: "confirmed" });
 
       const positionAfter = await program.account.lpPosition.fetch(
         lpPositionPda,
       );
-      expect(positionAfter.suppliedAmount.toNumber()).to.equal(
-        positionBefore.suppliedAmount.toNumber() + topUpAmount.toNumber(),
+      const accrued = afterAccrual(
+        vaultStateBefore,
+        await program.account.lendingVault.fetch(lendingVaultPda),
+      );
+      expect(positionAfter.shares.toString()).to.equal(
+        positionBefore.shares
+          .add(sharesForDeposit(accrued, topUpAmount))
+          .toString(),
       );
 
-      console.log("LP top-up successful");
-      console.log(
-        "Total supplied by LP:",
-        positionAfter.suppliedAmount.toNumber() / LAMPORTS_PER_SOL,
-        "WSOL",
+      // The accrual ahead of the deposit is reported for indexers
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const interestAccrued = [...events].find((e) => e.name === "interestAccrued")!;
+      expect(interestAccrued, "supply must emit InterestAccrued").to.not.be.undefined;
+      expect(interestAccrued.data.lp.toBase58()).to.equal(lp.publicKey.toBase58());
+      expect(interestAccrued.data.totalSupplied.toString()).to.equal(
+        accrued.totalSupplied.toString(),
+        "Totals are reported as of the accrual, before the deposit",
       );
+
+      console.log("LP top-up successful");
+      console.log("Shares held by LP:", positionAfter.shares.toString());
     });
 
     it("Multiple LPs can supply independently", async () => {
@@ -258,10 +311,15 @@ describe("Lending Vault", () => {
         program.programId,
       );
 
+      const vaultStateBefore = await program.account.lendingVault.fetch(
+        lendingVaultPda,
+      );
+
       await program.methods
         .supply(supplyAmount)
         .accountsStrict({
           signer: lp2.publicKey,
+          config: configPda,
           lendingVault: lendingVaultPda,
           wsolMint: NATIVE_MINT,
           wsolVault: wsolVaultPda,
@@ -277,13 +335,12 @@ describe("Lending Vault", () => {
         lp2PositionPda,
       );
       expect(lp2Position.lp.toBase58()).to.equal(lp2.publicKey.toBase58());
-      expect(lp2Position.suppliedAmount.toNumber()).to.equal(
-        supplyAmount.toNumber(),
-      );
-
       const vaultState = await program.account.lendingVault.fetch(
         lendingVaultPda,
       );
+      expect(lp2Position.shares.toString()).to.equal(
+        sharesForDeposit(afterAccrual(vaultStateBefore, vaultState), supplyAmount).toString(),
+      );
       console.log(
         "Total vault supplied:",
         vaultState.totalSupplied.toNumber() / LAMPORTS_PER_SOL,
@@ -369,9 +426,10 @@ describe("Lending Vault", () => {
 
       try {
         await program.methods
-          .withdraw()
+          .withdraw(new anchor.BN(LAMPORTS_PER_SOL))
           .accountsStrict({
             signer: noPosition.publicKey,
+            config: configPda,
             lpPosition: lpPositionPda,
             lendingVault: lendingVaultPda,
             wsolMint: NATIVE_MINT,
@@ -391,110 +449,1010 @@ describe("Lending Vault", () => {
         console.log("Correctly rejected withdraw with no position");
       }
     });
-  });
 
-  describe("Withdraw", () => {
-    it("LP withdraws and receives WSOL back", async () => {
+    it("Rejects a zero-amount supply", async () => {
       const [lpPositionPda] = PublicKey.findProgramAddressSync(
         [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
         program.programId,
       );
+      try {
+        await program.methods
+          .supply(new anchor.BN(0))
+          .accountsStrict({
+            signer: lp.publicKey,
+            config: configPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            signerWsolAta: lpWsolAta,
+            lpPosition: lpPositionPda,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lp])
+          .rpc();
 
-      const positionBefore = await program.account.lpPosition.fetch(
-        lpPositionPda,
-      );
-      const lpWsolBefore = await provider.connection.getTokenAccountBalance(
-        lpWsolAta,
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("InvalidAmount");
+      }
+    });
+  });
+
+  describe("Caps", () => {
+    const U64_MAX = new anchor.BN("18446744073709551615");
+
+    it("Authority can set the borrow cap, a stranger cannot", async () => {
+      const cap = new anchor.BN(5 * LAMPORTS_PER_SOL);
+
+      await program.methods
+        .updateBorrowCap(cap)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+
+      let vaultState = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultState.borrowCap.toString()).to.equal(cap.toString());
+
+      const rogue = Keypair.generate();
+      try {
+        await program.methods
+          .updateBorrowCap(U64_MAX)
+          .accountsStrict({
+            authority: rogue.publicKey,
+            config: configPda,
+            lendingVault: lendingVaultPda,
+          })
+          .signers([rogue])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.match(/Unauthorized/);
+      }
+
+      // Restore: uncapped
+      await program.methods
+        .updateBorrowCap(U64_MAX)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+      vaultState = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultState.borrowCap.toString()).to.equal(U64_MAX.toString());
+    });
+
+    it("Supply is rejected once the vault reaches its supply cap", async () => {
+      const [lpPositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
+        program.programId,
       );
-      const wsolVaultBefore = await provider.connection.getTokenAccountBalance(
-        wsolVaultPda,
+      const vaultState = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      await program.methods
+        .updateSupplyCap(vaultState.totalSupplied)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+
+      try {
+        await program.methods
+          .supply(new anchor.BN(0.1 * LAMPORTS_PER_SOL))
+          .accountsStrict({
+            signer: lp.publicKey,
+            config: configPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            signerWsolAta: lpWsolAta,
+            lpPosition: lpPositionPda,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lp])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("SupplyCapExceeded");
+        console.log("Correctly rejected supply over the cap");
+      } finally {
+        await program.methods
+          .updateSupplyCap(U64_MAX)
+          .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+          .rpc();
+      }
+    });
+  });
+
+  describe("Reserves", () => {
+    it("Authority can set the reserve factor, capped at 100%", async () => {
+      await program.methods
+        .updateReserveFactor(2000)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+
+      const vaultState = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultState.reserveFactorBps).to.equal(2000);
+
+      try {
+        await program.methods
+          .updateReserveFactor(10_001)
+          .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("InvalidAmount");
+      }
+
+      // Restore the default 10%
+      await program.methods
+        .updateReserveFactor(1000)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+    });
+
+    it("Authority retunes a subset of the rate curve with updateVaultParams", async () => {
+      const before = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      await program.methods
+        .updateVaultParams(null, 600, null, 9000, null, null)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+
+      const updated = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(updated.slope1Bps).to.equal(600);
+      expect(updated.optimalUtilizationBps).to.equal(9000);
+      expect(updated.baseRateBps).to.equal(before.baseRateBps);
+      expect(updated.slope2Bps).to.equal(before.slope2Bps);
+      expect(updated.maxBorrowRateBps).to.equal(before.maxBorrowRateBps);
+      expect(updated.reserveFactorBps).to.equal(before.reserveFactorBps);
+
+      // A kink at 100% utilization would divide by zero above it
+      try {
+        await program.methods
+          .updateVaultParams(null, null, null, 10_000, null, null)
+          .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("InvalidAmount");
+      }
+
+      await program.methods
+        .updateVaultParams(null, before.slope1Bps, null, before.optimalUtilizationBps, null, null)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+    });
+
+    it("Authority collects accrued protocol fees from the vault", async () => {
+      const destination = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        provider.wallet.payer,
+        NATIVE_MINT,
+        authority,
       );
-      const vaultStateBefore = await program.account.lendingVault.fetch(
-        lendingVaultPda,
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const balanceBefore = await provider.connection.getTokenAccountBalance(destination.address);
+
+      const collect = () =>
+        program.methods
+          .collectReserves()
+          .accountsStrict({
+            authority,
+            config: configPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            destination: destination.address,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+
+      if (vaultBefore.protocolFees.isZero()) {
+        try {
+          await collect();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect(e.message).to.include("InvalidAmount");
+        }
+        console.log("No protocol fees accrued yet; empty collect rejected");
+        return;
+      }
+
+      await collect();
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const balanceAfter = await provider.connection.getTokenAccountBalance(destination.address);
+      const collected = new anchor.BN(balanceAfter.value.amount).sub(new anchor.BN(balanceBefore.value.amount));
+
+      expect(collected.toString()).to.equal(
+        vaultBefore.protocolFees.sub(vaultAfter.protocolFees).toString(),
       );
+      expect(vaultAfter.totalSupplied.toString()).to.equal(vaultBefore.totalSupplied.toString());
+      console.log("Collected", collected.toString(), "lamports of protocol fees");
+    });
+
+    it("Authority tops up the insurance fund", async () => {
+      const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+      const source = await wrapSol(provider.wallet.payer, amount.toNumber());
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolBefore = await provider.connection.getTokenAccountBalance(wsolVaultPda);
 
       await program.methods
-        .withdraw()
+        .fundInsurance(amount, false)
         .accountsStrict({
-          signer: lp.publicKey,
-          lpPosition: lpPositionPda,
+          authority,
+          config: configPda,
           lendingVault: lendingVaultPda,
           wsolMint: NATIVE_MINT,
           wsolVault: wsolVaultPda,
-          signerWsolAta: lpWsolAta,
+          source,
           tokenProgram: TOKEN_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
         })
-        .signers([lp])
         .rpc();
 
-      try {
-        await program.account.lpPosition.fetch(lpPositionPda);
-        throw new Error("LP position should have been closed");
-      } catch (e) {
-        expect(e.message).to.match(/Account does not exist|not found/i);
-      }
-
-      const lpWsolAfter = await provider.connection.getTokenAccountBalance(
-        lpWsolAta,
-      );
-      expect(Number(lpWsolAfter.value.amount)).to.be.gte(
-        Number(lpWsolBefore.value.amount) +
-          positionBefore.suppliedAmount.toNumber(),
-      );
-
-      const wsolVaultAfter = await provider.connection.getTokenAccountBalance(
-        wsolVaultPda,
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolAfter = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+      expect(vaultAfter.insuranceFund.toString()).to.equal(
+        vaultBefore.insuranceFund.add(amount).toString(),
       );
       expect(
-        Number(wsolVaultBefore.value.amount) -
-          Number(wsolVaultAfter.value.amount),
-      ).to.equal(positionBefore.suppliedAmount.toNumber());
+        new anchor.BN(wsolAfter.value.amount).sub(new anchor.BN(wsolBefore.value.amount)).toString(),
+      ).to.equal(amount.toString());
+      // The fund is not LP liquidity
+      expect(vaultAfter.totalSupplied.toString()).to.equal(vaultBefore.totalSupplied.toString());
 
-      const vaultStateAfter = await program.account.lendingVault.fetch(
-        lendingVaultPda,
-      );
-      expect(vaultStateAfter.totalSupplied.toNumber()).to.equal(
-        vaultStateBefore.totalSupplied.toNumber() -
-          positionBefore.suppliedAmount.toNumber(),
-      );
+      // Earmarking more reserves than have accrued is rejected
+      try {
+        await program.methods
+          .fundInsurance(vaultAfter.protocolFees.addn(1), true)
+          .accountsStrict({
+            authority,
+            config: configPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            source: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("InvalidAmount");
+      }
+    });
 
-      console.log(
-        "LP withdrew:",
-        positionBefore.suppliedAmount.toNumber() / LAMPORTS_PER_SOL,
-        "WSOL",
-      );
-      console.log("LP WSOL balance after:", lpWsolAfter.value.uiAmount, "WSOL");
-      console.log("WSOL vault after:", wsolVaultAfter.value.uiAmount, "WSOL");
+    it("Non-authority cannot fund the insurance fund", async () => {
+      try {
+        await program.methods
+          .fundInsurance(new anchor.BN(1), false)
+          .accountsStrict({
+            authority: lp.publicKey,
+            config: configPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            source: lpWsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([lp])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("Unauthorized");
+      }
     });
 
-    it("Cannot withdraw someone else's position", async () => {
-      const [lp2PositionPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lp_position"), lp2.publicKey.toBuffer()],
-        program.programId,
+    it("Anyone can wrap stray lamports in wsol_vault into protocol fees", async () => {
+      const amount = 5_000_000;
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({ fromPubkey: authority, toPubkey: wsolVaultPda, lamports: amount })
+        )
       );
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolBefore = await provider.connection.getTokenAccountBalance(wsolVaultPda);
 
-      try {
-        await program.methods
-          .withdraw()
+      const sync = () =>
+        program.methods
+          .syncWsolVault()
           .accountsStrict({
-            signer: lp.publicKey,
-            lpPosition: lp2PositionPda,
+            caller: lp.publicKey,
             lendingVault: lendingVaultPda,
             wsolMint: NATIVE_MINT,
             wsolVault: wsolVaultPda,
-            signerWsolAta: lpWsolAta,
             tokenProgram: TOKEN_PROGRAM_ID,
-            systemProgram: SystemProgram.programId,
           })
           .signers([lp])
           .rpc();
+      await sync();
+
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolAfter = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+      expect(
+        new anchor.BN(wsolAfter.value.amount).sub(new anchor.BN(wsolBefore.value.amount)).toNumber(),
+      ).to.equal(amount);
+      expect(vaultAfter.protocolFees.sub(vaultBefore.protocolFees).toNumber()).to.equal(amount);
+      // Stray lamports are protocol revenue, not LP assets
+      expect(vaultAfter.totalSupplied.toString()).to.equal(vaultBefore.totalSupplied.toString());
 
+      // Nothing left to wrap
+      try {
+        await sync();
         throw new Error("Should have failed");
       } catch (e) {
-        expect(e.message).to.match(/InvalidOwner|constraint|seeds/i);
-        console.log("Correctly rejected unauthorized withdrawal");
+        expect(e.message).to.include("InvalidAmount");
       }
     });
+
+    it("Anyone can poke the vault's interest accrual, repeatedly", async () => {
+      const [lpPositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
+        program.programId,
+      );
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      const poke = (caller: Keypair, lpPosition: PublicKey | null) =>
+        program.methods
+          .pokeAccrual()
+          .accountsStrict({ caller: caller.publicKey, lendingVault: lendingVaultPda, lpPosition })
+          .signers([caller])
+          .rpc();
+      await poke(lp2, null);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultAfter.lastUpdate.gte(vaultBefore.lastUpdate)).to.equal(true);
+      expect(vaultAfter.borrowIndex.gte(vaultBefore.borrowIndex)).to.equal(true);
+
+      // The interest outstanding borrows earned is booked into the totals
+      const accrued = afterAccrual(vaultBefore, vaultAfter);
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(accrued.totalBorrowed.toString());
+      expect(vaultAfter.totalSupplied.toString()).to.equal(accrued.totalSupplied.toString());
+      expect(vaultAfter.protocolFees.toString()).to.equal(accrued.protocolFees.toString());
+
+      // Aimed at an LP, and again: each poke books only what accrued since
+      // the last one, and shares never move
+      await poke(lp2, lpPositionPda);
+      const vaultAimed = await program.account.lendingVault.fetch(lendingVaultPda);
+      await poke(lp, lpPositionPda);
+      const vaultFinal = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultAimed.totalSupplied.toString()).to.equal(
+        afterAccrual(vaultAfter, vaultAimed).totalSupplied.toString(),
+      );
+      expect(vaultFinal.totalSupplied.toString()).to.equal(
+        afterAccrual(vaultAimed, vaultFinal).totalSupplied.toString(),
+      );
+      expect(vaultFinal.totalShares.toString()).to.equal(vaultBefore.totalShares.toString());
+    });
+  });
+
+  describe("Flash loans", () => {
+    const flashAccounts = () => ({
+      borrower: lp.publicKey,
+      config: configPda,
+      lendingVault: lendingVaultPda,
+      wsolMint: NATIVE_MINT,
+      wsolVault: wsolVaultPda,
+      destination: lpWsolAta,
+      instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    });
+
+    it("Borrows and repays in one transaction, fee goes to protocol fees", async () => {
+      const amount = new anchor.BN(LAMPORTS_PER_SOL);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const lpBefore = await provider.connection.getTokenAccountBalance(lpWsolAta);
+      const fee = amount
+        .muln(vaultBefore.flashLoanFeeBps)
+        .addn(9_999)
+        .divn(10_000);
+
+      const borrowIx = await program.methods
+        .flashLoan(amount)
+        .accountsStrict(flashAccounts())
+        .instruction();
+      const repayIx = await program.methods
+        .flashLoanRepay()
+        .accountsStrict({
+          payer: lp.publicKey,
+          lendingVault: lendingVaultPda,
+          wsolMint: NATIVE_MINT,
+          wsolVault: wsolVaultPda,
+          source: lpWsolAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction();
+
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(borrowIx, repayIx),
+        [lp],
+      );
+
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const lpAfter = await provider.connection.getTokenAccountBalance(lpWsolAta);
+      expect(vaultAfter.protocolFees.sub(vaultBefore.protocolFees).toString()).to.equal(fee.toString());
+      expect(vaultAfter.flashLoanAmount.toNumber()).to.equal(0);
+      expect(vaultAfter.totalSupplied.toString()).to.equal(vaultBefore.totalSupplied.toString());
+      expect(
+        new anchor.BN(lpBefore.value.amount).sub(new anchor.BN(lpAfter.value.amount)).toString(),
+      ).to.equal(fee.toString());
+    });
+
+    it("Rejects a flash loan with no repay later in the transaction", async () => {
+      try {
+        await program.methods
+          .flashLoan(new anchor.BN(LAMPORTS_PER_SOL))
+          .accountsStrict(flashAccounts())
+          .signers([lp])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("FlashLoanNotRepaid");
+      }
+    });
+
+    it("Rejects a flash loan while the protocol is paused", async () => {
+      const setPaused = (paused: boolean) =>
+        program.methods
+          .updatePauseState(paused)
+          .accountsStrict({ authority, config: configPda })
+          .rpc();
+
+      const borrowIx = await program.methods
+        .flashLoan(new anchor.BN(LAMPORTS_PER_SOL))
+        .accountsStrict(flashAccounts())
+        .instruction();
+      const repayIx = await program.methods
+        .flashLoanRepay()
+        .accountsStrict({
+          payer: lp.publicKey,
+          lendingVault: lendingVaultPda,
+          wsolMint: NATIVE_MINT,
+          wsolVault: wsolVaultPda,
+          source: lpWsolAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction();
+
+      await setPaused(true);
+      try {
+        await provider.sendAndConfirm(
+          new anchor.web3.Transaction().add(borrowIx, repayIx),
+          [lp],
+        );
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("ProtocolPaused");
+      } finally {
+        await setPaused(false);
+      }
+    });
+  });
+
+  describe("Emergency withdraw", () => {
+    const setPaused = (paused: boolean) =>
+      program.methods
+        .updatePauseState(paused)
+        .accountsStrict({ authority, config: configPda })
+        .rpc();
+
+    const emergencyAccounts = (destination: PublicKey) => ({
+      authority,
+      config: configPda,
+      lendingVault: lendingVaultPda,
+      wsolMint: NATIVE_MINT,
+      wsolVault: wsolVaultPda,
+      destination,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    });
+
+    it("Is rejected while the protocol is running", async () => {
+      try {
+        await program.methods
+          .emergencyWithdraw(new anchor.BN(1))
+          .accountsStrict(emergencyAccounts(lpWsolAta))
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("ProtocolNotPaused");
+      }
+    });
+
+    it("Authority moves wSOL out of a paused vault and the amount is recorded", async () => {
+      const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+      const safe = (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          provider.wallet.payer,
+          NATIVE_MINT,
+          authority,
+        )
+      ).address;
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolBefore = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+
+      await setPaused(true);
+      try {
+        await program.methods
+          .emergencyWithdraw(amount)
+          .accountsStrict(emergencyAccounts(safe))
+          .rpc();
+      } finally {
+        await setPaused(false);
+      }
+
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolAfter = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+      expect(vaultAfter.emergencyWithdrawn.sub(vaultBefore.emergencyWithdrawn).toString())
+        .to.equal(amount.toString());
+      expect(
+        new anchor.BN(wsolBefore.value.amount).sub(new anchor.BN(wsolAfter.value.amount)).toString(),
+      ).to.equal(amount.toString());
+      // LP accounting is untouched
+      expect(vaultAfter.totalSupplied.toString()).to.equal(vaultBefore.totalSupplied.toString());
+
+      // Put the funds back so later suites see a whole vault
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          createTransferInstruction(safe, wsolVaultPda, authority, amount.toNumber()),
+        ),
+      );
+    });
+
+    it("LP supply and withdraw both revert while the protocol is paused", async () => {
+      const [lpPositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
+        program.programId,
+      );
+      const lpAccounts = {
+        signer: lp.publicKey,
+        config: configPda,
+        lendingVault: lendingVaultPda,
+        wsolMint: NATIVE_MINT,
+        wsolVault: wsolVaultPda,
+        signerWsolAta: lpWsolAta,
+        lpPosition: lpPositionPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      };
+
+      await setPaused(true);
+      try {
+        try {
+          await program.methods
+            .supply(new anchor.BN(LAMPORTS_PER_SOL / 10))
+            .accountsStrict(lpAccounts)
+            .signers([lp])
+            .rpc();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect(e.message).to.include("ProtocolPaused");
+        }
+
+        try {
+          await program.methods
+            .withdraw(new anchor.BN(1))
+            .accountsStrict(lpAccounts)
+            .signers([lp])
+            .rpc();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect(e.message).to.include("ProtocolPaused");
+        }
+      } finally {
+        await setPaused(false);
+      }
+    });
+  });
+
+  describe("Vault stats", () => {
+    it("getVaultStats reports totals, utilization and APYs from the rate model", async () => {
+      const stats = await program.methods
+        .getVaultStats()
+        .accountsStrict({ lendingVault: lendingVaultPda })
+        .view();
+      const vault = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      expect(stats.totalSupplied.toString()).to.equal(vault.totalSupplied.toString());
+      expect(stats.totalBorrowed.toString()).to.equal(vault.totalBorrowed.toString());
+      const utilization = vault.totalSupplied.isZero()
+        ? 0
+        : Math.min(vault.totalBorrowed.muln(10_000).div(vault.totalSupplied).toNumber(), 10_000);
+      expect(stats.utilizationBps.toNumber()).to.equal(utilization);
+
+      // Borrowers pay at least the base rate; LPs never earn more than borrowers pay
+      expect(stats.borrowApyBps.toNumber()).to.be.at.least(vault.baseRateBps);
+      expect(stats.supplyApyBps.lte(stats.borrowApyBps)).to.equal(true);
+      if (utilization === 0) {
+        expect(stats.supplyApyBps.toNumber()).to.equal(0);
+      }
+    });
+  });
+
+  describe("Withdraw", () => {
+    it("LP burns part of its shares and keeps the position open", async () => {
+      const [lpPositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
+        program.programId,
+      );
+
+      const positionBefore = await program.account.lpPosition.fetch(
+        lpPositionPda,
+      );
+      const vaultStateBefore = await program.account.lendingVault.fetch(
+        lendingVaultPda,
+      );
+      const lpWsolBefore = await provider.connection.getTokenAccountBalance(
+        lpWsolAta,
+      );
+      const partial = positionBefore.shares.divn(2);
+
+      await program.methods
+        .withdraw(partial)
+        .accountsStrict({
+          signer: lp.publicKey,
+          config: configPda,
+          lpPosition: lpPositionPda,
+          lendingVault: lendingVaultPda,
+          wsolMint: NATIVE_MINT,
+          wsolVault: wsolVaultPda,
+          signerWsolAta: lpWsolAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([lp])
+        .rpc();
+
+      const positionAfter = await program.account.lpPosition.fetch(
+        lpPositionPda,
+      );
+      expect(positionAfter.shares.toString()).to.equal(
+        positionBefore.shares.sub(partial).toString(),
+      );
+
+      const vaultStateAfter = await program.account.lendingVault.fetch(
+        lendingVaultPda,
+      );
+      const accrued = afterAccrual(vaultStateBefore, vaultStateAfter);
+      const expectedAssets = assetsForShares(accrued, partial);
+      expect(vaultStateAfter.totalSupplied.toString()).to.equal(
+        accrued.totalSupplied.sub(expectedAssets).toString(),
+      );
+      expect(vaultStateAfter.totalShares.toString()).to.equal(
+        vaultStateBefore.totalShares.sub(partial).toString(),
+      );
+
+      const lpWsolAfter = await provider.connection.getTokenAccountBalance(
+        lpWsolAta,
+      );
+      expect(
+        Number(lpWsolAfter.value.amount) - Number(lpWsolBefore.value.amount),
+      ).to.equal(expectedAssets.toNumber());
+    });
+
+    it("Rejects burning more shares than the LP holds", async () => {
+      const [lpPositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
+        program.programId,
+      );
+      const position = await program.account.lpPosition.fetch(lpPositionPda);
+
+      try {
+        await program.methods
+          .withdraw(position.shares.addn(1))
+          .accountsStrict({
+            signer: lp.publicKey,
+            config: configPda,
+            lpPosition: lpPositionPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            signerWsolAta: lpWsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lp])
+          .rpc();
+
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("InvalidAmount");
+      }
+    });
+
+    it("Holds withdrawals back for the supply cooldown", async () => {
+      const [lpPositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
+        program.programId,
+      );
+      const setCooldown = (seconds: number) =>
+        program.methods
+          .updateSupplyCooldown(new anchor.BN(seconds))
+          .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+          .rpc();
+
+      // The LP supplied earlier in this run, well inside an hour
+      await setCooldown(3600);
+      try {
+        await program.methods
+          .withdraw(new anchor.BN(1))
+          .accountsStrict({
+            signer: lp.publicKey,
+            config: configPda,
+            lpPosition: lpPositionPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            signerWsolAta: lpWsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lp])
+          .rpc();
+
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("SupplyCooldown");
+      } finally {
+        await setCooldown(0);
+      }
+    });
+
+    it("LP withdraws and receives WSOL back", async () => {
+      const [lpPositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp.publicKey.toBuffer()],
+        program.programId,
+      );
+
+      const positionBefore = await program.account.lpPosition.fetch(
+        lpPositionPda,
+      );
+      const lpWsolBefore = await provider.connection.getTokenAccountBalance(
+        lpWsolAta,
+      );
+      const wsolVaultBefore = await provider.connection.getTokenAccountBalance(
+        wsolVaultPda,
+      );
+      const vaultStateBefore = await program.account.lendingVault.fetch(
+        lendingVaultPda,
+      );
+      await program.methods
+        .withdraw(positionBefore.shares)
+        .accountsStrict({
+          signer: lp.publicKey,
+          config: configPda,
+          lpPosition: lpPositionPda,
+          lendingVault: lendingVaultPda,
+          wsolMint: NATIVE_MINT,
+          wsolVault: wsolVaultPda,
+          signerWsolAta: lpWsolAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([lp])
+        .rpc();
+
+      const vaultStateAfter = await program.account.lendingVault.fetch(
+        lendingVaultPda,
+      );
+      const accrued = afterAccrual(vaultStateBefore, vaultStateAfter);
+      const redeemed = assetsForShares(accrued, positionBefore.shares);
+
+      try {
+        await program.account.lpPosition.fetch(lpPositionPda);
+        throw new Error("LP position should have been closed");
+      } catch (e) {
+        expect(e.message).to.match(/Account does not exist|not found/i);
+      }
+
+      const lpWsolAfter = await provider.connection.getTokenAccountBalance(
+        lpWsolAta,
+      );
+      expect(Number(lpWsolAfter.value.amount)).to.be.gte(
+        Number(lpWsolBefore.value.amount) + redeemed.toNumber(),
+      );
+
+      const wsolVaultAfter = await provider.connection.getTokenAccountBalance(
+        wsolVaultPda,
+      );
+      expect(
+        Number(wsolVaultBefore.value.amount) -
+          Number(wsolVaultAfter.value.amount),
+      ).to.equal(redeemed.toNumber());
+
+      expect(vaultStateAfter.totalSupplied.toString()).to.equal(
+        accrued.totalSupplied.sub(redeemed).toString(),
+      );
+
+      console.log(
+        "LP withdrew:",
+        redeemed.toNumber() / LAMPORTS_PER_SOL,
+        "WSOL",
+      );
+      console.log("LP WSOL balance after:", lpWsolAfter.value.uiAmount, "WSOL");
+      console.log("WSOL vault after:", wsolVaultAfter.value.uiAmount, "WSOL");
+    });
+
+    it("Cannot withdraw someone else's position", async () => {
+      const [lp2PositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp2.publicKey.toBuffer()],
+        program.programId,
+      );
+
+      try {
+        await program.methods
+          .withdraw(new anchor.BN(LAMPORTS_PER_SOL))
+          .accountsStrict({
+            signer: lp.publicKey,
+            config: configPda,
+            lpPosition: lp2PositionPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            signerWsolAta: lpWsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lp])
+          .rpc();
+
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.match(/InvalidOwner|constraint|seeds/i);
+        console.log("Correctly rejected unauthorized withdrawal");
+      }
+    });
+  });
+
+  describe("Withdrawal queue", () => {
+    const requestPda = (ticket: anchor.BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("withdrawal_request"), ticket.toArrayLike(Buffer, "le", 8)],
+        program.programId,
+      )[0];
+
+    it("LP queues a withdrawal, direct exits wait, and processing pays it out in order", async () => {
+      const [lp2PositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp2.publicKey.toBuffer()],
+        program.programId,
+      );
+      const positionBefore = await program.account.lpPosition.fetch(lp2PositionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const shares = positionBefore.shares.divn(2);
+      const ticket = vaultBefore.withdrawalQueueTail;
+      const withdrawalRequest = requestPda(ticket);
+
+      await program.methods
+        .requestWithdrawal(shares)
+        .accountsStrict({
+          signer: lp2.publicKey,
+          config: configPda,
+          lpPosition: lp2PositionPda,
+          lendingVault: lendingVaultPda,
+          withdrawalRequest,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([lp2])
+        .rpc();
+
+      const request = await program.account.withdrawalRequest.fetch(withdrawalRequest);
+      expect(request.lp.toBase58()).to.equal(lp2.publicKey.toBase58());
+      expect(request.ticket.toString()).to.equal(ticket.toString());
+      expect(request.shares.toString()).to.equal(shares.toString());
+      const positionQueued = await program.account.lpPosition.fetch(lp2PositionPda);
+      expect(positionQueued.shares.toString()).to.equal(positionBefore.shares.sub(shares).toString());
+
+      // The remaining shares can't skip ahead of the queue
+      try {
+        await program.methods
+          .withdraw(new anchor.BN(1))
+          .accountsStrict({
+            signer: lp2.publicKey,
+            config: configPda,
+            lpPosition: lp2PositionPda,
+            lendingVault: lendingVaultPda,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            signerWsolAta: lp2WsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lp2])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect(e.message).to.include("WithdrawalsQueued");
+      }
+
+      // Anyone can process the head of the queue
+      const wsolBefore = await provider.connection.getTokenAccountBalance(lp2WsolAta);
+      await program.methods
+        .processWithdrawal()
+        .accountsStrict({
+          config: configPda,
+          lendingVault: lendingVaultPda,
+          withdrawalRequest,
+          lp: lp2.publicKey,
+          wsolMint: NATIVE_MINT,
+          wsolVault: wsolVaultPda,
+          lpWsolAta: lp2WsolAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolAfter = await provider.connection.getTokenAccountBalance(lp2WsolAta);
+      const paid = new anchor.BN(wsolAfter.value.amount).sub(new anchor.BN(wsolBefore.value.amount));
+      expect(paid.gte(assetsForShares(vaultBefore, shares))).to.equal(true);
+      expect(vaultAfter.totalShares.toString()).to.equal(vaultBefore.totalShares.sub(shares).toString());
+      expect(vaultAfter.withdrawalQueueHead.toString()).to.equal(vaultAfter.withdrawalQueueTail.toString());
+      expect(await provider.connection.getAccountInfo(withdrawalRequest)).to.be.null;
+    });
+
+    it("Queued exits can't be requested or processed while the protocol is paused", async () => {
+      const setPaused = (paused: boolean) =>
+        program.methods
+          .updatePauseState(paused)
+          .accountsStrict({ authority, config: configPda })
+          .rpc();
+      const [lp2PositionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), lp2.publicKey.toBuffer()],
+        program.programId,
+      );
+      const shares = (await program.account.lpPosition.fetch(lp2PositionPda)).shares.divn(2);
+      const withdrawalRequest = requestPda(
+        (await program.account.lendingVault.fetch(lendingVaultPda)).withdrawalQueueTail,
+      );
+      const request = () =>
+        program.methods
+          .requestWithdrawal(shares)
+          .accountsStrict({
+            signer: lp2.publicKey,
+            config: configPda,
+            lpPosition: lp2PositionPda,
+            lendingVault: lendingVaultPda,
+            withdrawalRequest,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lp2])
+          .rpc();
+      const processHead = () =>
+        program.methods
+          .processWithdrawal()
+          .accountsStrict({
+            config: configPda,
+            lendingVault: lendingVaultPda,
+            withdrawalRequest,
+            lp: lp2.publicKey,
+            wsolMint: NATIVE_MINT,
+            wsolVault: wsolVaultPda,
+            lpWsolAta: lp2WsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+
+      await setPaused(true);
+      try {
+        try {
+          await request();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect(e.message).to.include("ProtocolPaused");
+        }
+      } finally {
+        await setPaused(false);
+      }
+
+      // A request queued before the pause is held until it lifts
+      await request();
+      await setPaused(true);
+      try {
+        try {
+          await processHead();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect(e.message).to.include("ProtocolPaused");
+        }
+        expect(await provider.connection.getAccountInfo(withdrawalRequest)).to.not.be.null;
+      } finally {
+        await setPaused(false);
+      }
+
+      await processHead();
+      expect(await provider.connection.getAccountInfo(withdrawalRequest)).to.be.null;
+    });
   });
 });
@@ -13,6 +13,7 @@ import {
 } from "@solana/web3.js";
 import {
   getOrCreateAssociatedTokenAccount,
+  getAssociatedTokenAddressSync,
   createSyncNativeInstruction,
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -53,6 +54,32 @@ function deriveEventAuthority(): PublicKey {
   return pda;
 }
 
+const INDEX_ONE = new BN("1000000000000000000");
+
+// Interest one accrual books on all of the vault's outstanding borrows,
+// rounded up like the program does
+function bookedInterest(
+  before: { totalBorrowed: BN; borrowIndex: BN },
+  after: { borrowIndex: BN }
+): BN {
+  const growth = after.borrowIndex.sub(before.borrowIndex);
+  if (growth.isZero()) return new BN(0);
+  return before.totalBorrowed.mul(growth).add(before.borrowIndex).subn(1).div(before.borrowIndex);
+}
+
+// A position's debt brought up to `borrowIndex`, rounded down like the program does
+function accruedDebt(
+  position: { debtAmount: BN; borrowIndexSnapshot: BN; debtInterestRemainder: BN },
+  borrowIndex: BN
+): BN {
+  const snapshot = position.borrowIndexSnapshot;
+  if (position.debtAmount.isZero() || snapshot.isZero()) return position.debtAmount;
+  const growth = borrowIndex.sub(snapshot).mul(INDEX_ONE).div(snapshot);
+  return position.debtAmount.add(
+    position.debtAmount.mul(growth).add(position.debtInterestRemainder).div(INDEX_ONE)
+  );
+}
+
 describe("Liquidation", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -70,6 +97,7 @@ describe("Liquidation", () => {
   // Original config values to restore after tests
   const ORIGINAL_MAX_LTV = 7500;
   const ORIGINAL_LIQUIDATION_THRESHOLD = 8000;
+  const DEFAULT_CLOSE_FACTOR = 5000;
 
   // ─── Helpers ──────────────────────────────────────────────────────────────
 
@@ -90,6 +118,12 @@ describe("Liquidation", () => {
     return ata.address;
   }
 
+  async function tokenBalance(ata: PublicKey): Promise<number> {
+    const info = await provider.connection.getAccountInfo(ata);
+    if (!info) return 0;
+    return parseInt((await provider.connection.getTokenAccountBalance(ata)).value.amount);
+  }
+
   async function ensureBinArrayExists(index: BN): Promise<void> {
     const pda = deriveBinArrayPda(LB_PAIR, index);
     if (await provider.connection.getAccountInfo(pda)) return;
@@ -101,6 +135,7 @@ describe("Liquidation", () => {
     user: Keypair,
     positionPda: PublicKey,
     wsolVault: PublicKey,
+    leverage: BN = new BN(20_000), // 2x
   ): Promise<{ metPositionKp: Keypair; minBinId: number; maxBinId: number }> {
     await dlmmPool.refetchStates();
     const activeBin = await dlmmPool.getActiveBin();
@@ -156,19 +191,21 @@ describe("Liquidation", () => {
     // Refresh oracle timestamp before opening
     await program.methods
       .updateMockOracle(new BN(150_000_000))
-      .accountsStrict({ authority, config: configPda, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+      .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
       .rpc();
 
     const metPositionKp = Keypair.generate();
 
     await program.methods
       .openPosition(
-        new BN(20_000), // 2x leverage
+        leverage,
         minBinId,
         maxBinId - minBinId + 1,
         activeBinId,
         10,
-        binLiquidityDist
+        binLiquidityDist,
+        false, // subsidizeRent
+        null // strategyTag
       )
       .accountsStrict({
         user: user.publicKey,
@@ -180,6 +217,7 @@ describe("Liquidation", () => {
         collateralConfig: collateralConfigPda,
         priceOracle: priceOraclePda,
         metPosition: metPositionKp.publicKey,
+        rentReserve: null,
         lbPair: LB_PAIR,
         binArrayBitmapExtension: null,
         reserve,
@@ -232,18 +270,23 @@ describe("Liquidation", () => {
       accounts: {
         liquidator,
         config: configPda,
+        keeper: null as PublicKey | null,
         wsolMint: NATIVE_MINT,
         position: positionPda,
         lendingVault: lendingVaultPda,
         collateralConfig: collateralConfigPda,
         priceOracle: priceOraclePda,
         wsolVault: wsolVaultPda,
+        liquidatorWsolAta: getAssociatedTokenAddressSync(NATIVE_MINT, liquidator),
         positionOwner,
+        ownerWsolAta: getAssociatedTokenAddressSync(NATIVE_MINT, positionOwner),
         collateralVault,
         metPosition: metPositionPubkey,
+        rentReserve: null,
         lbPair: LB_PAIR,
         binArrayBitmapExtension: null,
         userTokenX: userTokenXAccount.address,
+        ownerTokenX: getAssociatedTokenAddressSync(dlmmPool.lbPair.tokenXMint, positionOwner),
         reserveX: dlmmPool.lbPair.reserveX,
         reserveY: dlmmPool.lbPair.reserveY,
         tokenXMint: dlmmPool.lbPair.tokenXMint,
@@ -293,6 +336,11 @@ describe("Liquidation", () => {
         .rpc();
     }
 
+    // Idempotent: the DLMM pair must be whitelisted before opening on it
+    await program.methods.whitelistPair(LB_PAIR)
+      .accountsStrict({ authority, config: configPda })
+      .rpc();
+
     // Idempotent: lending vault
     try {
       await program.account.lendingVault.fetch(lendingVaultPda);
@@ -310,7 +358,7 @@ describe("Liquidation", () => {
     try {
       await program.account.mockOracle.fetch(priceOraclePda);
     } catch {
-      await program.methods.initializeMockOracle(new BN(150_000_000))
+      await program.methods.initializeMockOracle(new BN(150_000_000), 6)
         .accountsStrict({
           authority, config: configPda, mint: NATIVE_MINT, mockOracle: priceOraclePda,
           systemProgram: SystemProgram.programId,
@@ -330,12 +378,14 @@ describe("Liquidation", () => {
     } catch {
       await program.methods.registerCollateral(
         priceOraclePda,
+        { mock: {} },
         ORIGINAL_MAX_LTV,
         ORIGINAL_LIQUIDATION_THRESHOLD,
         500,          // liquidation_penalty (5%)
         new BN(Math.floor(0.1 * LAMPORTS_PER_SOL)),
         500,          // interest_rate_bps (5%)
         new BN(3600),
+        new BN(50_000), // max_leverage_bps (5×)
       )
         .accountsStrict({
           authority, config: configPda, mint: NATIVE_MINT,
@@ -367,7 +417,7 @@ describe("Liquidation", () => {
     } catch {
       await program.methods.supply(new BN(8 * LAMPORTS_PER_SOL))
         .accountsStrict({
-          signer: lp.publicKey, lendingVault: lendingVaultPda,
+          signer: lp.publicKey, config: configPda, lendingVault: lendingVaultPda,
           wsolMint: NATIVE_MINT, wsolVault: wsolVaultPda, signerWsolAta: lpWsolAta,
           lpPosition: lpPositionPda, tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
@@ -392,12 +442,31 @@ describe("Liquidation", () => {
     console.log("  wsolVaultPda     :", wsolVaultPda.toBase58());
   });
 
+  async function setCloseFactor(closeFactorBps: number): Promise<void> {
+    await program.methods
+      .updateCollateralCloseFactor(NATIVE_MINT, closeFactorBps)
+      .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+      .rpc();
+  }
+
+  async function lowerThresholdBelow(positionPda: PublicKey): Promise<void> {
+    const pos = await program.account.position.fetch(positionPda);
+    const collateral = pos.collateralAmount.toNumber();
+    const debt = pos.debtAmount.toNumber();
+    const ltv = Math.floor((debt * 10000) / (collateral + debt));
+    await program.methods
+      .updateCollateralLtvParams(NATIVE_MINT, ltv - 200, ltv - 100)
+      .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+      .rpc();
+  }
+
   after("Restore original LTV params", async function () {
     try {
       await program.methods
         .updateCollateralLtvParams(NATIVE_MINT, ORIGINAL_MAX_LTV, ORIGINAL_LIQUIDATION_THRESHOLD)
         .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
         .rpc();
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
     } catch {
       // best-effort restore
     }
@@ -472,15 +541,19 @@ describe("Liquidation", () => {
         .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
         .rpc();
 
+      // Full unwind: a 50% close factor would restore health and liquidate partially
+      await setCloseFactor(10_000);
+
       // Refresh oracle timestamp
       await program.methods
         .updateMockOracle(new BN(150_000_000))
-        .accountsStrict({ authority, config: configPda, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
         .rpc();
     });
 
-    it("Liquidates unhealthy position, repays debt, sends penalty to liquidator", async () => {
+    it("Liquidates unhealthy position, repays debt, pays wSOL penalty to liquidator", async () => {
       const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const positionBefore = await program.account.position.fetch(positionPda);
       const wsolVaultBalanceBefore = await provider.connection.getTokenAccountBalance(wsolVaultPda);
 
       const { accounts } = await buildLiquidateAccounts(
@@ -492,13 +565,14 @@ describe("Liquidation", () => {
         openedMaxBinId,
       );
 
-      // Track native SOL balances (penalty + remainder are native SOL from collateral vault)
-      const liquidatorLamportsBefore = await provider.connection.getBalance(liquidator.publicKey);
+      // Collateral goes back to the owner as native SOL; penalty and residual proceeds are paid in wSOL
+      const ownerWsolBefore = await tokenBalance(accounts.ownerWsolAta);
+      const ownerTokenXBefore = await tokenBalance(accounts.ownerTokenX);
       const ownerLamportsBefore = await provider.connection.getBalance(positionUser.publicKey);
       const collateralVaultBefore = await provider.connection.getBalance(accounts.collateralVault);
 
       const tx = await program.methods
-        .liquidate(openedMinBinId, openedMaxBinId)
+        .liquidate(openedMinBinId, openedMaxBinId, false)
         .accountsStrict(accounts)
         .signers([liquidator])
         .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
@@ -523,7 +597,10 @@ describe("Liquidation", () => {
       // Verify lending vault accounting
       const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
       expect(vaultAfter.totalBorrowed.toString()).to.equal(
-        vaultBefore.totalBorrowed.sub(debtBefore).toString(),
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .sub(accruedDebt(positionBefore, vaultAfter.borrowIndex))
+          .toString(),
         "totalBorrowed must decrease by the debt amount"
       );
 
@@ -531,55 +608,912 @@ describe("Liquidation", () => {
       const metPositionInfo = await provider.connection.getAccountInfo(metPositionKp.publicKey);
       expect(metPositionInfo).to.be.null;
 
-      // Verify wSOL vault balance: LP proceeds returned to vault (repays debt)
+      // Verify wSOL vault balance: vault keeps exactly the debt, including
+      // interest accrued since open
       const wsolVaultBalanceAfter = await provider.connection.getTokenAccountBalance(wsolVaultPda);
       const vaultDelta = parseInt(wsolVaultBalanceAfter.value.amount) - parseInt(wsolVaultBalanceBefore.value.amount);
-      expect(vaultDelta).to.be.greaterThanOrEqual(0,
-        "wSOL vault must not lose funds from liquidation"
+      expect(vaultDelta).to.be.at.least(debtBefore.toNumber(),
+        "wSOL vault must be repaid at least the debt recorded at open"
       );
+      const debtRepaid = vaultDelta;
 
-      // Collateral vault should be drained (penalty + remainder distributed)
+      // Collateral vault is drained: any LP shortfall goes to the wSOL vault, the rest to the owner
       const collateralVaultAfter = await provider.connection.getBalance(accounts.collateralVault);
       const collateralDistributed = collateralVaultBefore - collateralVaultAfter;
 
-      // Liquidator receives penalty from collateral (native SOL)
-      // Note: liquidator also pays tx fee, so net delta may be slightly less
-      const liquidatorLamportsAfter = await provider.connection.getBalance(liquidator.publicKey);
-      const liquidatorDelta = liquidatorLamportsAfter - liquidatorLamportsBefore;
-
-      // Position owner receives remainder from collateral (native SOL)
       const ownerLamportsAfter = await provider.connection.getBalance(positionUser.publicKey);
       const ownerDelta = ownerLamportsAfter - ownerLamportsBefore;
+      const shortfallCovered = collateralDistributed - ownerDelta;
 
-      // Collateral should be fully distributed (penalty + remainder = original collateral)
       const collateral = depositAmount.toNumber();
-      const expectedPenalty = Math.floor(collateral * 500 / 10000); // 5% penalty
-      const expectedRemainder = collateral - expectedPenalty;
-
       expect(collateralDistributed).to.equal(collateral,
-        "Full collateral must be distributed from vault"
+        "Full collateral must be released from vault"
+      );
+
+      // Liquidator receives the penalty in wSOL, owner receives what's left
+      const penaltyReceived = await tokenBalance(accounts.liquidatorWsolAta);
+      const residualReceived = (await tokenBalance(accounts.ownerWsolAta)) - ownerWsolBefore;
+      const proceeds = vaultDelta - shortfallCovered + penaltyReceived + residualReceived;
+      const expectedPenalty = Math.min(
+        Math.floor(proceeds * 500 / 10000), // 5% penalty
+        Math.max(proceeds - debtRepaid, 0),
       );
-      expect(ownerDelta).to.equal(expectedRemainder,
-        "Owner must receive collateral minus penalty"
+      expect(penaltyReceived).to.equal(expectedPenalty,
+        "Liquidator must receive 5% of proceeds, capped at the surplus above debt"
       );
-      // Liquidator delta includes penalty minus tx fees + rent from DLMM close
-      expect(liquidatorDelta).to.be.greaterThan(0,
-        "Liquidator must receive penalty (net of tx fees)"
+      expect(residualReceived).to.equal(
+        Math.max(proceeds - debtRepaid - expectedPenalty, 0),
+        "Owner must receive proceeds left after debt and penalty"
       );
+      expect(shortfallCovered).to.equal(
+        Math.max(debtRepaid - proceeds, 0),
+        "Collateral must only cover the LP shortfall"
+      );
+
+      // The same figures come back as an event and as return data
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const liquidated = [...events].find((e) => e.name === "positionLiquidated")!;
+      expect(liquidated.data.position.toBase58()).to.equal(positionPda.toBase58());
+      expect(liquidated.data.liquidator.toBase58()).to.equal(liquidator.publicKey.toBase58());
+      expect(liquidated.data.debtRepaid.toNumber()).to.equal(debtRepaid);
+      expect(liquidated.data.proceeds.toNumber()).to.equal(proceeds);
+      expect(liquidated.data.liquidatorBonus.toNumber()).to.equal(penaltyReceived);
+      expect(liquidated.data.partial).to.equal(false);
+
+      // Token X the swap didn't need stays with the owner instead of being sold
+      const residualTokenX = (await tokenBalance(accounts.ownerTokenX)) - ownerTokenXBefore;
+      expect(liquidated.data.residualTokenX.toNumber()).to.equal(residualTokenX);
+
+      const [returnData] = txInfo!.meta!.returnData!.data;
+      const result = program.coder.types.decode(
+        "LiquidationResult",
+        Buffer.from(returnData, "base64")
+      );
+      expect(result.debtRepaid.toNumber()).to.equal(debtRepaid);
+      expect(result.penalty.toNumber()).to.equal(liquidated.data.penalty.toNumber());
+      expect(result.badDebt.toNumber()).to.equal(liquidated.data.badDebt.toNumber());
+      expect(result.partial).to.equal(false);
+      expect(result.residualTokenX.toNumber()).to.equal(residualTokenX);
 
-      const debt = debtBefore.toNumber();
+      const debt = debtRepaid;
       console.log("  Position status      : liquidated");
       console.log("  Debt repaid          :", debt / LAMPORTS_PER_SOL, "SOL");
       console.log("  totalBorrowed delta  :", debtBefore.toString(), "->", vaultAfter.totalBorrowed.toString());
-      console.log("  wSOL vault delta     :", vaultDelta / LAMPORTS_PER_SOL, "SOL (LP proceeds)");
+      console.log("  wSOL vault delta     :", vaultDelta / LAMPORTS_PER_SOL, "SOL (debt repaid)");
+      console.log("  LP proceeds          :", proceeds / LAMPORTS_PER_SOL, "SOL");
       console.log("  Collateral deposited :", collateral / LAMPORTS_PER_SOL, "SOL");
-      console.log("  Expected penalty (5%):", expectedPenalty / LAMPORTS_PER_SOL, "SOL");
-      console.log("  Owner remainder      :", ownerDelta / LAMPORTS_PER_SOL, "SOL");
-      console.log("  Liquidator net delta :", liquidatorDelta / LAMPORTS_PER_SOL, "SOL (penalty - tx fees + rent)");
+      console.log("  Penalty (wSOL)       :", penaltyReceived / LAMPORTS_PER_SOL, "SOL");
+      console.log("  Owner residual (wSOL):", residualReceived / LAMPORTS_PER_SOL, "SOL");
+      console.log("  Owner collateral     :", ownerDelta / LAMPORTS_PER_SOL, "SOL");
       console.log("  DLMM position        : closed on-chain");
     });
   });
 
+  // ─── Partial liquidation ──────────────────────────────────────────────────
+
+  describe("liquidate -- partial", () => {
+    it("Repays up to the close factor and keeps the position active", async () => {
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const result = await openPosition(user, positionPda, wsolVaultPda);
+      await lowerThresholdBelow(positionPda);
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .rpc();
+
+      const posBefore = await program.account.position.fetch(positionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      const { accounts } = await buildLiquidateAccounts(
+        liquidator.publicKey,
+        user.publicKey,
+        positionPda,
+        result.metPositionKp.publicKey,
+        result.minBinId,
+        result.maxBinId,
+      );
+
+      await program.methods
+        .liquidate(result.minBinId, result.maxBinId, false)
+        .accountsStrict(accounts)
+        .signers([liquidator])
+        .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .rpc({ commitment: "confirmed" });
+
+      const posAfter = await program.account.position.fetch(positionPda);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const repaid = posBefore.debtAmount.sub(posAfter.debtAmount);
+
+      expect(posAfter.status).to.deep.equal({ active: {} }, "Position must stay Active");
+      expect(repaid.gtn(0)).to.equal(true, "Some debt must be repaid");
+      expect(repaid.lte(posBefore.debtAmount.muln(DEFAULT_CLOSE_FACTOR).divn(10_000))).to.equal(
+        true, "Repayment must not exceed the close factor"
+      );
+      expect(vaultBefore.totalBorrowed.sub(vaultAfter.totalBorrowed).toString()).to.equal(
+        repaid.toString(), "totalBorrowed must decrease by the repaid amount"
+      );
+      expect(posAfter.collateralAmount.toString()).to.equal(
+        posBefore.collateralAmount.toString(), "Collateral must be untouched"
+      );
+
+      const metPositionInfo = await provider.connection.getAccountInfo(result.metPositionKp.publicKey);
+      expect(metPositionInfo).to.not.be.null;
+
+      console.log("  Debt before :", posBefore.debtAmount.toNumber() / LAMPORTS_PER_SOL, "SOL");
+      console.log("  Repaid      :", repaid.toNumber() / LAMPORTS_PER_SOL, "SOL");
+      console.log("  DLMM position still open");
+    });
+  });
+
+  describe("liquidate -- swap_all", () => {
+    it("Sells all of the position's token X on a full liquidation when asked", async () => {
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const result = await openPosition(user, positionPda, wsolVaultPda);
+      await lowerThresholdBelow(positionPda);
+      await setCloseFactor(10_000);
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .rpc();
+
+      const { accounts } = await buildLiquidateAccounts(
+        liquidator.publicKey,
+        user.publicKey,
+        positionPda,
+        result.metPositionKp.publicKey,
+        result.minBinId,
+        result.maxBinId,
+      );
+      const ownerTokenXBefore = await tokenBalance(accounts.ownerTokenX);
+
+      let tx: string;
+      try {
+        tx = await program.methods
+          .liquidate(result.minBinId, result.maxBinId, true)
+          .accountsStrict(accounts)
+          .signers([liquidator])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc({ commitment: "confirmed" });
+      } finally {
+        await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+      }
+
+      const posAfter = await program.account.position.fetch(positionPda);
+      expect(posAfter.status).to.deep.equal({ liquidated: {} });
+
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const liquidated = [...events].find((e) => e.name === "positionLiquidated")!;
+      expect(liquidated.data.partial).to.equal(false);
+      expect(liquidated.data.residualTokenX.toNumber()).to.equal(0, "All token X must be sold");
+      expect(await tokenBalance(accounts.ownerTokenX)).to.equal(ownerTokenXBefore);
+    });
+  });
+
+  describe("liquidate -- stale oracle fallback", () => {
+    it("Liquidates a deeply unhealthy position at the discounted last price", async () => {
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const result = await openPosition(user, positionPda, wsolVaultPda);
+
+      // Threshold 20% under the current LTV puts health around 0.8, well
+      // inside the stale-fallback gate
+      const pos = await program.account.position.fetch(positionPda);
+      const collateral = pos.collateralAmount.toNumber();
+      const debt = pos.debtAmount.toNumber();
+      const ltv = Math.floor((debt * 10000) / (collateral + debt));
+      const threshold = Math.floor(ltv * 0.8);
+      await program.methods
+        .updateCollateralLtvParams(NATIVE_MINT, threshold - 100, threshold)
+        .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+        .rpc();
+
+      const { accounts } = await buildLiquidateAccounts(
+        liquidator.publicKey,
+        user.publicKey,
+        positionPda,
+        result.metPositionKp.publicKey,
+        result.minBinId,
+        result.maxBinId,
+      );
+      const liquidate = () =>
+        program.methods
+          .liquidate(result.minBinId, result.maxBinId, false)
+          .accountsStrict(accounts)
+          .signers([liquidator])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc({ commitment: "confirmed" });
+      const setDiscount = (bps: number) =>
+        program.methods
+          .updateCollateralStaleLiquidationDiscount(NATIVE_MINT, bps)
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+
+      // The open recorded a last price; now let the feed go stale
+      const oracleAccounts = { authority, mint: NATIVE_MINT, mockOracle: priceOraclePda };
+      await program.methods.setMockOracleStaleness(new BN(0), true).accountsStrict(oracleAccounts).rpc();
+      try {
+        // Fallback off: a stale feed still blocks liquidation
+        try {
+          await liquidate();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.include("OracleStale");
+        }
+
+        // 5% under the last recorded price
+        await setDiscount(500);
+        const posBefore = await program.account.position.fetch(positionPda);
+        await liquidate();
+        const posAfter = await program.account.position.fetch(positionPda);
+        expect(posAfter.debtAmount.lt(posBefore.debtAmount)).to.equal(
+          true, "Debt must be repaid at the fallback price"
+        );
+        console.log("  Repaid at fallback:",
+          posBefore.debtAmount.sub(posAfter.debtAmount).toNumber() / LAMPORTS_PER_SOL, "SOL");
+      } finally {
+        await setDiscount(0);
+        await program.methods.setMockOracleStaleness(new BN(0), false).accountsStrict(oracleAccounts).rpc();
+        await program.methods
+          .updateCollateralLtvParams(NATIVE_MINT, ORIGINAL_MAX_LTV, ORIGINAL_LIQUIDATION_THRESHOLD)
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+      }
+    });
+  });
+
+  // ─── Collateral seizure ───────────────────────────────────────────────────
+
+  describe("seizeCollateral", () => {
+    it("Liquidator repays debt in wSOL and takes collateral plus the penalty", async () => {
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      // 1.5x: collateral well above the debt, so a seizure lowers LTV
+      await openPosition(user, positionPda, wsolVaultPda, new BN(15_000));
+      await lowerThresholdBelow(positionPda);
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .rpc();
+
+      const liquidatable = await program.methods
+        .isLiquidatable()
+        .accountsStrict({
+          position: positionPda,
+          collateralConfig: collateralConfigPda,
+          priceOracle: priceOraclePda,
+        })
+        .view();
+      expect(liquidatable).to.equal(true, "Keeper pre-check must flag the position");
+
+      // A stale feed blocks the liquidation path until it is fresh again
+      const oracleAccounts = { authority, mint: NATIVE_MINT, mockOracle: priceOraclePda };
+      await program.methods.setMockOracleStaleness(new BN(0), true).accountsStrict(oracleAccounts).rpc();
+      try {
+        await program.methods
+          .isLiquidatable()
+          .accountsStrict({
+            position: positionPda,
+            collateralConfig: collateralConfigPda,
+            priceOracle: priceOraclePda,
+          })
+          .view();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("OracleStale");
+      } finally {
+        await program.methods.setMockOracleStaleness(new BN(0), false).accountsStrict(oracleAccounts).rpc();
+      }
+
+      const repayAmount = new BN(LAMPORTS_PER_SOL / 2);
+      const liquidatorWsolAta = await wrapSol(liquidator, liquidator.publicKey, 2 * LAMPORTS_PER_SOL);
+
+      const posBefore = await program.account.position.fetch(positionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolBefore = await tokenBalance(liquidatorWsolAta);
+      const solBefore = await provider.connection.getBalance(liquidator.publicKey);
+
+      // Route 2% of the penalty to the insurance fund for this seizure
+      const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+      const bonusBps = config.liquidationPenaltyBps - 200;
+      const setBonus = (bps: number) =>
+        program.methods
+          .updateCollateralLiquidatorBonus(NATIVE_MINT, bps)
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+      await setBonus(bonusBps);
+      try {
+        await program.methods
+          .seizeCollateral(repayAmount)
+          .accountsStrict({
+            liquidator: liquidator.publicKey,
+            config: configPda,
+            keeper: null,
+            wsolMint: NATIVE_MINT,
+            position: positionPda,
+            lendingVault: lendingVaultPda,
+            collateralConfig: collateralConfigPda,
+            priceOracle: priceOraclePda,
+            wsolVault: wsolVaultPda,
+            liquidatorWsolAta,
+            collateralVault: collateralVaultPda,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([liquidator])
+          .rpc({ commitment: "confirmed" });
+      } finally {
+        await setBonus(config.liquidatorBonusBps);
+      }
+
+      const posAfter = await program.account.position.fetch(positionPda);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const seized = repayAmount.muln(10_000 + config.liquidationPenaltyBps).divn(10_000);
+      const penalty = seized.sub(repayAmount);
+      const bonus = penalty.muln(bonusBps).divn(config.liquidationPenaltyBps);
+
+      expect(wsolBefore - (await tokenBalance(liquidatorWsolAta))).to.equal(repayAmount.toNumber());
+      expect(posBefore.collateralAmount.sub(posAfter.collateralAmount).toString()).to.equal(seized.toString());
+      expect(posAfter.debtAmount.lte(posBefore.debtAmount.sub(repayAmount).addn(10))).to.equal(true);
+      expect(posAfter.status).to.deep.equal({ active: {} });
+      // Only the bonus share of the penalty reaches the liquidator; the rest
+      // is credited to the insurance fund
+      expect(vaultAfter.insuranceFund.sub(vaultBefore.insuranceFund).toString()).to.equal(
+        penalty.sub(bonus).toString()
+      );
+      // Liquidator pays the tx fee out of the seized SOL
+      const liquidatorGain = (await provider.connection.getBalance(liquidator.publicKey)) - solBefore;
+      expect(liquidatorGain).to.be.greaterThan(repayAmount.add(bonus).toNumber() - 10_000);
+      expect(liquidatorGain).to.be.at.most(repayAmount.add(bonus).toNumber());
+
+      console.log("  Repaid :", repayAmount.toNumber() / LAMPORTS_PER_SOL, "SOL");
+      console.log("  Seized :", seized.toNumber() / LAMPORTS_PER_SOL, "SOL");
+    });
+
+    it("Rejects a seizure that would raise the position's LTV", async () => {
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      // 2x: debt equals collateral, so taking debt plus the penalty in
+      // collateral leaves LTV higher than before
+      await openPosition(user, positionPda, wsolVaultPda);
+      await lowerThresholdBelow(positionPda);
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .rpc();
+
+      const liquidatorWsolAta = await wrapSol(liquidator, liquidator.publicKey, 2 * LAMPORTS_PER_SOL);
+      const posBefore = await program.account.position.fetch(positionPda);
+      try {
+        await program.methods
+          .seizeCollateral(new BN(LAMPORTS_PER_SOL / 2))
+          .accountsStrict({
+            liquidator: liquidator.publicKey,
+            config: configPda,
+            keeper: null,
+            wsolMint: NATIVE_MINT,
+            position: positionPda,
+            lendingVault: lendingVaultPda,
+            collateralConfig: collateralConfigPda,
+            priceOracle: priceOraclePda,
+            wsolVault: wsolVaultPda,
+            liquidatorWsolAta,
+            collateralVault: collateralVaultPda,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([liquidator])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("SeizureWorsensLtv");
+      }
+      const posAfter = await program.account.position.fetch(positionPda);
+      expect(posAfter.collateralAmount.toString()).to.equal(posBefore.collateralAmount.toString());
+    });
+  });
+
+  describe("liquidateBatch", () => {
+    it("Seizes the unhealthy positions in a batch and skips the healthy ones", async () => {
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+
+      const leveraged = Keypair.generate();
+      const unlevered = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(leveraged.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(unlevered.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const pdas = (user: Keypair) => ({
+        position: PublicKey.findProgramAddressSync(
+          [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+          program.programId
+        )[0],
+        vault: PublicKey.findProgramAddressSync(
+          [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+          program.programId
+        )[0],
+      });
+      const a = pdas(leveraged);
+      const b = pdas(unlevered);
+
+      for (const [user, p] of [[leveraged, a], [unlevered, b]] as const) {
+        await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+          .accountsStrict({
+            user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+            collateralConfig: collateralConfigPda, vault: p.vault,
+            position: p.position, systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user])
+          .rpc();
+      }
+      // Only the first borrows; the second holds collateral with no debt
+      await openPosition(leveraged, a.position, wsolVaultPda, new BN(15_000));
+      await lowerThresholdBelow(a.position);
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .rpc();
+
+      const liquidatorWsolAta = await wrapSol(liquidator, liquidator.publicKey, 2 * LAMPORTS_PER_SOL);
+      const aBefore = await program.account.position.fetch(a.position);
+      const bBefore = await program.account.position.fetch(b.position);
+
+      const tx = await program.methods
+        .liquidateBatch()
+        .accountsStrict({
+          liquidator: liquidator.publicKey,
+          config: configPda,
+          keeper: null,
+          wsolMint: NATIVE_MINT,
+          lendingVault: lendingVaultPda,
+          collateralConfig: collateralConfigPda,
+          priceOracle: priceOraclePda,
+          wsolVault: wsolVaultPda,
+          liquidatorWsolAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: b.position, isSigner: false, isWritable: true },
+          { pubkey: b.vault, isSigner: false, isWritable: true },
+          { pubkey: a.position, isSigner: false, isWritable: true },
+          { pubkey: a.vault, isSigner: false, isWritable: true },
+        ])
+        .signers([liquidator])
+        .rpc({ commitment: "confirmed" });
+
+      const aAfter = await program.account.position.fetch(a.position);
+      const bAfter = await program.account.position.fetch(b.position);
+      const repaid = aBefore.debtAmount.sub(aAfter.debtAmount);
+      expect(repaid.gtn(0)).to.equal(true, "The unhealthy position must be seized");
+      expect(repaid.lte(aBefore.debtAmount.muln(DEFAULT_CLOSE_FACTOR).divn(10_000))).to.equal(
+        true, "Repayment must not exceed the close factor"
+      );
+      expect(aAfter.collateralAmount.lt(aBefore.collateralAmount)).to.equal(true);
+      expect(bAfter.collateralAmount.toString()).to.equal(
+        bBefore.collateralAmount.toString(), "The healthy position must be skipped"
+      );
+
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = [...new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!)]
+        .filter((e) => e.name === "collateralSeized");
+      expect(events.length).to.equal(1, "Only the seized position emits CollateralSeized");
+      expect(events[0].data.position.toBase58()).to.equal(a.position.toBase58());
+      expect(events[0].data.debtRepaid.toString()).to.equal(repaid.toString());
+      console.log("  Repaid in batch:", repaid.toNumber() / LAMPORTS_PER_SOL, "SOL");
+    });
+
+    it("Rejects a batch that lists the same position twice", async () => {
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [position] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault,
+          position, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const liquidatorWsolAta = await wrapSol(liquidator, liquidator.publicKey, LAMPORTS_PER_SOL);
+      const entry = [
+        { pubkey: position, isSigner: false, isWritable: true },
+        { pubkey: vault, isSigner: false, isWritable: true },
+      ];
+
+      try {
+        await program.methods
+          .liquidateBatch()
+          .accountsStrict({
+            liquidator: liquidator.publicKey,
+            config: configPda,
+            keeper: null,
+            wsolMint: NATIVE_MINT,
+            lendingVault: lendingVaultPda,
+            collateralConfig: collateralConfigPda,
+            priceOracle: priceOraclePda,
+            wsolVault: wsolVaultPda,
+            liquidatorWsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts([...entry, ...entry])
+          .signers([liquidator])
+          .rpc({ commitment: "confirmed" });
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("DuplicatePosition");
+      }
+    });
+  });
+
+  // ─── Grace period ─────────────────────────────────────────────────────────
+
+  describe("liquidation grace period", () => {
+    async function setGrace(gracePeriodSeconds: number, hardThreshold: number): Promise<void> {
+      await program.methods
+        .updateCollateralLiquidationGrace(NATIVE_MINT, new BN(gracePeriodSeconds), hardThreshold)
+        .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+        .rpc();
+    }
+
+    it("Holds a flagged position until the grace period ends unless past the hard threshold", async () => {
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      await openPosition(user, positionPda, wsolVaultPda);
+      await lowerThresholdBelow(positionPda);
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .rpc();
+
+      const liquidatorWsolAta = await wrapSol(liquidator, liquidator.publicKey, 2 * LAMPORTS_PER_SOL);
+      const seize = () =>
+        program.methods
+          .seizeCollateral(new BN(LAMPORTS_PER_SOL / 2))
+          .accountsStrict({
+            liquidator: liquidator.publicKey,
+            config: configPda,
+            keeper: null,
+            wsolMint: NATIVE_MINT,
+            position: positionPda,
+            lendingVault: lendingVaultPda,
+            collateralConfig: collateralConfigPda,
+            priceOracle: priceOraclePda,
+            wsolVault: wsolVaultPda,
+            liquidatorWsolAta,
+            collateralVault: collateralVaultPda,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([liquidator])
+          .rpc({ commitment: "confirmed" });
+
+      const debtBefore = (await program.account.position.fetch(positionPda)).debtAmount;
+      await setGrace(3600, 10_000);
+      try {
+        // Not flagged yet
+        try {
+          await seize();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.include("LiquidationGracePeriod");
+        }
+
+        await program.methods
+          .flagLiquidation()
+          .accountsStrict({
+            position: positionPda,
+            collateralConfig: collateralConfigPda,
+            priceOracle: priceOraclePda,
+          })
+          .rpc();
+        const flagged = await program.account.position.fetch(positionPda);
+        expect(flagged.liquidationEligibleSince.toNumber()).to.be.greaterThan(0);
+
+        // Flagged, but the hour hasn't passed
+        try {
+          await seize();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.include("LiquidationGracePeriod");
+        }
+
+        // Past the hard threshold the delay no longer applies
+        const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+        await setGrace(3600, config.liquidationThreshold);
+        await seize();
+      } finally {
+        await setGrace(0, 10_000);
+      }
+
+      const posAfter = await program.account.position.fetch(positionPda);
+      expect(posAfter.debtAmount.lt(debtBefore)).to.equal(true, "Seizure must repay debt");
+    });
+
+    it("Holds a freshly opened position until min_position_age_seconds unless past the hard threshold", async () => {
+      await setCloseFactor(DEFAULT_CLOSE_FACTOR);
+
+      const user = Keypair.generate();
+      const liquidator = Keypair.generate();
+      const sigs = await Promise.all([
+        provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL),
+        provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(s => provider.connection.confirmTransaction(s)));
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      await openPosition(user, positionPda, wsolVaultPda);
+      const opened = await program.account.position.fetch(positionPda);
+      expect(opened.openedAt.toNumber()).to.be.greaterThan(0);
+      await lowerThresholdBelow(positionPda);
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOraclePda })
+        .rpc();
+
+      const liquidatorWsolAta = await wrapSol(liquidator, liquidator.publicKey, 2 * LAMPORTS_PER_SOL);
+      const seize = () =>
+        program.methods
+          .seizeCollateral(new BN(LAMPORTS_PER_SOL / 2))
+          .accountsStrict({
+            liquidator: liquidator.publicKey,
+            config: configPda,
+            keeper: null,
+            wsolMint: NATIVE_MINT,
+            position: positionPda,
+            lendingVault: lendingVaultPda,
+            collateralConfig: collateralConfigPda,
+            priceOracle: priceOraclePda,
+            wsolVault: wsolVaultPda,
+            liquidatorWsolAta,
+            collateralVault: collateralVaultPda,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([liquidator])
+          .rpc({ commitment: "confirmed" });
+      const setMinAge = (seconds: number) =>
+        program.methods
+          .updateCollateralMinPositionAge(NATIVE_MINT, new BN(seconds))
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+
+      await setMinAge(3600);
+      try {
+        try {
+          await seize();
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.include("PositionTooNew");
+        }
+
+        // Deeply insolvent positions are cleared regardless of age
+        const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+        await setGrace(0, config.liquidationThreshold);
+        await seize();
+      } finally {
+        await setMinAge(0);
+        await setGrace(0, 10_000);
+      }
+
+      const posAfter = await program.account.position.fetch(positionPda);
+      expect(posAfter.debtAmount.lt(opened.debtAmount)).to.equal(true, "Seizure must repay debt");
+    });
+  });
+
   // ─── Constraints ──────────────────────────────────────────────────────────
 
   describe("liquidate -- constraints", () => {
@@ -631,7 +1565,7 @@ describe("Liquidation", () => {
 
       try {
         await program.methods
-          .liquidate(result.minBinId, result.maxBinId)
+          .liquidate(result.minBinId, result.maxBinId, false)
           .accountsStrict(accounts)
           .signers([liquidator])
           .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
@@ -642,5 +1576,94 @@ describe("Liquidation", () => {
         console.log("  Correctly rejected liquidation of healthy position");
       }
     });
+
+    it("Only registered keepers can liquidate while liquidation is permissioned", async () => {
+      const user = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      const [positionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods.depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey, config: configPda, mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda, vault: collateralVaultPda,
+          position: positionPda, systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const result = await openPosition(user, positionPda, wsolVaultPda);
+
+      const liquidator = Keypair.generate();
+      const liqSig = await provider.connection.requestAirdrop(liquidator.publicKey, 5 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(liqSig);
+
+      const { accounts } = await buildLiquidateAccounts(
+        liquidator.publicKey,
+        user.publicKey,
+        positionPda,
+        result.metPositionKp.publicKey,
+        result.minBinId,
+        result.maxBinId,
+      );
+      const [keeperPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("keeper"), liquidator.publicKey.toBuffer()],
+        program.programId
+      );
+      const liquidate = (keeper: PublicKey | null) =>
+        program.methods
+          .liquidate(result.minBinId, result.maxBinId, false)
+          .accountsStrict({ ...accounts, keeper })
+          .signers([liquidator])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+          .rpc();
+
+      await program.methods.updatePermissionedLiquidation(true)
+        .accountsStrict({ authority, config: configPda })
+        .rpc();
+      try {
+        try {
+          await liquidate(null);
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.match(/Unauthorized/);
+        }
+
+        await program.methods.addKeeper(liquidator.publicKey)
+          .accountsStrict({
+            authority, config: configPda, keeperRegistration: keeperPda,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        const registration = await program.account.keeper.fetch(keeperPda);
+        expect(registration.keeper.toBase58()).to.equal(liquidator.publicKey.toBase58());
+
+        // A keeper gets past the gate and hits the normal health check
+        try {
+          await liquidate(keeperPda);
+          throw new Error("Should have failed");
+        } catch (e) {
+          expect((e as Error).message).to.match(/PositionHealthy|6007/i);
+        }
+
+        await program.methods.removeKeeper(liquidator.publicKey)
+          .accountsStrict({ authority, config: configPda, keeperRegistration: keeperPda })
+          .rpc();
+        expect(await provider.connection.getAccountInfo(keeperPda)).to.be.null;
+      } finally {
+        await program.methods.updatePermissionedLiquidation(false)
+          .accountsStrict({ authority, config: configPda })
+          .rpc();
+      }
+    });
   });
 });
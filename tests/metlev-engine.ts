@@ -35,6 +35,7 @@ describe("metlev-engine", () => {
     minDeposit: 0.1 * LAMPORTS_PER_SOL, // 0.1 SOL
     interestRateBps: 500,       // 5% APR
     oracleMaxAge: 3600,         // 1 hour
+    maxLeverageBps: 50_000,     // 5×
   };
 
   const USDC_CONFIG = {
@@ -44,6 +45,7 @@ describe("metlev-engine", () => {
     minDeposit: 10_000_000,     // 10 USDC (6 decimals)
     interestRateBps: 300,       // 3% APR
     oracleMaxAge: 60,
+    maxLeverageBps: 90_000,     // 9×
   };
 
   before(async () => {
@@ -161,12 +163,14 @@ describe("metlev-engine", () => {
       await program.methods
         .registerCollateral(
           solOraclePda,
+          { mock: {} },
           SOL_CONFIG.maxLtv,
           SOL_CONFIG.liquidationThreshold,
           SOL_CONFIG.liquidationPenalty,
           new anchor.BN(SOL_CONFIG.minDeposit),
           SOL_CONFIG.interestRateBps,
-          new anchor.BN(SOL_CONFIG.oracleMaxAge)
+          new anchor.BN(SOL_CONFIG.oracleMaxAge),
+          new anchor.BN(SOL_CONFIG.maxLeverageBps)
         )
         .accountsStrict({
           authority,
@@ -186,9 +190,12 @@ describe("metlev-engine", () => {
       expect(solConfig.oracle.toBase58()).to.equal(solOraclePda.toBase58());
       expect(solConfig.maxLtv).to.equal(SOL_CONFIG.maxLtv);
       expect(solConfig.liquidationThreshold).to.equal(SOL_CONFIG.liquidationThreshold);
-      expect(solConfig.liquidationPenalty).to.equal(SOL_CONFIG.liquidationPenalty);
+      expect(solConfig.liquidationPenaltyBps).to.equal(SOL_CONFIG.liquidationPenalty);
+      expect(solConfig.liquidatorBonusBps).to.equal(SOL_CONFIG.liquidationPenalty);
       expect(solConfig.minDeposit.toNumber()).to.equal(SOL_CONFIG.minDeposit);
       expect(solConfig.interestRateBps).to.equal(SOL_CONFIG.interestRateBps);
+      expect(solConfig.openOracleMaxAge.toNumber()).to.equal(SOL_CONFIG.oracleMaxAge);
+      expect(solConfig.liquidationOracleMaxAge.toNumber()).to.equal(SOL_CONFIG.oracleMaxAge);
       expect(solConfig.enabled).to.equal(true);
 
       console.log("SOL collateral registered:");
@@ -210,12 +217,14 @@ describe("metlev-engine", () => {
       await program.methods
         .registerCollateral(
           USDC_ORACLE,
+          { mock: {} },
           USDC_CONFIG.maxLtv,
           USDC_CONFIG.liquidationThreshold,
           USDC_CONFIG.liquidationPenalty,
           new anchor.BN(USDC_CONFIG.minDeposit),
           USDC_CONFIG.interestRateBps,
-          new anchor.BN(USDC_CONFIG.oracleMaxAge)
+          new anchor.BN(USDC_CONFIG.oracleMaxAge),
+          new anchor.BN(USDC_CONFIG.maxLeverageBps)
         )
         .accountsStrict({
           authority,
@@ -251,7 +260,7 @@ describe("metlev-engine", () => {
       );
 
       expect(solConfig.maxLtv).to.be.lessThan(usdcConfig.maxLtv);
-      expect(solConfig.liquidationPenalty).to.be.greaterThan(usdcConfig.liquidationPenalty);
+      expect(solConfig.liquidationPenaltyBps).to.be.greaterThan(usdcConfig.liquidationPenaltyBps);
 
       console.log("Risk parameters correctly differentiated:");
       console.log("  SOL LTV:", solConfig.maxLtv / 100, "% vs USDC LTV:", usdcConfig.maxLtv / 100, "%");
@@ -268,12 +277,14 @@ describe("metlev-engine", () => {
         await program.methods
           .registerCollateral(
             solOraclePda,
+            { mock: {} },
             8000,  // max_ltv
             7500,  // liquidation_threshold (INVALID: should be > max_ltv)
             500,
             new anchor.BN(LAMPORTS_PER_SOL),
             500,
-            new anchor.BN(60)
+            new anchor.BN(60),
+            new anchor.BN(50_000)
           )
           .accountsStrict({
             authority,
@@ -483,6 +494,66 @@ describe("metlev-engine", () => {
     });
   });
 
+  describe("Authority Transfer", () => {
+    it("Only the proposed key can accept, and the handover is reversible", async () => {
+      await program.methods
+        .proposeAuthority(user.publicKey)
+        .accountsStrict({ authority, config: configPda })
+        .rpc();
+
+      let config = await program.account.config.fetch(configPda);
+      expect(config.pendingAuthority.toBase58()).to.equal(user.publicKey.toBase58());
+      expect(config.authority.toBase58()).to.equal(authority.toBase58());
+
+      const stranger = Keypair.generate();
+      try {
+        await program.methods
+          .acceptAuthority()
+          .accountsStrict({ pendingAuthority: stranger.publicKey, config: configPda })
+          .signers([stranger])
+          .rpc();
+        assert.fail("Should have failed with Unauthorized");
+      } catch (error) {
+        expect(error.message).to.include("Unauthorized");
+      }
+
+      await program.methods
+        .acceptAuthority()
+        .accountsStrict({ pendingAuthority: user.publicKey, config: configPda })
+        .signers([user])
+        .rpc();
+
+      config = await program.account.config.fetch(configPda);
+      expect(config.authority.toBase58()).to.equal(user.publicKey.toBase58());
+      expect(config.pendingAuthority).to.be.null;
+
+      // Old authority is locked out
+      try {
+        await program.methods
+          .updatePauseState(true)
+          .accountsStrict({ authority, config: configPda })
+          .rpc();
+        assert.fail("Should have failed with Unauthorized");
+      } catch (error) {
+        expect(error.message).to.include("Unauthorized");
+      }
+
+      // Hand it back so the remaining suites keep the provider wallet as admin
+      await program.methods
+        .proposeAuthority(authority)
+        .accountsStrict({ authority: user.publicKey, config: configPda })
+        .signers([user])
+        .rpc();
+      await program.methods
+        .acceptAuthority()
+        .accountsStrict({ pendingAuthority: authority, config: configPda })
+        .rpc();
+
+      config = await program.account.config.fetch(configPda);
+      expect(config.authority.toBase58()).to.equal(authority.toBase58());
+    });
+  });
+
   describe("Update Collateral Config", () => {
     it("Authority can disable a collateral", async () => {
       await program.methods
@@ -573,7 +644,7 @@ describe("metlev-engine", () => {
         .rpc();
 
       const config = await program.account.collateralConfig.fetch(solCollateralConfigPda);
-      expect(config.liquidationPenalty).to.equal(700);
+      expect(config.liquidationPenaltyBps).to.equal(700);
 
       // Restore
       await program.methods
@@ -586,6 +657,51 @@ describe("metlev-engine", () => {
         .rpc();
     });
 
+    it("Liquidator bonus splits the penalty and can't exceed it", async () => {
+      const accounts = {
+        authority,
+        config: configPda,
+        collateralConfig: solCollateralConfigPda,
+      };
+      const before = await program.account.collateralConfig.fetch(solCollateralConfigPda);
+
+      await program.methods
+        .updateCollateralLiquidatorBonus(SOL_MINT, 300)
+        .accountsStrict(accounts)
+        .rpc();
+      try {
+        const config = await program.account.collateralConfig.fetch(solCollateralConfigPda);
+        expect(config.liquidatorBonusBps).to.equal(300);
+        expect(config.liquidationPenaltyBps).to.equal(before.liquidationPenaltyBps);
+
+        try {
+          await program.methods
+            .updateCollateralLiquidatorBonus(SOL_MINT, before.liquidationPenaltyBps + 1)
+            .accountsStrict(accounts)
+            .rpc();
+          assert.fail("Should have failed with InvalidAmount");
+        } catch (error) {
+          expect(error.message).to.include("InvalidAmount");
+        }
+
+        // Nor can the penalty be cut below the bonus already granted
+        try {
+          await program.methods
+            .updateCollateralLiquidationPenalty(SOL_MINT, 299)
+            .accountsStrict(accounts)
+            .rpc();
+          assert.fail("Should have failed with InvalidAmount");
+        } catch (error) {
+          expect(error.message).to.include("InvalidAmount");
+        }
+      } finally {
+        await program.methods
+          .updateCollateralLiquidatorBonus(SOL_MINT, before.liquidatorBonusBps)
+          .accountsStrict(accounts)
+          .rpc();
+      }
+    });
+
     it("Authority can update min deposit", async () => {
       const newMin = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
 
@@ -612,6 +728,186 @@ describe("metlev-engine", () => {
         .rpc();
     });
 
+    it("Authority can update max leverage and rejects zero", async () => {
+      await program.methods
+        .updateCollateralMaxLeverage(USDC_MINT, new anchor.BN(30_000))
+        .accountsStrict({
+          authority,
+          config: configPda,
+          collateralConfig: usdcCollateralConfigPda,
+        })
+        .rpc();
+
+      const config = await program.account.collateralConfig.fetch(usdcCollateralConfigPda);
+      expect(config.maxLeverageBps.toNumber()).to.equal(30_000);
+
+      try {
+        await program.methods
+          .updateCollateralMaxLeverage(USDC_MINT, new anchor.BN(0))
+          .accountsStrict({
+            authority,
+            config: configPda,
+            collateralConfig: usdcCollateralConfigPda,
+          })
+          .rpc();
+        assert.fail("Should have rejected a zero leverage cap");
+      } catch (err) {
+        expect(err.toString()).to.include("InvalidAmount");
+      }
+
+      // Restore
+      await program.methods
+        .updateCollateralMaxLeverage(USDC_MINT, new anchor.BN(USDC_CONFIG.maxLeverageBps))
+        .accountsStrict({
+          authority,
+          config: configPda,
+          collateralConfig: usdcCollateralConfigPda,
+        })
+        .rpc();
+    });
+
+    it("Authority can set separate open and liquidation oracle max ages", async () => {
+      await program.methods
+        .updateCollateralOracleMaxAge(USDC_MINT, new anchor.BN(30), new anchor.BN(120))
+        .accountsStrict({
+          authority,
+          config: configPda,
+          collateralConfig: usdcCollateralConfigPda,
+        })
+        .rpc();
+
+      let config = await program.account.collateralConfig.fetch(usdcCollateralConfigPda);
+      expect(config.openOracleMaxAge.toNumber()).to.equal(30);
+      expect(config.liquidationOracleMaxAge.toNumber()).to.equal(120);
+      expect(config.oracleMaxAge.toNumber()).to.equal(USDC_CONFIG.oracleMaxAge);
+
+      try {
+        await program.methods
+          .updateCollateralOracleMaxAge(USDC_MINT, null, new anchor.BN(0))
+          .accountsStrict({
+            authority,
+            config: configPda,
+            collateralConfig: usdcCollateralConfigPda,
+          })
+          .rpc();
+        assert.fail("Should have rejected a zero max age");
+      } catch (err) {
+        expect(err.toString()).to.include("InvalidAmount");
+      }
+
+      // Restore
+      await program.methods
+        .updateCollateralOracleMaxAge(
+          USDC_MINT,
+          new anchor.BN(USDC_CONFIG.oracleMaxAge),
+          new anchor.BN(USDC_CONFIG.oracleMaxAge)
+        )
+        .accountsStrict({
+          authority,
+          config: configPda,
+          collateralConfig: usdcCollateralConfigPda,
+        })
+        .rpc();
+
+      config = await program.account.collateralConfig.fetch(usdcCollateralConfigPda);
+      expect(config.openOracleMaxAge.toNumber()).to.equal(USDC_CONFIG.oracleMaxAge);
+      expect(config.liquidationOracleMaxAge.toNumber()).to.equal(USDC_CONFIG.oracleMaxAge);
+    });
+
+    it("Authority can point a collateral at a new oracle and read its price", async () => {
+      const [usdcOraclePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), USDC_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initializeMockOracle(new anchor.BN(1_000_000), 6) // $1.00
+        .accountsStrict({
+          authority,
+          config: configPda,
+          mint: USDC_MINT,
+          mockOracle: usdcOraclePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await program.methods
+        .updateCollateralOracle(USDC_MINT, usdcOraclePda)
+        .accountsStrict({
+          authority,
+          config: configPda,
+          collateralConfig: usdcCollateralConfigPda,
+        })
+        .rpc();
+
+      const config = await program.account.collateralConfig.fetch(usdcCollateralConfigPda);
+      expect(config.oracle.toBase58()).to.equal(usdcOraclePda.toBase58());
+
+      const oracle = await program.account.mockOracle.fetch(config.oracle);
+      expect(oracle.price.toNumber()).to.equal(1_000_000);
+    });
+
+    it("Rejects the default pubkey as an oracle", async () => {
+      try {
+        await program.methods
+          .updateCollateralOracle(USDC_MINT, PublicKey.default)
+          .accountsStrict({
+            authority,
+            config: configPda,
+            collateralConfig: usdcCollateralConfigPda,
+          })
+          .rpc();
+
+        assert.fail("Should have failed with OraclePriceUnavailable");
+      } catch (error) {
+        expect(error.message).to.include("OraclePriceUnavailable");
+      }
+    });
+
+    it("Authority can set backup oracles, but not the primary or a duplicate", async () => {
+      const accounts = { authority, config: configPda, collateralConfig: usdcCollateralConfigPda };
+      const { oracle } = await program.account.collateralConfig.fetch(usdcCollateralConfigPda);
+      const backup = Keypair.generate().publicKey;
+
+      try {
+        await program.methods
+          .updateCollateralBackupOracles(USDC_MINT, [backup, PublicKey.default])
+          .accountsStrict(accounts)
+          .rpc();
+        let config = await program.account.collateralConfig.fetch(usdcCollateralConfigPda);
+        expect(config.backupOracles[0].toBase58()).to.equal(backup.toBase58());
+        expect(config.backupOracles[1].toBase58()).to.equal(PublicKey.default.toBase58());
+
+        for (const invalid of [[oracle, PublicKey.default], [backup, backup]]) {
+          try {
+            await program.methods
+              .updateCollateralBackupOracles(USDC_MINT, invalid)
+              .accountsStrict(accounts)
+              .rpc();
+            assert.fail("Should have failed with OraclePriceUnavailable");
+          } catch (error) {
+            expect(error.message).to.include("OraclePriceUnavailable");
+          }
+        }
+
+        // A configured backup can't be promoted while it's still a backup
+        try {
+          await program.methods
+            .updateCollateralOracle(USDC_MINT, backup)
+            .accountsStrict(accounts)
+            .rpc();
+          assert.fail("Should have failed with OraclePriceUnavailable");
+        } catch (error) {
+          expect(error.message).to.include("OraclePriceUnavailable");
+        }
+      } finally {
+        await program.methods
+          .updateCollateralBackupOracles(USDC_MINT, [PublicKey.default, PublicKey.default])
+          .accountsStrict(accounts)
+          .rpc();
+      }
+    });
+
     it("Fails when LTV params violate threshold invariant", async () => {
       try {
         // max_ltv (8500) >= liquidation_threshold (8000) — invalid
@@ -630,6 +926,37 @@ describe("metlev-engine", () => {
       }
     });
 
+    it("Bounds the liquidation threshold at exactly 100%", async () => {
+      const accounts = {
+        authority,
+        config: configPda,
+        collateralConfig: solCollateralConfigPda,
+      };
+
+      try {
+        await program.methods
+          .updateCollateralLtvParams(SOL_MINT, null, 10_001)
+          .accountsStrict(accounts)
+          .rpc();
+
+        assert.fail("Should have failed with InvalidLiquidationThreshold");
+      } catch (error) {
+        expect(error.message).to.include("InvalidLiquidationThreshold");
+      }
+
+      await program.methods
+        .updateCollateralLtvParams(SOL_MINT, null, 10_000)
+        .accountsStrict(accounts)
+        .rpc();
+      const config = await program.account.collateralConfig.fetch(solCollateralConfigPda);
+      expect(config.liquidationThreshold).to.equal(10_000);
+
+      await program.methods
+        .updateCollateralLtvParams(SOL_MINT, null, SOL_CONFIG.liquidationThreshold)
+        .accountsStrict(accounts)
+        .rpc();
+    });
+
     it("Fails when liquidation penalty exceeds 20%", async () => {
       try {
         await program.methods
@@ -52,7 +52,7 @@ describe("Mock Oracle", () => {
       } catch {}
 
       await program.methods
-        .initializeMockOracle(SOL_PRICE_USD)
+        .initializeMockOracle(SOL_PRICE_USD, 6)
         .accountsStrict({
           authority,
           config: configPda,
@@ -90,7 +90,7 @@ describe("Mock Oracle", () => {
 
       try {
         await program.methods
-          .initializeMockOracle(SOL_PRICE_USD)
+          .initializeMockOracle(SOL_PRICE_USD, 6)
           .accountsStrict({
             authority: attacker.publicKey,
             config: configPda,
@@ -106,6 +106,36 @@ describe("Mock Oracle", () => {
         expect(error.message).to.include("Unauthorized");
       }
     });
+    it("Stores the decimals a feed is quoted in, up to 18", async () => {
+      const mint = anchor.web3.Keypair.generate().publicKey;
+      const [oraclePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), mint.toBuffer()],
+        program.programId
+      );
+      const init = (decimals: number) =>
+        program.methods
+          .initializeMockOracle(new anchor.BN(15_000_000_000), decimals) // $150 at 8 decimals
+          .accountsStrict({
+            authority,
+            config: configPda,
+            mint,
+            mockOracle: oraclePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+      try {
+        await init(19);
+        assert.fail("Should have failed with InvalidAmount");
+      } catch (error) {
+        expect(error.message).to.include("InvalidAmount");
+      }
+
+      await init(8);
+      const oracle = await program.account.mockOracle.fetch(oraclePda);
+      expect(oracle.decimals).to.equal(8);
+      expect(oracle.price.toString()).to.equal("15000000000");
+    });
   });
 
   describe("Update Mock Oracle", () => {
@@ -116,7 +146,6 @@ describe("Mock Oracle", () => {
         .updateMockOracle(newPrice)
         .accountsStrict({
           authority,
-          config: configPda,
           mint: SOL_MINT,
           mockOracle: solOraclePda,
         })
@@ -137,7 +166,6 @@ describe("Mock Oracle", () => {
         .updateMockOracle(new anchor.BN(180_000_000))
         .accountsStrict({
           authority,
-          config: configPda,
           mint: SOL_MINT,
           mockOracle: solOraclePda,
         })
@@ -162,7 +190,6 @@ describe("Mock Oracle", () => {
           .updateMockOracle(new anchor.BN(1_000_000)) // $1.00 — crash the price
           .accountsStrict({
             authority: attacker.publicKey,
-            config: configPda,
             mint: SOL_MINT,
             mockOracle: solOraclePda,
           })
@@ -175,12 +202,56 @@ describe("Mock Oracle", () => {
       }
     });
 
+    it("Oracle authority can hand the feed to another keypair", async () => {
+      const feedOwner = anchor.web3.Keypair.generate();
+      const airdropSig = await provider.connection.requestAirdrop(
+        feedOwner.publicKey,
+        LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSig);
+
+      const accounts = { authority, mint: SOL_MINT, mockOracle: solOraclePda };
+      await program.methods
+        .transferOracleAuthority(feedOwner.publicKey)
+        .accountsStrict(accounts)
+        .rpc();
+
+      try {
+        const oracle = await program.account.mockOracle.fetch(solOraclePda);
+        expect(oracle.authority.toBase58()).to.equal(feedOwner.publicKey.toBase58());
+
+        // The protocol authority no longer controls this feed
+        try {
+          await program.methods
+            .updateMockOracle(new anchor.BN(1_000_000))
+            .accountsStrict(accounts)
+            .rpc();
+          assert.fail("Should have failed with Unauthorized");
+        } catch (error) {
+          expect(error.message).to.include("Unauthorized");
+        }
+
+        await program.methods
+          .updateMockOracle(new anchor.BN(160_000_000))
+          .accountsStrict({ ...accounts, authority: feedOwner.publicKey })
+          .signers([feedOwner])
+          .rpc();
+        const updated = await program.account.mockOracle.fetch(solOraclePda);
+        expect(updated.price.toNumber()).to.equal(160_000_000);
+      } finally {
+        await program.methods
+          .transferOracleAuthority(authority)
+          .accountsStrict({ ...accounts, authority: feedOwner.publicKey })
+          .signers([feedOwner])
+          .rpc();
+      }
+    });
+
     it("Restores SOL price to $150 for subsequent tests", async () => {
       await program.methods
         .updateMockOracle(SOL_PRICE_USD)
         .accountsStrict({
           authority,
-          config: configPda,
           mint: SOL_MINT,
           mockOracle: solOraclePda,
         })
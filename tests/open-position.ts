@@ -54,6 +54,17 @@ function deriveEventAuthority(): PublicKey {
   return pda;
 }
 
+// Interest one accrual books on all of the vault's outstanding borrows,
+// rounded up like the program does
+function bookedInterest(
+  before: { totalBorrowed: BN; borrowIndex: BN },
+  after: { borrowIndex: BN }
+): BN {
+  const growth = after.borrowIndex.sub(before.borrowIndex);
+  if (growth.isZero()) return new BN(0);
+  return before.totalBorrowed.mul(growth).add(before.borrowIndex).subn(1).div(before.borrowIndex);
+}
+
 describe("Open Position", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -157,6 +168,12 @@ describe("Open Position", () => {
       console.log("  Protocol config initialised.");
     }
 
+    // No-op if the pair is already whitelisted
+    await program.methods
+      .whitelistPair(LB_PAIR)
+      .accountsStrict({ authority, config: configPda })
+      .rpc();
+
     try {
       await program.account.lendingVault.fetch(lendingVaultPda);
       console.log("  Lending vault already initialised, skipping.");
@@ -185,6 +202,7 @@ describe("Open Position", () => {
         .supply(supplyAmount)
         .accountsStrict({
           signer: lp.publicKey,
+          config: configPda,
           lendingVault: lendingVaultPda,
           wsolMint: NATIVE_MINT,
           wsolVault: wsolVaultPda,
@@ -256,7 +274,7 @@ describe("Open Position", () => {
     console.log("  Pool (lb_pair)    : ", LB_PAIR.toBase58());
   });
 
-  async function buildOpenPositionAccounts(positionKeypair: Keypair) {
+  async function buildOpenPositionAccounts(positionKeypair: Keypair, owner: Keypair = user) {
     await dlmmPool.refetchStates();
     const activeBin = await dlmmPool.getActiveBin();
     const activeBinId = activeBin.binId;
@@ -320,6 +338,11 @@ describe("Open Position", () => {
       program.programId
     );
 
+    const [ownerPosition] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), owner.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+      program.programId
+    );
+
     return {
       params: {
         leverage: new BN(20_000), // 2× leverage
@@ -330,15 +353,16 @@ describe("Open Position", () => {
         binLiquidityDist,
       },
       accounts: {
-        user: user.publicKey,
+        user: owner.publicKey,
         config: configPda,
         wsolMint: NATIVE_MINT,
-        position: positionPda,
+        position: ownerPosition,
         lendingVault: lendingVaultPda,
         wsolVault: wsolVaultPda,
         collateralConfig: collateralConfigPda,
         priceOracle,
         metPosition: positionKeypair.publicKey,
+        rentReserve: null,
         lbPair: LB_PAIR,
         binArrayBitmapExtension: null,
         reserve,
@@ -388,7 +412,6 @@ describe("Open Position", () => {
         .updateMockOracle(new BN(150_000_000))
         .accountsStrict({
           authority,
-          config: configPda,
           mint: NATIVE_MINT,
           mockOracle: mockOraclePda,
         })
@@ -401,7 +424,9 @@ describe("Open Position", () => {
           params.width,
           params.activeId,
           params.maxActiveBinSlippage,
-          params.binLiquidityDist
+          params.binLiquidityDist,
+          false, // subsidizeRent
+          null // strategyTag
         )
         .accountsStrict(accounts)
         .signers([user, metPositionKp])
@@ -429,10 +454,45 @@ describe("Open Position", () => {
 
       const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
       expect(vaultAfter.totalBorrowed.toString()).to.equal(
-        vaultBefore.totalBorrowed.add(expectedBorrow).toString(),
+        vaultBefore.totalBorrowed
+          .add(bookedInterest(vaultBefore, vaultAfter))
+          .add(expectedBorrow)
+          .toString(),
         "totalBorrowed mismatch"
       );
 
+      // Debt is in wSOL lamports like the SOL collateral, so at 2x the
+      // LTV is debt / (collateral + debt) = 2 / 3 whatever the price
+      const health = await program.methods
+        .getPositionHealth()
+        .accountsStrict({
+          position: positionPda,
+          collateralConfig: collateralConfigPda,
+          lendingVault: lendingVaultPda,
+          priceOracle: mockOraclePda,
+        })
+        .view();
+      // $150 with 6 decimals: value = lamports / 1000 * 150
+      expect(health.collateralValue.toString()).to.equal(
+        positionState.collateralAmount.divn(1_000).muln(150).toString(),
+        "collateral value mismatch"
+      );
+      expect(health.ltv.toNumber()).to.equal(6666, "LTV mismatch");
+
+      // The same outcome comes back as open_position's return data
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const [returnData] = txInfo!.meta!.returnData!.data;
+      const result = program.coder.types.decode(
+        "OpenPositionResult",
+        Buffer.from(returnData, "base64")
+      );
+      expect(result.debtAmount.toString()).to.equal(positionState.debtAmount.toString());
+      expect(result.ltvBps.toNumber()).to.equal(6666);
+      expect(result.dlmmPosition.toBase58()).to.equal(metPositionKp.publicKey.toBase58());
+
       const wsolAfter = await provider.connection.getTokenAccountBalance(wsolVaultPda);
       const delta = Number(wsolBefore.value.amount) - Number(wsolAfter.value.amount);
       expect(delta).to.equal(
@@ -480,9 +540,155 @@ describe("Open Position", () => {
       expect(totalLiquidity).to.be.greaterThan(0, "Position has no liquidity");
       console.log("\n  ✓ Total position liquidity:", totalLiquidity);
     });
+
+    it("Rejects opening a position that is already open", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp);
+      const before = await program.account.position.fetch(positionPda);
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("PositionAlreadyOpen");
+      }
+
+      const after = await program.account.position.fetch(positionPda);
+      expect(after.debtAmount.toString()).to.equal(before.debtAmount.toString());
+      expect(after.meteoraPosition.toBase58()).to.equal(before.meteoraPosition.toBase58());
+      console.log("  ✓ Second open on an open position rejected");
+    });
+
+    it("Records only the wSOL the pool actually consumed as debt", async () => {
+      // Odd amounts and uneven weights make DLMM round per-bin deposits down
+      const borrower = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(borrower.publicKey, 2 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      const [borrowerPosition] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), borrower.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [borrowerVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), borrower.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .depositSolCollateral(new BN(LAMPORTS_PER_SOL + 7))
+        .accountsStrict({
+          user: borrower.publicKey,
+          config: configPda,
+          mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda,
+          vault: borrowerVault,
+          position: borrowerPosition,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([borrower])
+        .rpc();
+
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp);
+      const binLiquidityDist = params.binLiquidityDist.map((b, i) => ({
+        binId: b.binId,
+        weight: 3 + 7 * i,
+      }));
+      const leverage = new BN(15_000);
+      const requested = new BN(LAMPORTS_PER_SOL + 7).mul(leverage).divn(10_000);
+
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolBefore = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: accounts.priceOracle })
+        .rpc();
+      await program.methods
+        .openPosition(
+          leverage,
+          params.lowerBinId,
+          params.width,
+          params.activeId,
+          params.maxActiveBinSlippage,
+          binLiquidityDist,
+          false, // subsidizeRent
+          null // strategyTag
+        )
+        .accountsStrict({ ...accounts, user: borrower.publicKey, position: borrowerPosition })
+        .signers([borrower, metPositionKp])
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+        ])
+        .rpc();
+
+      const wsolAfter = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+      const consumed = Number(wsolBefore.value.amount) - Number(wsolAfter.value.amount);
+      const positionState = await program.account.position.fetch(borrowerPosition);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+
+      expect(consumed).to.be.at.most(requested.toNumber());
+      expect(positionState.debtAmount.toNumber()).to.equal(
+        consumed,
+        "Debt must match what left the wSOL vault"
+      );
+      const booked = bookedInterest(vaultBefore, vaultAfter);
+      expect(vaultAfter.totalBorrowed.sub(vaultBefore.totalBorrowed).sub(booked).toNumber()).to.equal(
+        consumed,
+        "totalBorrowed must grow by what the pool consumed"
+      );
+      console.log("  ✓ Requested:", requested.toString(), "consumed:", consumed);
+    });
   });
 
   describe("openPosition — constraints", () => {
+    // The outer user's position is already open and can't be opened again,
+    // so these run against a fresh position with collateral and no debt
+    const user = Keypair.generate();
+    const [positionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+      program.programId
+    );
+
+    before(async () => {
+      const sig = await provider.connection.requestAirdrop(user.publicKey, 3 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .depositSolCollateral(new BN(2 * LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: user.publicKey,
+          config: configPda,
+          mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda,
+          vault: collateralVaultPda,
+          position: positionPda,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    });
+
     it("Rejects when protocol is paused", async () => {
       await program.methods
         .updatePauseState(true)
@@ -490,7 +696,7 @@ describe("Open Position", () => {
         .rpc();
 
       const metPositionKp = Keypair.generate();
-      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp);
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
 
       try {
         await program.methods
@@ -500,7 +706,9 @@ describe("Open Position", () => {
             params.width,
             params.activeId,
             params.maxActiveBinSlippage,
-            params.binLiquidityDist
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
           )
           .accountsStrict(accounts)
           .signers([user, metPositionKp])
@@ -522,17 +730,19 @@ describe("Open Position", () => {
 
     it("Rejects when LTV exceeds maximum", async () => {
       const metPositionKp = Keypair.generate();
-      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp);
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
 
       try {
         await program.methods
           .openPosition(
-            new BN(500_000), // 50× leverage → LTV >> max
+            new BN(40_000), // 4× leverage → LTV 80% > max 75%
             params.lowerBinId,
             params.width,
             params.activeId,
             params.maxActiveBinSlippage,
-            params.binLiquidityDist
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
           )
           .accountsStrict(accounts)
           .signers([user, metPositionKp])
@@ -549,19 +759,22 @@ describe("Open Position", () => {
       }
     });
 
-    it("Rejects when vault has insufficient liquidity", async () => {
+    it("Rejects leverage above the collateral's max_leverage_bps", async () => {
       const metPositionKp = Keypair.generate();
-      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp);
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const config = await program.account.collateralConfig.fetch(collateralConfigPda);
 
       try {
         await program.methods
           .openPosition(
-            new BN(10_000_000), // leverage so large borrow >> vault balance
+            config.maxLeverageBps.addn(1),
             params.lowerBinId,
             params.width,
             params.activeId,
             params.maxActiveBinSlippage,
-            params.binLiquidityDist
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
           )
           .accountsStrict(accounts)
           .signers([user, metPositionKp])
@@ -571,24 +784,120 @@ describe("Open Position", () => {
           .rpc();
         throw new Error("Should have failed");
       } catch (e) {
-        expect((e as Error).message).to.match(
-          /InsufficientLiquidity|insufficient|ExceedsMaxLTV/i
-        );
-        console.log("  ✓ Correctly rejected due to insufficient vault liquidity");
+        expect((e as Error).message).to.include("ExceedsMaxLeverage");
+        console.log("  ✓ Correctly rejected leverage above the cap");
       }
     });
 
-    it("Rejects when the wrong user tries to open against someone else's position", async () => {
-      const rogue = Keypair.generate();
-      const sig = await provider.connection.requestAirdrop(
-        rogue.publicKey,
-        3 * LAMPORTS_PER_SOL
-      );
-      await provider.connection.confirmTransaction(sig);
+    it("Rejects an LB pair that is not on the whitelist", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+
+      await program.methods
+        .removeWhitelistedPair(LB_PAIR)
+        .accountsStrict({ authority, config: configPda })
+        .rpc();
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("PairNotWhitelisted");
+        console.log("  ✓ Correctly rejected an unlisted LB pair");
+      } finally {
+        await program.methods
+          .whitelistPair(LB_PAIR)
+          .accountsStrict({ authority, config: configPda })
+          .rpc();
+      }
+    });
 
+    it("Rejects a liquidity distribution bin outside the position range", async () => {
       const metPositionKp = Keypair.generate();
+      const { params, accounts, meta } = await buildOpenPositionAccounts(metPositionKp, user);
+      const binLiquidityDist = [
+        ...params.binLiquidityDist,
+        { binId: meta.maxBinId + 1, weight: 1000 },
+      ];
 
-      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp);
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("BinOutOfRange");
+        console.log("  ✓ Correctly rejected a bin outside the position");
+      }
+    });
+
+    it("Rejects a liquidity distribution bin on the wrong side of the active bin", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts, meta } = await buildOpenPositionAccounts(metPositionKp, user);
+      // wSOL as X belongs strictly above the active bin, as Y at or below it
+      const wrongBin = meta.isWsolX ? meta.activeBinId : meta.activeBinId + 1;
+      const lowerBinId = Math.min(params.lowerBinId, wrongBin);
+      const upperBinId = Math.max(meta.maxBinId, wrongBin);
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            lowerBinId,
+            upperBinId - lowerBinId + 1,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            [...params.binLiquidityDist, { binId: wrongBin, weight: 1000 }],
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("BinWrongSide");
+        console.log("  ✓ Correctly rejected a bin on the wrong side of the active bin");
+      }
+    });
+
+    it("Rejects a liquidity distribution whose weights are all zero", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const binLiquidityDist = params.binLiquidityDist.map((b) => ({ ...b, weight: 0 }));
 
       try {
         await program.methods
@@ -598,20 +907,954 @@ describe("Open Position", () => {
             params.width,
             params.activeId,
             params.maxActiveBinSlippage,
-            params.binLiquidityDist
+            binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
           )
-          .accountsStrict({ ...accounts, user: rogue.publicKey })
-          .signers([rogue, metPositionKp])
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
           .preInstructions([
             ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
           ])
           .rpc();
         throw new Error("Should have failed");
       } catch (e) {
-        expect((e as Error).message).to.match(
-          /InvalidOwner|seeds|constraint|AccountNotFound|2006/i
-        );
-        console.log("  ✓ Correctly rejected unauthorized access to position");
+        expect((e as Error).message).to.include("InvalidAmount");
+        console.log("  ✓ Correctly rejected an all-zero-weight distribution");
+      }
+    });
+
+    it("Rolls back the borrow when the add-liquidity CPI fails", async () => {
+      const borrower = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(borrower.publicKey, 2 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+
+      const [borrowerPosition] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), borrower.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [borrowerVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), borrower.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .depositSolCollateral(new BN(LAMPORTS_PER_SOL))
+        .accountsStrict({
+          user: borrower.publicKey,
+          config: configPda,
+          mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda,
+          vault: borrowerVault,
+          position: borrowerPosition,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([borrower])
+        .rpc();
+
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      // Bin arrays far from the position's range: every protocol check passes
+      // and the DLMM position is created, but add_liquidity_one_side can't
+      // find the bins and fails after the borrow has been booked
+      const farIdx = binArrayIndex(params.lowerBinId).addn(50);
+      const badAccounts = {
+        ...accounts,
+        user: borrower.publicKey,
+        position: borrowerPosition,
+        binArrayLower: deriveBinArrayPda(LB_PAIR, farIdx),
+        binArrayUpper: deriveBinArrayPda(LB_PAIR, farIdx.addn(1)),
+      };
+
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: accounts.priceOracle })
+        .rpc();
+
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolBefore = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+
+      let failed = false;
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(badAccounts)
+          .signers([borrower, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+      } catch (e) {
+        failed = true;
+      }
+      expect(failed).to.equal(true, "The open must fail inside the add-liquidity CPI");
+
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolAfter = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+      const positionState = await program.account.position.fetch(borrowerPosition);
+
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(
+        vaultBefore.totalBorrowed.toString(),
+        "totalBorrowed must be unchanged"
+      );
+      expect(positionState.debtAmount.toNumber()).to.equal(0, "No debt may be recorded");
+      expect(positionState.meteoraPosition.equals(PublicKey.default)).to.equal(true);
+      expect(wsolAfter.value.amount).to.equal(wsolBefore.value.amount);
+      expect(await provider.connection.getAccountInfo(metPositionKp.publicKey)).to.equal(
+        null,
+        "The DLMM position created before the failure must be rolled back too"
+      );
+      console.log("  ✓ Failed add-liquidity CPI left the vault and position untouched");
+    });
+
+    it("Rejects a position wider than the per-position bin limit", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const MAX_BINS_PER_POSITION = 70;
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            MAX_BINS_PER_POSITION + 1,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("TooManyBins");
+        console.log("  ✓ Correctly rejected a 71-bin position");
+      }
+    });
+
+    it("Rejects a swap pair for collateral that is already wSOL", async () => {
+      try {
+        await program.methods
+          .updateCollateralSwapPair(NATIVE_MINT)
+          .accountsStrict({
+            authority,
+            config: configPda,
+            collateralConfig: collateralConfigPda,
+            lbPair: LB_PAIR,
+          })
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InvalidSwapPair");
+      }
+      const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+      expect(config.swapLbPair.equals(PublicKey.default)).to.be.true;
+    });
+
+    it("Rejects an open right after a sudden 50% oracle price jump", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const oracleAccounts = {
+        authority,
+        mint: NATIVE_MINT,
+        mockOracle: accounts.priceOracle,
+      };
+
+      // The happy-path open recorded $150 as the reference price
+      const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+      expect(config.lastPrice.toNumber()).to.equal(150_000_000);
+
+      await program.methods
+        .updateMockOracle(new BN(225_000_000))
+        .accountsStrict(oracleAccounts)
+        .rpc();
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("PriceDeviationTooHigh");
+        console.log("  ✓ Correctly rejected an open after a 50% price jump");
+      } finally {
+        await program.methods
+          .updateMockOracle(new BN(150_000_000))
+          .accountsStrict(oracleAccounts)
+          .rpc();
+      }
+    });
+
+    it("Rejects an open against a mock feed forced stale", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const oracleAccounts = {
+        authority,
+        mint: NATIVE_MINT,
+        mockOracle: accounts.priceOracle,
+      };
+
+      await program.methods
+        .setMockOracleStaleness(new BN(0), true)
+        .accountsStrict(oracleAccounts)
+        .rpc();
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("OracleStale");
+        console.log("  ✓ Correctly rejected an open on a stale feed");
+      } finally {
+        await program.methods
+          .setMockOracleStaleness(new BN(0), false)
+          .accountsStrict(oracleAccounts)
+          .rpc();
+      }
+    });
+
+    it("Rejects an open whose health factor only reaches min_open_health_bps", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+
+      // HF = (collateral + debt) * threshold / debt; at 2× that's 3/2 * threshold
+      const healthAt2x = new BN(config.liquidationThreshold).muln(3).divn(2);
+
+      await program.methods
+        .updateCollateralMinOpenHealth(NATIVE_MINT, healthAt2x)
+        .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+        .rpc();
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("OpenHealthTooLow");
+        console.log("  ✓ Correctly rejected an open at the minimum health factor");
+      } finally {
+        await program.methods
+          .updateCollateralMinOpenHealth(NATIVE_MINT, config.minOpenHealthBps)
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+      }
+    });
+
+    it("Rejects a borrow one lamport below min_position_borrow", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const position = await program.account.position.fetch(positionPda);
+      const borrow = position.collateralAmount.mul(params.leverage).divn(10_000);
+
+      await program.methods
+        .updateCollateralMinPositionBorrow(NATIVE_MINT, borrow.addn(1))
+        .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+        .rpc();
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InvalidAmount");
+        console.log("  ✓ Correctly rejected a dust-sized position");
+      } finally {
+        await program.methods
+          .updateCollateralMinPositionBorrow(NATIVE_MINT, new BN(0))
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+      }
+    });
+
+    it("Rejects a borrow taking the position's debt one lamport over max_position_debt", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      const position = await program.account.position.fetch(positionPda);
+      const borrow = position.collateralAmount.mul(params.leverage).divn(10_000);
+
+      await program.methods
+        .updateCollateralMaxPositionDebt(NATIVE_MINT, position.debtAmount.add(borrow).subn(1))
+        .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+        .rpc();
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("PositionDebtCapExceeded");
+        console.log("  ✓ Correctly rejected a borrow over the per-position debt cap");
+      } finally {
+        await program.methods
+          .updateCollateralMaxPositionDebt(NATIVE_MINT, new BN("18446744073709551615"))
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+      }
+    });
+
+    it("Rejects when vault has insufficient liquidity", async () => {
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+
+      try {
+        await program.methods
+          .openPosition(
+            new BN(10_000_000), // leverage so large borrow >> vault balance
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.match(
+          /InsufficientLiquidity|insufficient|ExceedsMaxLTV|ExceedsMaxLeverage/i
+        );
+        console.log("  ✓ Correctly rejected due to insufficient vault liquidity");
+      }
+    });
+
+    it("Rejects a borrow that would dip into the vault's withdrawal reserve", async () => {
+      const vault = await program.account.lendingVault.fetch(lendingVaultPda);
+      // Reserve everything that is idle beyond a lamport, so any real borrow breaches it
+      const idle = vault.totalSupplied.sub(vault.totalBorrowed);
+      const reserveBps = Math.min(
+        10_000,
+        Math.ceil((idle.toNumber() - 1) * 10_000 / vault.totalSupplied.toNumber())
+      );
+      await program.methods
+        .updateMinVaultReserve(reserveBps)
+        .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+        .rpc();
+
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict(accounts)
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InsufficientLiquidity");
+        console.log("  ✓ Correctly rejected a borrow breaching the vault reserve");
+      } finally {
+        await program.methods
+          .updateMinVaultReserve(0)
+          .accountsStrict({ authority, config: configPda, lendingVault: lendingVaultPda })
+          .rpc();
+      }
+    });
+
+    it("Rejects when the wrong user tries to open against someone else's position", async () => {
+      const rogue = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(
+        rogue.publicKey,
+        3 * LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(sig);
+
+      const metPositionKp = Keypair.generate();
+
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict({ ...accounts, user: rogue.publicKey })
+          .signers([rogue, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.match(
+          /InvalidOwner|seeds|constraint|AccountNotFound|2006/i
+        );
+        console.log("  ✓ Correctly rejected unauthorized access to position");
+      }
+    });
+
+    it("Only the lending vault created at init can back an open", async () => {
+      // The single canonical vault: ["lending_vault"], initialized once
+      const [canonical] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lending_vault")],
+        program.programId
+      );
+      const vault = await program.account.lendingVault.fetch(canonical);
+      expect(vault.borrowMint.toBase58()).to.equal(NATIVE_MINT.toBase58());
+
+      const metPositionKp = Keypair.generate();
+      const { params, accounts } = await buildOpenPositionAccounts(metPositionKp, user);
+      expect(accounts.lendingVault.toBase58()).to.equal(canonical.toBase58());
+
+      // Any other program-owned account in its place is rejected
+      try {
+        await program.methods
+          .openPosition(
+            params.leverage,
+            params.lowerBinId,
+            params.width,
+            params.activeId,
+            params.maxActiveBinSlippage,
+            params.binLiquidityDist,
+            false, // subsidizeRent
+            null // strategyTag
+          )
+          .accountsStrict({ ...accounts, lendingVault: collateralConfigPda })
+          .signers([user, metPositionKp])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+          ])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.match(/AccountDiscriminatorMismatch|ConstraintSeeds|3002|2006/);
+      }
+    });
+  });
+  describe("previewBorrow", () => {
+    it("Previews an open without sending it, and reports why one would fail", async () => {
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const accounts = {
+        config: configPda,
+        lendingVault: lendingVaultPda,
+        collateralConfig: collateralConfigPda,
+        priceOracle,
+      };
+      const collateral = new BN(LAMPORTS_PER_SOL / 100);
+
+      const ok = await program.methods
+        .previewBorrow(collateral, new BN(20_000))
+        .accountsStrict(accounts)
+        .view();
+      const vault = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(ok.ok).to.be.true;
+      expect(ok.errorCode).to.equal(0);
+      expect(ok.borrowAmount.toString()).to.equal(collateral.muln(2).toString());
+      expect(ok.availableLiquidity.toString()).to.equal(
+        vault.totalSupplied.sub(vault.totalBorrowed).toString()
+      );
+      // 2x → debt / (collateral + debt) = 2/3
+      expect(ok.ltvBps.toNumber()).to.be.closeTo(6_666, 1);
+
+      const tooHigh = await program.methods
+        .previewBorrow(collateral, new BN(40_000)) // 4× → LTV 80% > max 75%
+        .accountsStrict(accounts)
+        .view();
+      const exceedsMaxLtv = program.idl.errors.find(
+        (e) => e.name.toLowerCase() === "exceedsmaxltv"
+      );
+      expect(tooHigh.ok).to.be.false;
+      expect(tooHigh.errorCode).to.equal(exceedsMaxLtv.code);
+      expect(tooHigh.ltvBps.toNumber()).to.equal(0);
+
+      // Nothing was borrowed by either preview
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      expect(vaultAfter.totalBorrowed.toString()).to.equal(vault.totalBorrowed.toString());
+    });
+  });
+
+  describe("addCollateral", () => {
+    it("Tops up collateral on the open position and lowers its LTV", async () => {
+      const [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({
+          authority,
+          mint: NATIVE_MINT,
+          mockOracle: priceOracle,
+        })
+        .rpc();
+
+      const before = await program.account.position.fetch(positionPda);
+      const vaultBalanceBefore = await provider.connection.getBalance(collateralVaultPda);
+      const topUp = new BN(LAMPORTS_PER_SOL);
+
+      const tx = await program.methods
+        .addCollateral(topUp)
+        .accountsStrict({
+          user: user.publicKey,
+          mint: NATIVE_MINT,
+          collateralConfig: collateralConfigPda,
+          position: positionPda,
+          lendingVault: lendingVaultPda,
+          vault: collateralVaultPda,
+          userTokenAccount: null,
+          priceOracle,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+
+      const after = await program.account.position.fetch(positionPda);
+      expect(after.collateralAmount.toString()).to.equal(
+        before.collateralAmount.add(topUp).toString()
+      );
+      expect(after.debtAmount.gte(before.debtAmount)).to.be.true;
+
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const events = new anchor.EventParser(program.programId, program.coder)
+        .parseLogs(txInfo!.meta!.logMessages!);
+      const added = [...events].find((e) => e.name === "collateralAdded")!;
+      expect(added.data.amount.toString()).to.equal(topUp.toString());
+      expect(added.data.collateralAmount.toString()).to.equal(after.collateralAmount.toString());
+      expect(added.data.ltvAfter.toNumber()).to.be.lessThan(added.data.ltvBefore.toNumber());
+
+      const vaultBalanceAfter = await provider.connection.getBalance(collateralVaultPda);
+      expect(vaultBalanceAfter - vaultBalanceBefore).to.equal(topUp.toNumber());
+
+      // LTV = debt / (collateral + debt)
+      const ltv = (p: typeof before) =>
+        p.debtAmount.muln(10_000).div(p.collateralAmount.add(p.debtAmount)).toNumber();
+      expect(ltv(after)).to.be.lessThan(ltv(before));
+
+      console.log(`  ✓ LTV ${ltv(before)} → ${ltv(after)} bps`);
+    });
+
+    it("Rejects a top-up from someone other than the position owner", async () => {
+      const rogue = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(
+        rogue.publicKey,
+        2 * LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(sig);
+
+      const [rogueVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), rogue.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .addCollateral(new BN(LAMPORTS_PER_SOL))
+          .accountsStrict({
+            user: rogue.publicKey,
+            mint: NATIVE_MINT,
+            collateralConfig: collateralConfigPda,
+            position: positionPda,
+            lendingVault: lendingVaultPda,
+            vault: rogueVaultPda,
+            userTokenAccount: null,
+            priceOracle,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([rogue])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.match(/InvalidOwner|seeds|ConstraintSeeds|2006/i);
+        console.log("  ✓ Correctly rejected top-up on someone else's position");
+      }
+    });
+  });
+
+  describe("withdrawExcessCollateral", () => {
+    let collateralVaultPda: PublicKey;
+    let priceOracle: PublicKey;
+    let accounts: Record<string, PublicKey>;
+
+    before(async () => {
+      [collateralVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), user.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+      accounts = {
+        user: user.publicKey,
+        wsolMint: NATIVE_MINT,
+        collateralConfig: collateralConfigPda,
+        position: positionPda,
+        lendingVault: lendingVaultPda,
+        collateralVault: collateralVaultPda,
+        priceOracle,
+        systemProgram: SystemProgram.programId,
+      };
+
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({ authority, mint: NATIVE_MINT, mockOracle: priceOracle })
+        .rpc();
+    });
+
+    // LTV = debt / (collateral + debt)
+    const ltvAfter = (collateral: BN, debt: BN) =>
+      debt.muln(10_000).div(collateral.add(debt)).toNumber();
+
+    it("Withdraws collateral the open position doesn't need", async () => {
+      const before = await program.account.position.fetch(positionPda);
+      const { maxLtv } = await program.account.collateralConfig.fetch(collateralConfigPda);
+
+      // A tenth of what the position could give up before reaching max_ltv
+      const minCollateral = before.debtAmount.muln(10_000 - maxLtv).divn(maxLtv);
+      const amount = before.collateralAmount.sub(minCollateral).divn(10);
+      expect(amount.toNumber()).to.be.greaterThan(0, "Position must hold excess collateral");
+
+      const vaultBalanceBefore = await provider.connection.getBalance(collateralVaultPda);
+
+      await program.methods
+        .withdrawExcessCollateral(amount)
+        .accountsStrict(accounts)
+        .signers([user])
+        .rpc();
+
+      const after = await program.account.position.fetch(positionPda);
+      expect(after.collateralAmount.toString()).to.equal(
+        before.collateralAmount.sub(amount).toString()
+      );
+      expect(after.status).to.deep.equal({ active: {} });
+      const vaultBalanceAfter = await provider.connection.getBalance(collateralVaultPda);
+      expect(vaultBalanceBefore - vaultBalanceAfter).to.equal(amount.toNumber());
+      expect(ltvAfter(after.collateralAmount, after.debtAmount)).to.be.at.most(maxLtv);
+    });
+
+    it("Rejects a withdrawal that would push LTV past max_ltv", async () => {
+      const position = await program.account.position.fetch(positionPda);
+      const { maxLtv } = await program.account.collateralConfig.fetch(collateralConfigPda);
+
+      // Leave just under the collateral max_ltv needs
+      const minCollateral = position.debtAmount.muln(10_000 - maxLtv).divn(maxLtv);
+      const amount = position.collateralAmount.sub(minCollateral).addn(LAMPORTS_PER_SOL / 100);
+
+      try {
+        await program.methods
+          .withdrawExcessCollateral(amount)
+          .accountsStrict(accounts)
+          .signers([user])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("ExceedsMaxLTV");
+      }
+
+      const after = await program.account.position.fetch(positionPda);
+      expect(after.collateralAmount.toString()).to.equal(position.collateralAmount.toString());
+    });
+  });
+  describe("repay", () => {
+    let userWsolAta: PublicKey;
+
+    before(async () => {
+      userWsolAta = await wrapSol(user, user.publicKey, 2 * LAMPORTS_PER_SOL);
+    });
+
+    it("Partially repays debt without touching the DLMM position", async () => {
+      const before      = await program.account.position.fetch(positionPda);
+      const vaultBefore = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolBefore  = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+      const amount = new BN(LAMPORTS_PER_SOL);
+
+      await program.methods
+        .repay(amount)
+        .accountsStrict({
+          user: user.publicKey,
+          wsolMint: NATIVE_MINT,
+          position: positionPda,
+          collateralConfig: collateralConfigPda,
+          lendingVault: lendingVaultPda,
+          wsolVault: wsolVaultPda,
+          userWsolAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      const after      = await program.account.position.fetch(positionPda);
+      const vaultAfter = await program.account.lendingVault.fetch(lendingVaultPda);
+      const wsolAfter  = await provider.connection.getTokenAccountBalance(wsolVaultPda);
+
+      // Debt may have picked up a few lamports of interest before the repay
+      expect(after.debtAmount.lte(before.debtAmount.sub(amount).addn(1_000))).to.be.true;
+      expect(after.debtAmount.gte(before.debtAmount.sub(amount))).to.be.true;
+      expect(vaultAfter.totalBorrowed.lt(vaultBefore.totalBorrowed)).to.be.true;
+      expect(Number(wsolAfter.value.amount) - Number(wsolBefore.value.amount)).to.equal(
+        amount.toNumber()
+      );
+      expect(after.status).to.deep.equal({ active: {} });
+
+      console.log("  ✓ Debt", before.debtAmount.toString(), "→", after.debtAmount.toString());
+    });
+
+    it("Rejects repaying more than the outstanding debt", async () => {
+      const position = await program.account.position.fetch(positionPda);
+
+      try {
+        await program.methods
+          .repay(position.debtAmount.muln(2))
+          .accountsStrict({
+            user: user.publicKey,
+            wsolMint: NATIVE_MINT,
+            position: positionPda,
+            collateralConfig: collateralConfigPda,
+            lendingVault: lendingVaultPda,
+            wsolVault: wsolVaultPda,
+            userWsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InvalidAmount");
+        console.log("  ✓ Correctly rejected over-repayment");
+      }
+    });
+
+    it("Rejects a repay that would leave less than min_position_borrow outstanding", async () => {
+      const position = await program.account.position.fetch(positionPda);
+      await program.methods
+        .updateCollateralMinPositionBorrow(NATIVE_MINT, new BN(1_000_000))
+        .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+        .rpc();
+
+      try {
+        await program.methods
+          .repay(position.debtAmount.subn(1))
+          .accountsStrict({
+            user: user.publicKey,
+            wsolMint: NATIVE_MINT,
+            position: positionPda,
+            collateralConfig: collateralConfigPda,
+            lendingVault: lendingVaultPda,
+            wsolVault: wsolVaultPda,
+            userWsolAta,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user])
+          .rpc();
+        throw new Error("Should have failed");
+      } catch (e) {
+        expect((e as Error).message).to.include("InvalidAmount");
+      } finally {
+        await program.methods
+          .updateCollateralMinPositionBorrow(NATIVE_MINT, new BN(0))
+          .accountsStrict({ authority, config: configPda, collateralConfig: collateralConfigPda })
+          .rpc();
+      }
+
+      const after = await program.account.position.fetch(positionPda);
+      expect(after.debtAmount.gte(position.debtAmount)).to.be.true;
+    });
+  });
+  describe("getPositionHealth", () => {
+    it("Returns LTV and health factor consistent with the stored position", async () => {
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .updateMockOracle(new BN(150_000_000))
+        .accountsStrict({
+          authority,
+          mint: NATIVE_MINT,
+          mockOracle: priceOracle,
+        })
+        .rpc();
+
+      const health = await program.methods
+        .getPositionHealth()
+        .accountsStrict({
+          position: positionPda,
+          collateralConfig: collateralConfigPda,
+          lendingVault: lendingVaultPda,
+          priceOracle,
+        })
+        .view();
+
+      const position = await program.account.position.fetch(positionPda);
+      const config = await program.account.collateralConfig.fetch(collateralConfigPda);
+
+      const expectedLtv = health.debtValue
+        .muln(10_000)
+        .div(health.collateralValue.add(health.debtValue));
+      expect(health.ltv.toString()).to.equal(expectedLtv.toString());
+      expect(health.debtValue.gtn(0)).to.be.true;
+      expect(health.ltv.toNumber()).to.be.lessThan(config.liquidationThreshold);
+      expect(health.healthFactor.toNumber()).to.be.greaterThan(10_000);
+      expect(health.liquidatable).to.be.false;
+
+      console.log("  ✓ LTV:", health.ltv.toNumber(), "bps, HF:", health.healthFactor.toNumber() / 10_000);
+      console.log("    debt on-chain:", position.debtAmount.toString());
+    });
+
+    it("isLiquidatable agrees with getPositionHealth for a healthy position", async () => {
+      const [priceOracle] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+
+      const liquidatable = await program.methods
+        .isLiquidatable()
+        .accountsStrict({
+          position: positionPda,
+          collateralConfig: collateralConfigPda,
+          priceOracle,
+        })
+        .view();
+
+      expect(liquidatable).to.equal(false);
+    });
+
+    it("getPriceHistory returns the prices opens acted on, newest last", async () => {
+      const collateralConfig = await program.account.collateralConfig.fetch(collateralConfigPda);
+      const history = await program.methods
+        .getPriceHistory(NATIVE_MINT)
+        .accountsStrict({ collateralConfig: collateralConfigPda })
+        .view();
+
+      expect(history.length).to.be.greaterThan(0);
+      expect(history.length).to.be.at.most(8);
+      const latest = history[history.length - 1];
+      expect(latest.price.toString()).to.equal(collateralConfig.lastPrice.toString());
+      expect(latest.timestamp.toString()).to.equal(collateralConfig.lastPriceTs.toString());
+      for (let i = 1; i < history.length; i++) {
+        expect(history[i].timestamp.gte(history[i - 1].timestamp)).to.equal(true);
       }
     });
   });